@@ -0,0 +1,41 @@
+// Run with `--features span-locations`; without it nothing is located and
+// every lookup returns immediately.
+use criterion::{criterion_group, criterion_main, Criterion};
+use klepto::{EnclosingIndex, FileLocation};
+use std::hint::black_box;
+use std::path::PathBuf;
+
+// one file of `n` impl blocks, each with a few methods holding nested fns
+fn synthetic(n: usize) -> String {
+    let mut s = String::new();
+    for i in 0..n {
+        s.push_str(&format!("struct T{i};\nimpl T{i} {{\n"));
+        for j in 0..4 {
+            s.push_str(&format!(
+                "    fn m{j}(&self) -> u32 {{\n        fn inner() -> u32 {{ {j} }}\n        let x = inner();\n        x + 1\n    }}\n"
+            ));
+        }
+        s.push_str("}\n");
+    }
+    s
+}
+
+fn bench_enclosing(c: &mut Criterion) {
+    for n in [100, 1000] {
+        let src = synthetic(n);
+        let path = PathBuf::from("synthetic.rs");
+        let ast = syn::parse_file(&src).unwrap();
+        let index = EnclosingIndex::build("bench", &path, &ast);
+        let lines = src.lines().count() as u32;
+        let locs: Vec<FileLocation> = (1..=lines)
+            .map(|line| FileLocation { path: path.clone().into(), line: Some(line), column: Some(8), byte_range: None })
+            .collect();
+
+        c.bench_function(&format!("enclosing/{n}_impls"), |b| {
+            b.iter(|| locs.iter().filter(|l| index.enclosing(black_box(l)).is_some()).count())
+        });
+    }
+}
+
+criterion_group!(benches, bench_enclosing);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+// Indexed `find_*` against the linear scans they replaced.
+use criterion::{criterion_group, criterion_main, Criterion};
+use klepto::Klepto;
+use std::hint::black_box;
+
+// `n` modules of fns full of paths, macro calls and method calls; each module
+// also has a few names of its own, so lookups hit a handful of occurrences
+fn synthetic(n: usize) -> String {
+    let mut s = String::new();
+    for i in 0..n {
+        s.push_str(&format!("pub mod m{i} {{\n    use std::collections::HashMap;\n    pub enum Kind{i} {{ A, B }}\n    fn helper{i}() -> u32 {{ {i} }}\n"));
+        for j in 0..8 {
+            s.push_str(&format!(
+                "    pub fn f{j}(v: Vec<u32>) -> Option<u32> {{\n        let m: HashMap<u32, u32> = HashMap::new();\n        let _k = if m.is_empty() {{ Kind{i}::A }} else {{ Kind{i}::B }};\n        let x = std::cmp::max(v.first().copied().unwrap(), helper{i}());\n        let _s = format!(\"{{x}}\");\n        let _t = vec{i}!();\n        Some(core::convert::identity(x)).map(|y| y.pow(2))\n    }}\n"
+            ));
+        }
+        s.push_str("}\n");
+    }
+    s
+}
+
+fn bench_find(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("klepto-bench-find-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.rs"), synthetic(500)).unwrap();
+    let k = Klepto::new("bench").scan_in_folder(&dir).parse().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    c.bench_function("find/paths/linear", |b| {
+        b.iter(|| k.paths.iter().filter(|p| p.path == black_box("Kind250::A")).cloned().collect::<Vec<_>>())
+    });
+    c.bench_function("find/paths/indexed", |b| b.iter(|| k.find_paths(black_box("Kind250::A"))));
+    c.bench_function("find/paths/borrowed", |b| b.iter(|| k.paths_eq(black_box("Kind250::A")).count()));
+
+    c.bench_function("find/macros/linear", |b| {
+        b.iter(|| k.macros_inv.iter().filter(|m| m.name == black_box("vec250")).cloned().collect::<Vec<_>>())
+    });
+    c.bench_function("find/macros/indexed", |b| b.iter(|| k.find_macro_invocations(black_box("vec250"))));
+
+    c.bench_function("find/calls/linear", |b| {
+        b.iter(|| k.calls.iter().filter(|c| c.callee.contains(black_box("helper250"))).cloned().collect::<Vec<_>>())
+    });
+    c.bench_function("find/calls/indexed", |b| b.iter(|| k.find_calls(black_box("helper250"))));
+}
+
+criterion_group!(benches, bench_find);
+criterion_main!(benches);
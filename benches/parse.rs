@@ -0,0 +1,41 @@
+// Whole-scan wall time: `cargo bench --bench parse`, then again with
+// `--features parallel`. Each file is read, parsed and extracted once; the
+// retained ASTs are only parsed again when an analysis asks for one.
+use criterion::{criterion_group, criterion_main, Criterion};
+use klepto::Klepto;
+
+// `files` modules of a few dozen fns each, spread over a src tree
+fn synthetic(dir: &std::path::Path, files: usize) {
+    let src = dir.join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    let mut lib = String::new();
+    for i in 0..files {
+        lib.push_str(&format!("pub mod m{i};\n"));
+        let mut s = String::from("use std::collections::HashMap;\n\n");
+        for j in 0..40 {
+            s.push_str(&format!(
+                "/// Doc for f{j}.\npub fn f{j}(v: &[u32]) -> Option<u32> {{\n    let mut m: HashMap<u32, u32> = HashMap::new();\n    for x in v {{ *m.entry(*x).or_default() += {j}; }}\n    let total: u32 = m.values().sum();\n    if total > 10 {{ Some(crate::m{i}::g{j}(total)) }} else {{ None }}\n}}\n\nfn g{j}(x: u32) -> u32 {{ format!(\"{{x}}\").len() as u32 }}\n\n"
+            ));
+        }
+        std::fs::write(src.join(format!("m{i}.rs")), s).unwrap();
+    }
+    std::fs::write(src.join("lib.rs"), lib).unwrap();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("klepto-bench-parse-{}", std::process::id()));
+    synthetic(&dir, 200);
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.bench_function("scan/200_files", |b| {
+        b.iter(|| Klepto::new("bench").scan_in_folder(dir.join("src")).parse().unwrap())
+    });
+    group.bench_function("scan/200_files/no_source", |b| {
+        b.iter(|| Klepto::new("bench").scan_in_folder(dir.join("src")).retain_source(false).parse().unwrap())
+    });
+    group.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
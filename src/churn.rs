@@ -0,0 +1,130 @@
+//! Git history per function, from `git blame`: when and by whom it last
+//! changed and how many commits its current lines come from. Combined with
+//! size and a test check, that ranks hotspots: big, often-changed, untested code.
+//!
+//! Needs `git` on the PATH and `span-locations` (function line ranges).
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{FileLocation, TargetKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FnChurn {
+    pub fq_name: String,
+    pub is_public: bool,
+    pub location: FileLocation,
+    /// Lines the function spans; klepto has no complexity metric, so size stands in.
+    pub lines: u32,
+    pub last_commit: String,
+    pub last_author: String,
+    /// Author time of `last_commit`, seconds since the epoch.
+    pub last_modified: i64,
+    /// Distinct commits the function's current lines were last changed in.
+    pub commits: usize,
+    /// Called by name from test code (`tests/`, or a `tests` module).
+    pub tested: bool,
+}
+
+impl FnChurn {
+    /// Hotspot score: `commits * lines`.
+    pub fn score(&self) -> u64 { self.commits as u64 * self.lines as u64 }
+}
+
+// (commit, author, author-time) of one line
+type LineBlame = (String, String, i64);
+
+// `git blame --line-porcelain`: every line gets its commit's full header
+fn blame(file: &Path) -> Result<Vec<LineBlame>, KleptoError> {
+    let (dir, name) = match (file.parent(), file.file_name()) {
+        (Some(d), Some(n)) => (if d.as_os_str().is_empty() { Path::new(".") } else { d }, n),
+        _ => return Ok(Vec::new()),
+    };
+    let out = Command::new("git")
+        .arg("-C").arg(dir)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(name)
+        .output()
+        .map_err(|e| KleptoError::Git(format!("failed to run git: {}", e)))?;
+    // untracked files and files outside a repository have no history
+    if !out.status.success() { return Ok(Vec::new()); }
+
+    let mut lines = Vec::new();
+    let (mut commit, mut author, mut time) = (String::new(), String::new(), 0);
+    for l in String::from_utf8_lossy(&out.stdout).lines() {
+        if l.starts_with('\t') {
+            lines.push((commit.clone(), author.clone(), time));
+        } else if let Some(a) = l.strip_prefix("author ") {
+            author = a.to_string();
+        } else if let Some(t) = l.strip_prefix("author-time ") {
+            time = t.parse().unwrap_or(0);
+        } else if let Some(hash) = l.split(' ').next().filter(|h| h.len() == 40 && h.bytes().all(|b| b.is_ascii_hexdigit())) {
+            commit = hash.to_string();
+        }
+    }
+    Ok(lines)
+}
+
+impl Klepto {
+    /// Blame-based history of every located function, file order. Files git
+    /// doesn't track are left out.
+    pub fn git_churn(&self) -> Result<Vec<FnChurn>, KleptoError> {
+        // names called from test code; by name only, so a same-named fn elsewhere counts too
+        let called_from_tests: HashSet<&str> = self.calls.iter()
+            .filter(|c| TargetKind::of_path(&c.location.path) == TargetKind::Test || c.module_path.iter().any(|m| m.as_str() == "tests"))
+            .map(|c| c.callee.rsplit("::").next().unwrap_or(&c.callee).trim())
+            .collect();
+
+        let mut out = Vec::new();
+        for pf in &self.files {
+            let spans = self.index.spans(&pf.path);
+            if spans.is_empty() { continue; }
+            let blamed = blame(&self.absolute_path(&pf.path))?;
+            if blamed.is_empty() { continue; }
+
+            for s in spans {
+                let (Some((start, col)), Some((end, _))) = (s.start, s.end) else { continue; };
+                let range = blamed.get((start as usize).saturating_sub(1)..(end as usize).min(blamed.len())).unwrap_or(&[]);
+                let Some(last) = range.iter().max_by_key(|b| b.2) else { continue; };
+                let commits: HashSet<&str> = range.iter().map(|b| b.0.as_str()).collect();
+                let name = s.fq_name.rsplit("::").next().unwrap_or(&s.fq_name);
+                out.push(FnChurn {
+                    fq_name: s.fq_name.clone(),
+                    is_public: s.is_public,
+                    location: FileLocation { path: pf.path.as_path().into(), line: Some(start), column: Some(col), byte_range: s.byte_range.clone() },
+                    lines: end - start + 1,
+                    last_commit: last.0.clone(),
+                    last_author: last.1.clone(),
+                    last_modified: last.2,
+                    commits: commits.len(),
+                    tested: called_from_tests.contains(name),
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Untested functions by descending `score`, at most `n`.
+pub fn hotspots(churn: &[FnChurn], n: usize) -> Vec<&FnChurn> {
+    let mut v: Vec<&FnChurn> = churn.iter().filter(|c| !c.tested).collect();
+    v.sort_by(|a, b| b.score().cmp(&a.score()).then_with(|| a.fq_name.cmp(&b.fq_name)));
+    v.truncate(n);
+    v
+}
+
+/// Markdown table of `hotspots(churn, n)`.
+pub fn hotspots_to_markdown(churn: &[FnChurn], n: usize) -> String {
+    let mut s = String::from("# Hotspots\n\nUntested functions by commits × lines.\n\n");
+    s.push_str("| Function | Score | Commits | Lines | Last author | Location |\n|---|---:|---:|---:|---|---|\n");
+    for c in hotspots(churn, n) {
+        s.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} | {}:{} |\n",
+            c.fq_name, c.score(), c.commits, c.lines, c.last_author, c.location.path.display(), c.location.line.unwrap_or(0),
+        ));
+    }
+    s
+}
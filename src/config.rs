@@ -0,0 +1,269 @@
+//! `klepto.toml`: per-rule levels and the baseline snapshot.
+//!
+//! ```toml
+//! [rules]
+//! KLEP001 = "deny"   # undocumented public API fails `klepto rules`
+//! KLEP005 = "off"
+//!
+//! [baseline]
+//! snapshot = "klepto-baseline.json"
+//!
+//! [msrv]
+//! version = "1.70"   # KLEP015 flags syntax and std items newer than this
+//!
+//! [[msrv.patterns]]  # rows added to the builtin table
+//! name = "`std::sync::Exclusive`"
+//! since = "1.90"
+//! path = "std::sync::Exclusive"
+//!
+//! [imports]          # KLEP022
+//! absolute = "forbid"                         # or "require" a leading `::` on external paths
+//! groups = ["std", "external", "internal"]    # order of `use`s within a module
+//!
+//! [doc_coverage]     # KLEP024
+//! min = 80.0                                  # percent of public items documented, per module
+//! modules = { "crate::api" = 100.0 }          # stricter (or looser) for a module and its children
+//! ```
+
+use crate::klepto::KleptoError;
+use crate::model::Severity;
+use crate::msrv::{MsrvEntry, MsrvPattern, RustVersion};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = "klepto.toml";
+pub const DEFAULT_BASELINE: &str = "klepto-baseline.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Info,
+    Warn,
+    Deny,
+}
+
+impl RuleLevel {
+    /// `None` for `Off`.
+    pub fn severity(self) -> Option<Severity> {
+        match self {
+            RuleLevel::Off => None,
+            RuleLevel::Info => Some(Severity::Info),
+            RuleLevel::Warn => Some(Severity::Warn),
+            RuleLevel::Deny => Some(Severity::Deny),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuleLevel::Off => "off",
+            RuleLevel::Info => "info",
+            RuleLevel::Warn => "warn",
+            RuleLevel::Deny => "deny",
+        }
+    }
+}
+
+impl From<Severity> for RuleLevel {
+    fn from(s: Severity) -> Self {
+        match s {
+            Severity::Info => RuleLevel::Info,
+            Severity::Warn => RuleLevel::Warn,
+            Severity::Deny => RuleLevel::Deny,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    /// Snapshot (with findings) that `klepto diff` compares against by default;
+    /// relative to the config file.
+    pub snapshot: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct MsrvConfig {
+    /// The MSRV the code has to build on.
+    pub version: RustVersion,
+    /// Added to `MsrvTable::builtin`.
+    #[serde(default)]
+    pub patterns: Vec<MsrvEntry>,
+}
+
+/// Whether external `use` paths start with `::`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AbsolutePaths {
+    Require,
+    Forbid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ImportGroup {
+    /// `std`, `core` and `alloc`.
+    Std,
+    /// Dependencies and other workspace members.
+    External,
+    /// `crate`, `self` and `super`.
+    Internal,
+}
+
+impl ImportGroup {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportGroup::Std => "std",
+            ImportGroup::External => "external",
+            ImportGroup::Internal => "internal",
+        }
+    }
+}
+
+/// `[imports]`: the `use` style KLEP022 checks; every check is off unless set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ImportStyleConfig {
+    #[serde(default)]
+    pub absolute: Option<AbsolutePaths>,
+    /// Order of the groups within a module; groups left out may go anywhere.
+    #[serde(default)]
+    pub groups: Option<Vec<ImportGroup>>,
+}
+
+/// `[doc_coverage]`: minimum public doc coverage per module, for KLEP024.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct DocCoverageConfig {
+    /// Percent every module must reach; unset checks only the listed modules.
+    #[serde(default)]
+    pub min: Option<f64>,
+    /// Per-module minimums (`crate::a::b`), covering child modules too; the
+    /// longest matching one wins.
+    #[serde(default)]
+    pub modules: BTreeMap<String, f64>,
+}
+
+impl DocCoverageConfig {
+    /// The minimum that applies to `module`, if any.
+    pub fn threshold(&self, module: &str) -> Option<f64> {
+        self.modules.iter()
+            .filter(|(m, _)| module == m.as_str() || module.strip_prefix(m.as_str()).is_some_and(|rest| rest.starts_with("::")))
+            .max_by_key(|(m, _)| m.len())
+            .map(|(_, min)| *min)
+            .or(self.min)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Rule code -> level; rules not listed keep their builtin severity.
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleLevel>,
+    #[serde(default)]
+    pub baseline: Option<Baseline>,
+    #[serde(default)]
+    pub msrv: Option<MsrvConfig>,
+    #[serde(default)]
+    pub imports: Option<ImportStyleConfig>,
+    #[serde(default)]
+    pub doc_coverage: Option<DocCoverageConfig>,
+}
+
+impl Config {
+    pub fn parse(src: &str) -> Result<Self, KleptoError> {
+        toml::from_str(src).map_err(KleptoError::Config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        Self::parse(&src)
+    }
+
+    /// `dir/klepto.toml` if it exists, else the empty config.
+    pub fn discover(dir: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = dir.as_ref().join(CONFIG_FILE_NAME);
+        if path.is_file() { Self::load(path) } else { Ok(Self::default()) }
+    }
+
+    /// Every builtin rule at its default severity (the `guidelines` pack listed
+    /// as `off`), plus the default baseline path.
+    pub fn starter() -> Self {
+        Self {
+            rules: crate::rules::builtin::defaults().iter()
+                .map(|r| (r.code().to_string(), r.default_severity().into()))
+                .chain(crate::rules::guidelines::pack().iter().map(|r| (r.code().to_string(), RuleLevel::Off)))
+                .collect(),
+            baseline: Some(Baseline { snapshot: DEFAULT_BASELINE.into() }),
+            msrv: None,
+            imports: None,
+            doc_coverage: None,
+        }
+    }
+
+    /// TOML text for this config, with each builtin rule's name as a comment.
+    pub fn to_toml_string(&self) -> String {
+        let mut out = String::from("# klepto configuration\n\n[rules]\n# \"deny\" | \"warn\" | \"info\" | \"off\"; unlisted rules keep their builtin severity\n");
+        for (code, level) in &self.rules {
+            let entry = format!("{} = \"{}\"", code, level.as_str());
+            match crate::rules::builtin::rule_name(&crate::model::RuleCode::new(code.clone())) {
+                Some(name) => out.push_str(&format!("{:<18} # {}\n", entry, name)),
+                None => out.push_str(&format!("{}\n", entry)),
+            }
+        }
+        if let Some(b) = &self.baseline {
+            out.push_str("\n[baseline]\n");
+            out.push_str(&format!("snapshot = {}\n", toml::Value::String(b.snapshot.display().to_string())));
+        }
+        if let Some(m) = &self.msrv {
+            out.push_str(&format!("\n[msrv]\nversion = \"{}\"\n", m.version));
+            for e in &m.patterns {
+                let (key, value) = match &e.pattern {
+                    MsrvPattern::Syntax(s) => ("syntax", toml::Value::try_from(s).map_or_else(|_| String::new(), |v| v.to_string())),
+                    MsrvPattern::Path(p) => ("path", toml::Value::String(p.clone()).to_string()),
+                    MsrvPattern::Method(m) => ("method", toml::Value::String(m.clone()).to_string()),
+                    MsrvPattern::Macro(m) => ("macro", toml::Value::String(m.clone()).to_string()),
+                };
+                out.push_str(&format!(
+                    "\n[[msrv.patterns]]\nname = {}\nsince = \"{}\"\n{} = {}\n",
+                    toml::Value::String(e.name.clone()), e.since, key, value,
+                ));
+            }
+        }
+        if let Some(i) = &self.imports {
+            out.push_str("\n[imports]\n");
+            if let Some(a) = i.absolute {
+                out.push_str(&format!("absolute = \"{}\"\n", match a { AbsolutePaths::Require => "require", AbsolutePaths::Forbid => "forbid" }));
+            }
+            if let Some(groups) = &i.groups {
+                let groups: Vec<String> = groups.iter().map(|g| format!("\"{}\"", g.as_str())).collect();
+                out.push_str(&format!("groups = [{}]\n", groups.join(", ")));
+            }
+        }
+        if let Some(d) = &self.doc_coverage {
+            out.push_str("\n[doc_coverage]\n");
+            if let Some(min) = d.min {
+                out.push_str(&format!("min = {}\n", toml::Value::Float(min)));
+            }
+            if !d.modules.is_empty() {
+                out.push_str("\n[doc_coverage.modules]\n");
+                for (m, min) in &d.modules {
+                    out.push_str(&format!("{} = {}\n", toml::Value::String(m.clone()), toml::Value::Float(*min)));
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,262 @@
+//! Functions that look like they could be `const fn` but aren't, in the spirit
+//! of clippy's `missing_const_for_fn` (a heuristic: nothing is type-checked).
+
+use crate::klepto::Klepto;
+use crate::model::{CapturedFn, FileLocation, FnKind};
+use crate::scope::span_to_location;
+use std::collections::HashSet;
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+// macros that expand to const-evaluable code
+const CONST_MACROS: &[&str] = &[
+    "panic", "assert", "assert_eq", "assert_ne", "debug_assert", "debug_assert_eq", "debug_assert_ne",
+    "unreachable", "matches", "concat", "stringify", "include_str", "include_bytes", "env", "line",
+    "column", "file", "module_path", "cfg",
+];
+
+// std methods callable in const context whatever the receiver, by name or prefix
+const CONST_METHODS: &[&str] = &[
+    "len", "is_empty", "is_some", "is_none", "is_ok", "is_err", "as_ptr", "as_bytes", "as_str", "pow",
+    "abs", "count_ones", "count_zeros", "leading_zeros", "trailing_zeros", "rotate_left", "rotate_right",
+    "swap_bytes", "reverse_bits", "to_be", "to_le", "to_be_bytes", "to_le_bytes", "to_ne_bytes", "min", "max",
+];
+const CONST_METHOD_PREFIXES: &[&str] = &["wrapping_", "checked_", "saturating_", "overflowing_"];
+
+// std functions and constructors that are `const fn`, by trailing path
+const CONST_STD_FNS: &[&str] = &[
+    "Vec::new", "String::new", "mem::size_of", "size_of", "mem::align_of", "align_of", "from_le_bytes",
+    "from_be_bytes", "from_ne_bytes", "Duration::from_secs", "Duration::from_millis", "Duration::from_nanos",
+    "PhantomData", "Cell::new", "RefCell::new", "UnsafeCell::new", "Mutex::new", "RwLock::new",
+    "ManuallyDrop::new", "MaybeUninit::uninit", "NonNull::dangling", "ptr::null", "ptr::null_mut",
+];
+
+// owned types whose values need dropping; a by-value parameter of one is
+// dropped (or mutated) in the body, which a const fn can't do
+const DROP_TYPES: &[&str] = &[
+    "String", "Vec", "Box", "Rc", "Arc", "HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque",
+    "PathBuf", "OsString", "CString",
+];
+
+// unsized targets a `&field` return would reach through `Deref` (`&Vec<T>` as `&[T]`)
+const DEREF_TARGETS: &[&str] = &["str", "Path", "OsStr", "CStr"];
+
+// what the compiler wouldn't take in a const fn body: `for` (iterators), `?`,
+// closures, async blocks and `.await`, dropping non-Copy values (assigning
+// into a by-value `self`, by-value `String`/`Vec`/`Box`.. parameters), deref
+// coercions in returns, and matching or comparing `str`s; plus the `static`s
+// declared, and every SCREAMING_CASE name read, which is one of them or a const
+struct Walker<'a> {
+    file: &'a Path,
+    blocked: Vec<FileLocation>,
+    statics: HashSet<String>,
+    upper: Vec<(String, FileLocation)>,
+    // of the fn being walked
+    by_value_self: bool,
+    returns_deref_target: bool,
+}
+
+impl Walker<'_> {
+    fn block(&mut self, span: proc_macro2::Span) { self.blocked.push(span_to_location(self.file, span)); }
+
+    fn in_fn(&mut self, sig: &syn::Signature, body: impl FnOnce(&mut Self)) {
+        let by_value_self = sig.receiver().is_some_and(|r| r.reference.is_none() && r.colon_token.is_none());
+        let returns_deref_target = match &sig.output {
+            syn::ReturnType::Type(_, ty) => is_deref_target(ty),
+            syn::ReturnType::Default => false,
+        };
+        let drops_param = sig.inputs.iter().any(|i| matches!(i, syn::FnArg::Typed(t) if owns_drop_type(&t.ty)));
+        if drops_param { self.block(sig.ident.span()); }
+        let saved = (std::mem::replace(&mut self.by_value_self, by_value_self), std::mem::replace(&mut self.returns_deref_target, returns_deref_target));
+        body(self);
+        (self.by_value_self, self.returns_deref_target) = saved;
+    }
+}
+
+// `&str`, `&[T]`, `&Path`..
+fn is_deref_target(ty: &syn::Type) -> bool {
+    let syn::Type::Reference(r) = ty else { return false };
+    match &*r.elem {
+        syn::Type::Slice(_) => true,
+        syn::Type::Path(p) => p.path.segments.last().is_some_and(|s| DEREF_TARGETS.iter().any(|t| s.ident == t)),
+        _ => false,
+    }
+}
+
+// by value, not behind a reference or pointer
+fn owns_drop_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => p.path.segments.iter().any(|s| {
+            DROP_TYPES.iter().any(|t| s.ident == t)
+                || matches!(&s.arguments, syn::PathArguments::AngleBracketed(a)
+                    if a.args.iter().any(|g| matches!(g, syn::GenericArgument::Type(t) if owns_drop_type(t))))
+        }),
+        syn::Type::Tuple(t) => t.elems.iter().any(owns_drop_type),
+        syn::Type::Array(a) => owns_drop_type(&a.elem),
+        syn::Type::Paren(p) => owns_drop_type(&p.elem),
+        _ => false,
+    }
+}
+
+fn is_str_lit(e: &syn::Expr) -> bool {
+    matches!(e, syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(_), .. }))
+}
+
+fn has_str_pattern(p: &syn::Pat) -> bool {
+    match p {
+        syn::Pat::Lit(l) => matches!(l.lit, syn::Lit::Str(_)),
+        syn::Pat::Or(o) => o.cases.iter().any(has_str_pattern),
+        syn::Pat::Reference(r) => has_str_pattern(&r.pat),
+        syn::Pat::Tuple(t) => t.elems.iter().any(has_str_pattern),
+        syn::Pat::Slice(s) => s.elems.iter().any(has_str_pattern),
+        syn::Pat::TupleStruct(t) => t.elems.iter().any(has_str_pattern),
+        syn::Pat::Struct(s) => s.fields.iter().any(|f| has_str_pattern(&f.pat)),
+        syn::Pat::Ident(i) => i.subpat.as_ref().is_some_and(|(_, p)| has_str_pattern(p)),
+        _ => false,
+    }
+}
+
+// `self`, `self.a.b`, `self.0[i]`: what an assignment writes into
+fn rooted_in_self(e: &syn::Expr) -> bool {
+    match e {
+        syn::Expr::Path(p) => p.path.is_ident("self"),
+        syn::Expr::Field(f) => rooted_in_self(&f.base),
+        syn::Expr::Index(i) => rooted_in_self(&i.expr),
+        syn::Expr::Paren(p) => rooted_in_self(&p.expr),
+        _ => false,
+    }
+}
+
+impl<'ast> Visit<'ast> for Walker<'_> {
+    fn visit_expr_for_loop(&mut self, e: &'ast syn::ExprForLoop) { self.block(e.for_token.span()); syn::visit::visit_expr_for_loop(self, e); }
+    fn visit_expr_try(&mut self, e: &'ast syn::ExprTry) { self.block(e.question_token.span()); syn::visit::visit_expr_try(self, e); }
+    fn visit_expr_closure(&mut self, e: &'ast syn::ExprClosure) { self.block(e.or1_token.span()); syn::visit::visit_expr_closure(self, e); }
+    fn visit_expr_async(&mut self, e: &'ast syn::ExprAsync) { self.block(e.async_token.span()); syn::visit::visit_expr_async(self, e); }
+    fn visit_expr_await(&mut self, e: &'ast syn::ExprAwait) { self.block(e.await_token.span()); syn::visit::visit_expr_await(self, e); }
+
+    fn visit_item_fn(&mut self, f: &'ast syn::ItemFn) { self.in_fn(&f.sig, |w| syn::visit::visit_item_fn(w, f)); }
+    fn visit_impl_item_fn(&mut self, f: &'ast syn::ImplItemFn) { self.in_fn(&f.sig, |w| syn::visit::visit_impl_item_fn(w, f)); }
+
+    // the old value of a field of a by-value `self` is dropped (E0493)
+    fn visit_expr_assign(&mut self, e: &'ast syn::ExprAssign) {
+        if self.by_value_self && rooted_in_self(&e.left) { self.block(e.eq_token.span()); }
+        syn::visit::visit_expr_assign(self, e);
+    }
+
+    fn visit_expr_reference(&mut self, e: &'ast syn::ExprReference) {
+        if self.returns_deref_target && !matches!(&*e.expr, syn::Expr::Lit(_) | syn::Expr::Array(_) | syn::Expr::Repeat(_)) {
+            self.block(e.and_token.span());
+        }
+        syn::visit::visit_expr_reference(self, e);
+    }
+
+    // `str`'s `PartialEq` isn't const
+    fn visit_expr_match(&mut self, e: &'ast syn::ExprMatch) {
+        if e.arms.iter().any(|a| has_str_pattern(&a.pat)) { self.block(e.match_token.span()); }
+        syn::visit::visit_expr_match(self, e);
+    }
+
+    fn visit_expr_binary(&mut self, e: &'ast syn::ExprBinary) {
+        if matches!(e.op, syn::BinOp::Eq(_) | syn::BinOp::Ne(_)) && (is_str_lit(&e.left) || is_str_lit(&e.right)) {
+            self.block(e.op.span());
+        }
+        syn::visit::visit_expr_binary(self, e);
+    }
+
+    fn visit_expr_path(&mut self, e: &'ast syn::ExprPath) {
+        if let Some(last) = e.path.segments.last() {
+            let name = last.ident.to_string();
+            if name.len() > 1 && !name.chars().any(|c| c.is_lowercase()) {
+                self.upper.push((name, span_to_location(self.file, e.span())));
+            }
+        }
+        syn::visit::visit_expr_path(self, e);
+    }
+
+    fn visit_item_static(&mut self, s: &'ast syn::ItemStatic) {
+        self.statics.insert(s.ident.to_string());
+        syn::visit::visit_item_static(self, s);
+    }
+}
+
+impl Klepto {
+    /// Non-`const` free functions and inherent methods whose bodies only call
+    /// `const fn`s of the scan, constructors and a list of std `const fn`s, use
+    /// no macros but `panic!`/`assert!`-likes, read no `static`, and have no
+    /// `for` loop, `?`, closure or `.await`. Method calls count only for a list
+    /// of std methods that are `const` on every type that has them. Bodies that
+    /// would drop a non-Copy value (assigning into a by-value `self`, owned
+    /// `String`/`Vec`/`Box`.. parameters), return `&field` as `&str`/`&[T]`/`&Path`
+    /// through `Deref`, or match or compare `str`s are left out.
+    ///
+    /// Needs `span-locations` and the ASTs; empty without them.
+    pub fn const_fn_candidates(&self) -> Vec<&CapturedFn> {
+        if !cfg!(feature = "span-locations") { return Vec::new(); }
+
+        let mut blocked_fns: HashSet<&str> = HashSet::new();
+        let mut statics = HashSet::new();
+        let mut upper = Vec::new();
+        for pf in &self.files {
+            let mut w = Walker {
+                file: &pf.path, blocked: Vec::new(), statics: HashSet::new(), upper: Vec::new(),
+                by_value_self: false, returns_deref_target: false,
+            };
+            w.visit_file(&pf.ast());
+            blocked_fns.extend(w.blocked.iter().filter_map(|loc| self.index.enclosing(loc)).map(|f| f.fq_name.as_str()));
+            statics.extend(w.statics);
+            upper.extend(w.upper);
+        }
+        for (name, loc) in &upper {
+            if statics.contains(name)
+                && let Some(f) = self.index.enclosing(loc)
+            {
+                blocked_fns.insert(f.fq_name.as_str());
+            }
+        }
+
+        let const_fns: Vec<&CapturedFn> = self.functions.iter().filter(|f| f.is_const).collect();
+        let callee_is_const = |callee: &str, caller: &CapturedFn| {
+            let callee = callee.split_whitespace().collect::<String>();
+            let callee = callee.split("::<").next().unwrap_or_default();
+            let mut segs: Vec<&str> = callee.split("::").collect();
+            let name = segs.pop().unwrap_or_default();
+            if name.starts_with(|c: char| c.is_uppercase()) { return true; } // tuple struct or variant
+            if CONST_STD_FNS.iter().any(|s| callee == *s || callee.ends_with(&format!("::{}", s))) { return true; }
+            // `Self::f` and `Type::f` name a method, `f` and `module::f` a free function
+            let owner = match (segs.last(), &caller.kind) {
+                (Some(&"Self"), FnKind::ImplMethod { self_ty, .. }) => Some(self_ty.as_str()),
+                (Some(s), _) if s.starts_with(|c: char| c.is_uppercase()) => Some(*s),
+                _ => None,
+            };
+            const_fns.iter().any(|f| f.name == name && match (&f.kind, owner) {
+                (FnKind::ImplMethod { self_ty, .. }, Some(o)) => self_ty == o,
+                (FnKind::FreeFn, None) => true,
+                _ => false,
+            })
+        };
+
+        for c in &self.calls {
+            let Some(enclosing) = c.enclosing_fn.as_deref() else { continue };
+            let ok = match &c.receiver {
+                Some(_) => CONST_METHODS.contains(&c.callee.as_str()) || CONST_METHOD_PREFIXES.iter().any(|p| c.callee.starts_with(p)),
+                None => self.fn_by_name(enclosing).is_some_and(|caller| callee_is_const(&c.callee, caller)),
+            };
+            if !ok { blocked_fns.insert(enclosing); }
+        }
+        for m in &self.macros_inv {
+            if let Some(f) = m.enclosing_fn.as_deref()
+                && !CONST_MACROS.contains(&m.name.as_str())
+            {
+                blocked_fns.insert(f);
+            }
+        }
+        self.functions.iter()
+            .filter(|f| !f.is_const && !f.is_async && !f.is_test_code && f.name != "main")
+            .filter(|f| matches!(f.kind, FnKind::FreeFn | FnKind::ImplMethod { trait_ty: None, .. }))
+            // `extern "C" fn`s and proc-macro entry points stay as they are
+            .filter(|f| !f.signature.contains("extern") && !f.attrs.iter().any(|a| a.contains("proc_macro")))
+            .filter(|f| !blocked_fns.contains(f.fq_name.as_str()))
+            .collect()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::extract::file_module_path;
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CoverageCounts {
+    pub total: usize,
+    pub documented: usize,
+}
+
+impl CoverageCounts {
+    fn add(&mut self, documented: bool) {
+        self.total += 1;
+        if documented { self.documented += 1; }
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 { 100.0 } else { (self.documented as f64) * 100.0 / (self.total as f64) }
+    }
+}
+
+/// Public item doc coverage split by item kind.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KindCoverage {
+    pub fns: CoverageCounts,
+    pub types: CoverageCounts, // structs, enums, unions, type aliases
+    pub traits: CoverageCounts,
+}
+
+impl KindCoverage {
+    pub fn all(&self) -> CoverageCounts {
+        CoverageCounts {
+            total: self.fns.total + self.types.total + self.traits.total,
+            documented: self.fns.documented + self.types.documented + self.traits.documented,
+        }
+    }
+}
+
+/// Public doc coverage overall, per module (`crate::a::b`) and per file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DocCoverageReport {
+    pub overall: KindCoverage,
+    pub by_module: BTreeMap<String, KindCoverage>,
+    #[serde(with = "crate::intern::portable_keys")]
+    #[cfg_attr(feature = "schema", schemars(with = "BTreeMap<PathBuf, KindCoverage>"))]
+    pub by_file: BTreeMap<PathBuf, KindCoverage>,
+}
+
+/// `crate::a::b` for an item at `module_path` inside the file at `path`.
+pub fn module_key(path: &Path, module_path: &[IStr]) -> String {
+    let mut parts = vec!["crate".to_string()];
+    parts.extend(file_module_path(path));
+    parts.extend(module_path.iter().map(|s| s.to_string()));
+    parts.join("::")
+}
+
+impl DocCoverageReport {
+    pub fn from_klepto(k: &Klepto) -> Self {
+        let mut r = DocCoverageReport::default();
+
+        let mut record = |loc: &FileLocation, module_path: &[IStr], pick: fn(&mut KindCoverage) -> &mut CoverageCounts, documented: bool| {
+            pick(&mut r.overall).add(documented);
+            pick(r.by_module.entry(module_key(&loc.path, module_path)).or_default()).add(documented);
+            pick(r.by_file.entry(loc.path.to_path_buf()).or_default()).add(documented);
+        };
+
+        let vis = &k.effective_visibility;
+        for f in k.functions.iter().filter(|f| vis.fn_public(f)) {
+            record(&f.location, &f.module_path, |c| &mut c.fns, f.has_docs);
+        }
+        for t in k.types.iter().filter(|t| vis.type_public(t)) {
+            if t.kind == TypeKind::Trait {
+                record(&t.location, &t.module_path, |c| &mut c.traits, t.has_docs);
+            } else {
+                record(&t.location, &t.module_path, |c| &mut c.types, t.has_docs);
+            }
+        }
+        r
+    }
+
+    pub fn to_json(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        fn row(name: &str, c: &KindCoverage) -> String {
+            let cell = |x: &CoverageCounts| format!("{}/{}", x.documented, x.total);
+            format!(
+                "| {} | {:.1}% | {} | {} | {} |\n",
+                name, c.all().percent(), cell(&c.fns), cell(&c.types), cell(&c.traits)
+            )
+        }
+        const HEADER: &str = "| Fns | Types | Traits |\n|---|---:|---:|---:|---:|\n";
+
+        let mut s = String::new();
+        s.push_str("# Doc Coverage\n\n");
+        s.push_str(&format!("| Scope | Coverage {}", HEADER));
+        s.push_str(&row("**overall**", &self.overall));
+
+        s.push_str(&format!("\n## By module\n\n| Module | Coverage {}", HEADER));
+        for (m, c) in &self.by_module {
+            s.push_str(&row(&format!("`{}`", m), c));
+        }
+
+        s.push_str(&format!("\n## By file\n\n| File | Coverage {}", HEADER));
+        for (p, c) in &self.by_file {
+            s.push_str(&row(&format!("`{}`", p.display()), c));
+        }
+        s
+    }
+}
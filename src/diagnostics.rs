@@ -0,0 +1,90 @@
+//! Compiler and clippy diagnostics as findings, so one report and one baseline
+//! cover both (`cargo clippy --message-format=json > clippy.json`).
+//!
+//! Codes are kept as the compiler reports them (`clippy::unwrap_used`,
+//! `unused_imports`, `E0308`), so `[rules]` in `klepto.toml` can set their level
+//! like any klepto rule's; errors without a code report under `rustc`.
+
+use crate::klepto::KleptoError;
+use crate::model::*;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Code of diagnostics that carry none (mostly hard errors).
+pub const UNCODED: &str = "rustc";
+
+fn severity(level: &str) -> Severity {
+    match level {
+        "warning" => Severity::Warn,
+        l if l.starts_with("error") => Severity::Deny,
+        _ => Severity::Info,
+    }
+}
+
+/// Findings from a diagnostics stream, one JSON object per line: cargo's
+/// `--message-format=json` output or bare rustc `--error-format=json` lines.
+/// Cargo's other messages, non-JSON lines and span-less summaries ("N warnings
+/// emitted") are skipped, as are the duplicates cargo emits when a file is built
+/// for several targets. Relative file names are joined onto `base`, the
+/// directory cargo ran in relative to the scan's `workspace_root` (`""` when
+/// cargo ran there), so they match the scan's locations.
+pub fn from_cargo_json(text: &str, base: impl AsRef<Path>) -> Result<Vec<Finding>, KleptoError> {
+    let base = base.as_ref();
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| l.starts_with('{')) {
+        let v: Value = serde_json::from_str(line).map_err(KleptoError::DiagnosticsFormat)?;
+        let msg = match v.get("reason").and_then(Value::as_str) {
+            Some("compiler-message") => match v.get("message") { Some(m) => m, None => continue },
+            Some(_) => continue,
+            None => &v,
+        };
+        if let Some(f) = finding(msg, base)
+            && seen.insert((f.code.clone(), f.location.path.clone(), f.location.line, f.location.column, f.message.clone()))
+        {
+            out.push(f);
+        }
+    }
+    Ok(out)
+}
+
+/// `from_cargo_json` on a file.
+pub fn from_cargo_json_file(path: impl AsRef<Path>, base: impl AsRef<Path>) -> Result<Vec<Finding>, KleptoError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+    from_cargo_json(&text, base)
+}
+
+fn finding(msg: &Value, base: &Path) -> Option<Finding> {
+    let level = msg.get("level")?.as_str()?;
+    let message = msg.get("message")?.as_str()?;
+    let spans = msg.get("spans")?.as_array()?;
+    let span = spans.iter().find(|s| s.get("is_primary").and_then(Value::as_bool) == Some(true))?;
+
+    let file = Path::new(span.get("file_name")?.as_str()?);
+    let file = if file.is_relative() { base.join(file) } else { file.to_path_buf() };
+    let num = |k: &str| span.get(k).and_then(Value::as_u64);
+    let byte_range = num("byte_start").zip(num("byte_end")).map(|(s, e)| s as usize..e as usize);
+    let location = FileLocation {
+        path: file.into(),
+        line: num("line_start").map(|n| n as u32),
+        // rustc counts columns from 1, klepto (like proc-macro2) from 0
+        column: num("column_start").map(|n| n.saturating_sub(1) as u32),
+        byte_range,
+    };
+
+    let code = msg.get("code").and_then(|c| c.get("code")).and_then(Value::as_str);
+    let source = if code.is_some_and(|c| c.starts_with("clippy::")) { "clippy" } else { "rustc" };
+    Some(Finding {
+        severity: severity(level),
+        code: RuleCode::new(code.unwrap_or(UNCODED)),
+        message: message.to_string(),
+        location,
+        extra: json!({
+            "source": source,
+            "level": level,
+            "rendered": msg.get("rendered").and_then(Value::as_str),
+        }),
+    })
+}
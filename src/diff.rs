@@ -0,0 +1,83 @@
+use crate::coverage::{CoverageCounts, DocCoverageReport};
+use crate::imports_ext::ImportVecExt;
+use crate::klepto::Klepto;
+use crate::model::*;
+use crate::snapshot::SnapshotDiff;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Occurrence count of one key before and after.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CountChange {
+    pub old: usize,
+    pub new: usize,
+}
+
+impl CountChange {
+    pub fn delta(&self) -> isize { self.new as isize - self.old as isize }
+}
+
+/// Before/after comparison of two live analyses (e.g. pre/post refactor).
+///
+/// `api` is the same diff `Snapshot::diff` produces; the count maps only hold
+/// keys whose count changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KleptoDiff {
+    pub api: SnapshotDiff,
+    pub paths: BTreeMap<String, CountChange>,   // by full path
+    pub calls: BTreeMap<String, CountChange>,   // by callee
+    pub macros: BTreeMap<String, CountChange>,  // by macro name
+    pub imports_by_origin: BTreeMap<ImportOrigin, CountChange>,
+    pub doc_coverage: (CoverageCounts, CoverageCounts), // (old,new), all item kinds
+}
+
+impl KleptoDiff {
+    /// `new` compared against `old`.
+    pub fn between(old: &Klepto, new: &Klepto) -> Self {
+        let paths = count_changes(old.paths.iter().map(|p| p.path.as_str()), new.paths.iter().map(|p| p.path.as_str()));
+        let calls = count_changes(old.calls.iter().map(|c| c.callee.as_str()), new.calls.iter().map(|c| c.callee.as_str()));
+        let macros = count_changes(old.macros_inv.iter().map(|m| m.name.as_str()), new.macros_inv.iter().map(|m| m.name.as_str()));
+
+        let mut imports_by_origin: BTreeMap<ImportOrigin, CountChange> = BTreeMap::new();
+        for (o, n) in old.imports.summary().by_origin {
+            imports_by_origin.entry(o).or_default().old = n;
+        }
+        for (o, n) in new.imports.summary().by_origin {
+            imports_by_origin.entry(o).or_default().new = n;
+        }
+        imports_by_origin.retain(|_, c| c.old != c.new);
+
+        KleptoDiff {
+            api: new.snapshot().diff(&old.snapshot()),
+            paths,
+            calls,
+            macros,
+            imports_by_origin,
+            doc_coverage: (
+                DocCoverageReport::from_klepto(old).overall.all(),
+                DocCoverageReport::from_klepto(new).overall.all(),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.api.is_empty()
+            && self.paths.is_empty()
+            && self.calls.is_empty()
+            && self.macros.is_empty()
+            && self.imports_by_origin.is_empty()
+            && self.doc_coverage.0 == self.doc_coverage.1
+    }
+}
+
+fn count_changes<'a>(old: impl Iterator<Item = &'a str>, new: impl Iterator<Item = &'a str>) -> BTreeMap<String, CountChange> {
+    let mut m: BTreeMap<&str, CountChange> = BTreeMap::new();
+    for k in old { m.entry(k).or_default().old += 1; }
+    for k in new { m.entry(k).or_default().new += 1; }
+    m.into_iter()
+        .filter(|(_, c)| c.old != c.new)
+        .map(|(k, c)| (k.to_string(), c))
+        .collect()
+}
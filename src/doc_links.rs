@@ -0,0 +1,218 @@
+//! Intra-doc links (`[`Type`]`, `[text](crate::a::f)`) in captured doc text,
+//! checked against the scanned items without a rustdoc build.
+
+use crate::klepto::Klepto;
+use crate::model::{FileLocation, FnKind, UseKind};
+use crate::visibility::full_module_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// An intra-doc link whose target isn't a captured item, import or dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BrokenDocLink {
+    /// fq_name of the documented function or type.
+    pub item: String,
+    /// The target as written, backticks and disambiguators stripped.
+    pub link: String,
+    pub location: FileLocation,
+}
+
+// the std prelude, plus `std`'s macros that are linked to by name
+const PRELUDE: &[&str] = &[
+    "Option", "Some", "None", "Result", "Ok", "Err", "Vec", "String", "Box", "ToString", "ToOwned",
+    "Clone", "Copy", "Send", "Sync", "Sized", "Unpin", "Drop", "Fn", "FnMut", "FnOnce", "Default",
+    "Iterator", "IntoIterator", "Extend", "DoubleEndedIterator", "ExactSizeIterator", "FromIterator",
+    "Eq", "PartialEq", "Ord", "PartialOrd", "Hash", "Debug", "AsRef", "AsMut", "Into", "From",
+    "TryFrom", "TryInto", "println", "print", "eprintln", "eprint", "format", "write", "writeln",
+    "vec", "panic", "assert", "assert_eq", "assert_ne", "debug_assert", "todo", "unimplemented",
+    "unreachable", "matches", "dbg",
+];
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f32", "f64", "slice", "array", "tuple", "unit", "never", "pointer", "reference",
+];
+
+// what a module declares: item names, child modules, and whether a glob import may add more
+#[derive(Default)]
+struct Scope<'a> {
+    names: HashSet<&'a str>,
+    children: HashSet<&'a str>,
+    globbed: bool,
+}
+
+impl Klepto {
+    /// Intra-doc links in the docs of non-test functions and types that don't
+    /// resolve. A link resolves when it names an item or import of the
+    /// documented item's module, walks `crate::`/`self::`/`super::` to one,
+    /// starts at `Self` in a method or type, or starts at std, a dependency or
+    /// a prelude name. Anything after a type (methods, variants, fields) isn't
+    /// checked, and names a glob import might bring in count as resolved.
+    pub fn broken_doc_links(&self) -> Vec<BrokenDocLink> {
+        let mut scopes: HashMap<Vec<String>, Scope> = HashMap::new();
+        for pf in &self.files {
+            scopes.entry(crate::extract::file_module_path(&pf.path)).or_default();
+        }
+        for m in &self.modules {
+            let parent = full_module_path(&m.location.path, &m.module_path);
+            let mut full = parent.clone();
+            full.push(m.name.clone());
+            scopes.entry(full).or_default();
+            scopes.entry(parent).or_default().children.insert(&m.name);
+        }
+        for f in self.functions.iter().filter(|f| f.kind == FnKind::FreeFn) {
+            scopes.entry(full_module_path(&f.location.path, &f.module_path)).or_default().names.insert(&f.name);
+        }
+        for t in &self.types {
+            scopes.entry(full_module_path(&t.location.path, &t.module_path)).or_default().names.insert(&t.name);
+        }
+        for i in &self.imports {
+            let scope = scopes.entry(full_module_path(&i.location.path, &i.module_path)).or_default();
+            match &i.kind {
+                UseKind::Glob => scope.globbed = true,
+                UseKind::Rename { alias } => { scope.names.insert(alias); }
+                UseKind::Name => {
+                    // `use a::b::{self}` binds `b`
+                    let mut segs = i.full_path.rsplit("::");
+                    let name = segs.next().filter(|s| *s != "self").or_else(|| segs.next());
+                    scope.names.extend(name);
+                }
+            }
+        }
+        let macros: HashSet<&str> = self.macros_def.iter().map(|m| m.name.as_str()).collect();
+
+        let documented = self.functions.iter()
+            .filter(|f| !f.is_test_code)
+            .map(|f| (&f.fq_name, &f.doc, &f.location, &f.module_path, f.kind != FnKind::FreeFn))
+            .chain(self.types.iter().filter(|t| !t.is_test_code).map(|t| (&t.fq_name, &t.doc, &t.location, &t.module_path, true)));
+
+        let mut out = Vec::new();
+        for (item, doc, location, module_path, has_self) in documented {
+            let Some(doc) = doc else { continue };
+            let module = full_module_path(&location.path, module_path);
+            for link in doc_link_targets(doc) {
+                if !self.link_resolves(&link, &module, has_self, &scopes, &macros) {
+                    out.push(BrokenDocLink { item: item.clone(), link, location: location.clone() });
+                }
+            }
+        }
+        out
+    }
+
+    fn link_resolves(&self, link: &str, module: &[String], has_self: bool, scopes: &HashMap<Vec<String>, Scope>, macros: &HashSet<&str>) -> bool {
+        if link.starts_with("::") { return true; }
+        let segs: Vec<&str> = link.split("::").collect();
+        let first = segs[0];
+        let (mut cur, rest) = match first {
+            "crate" => (Vec::new(), &segs[1..]),
+            "self" => (module.to_vec(), &segs[1..]),
+            "super" => {
+                let ups = segs.iter().take_while(|s| **s == "super").count();
+                if ups > module.len() { return false; }
+                (module[..module.len() - ups].to_vec(), &segs[ups..])
+            }
+            "Self" => return has_self,
+            _ if first == self.crate_name.replace('-', "_") => (Vec::new(), &segs[1..]),
+            _ if segs.len() == 1 && macros.contains(first) => return true,
+            _ if matches!(first, "std" | "core" | "alloc" | "proc_macro")
+                || PRIMITIVES.contains(&first)
+                || PRELUDE.contains(&first)
+                || self.normal_dependencies.contains(first)
+                || self.dependency_renames.contains_key(first) => return true,
+            _ => (module.to_vec(), &segs[..]),
+        };
+
+        for s in rest {
+            let Some(scope) = scopes.get(&cur) else { return false };
+            if scope.children.contains(s) {
+                cur.push(s.to_string());
+            } else {
+                // past a type or import, the rest names members this can't check
+                return scope.names.contains(s) || scope.globbed;
+            }
+        }
+        true
+    }
+}
+
+/// Targets of the intra-doc links in `doc`: `[`X`]`, `[text](X)`, and
+/// reference links whose definition is a path. Fenced code blocks and URLs
+/// are skipped; bare `[X]` is left alone, since plain brackets are common prose.
+pub fn doc_link_targets(doc: &str) -> Vec<String> {
+    let mut refs: HashMap<&str, &str> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut fenced = false;
+    for line in doc.lines() {
+        let t = line.trim_start();
+        if t.starts_with("```") || t.starts_with("~~~") {
+            fenced = !fenced;
+            continue;
+        }
+        if fenced { continue; }
+        // `[label]: target`
+        if let Some(def) = t.strip_prefix('[')
+            && let Some((label, target)) = def.split_once("]:")
+        {
+            refs.insert(label, target.split_whitespace().next().unwrap_or(""));
+            continue;
+        }
+        lines.push(line);
+    }
+
+    let mut out = Vec::new();
+    for line in lines {
+        let b = line.as_bytes();
+        let mut i = 0;
+        while i < b.len() {
+            match b[i] {
+                // a code span: brackets inside it aren't links
+                b'`' => {
+                    let run = b[i..].iter().take_while(|c| **c == b'`').count();
+                    let fence = &line[i..i + run];
+                    i = line[i + run..].find(fence).map_or(b.len(), |at| i + run + at + run);
+                }
+                b'[' => {
+                    let Some(close) = line[i + 1..].find(']').map(|at| i + 1 + at) else { break };
+                    let text = &line[i + 1..close];
+                    let after = &line[close + 1..];
+                    let (target, end) = if let Some(rest) = after.strip_prefix('(') {
+                        match rest.find(')') {
+                            Some(at) => (rest[..at].split_whitespace().next(), close + 2 + at + 1),
+                            None => (None, close + 1),
+                        }
+                    } else if let Some(rest) = after.strip_prefix('[') {
+                        match rest.find(']') {
+                            Some(at) => {
+                                let label = if at == 0 { text } else { &rest[..at] };
+                                (refs.get(label).copied(), close + 2 + at + 1)
+                            }
+                            None => (None, close + 1),
+                        }
+                    } else {
+                        let quoted = text.len() > 2 && text.starts_with('`') && text.ends_with('`');
+                        (refs.get(text).copied().or(quoted.then_some(text)), close + 1)
+                    };
+                    out.extend(target.and_then(link_path));
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+    out
+}
+
+// the path an intra-doc link target names, or None for URLs and non-paths
+fn link_path(target: &str) -> Option<String> {
+    let t = target.trim_matches(|c| c == '<' || c == '>').trim_matches('`');
+    if t.is_empty() || t.contains("://") || t.contains('/') || t.starts_with('#') { return None; }
+    let t = t.split('#').next().unwrap_or(t);
+    // `struct@Foo`, `fn@foo`, `foo()`, `foo!`, `Vec<T>`
+    let t = t.split_once('@').map_or(t, |(_, p)| p);
+    let t = t.strip_suffix("()").or_else(|| t.strip_suffix('!')).unwrap_or(t);
+    let t = t.split('<').next().unwrap_or(t);
+    let body = t.strip_prefix("::").unwrap_or(t);
+    let ident = |s: &str| !s.is_empty() && !s.starts_with(|c: char| c.is_ascii_digit()) && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+    body.split("::").all(ident).then(|| t.to_string())
+}
@@ -0,0 +1,157 @@
+//! A one-line textual form of `FnQuery` / `ImportQuery`, for the CLI and scripts.
+//!
+//! ```text
+//! fn pub returns:Result in:crate::api
+//! fn async !pub uses:tokio name:handle
+//! import deps root:serde
+//! ```
+//!
+//! The first word picks the item kind; the rest are flags (`pub`, `async`, prefix
+//! `!` to negate where it makes sense) and `key:value` filters, all ANDed.
+//!
+//! - `fn` flags: `pub`, `undocumented`, `async`, `unsafe`, `const`, `generic`;
+//!   filters: `name:`, `re:`, `returns:`, `arg:`, `impl:`, `trait:`, `in-trait:`,
+//!   `attr:`, `uses:`, `in:`
+//! - `import` flags: `pub`, `internal`, `std`, `deps`, `workspace`;
+//!   filters: `root:`, `prefix:`
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{CapturedFn, FileLocation, StolenPath};
+use crate::query::{FnQuery, ImportQuery};
+
+/// A parsed query, ready to `collect`.
+pub enum Query<'k> {
+    Fns(FnQuery<'k>),
+    Imports(ImportQuery<'k>),
+}
+
+/// What a `Query` matched.
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "items", rename_all = "snake_case")]
+pub enum QueryResults {
+    Fns(Vec<CapturedFn>),
+    Imports(Vec<StolenPath>),
+}
+
+impl QueryResults {
+    pub fn len(&self) -> usize {
+        match self {
+            QueryResults::Fns(v) => v.len(),
+            QueryResults::Imports(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Fixed-width table: name, location and signature (fns) or origin (imports).
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        match self {
+            QueryResults::Fns(fns) => {
+                out.push_str("FUNCTION                                 LOCATION                         SIGNATURE\n");
+                out.push_str("---------------------------------------- -------------------------------  ------------------------------\n");
+                for f in fns {
+                    out.push_str(&format!("{:<40} {:<31}  {}\n", f.fq_name, loc(&f.location), f.signature));
+                }
+            }
+            QueryResults::Imports(imps) => {
+                out.push_str("IMPORT                                   ORIGIN          LOCATION\n");
+                out.push_str("---------------------------------------- --------------- -------------------------------\n");
+                for i in imps {
+                    let origin = i.origin.as_ref().map(|o| format!("{:?}", o)).unwrap_or_else(|| "-".into());
+                    out.push_str(&format!("{:<40} {:<15} {}\n", i.full_path, origin, loc(&i.location)));
+                }
+            }
+        }
+        out
+    }
+
+    /// `{"kind": "fns"|"imports", "items": [...]}`, pretty-printed.
+    pub fn to_json_string(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+}
+
+fn loc(l: &FileLocation) -> String {
+    match l.line {
+        Some(line) => format!("{}:{}", l.path.display(), line),
+        None => l.path.display().to_string(),
+    }
+}
+
+impl Query<'_> {
+    pub fn collect(self) -> QueryResults {
+        match self {
+            Query::Fns(q) => QueryResults::Fns(q.collect()),
+            Query::Imports(q) => QueryResults::Imports(q.collect()),
+        }
+    }
+}
+
+fn bad(msg: impl Into<String>) -> KleptoError { KleptoError::Query(msg.into()) }
+
+fn fn_query<'k>(mut q: FnQuery<'k>, words: &[&str]) -> Result<FnQuery<'k>, KleptoError> {
+    for w in words {
+        if let Some((key, value)) = w.split_once(':') {
+            if value.is_empty() { return Err(bad(format!("`{}` needs a value", key))); }
+            q = match key {
+                "name" => q.name_contains(value),
+                "re" => q.name_matches(value)?,
+                "returns" => q.returns(value),
+                "arg" => q.takes_arg(value),
+                "impl" => q.in_impl(value),
+                "trait" => q.impls_trait(value),
+                "in-trait" => q.in_trait(value),
+                "attr" => q.has_attr(value),
+                "uses" => q.uses_dep(value),
+                "in" => q.in_module(value),
+                _ => return Err(bad(format!("unknown fn filter `{}:`", key))),
+            };
+            continue;
+        }
+        let (yes, flag) = match w.strip_prefix('!') { Some(f) => (false, f), None => (true, *w) };
+        q = match flag {
+            "pub" if yes => q.public_only(),
+            "undocumented" if yes => q.no_docs(),
+            "async" => q.is_async(yes),
+            "unsafe" => q.is_unsafe(yes),
+            "const" => q.is_const(yes),
+            "generic" => q.is_generic(yes),
+            _ => return Err(bad(format!("unknown fn flag `{}`", w))),
+        };
+    }
+    Ok(q)
+}
+
+fn import_query<'k>(mut q: ImportQuery<'k>, words: &[&str]) -> Result<ImportQuery<'k>, KleptoError> {
+    for w in words {
+        q = match w.split_once(':') {
+            Some(("root", v)) if !v.is_empty() => q.root(v),
+            Some(("prefix", v)) if !v.is_empty() => q.full_path_starts_with(v),
+            Some((key, _)) => return Err(bad(format!("unknown or empty import filter `{}:`", key))),
+            None => match *w {
+                "pub" => q.public_use_only(),
+                "internal" => q.internal_only(),
+                "std" => q.stdish_only(),
+                "deps" => q.deps_only(),
+                "workspace" => q.workspace_only(),
+                _ => return Err(bad(format!("unknown import flag `{}`", w))),
+            },
+        };
+    }
+    Ok(q)
+}
+
+impl Klepto {
+    /// Parses a textual query (see the `dsl` module docs) against this scan.
+    pub fn query(&self, src: &str) -> Result<Query<'_>, KleptoError> {
+        let words: Vec<&str> = src.split_whitespace().collect();
+        match words.split_first() {
+            Some((&"fn", rest)) => Ok(Query::Fns(fn_query(self.functions(), rest)?)),
+            Some((&"import", rest)) => Ok(Query::Imports(import_query(self.imports(), rest)?)),
+            Some((kind, _)) => Err(bad(format!("unknown item kind `{}` (expected `fn` or `import`)", kind))),
+            None => Err(bad("empty query")),
+        }
+    }
+}
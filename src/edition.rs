@@ -0,0 +1,98 @@
+//! Idioms a file's edition retired: `extern crate` from 2018, bare trait
+//! objects from 2021.
+
+use crate::extract::tidy_tokens;
+use crate::klepto::Klepto;
+use crate::model::{Edition, FileLocation, TypeKind};
+use crate::scope::span_to_location;
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EditionIdiomKind {
+    /// Not needed since 2018: Cargo passes dependencies to rustc. Sysroot
+    /// crates (`alloc`, `proc_macro`, ..) and `extern crate self` are left alone.
+    ExternCrate { name: String },
+    /// A trait used as a type without `dyn`; an error from 2021.
+    BareTraitObject { ty: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EditionIdiom {
+    pub kind: EditionIdiomKind,
+    /// The file's edition.
+    pub edition: Edition,
+    pub location: FileLocation,
+}
+
+struct Walker<'a> {
+    file: &'a Path,
+    edition: Edition,
+    traits: &'a HashSet<&'a str>,
+    out: &'a mut Vec<EditionIdiom>,
+}
+
+impl Walker<'_> {
+    fn hit(&mut self, kind: EditionIdiomKind, span: proc_macro2::Span) {
+        self.out.push(EditionIdiom { kind, edition: self.edition, location: span_to_location(self.file, span) });
+    }
+}
+
+impl<'ast> Visit<'ast> for Walker<'_> {
+    fn visit_item_extern_crate(&mut self, i: &'ast syn::ItemExternCrate) {
+        let name = i.ident.to_string();
+        if self.edition >= Edition::E2018 && !matches!(name.as_str(), "self" | "std" | "core" | "alloc" | "proc_macro" | "test") {
+            self.hit(EditionIdiomKind::ExternCrate { name }, i.span());
+        }
+        syn::visit::visit_item_extern_crate(self, i);
+    }
+
+    // bounds and `impl Trait for` name traits through paths, not types, so a
+    // trait reached here is used as a type
+    fn visit_type(&mut self, t: &'ast syn::Type) {
+        if self.edition >= Edition::E2021 {
+            let bare = match t {
+                syn::Type::TraitObject(o) => o.dyn_token.is_none(),
+                syn::Type::Path(p) => p.qself.is_none() && p.path.segments.last().is_some_and(|s| self.traits.contains(s.ident.to_string().as_str())),
+                _ => false,
+            };
+            if bare {
+                self.hit(EditionIdiomKind::BareTraitObject { ty: tidy_tokens(&t.to_token_stream().to_string()) }, t.span());
+            }
+        }
+        syn::visit::visit_type(self, t);
+    }
+}
+
+impl Klepto {
+    /// Edition of a file, by its stored path; None when the file isn't in the
+    /// scan or its edition isn't known.
+    pub fn edition_of(&self, path: &Path) -> Option<Edition> {
+        self.files.iter().find(|pf| pf.path == path).and_then(|pf| pf.edition)
+    }
+
+    /// `extern crate` in 2018+ files and bare trait objects in 2021+ files.
+    /// Bare trait objects are only recognized for traits the scan captured (or
+    /// `Trait + Send`-style sums); files without an edition are skipped.
+    pub fn edition_idioms(&self) -> Vec<EditionIdiom> {
+        // a name that's also a struct or alias somewhere is more likely that
+        let others: HashSet<&str> = self.types.iter().filter(|t| t.kind != TypeKind::Trait).map(|t| t.name.as_str()).collect();
+        let traits: HashSet<&str> = self.types.iter()
+            .filter(|t| t.kind == TypeKind::Trait && !others.contains(t.name.as_str()))
+            .map(|t| t.name.as_str())
+            .collect();
+
+        let mut out = Vec::new();
+        for pf in &self.files {
+            let Some(edition) = pf.edition else { continue };
+            Walker { file: &pf.path, edition, traits: &traits, out: &mut out }.visit_file(&pf.ast());
+        }
+        out
+    }
+}
@@ -0,0 +1,77 @@
+//! Which public functions `examples/` and `benches/` exercise, for the scans
+//! that include them (`include_examples`, `include_benches`).
+//!
+//! Like `test_map`, but by file: every `main` shares one fq_name, so an example
+//! exercises the library functions called or named anywhere in its file.
+
+use crate::intern::FilePath;
+use crate::klepto::Klepto;
+use crate::model::{CapturedFn, TargetKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// One example or bench file and what it exercises.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExampleTarget {
+    pub path: FilePath,
+    /// `Example` or `Bench`.
+    pub kind: TargetKind,
+    /// fq_names of the library functions it reaches.
+    pub exercises: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExampleCoverage {
+    pub targets: Vec<ExampleTarget>,
+    /// Public library functions no example exercises; empty when the scan has no examples.
+    pub without_examples: Vec<String>,
+    /// Same for benches.
+    pub without_benches: Vec<String>,
+}
+
+impl ExampleCoverage {
+    /// Example and bench files exercising `fq_name`.
+    pub fn exercised_by(&self, fq_name: &str) -> impl Iterator<Item = &ExampleTarget> {
+        self.targets.iter().filter(move |t| t.exercises.contains(fq_name))
+    }
+}
+
+fn example_or_bench_file(path: &Path) -> Option<TargetKind> {
+    Some(TargetKind::of_path(path)).filter(|t| matches!(t, TargetKind::Example | TargetKind::Bench))
+}
+
+fn example_or_bench(f: &CapturedFn) -> Option<TargetKind> { example_or_bench_file(&f.location.path) }
+
+impl Klepto {
+    /// Public library API exercised (or not) by examples and benches.
+    pub fn example_coverage(&self) -> ExampleCoverage {
+        let graph = self.call_graph();
+        let mut by_file: BTreeMap<FilePath, ExampleTarget> = BTreeMap::new();
+        for e in graph.edges() {
+            let Some(kind) = example_or_bench_file(&e.location.path) else { continue; };
+            if self.fn_by_name(&e.callee).is_none_or(|f| example_or_bench(f).is_some()) { continue; }
+            by_file.entry(e.location.path.clone())
+                .or_insert_with(|| ExampleTarget { path: e.location.path.clone(), kind, exercises: BTreeSet::new() })
+                .exercises.insert(e.callee.clone());
+        }
+        let targets: Vec<ExampleTarget> = by_file.into_values().collect();
+
+        let api: Vec<&CapturedFn> = self.functions.iter()
+            .filter(|f| !f.is_test_code && TargetKind::of_path(&f.location.path) == TargetKind::Lib && self.effective_visibility.fn_public(f))
+            .collect();
+        let without = |kind: TargetKind| -> Vec<String> {
+            let of_kind: Vec<&ExampleTarget> = targets.iter().filter(|t| t.kind == kind).collect();
+            if of_kind.is_empty() { return Vec::new(); }
+            api.iter()
+                .filter(|f| !of_kind.iter().any(|t| t.exercises.contains(f.fq_name.as_str())))
+                .map(|f| f.fq_name.to_string())
+                .collect()
+        };
+        let without_examples = without(TargetKind::Example);
+        let without_benches = without(TargetKind::Bench);
+        ExampleCoverage { targets, without_examples, without_benches }
+    }
+}
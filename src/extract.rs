@@ -1,35 +1,87 @@
 use crate::model::*;
 use proc_macro2::Span;
 use quote::ToTokens;
+use crate::scope::{FnInfo, Scope, path_to_string, span_to_location, vis_is_public};
 use syn::{Attribute, File, Item, Visibility, spanned::Spanned, visit::Visit};
 
-fn span_to_location(path: &std::path::Path, span: Span) -> FileLocation {
-    #[cfg(feature = "span-locations")]
-    {
-        let start = span.start();
-        return FileLocation {
-            path: path.to_path_buf(),
-            line: Some(start.line as u32),
-            column: Some(start.column as u32),
-        };
-    }
-    #[cfg(not(feature = "span-locations"))]
-    {
-        let _ = span;
-        FileLocation {
-            path: path.to_path_buf(),
-            line: None,
-            column: None,
+// values of `#[doc = ..]`: string literals, `include_str!(..)` and other macros,
+// also inside `#[cfg_attr(pred, doc = ..)]`
+fn doc_values(attrs: &[Attribute]) -> Vec<syn::Expr> {
+    let mut out = Vec::new();
+    for a in attrs {
+        match &a.meta {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("doc") => out.push(nv.value.clone()),
+            syn::Meta::List(l) if l.path.is_ident("cfg_attr") => {
+                let parsed = l.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+                let Ok(metas) = parsed else { continue; };
+                out.extend(metas.into_iter().skip(1).filter_map(|m| match m {
+                    syn::Meta::NameValue(nv) if nv.path.is_ident("doc") => Some(nv.value),
+                    _ => None,
+                }));
+            }
+            _ => {}
         }
     }
+    out
 }
 
-fn vis_is_public(vis: &Visibility) -> bool {
-    matches!(vis, Visibility::Public(_))
+// `#[doc(alias)]`, `#[doc(cfg(..))]` etc. aren't docs; `#[doc(hidden)]` items need none,
+// as with rustc's `missing_docs`
+fn has_docs(attrs: &[Attribute]) -> bool {
+    let hidden = attrs.iter().any(|a| match &a.meta {
+        syn::Meta::List(l) if l.path.is_ident("doc") => l.tokens.to_string() == "hidden",
+        _ => false,
+    });
+    hidden || !doc_values(attrs).is_empty()
 }
 
-fn has_docs(attrs: &[Attribute]) -> bool {
-    attrs.iter().any(|a| a.path().is_ident("doc"))
+// `/// text` lines (and `#[doc = "text"]`) joined with newlines; included files aren't read
+fn doc_text(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = doc_values(attrs)
+        .into_iter()
+        .filter_map(|v| match v {
+            // `/** .. */` keeps a CRLF file's line endings
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) => Some(s.value().replace("\r\n", "\n")),
+            _ => None,
+        })
+        .map(|l| l.strip_prefix(' ').unwrap_or(&l).to_string())
+        .collect();
+    if lines.is_empty() { None } else { Some(lines.join("\n")) }
+}
+
+// `#[cfg(test)]` (or `all(test, ..)`), `#[test]`, `#[tokio::test]`: only built for tests
+pub(crate) fn is_test_only(attrs: &[Attribute]) -> bool {
+    fn requires_test(m: &syn::Meta) -> bool {
+        match m {
+            syn::Meta::Path(p) => p.is_ident("test"),
+            syn::Meta::List(l) if l.path.is_ident("all") || l.path.is_ident("any") => {
+                let Ok(args) = l.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) else {
+                    return false;
+                };
+                if l.path.is_ident("all") {
+                    args.iter().any(requires_test)
+                } else {
+                    !args.is_empty() && args.iter().all(requires_test)
+                }
+            }
+            _ => false,
+        }
+    }
+    attrs.iter().any(|a| {
+        a.path().segments.last().is_some_and(|s| s.ident == "test")
+            || (a.path().is_ident("cfg") && a.parse_args::<syn::Meta>().is_ok_and(|m| requires_test(&m)))
+    })
+}
+
+fn derive_paths(attrs: &[Attribute]) -> Vec<String> {
+    let mut out = Vec::new();
+    for a in attrs.iter().filter(|a| a.path().is_ident("derive")) {
+        let parsed = a.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated);
+        if let Ok(paths) = parsed {
+            out.extend(paths.iter().map(|p| p.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>().join("::")));
+        }
+    }
+    out
 }
 
 fn attr_paths(attrs: &[Attribute]) -> Vec<String> {
@@ -46,50 +98,90 @@ fn attr_paths(attrs: &[Attribute]) -> Vec<String> {
         .collect()
 }
 
-fn path_to_string(p: &syn::Path) -> String {
-    p.segments
-        .iter()
-        .map(|s| s.ident.to_string())
-        .collect::<Vec<_>>()
-        .join("::")
-}
-
 fn type_to_string(t: &syn::Type) -> String {
     t.to_token_stream().to_string()
 }
 
-fn fn_args(sig: &syn::Signature) -> Vec<String> {
+/// `impl .. for X` target as (base, generic args): references and parens are
+/// stripped and a path keeps its last segment, so `&'a mut foo::Vec<T>` is
+/// ("Vec", ["T"]); tuples, slices and the like keep their tidied text.
+pub(crate) fn self_ty_parts(ty: &syn::Type) -> (String, Vec<String>) {
+    match ty {
+        syn::Type::Reference(r) => self_ty_parts(&r.elem),
+        syn::Type::Paren(p) => self_ty_parts(&p.elem),
+        syn::Type::Group(g) => self_ty_parts(&g.elem),
+        syn::Type::Path(tp) => match tp.path.segments.last() {
+            Some(seg) => (seg.ident.to_string(), generic_args(&seg.arguments)),
+            None => (tidy_tokens(&type_to_string(ty)), Vec::new()),
+        },
+        _ => (tidy_tokens(&type_to_string(ty)), Vec::new()),
+    }
+}
+
+// a path segment's `<..>` arguments, lifetimes left out
+pub(crate) fn generic_args(args: &syn::PathArguments) -> Vec<String> {
+    match args {
+        syn::PathArguments::AngleBracketed(a) => a.args.iter()
+            .filter(|a| !matches!(a, syn::GenericArgument::Lifetime(_)))
+            .map(|a| tidy_tokens(&a.to_token_stream().to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// token-stream text without the spaces around punctuation: `& 'a [u8 ; 4]` -> `&'a [u8; 4]`,
+// `fn f < 'a > (x : & str)` -> `fn f<'a>(x: &str)`
+pub(crate) fn tidy_tokens(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let prev = out.chars().last();
+            let before = out.chars().rev().nth(1);
+            let next = chars.peek().copied();
+            // `x: T` keeps its space, `a:: b` doesn't
+            let single_colon = prev == Some(':') && before != Some(':');
+            let generics_end = prev == Some('>') && before != Some('-');
+            let call = next == Some('(') && prev.is_some_and(|p| p.is_alphanumeric() || p == '_' || generics_end);
+            if call
+                || (prev.is_some_and(|p| "<([&:*!'".contains(p)) && !single_colon)
+                || next.is_some_and(|n| "<>)]:,;!".contains(n))
+            {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+// expression text as written: `self . inner` -> `self.inner`, `& * x` -> `&*x`
+pub(crate) fn expr_text(e: &syn::Expr) -> String {
+    let s = tidy_tokens(&e.to_token_stream().to_string()).replace(" . ", ".").replace(" ?", "?");
+    // indexing `a [0]` -> `a[0]`, but not `&mut [0]`
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.char_indices() {
+        let index = c == ' ' && s[i + 1..].starts_with('[') && !out.ends_with("mut")
+            && out.chars().last().is_some_and(|p| p.is_alphanumeric() || p == '_' || p == ')' || p == ']');
+        if !index { out.push(c); }
+    }
+    out
+}
+
+pub(crate) fn fn_args(sig: &syn::Signature) -> Vec<String> {
     sig.inputs
         .iter()
         .map(|i| i.to_token_stream().to_string())
         .collect()
 }
 
-fn fn_return(sig: &syn::Signature) -> Option<String> {
+pub(crate) fn fn_return(sig: &syn::Signature) -> Option<String> {
     match &sig.output {
         syn::ReturnType::Default => None,
         syn::ReturnType::Type(_, ty) => Some(ty.to_token_stream().to_string()),
     }
 }
 
-fn fq_name(crate_name: &str, module_path: &[String], kind: &FnKind, name: &str) -> String {
-    let mut parts = Vec::new();
-    parts.push(crate_name.to_string());
-    parts.extend(module_path.iter().cloned());
-    match kind {
-        FnKind::FreeFn => parts.push(name.to_string()),
-        FnKind::TraitMethod { trait_name } => {
-            parts.push(trait_name.clone());
-            parts.push(name.to_string());
-        }
-        FnKind::ImplMethod { self_ty, .. } => {
-            parts.push(self_ty.clone());
-            parts.push(name.to_string());
-        }
-    }
-    parts.join("::")
-}
-
 pub fn crate_is_no_std(ast: &File) -> bool {
     ast.attrs.iter().any(|a| a.path().is_ident("no_std"))
 }
@@ -123,8 +215,9 @@ pub fn extract_public_surface(file_path: &std::path::Path, ast: &File) -> Vec<Ex
                 out.push(ExportedSymbol {
                     exported_as,
                     source_path: src,
-                    module_path: module_path.to_vec(),
+                    module_path: module_path.into(),
                     location: span_to_location(file_path, n.span()),
+                    resolved_target: None,
                 });
             }
             syn::UseTree::Rename(r) => {
@@ -135,8 +228,9 @@ pub fn extract_public_surface(file_path: &std::path::Path, ast: &File) -> Vec<Ex
                 out.push(ExportedSymbol {
                     exported_as,
                     source_path: src,
-                    module_path: module_path.to_vec(),
+                    module_path: module_path.into(),
                     location: span_to_location(file_path, r.span()),
+                    resolved_target: None,
                 });
             }
             syn::UseTree::Glob(g) => {
@@ -145,11 +239,11 @@ pub fn extract_public_surface(file_path: &std::path::Path, ast: &File) -> Vec<Ex
                 out.push(ExportedSymbol {
                     exported_as: "*".to_string(),
                     source_path: segs.join("::"),
-                    module_path: module_path.to_vec(),
+                    module_path: module_path.into(),
                     location: span_to_location(file_path, g.span()),
+                    resolved_target: None,
                 });
             }
-            _ => {}
         }
     }
 
@@ -157,21 +251,21 @@ pub fn extract_public_surface(file_path: &std::path::Path, ast: &File) -> Vec<Ex
     fn walk_items(
         file_path: &std::path::Path,
         items: &[Item],
-        mod_stack: &mut Vec<String>,
+        scope: &mut Scope,
         out: &mut Vec<ExportedSymbol>,
     ) {
         for it in items {
             match it {
                 Item::Use(u) => {
                     if matches!(u.vis, Visibility::Public(_)) {
-                        walk(file_path, &u.tree, Vec::new(), mod_stack, out);
+                        walk(file_path, &u.tree, Vec::new(), scope.mod_stack(), out);
                     }
                 }
                 Item::Mod(m) => {
                     if let Some((_, items)) = &m.content {
-                        mod_stack.push(m.ident.to_string());
-                        walk_items(file_path, items, mod_stack, out);
-                        mod_stack.pop();
+                        let saved = scope.enter_mod(m);
+                        walk_items(file_path, items, scope, out);
+                        scope.leave(saved);
                     }
                 }
                 _ => {}
@@ -179,8 +273,7 @@ pub fn extract_public_surface(file_path: &std::path::Path, ast: &File) -> Vec<Ex
         }
     }
 
-    let mut ms = Vec::new();
-    walk_items(file_path, &ast.items, &mut ms, &mut out);
+    walk_items(file_path, &ast.items, &mut Scope::default(), &mut out);
     out
 }
 
@@ -262,7 +355,6 @@ pub fn extract_imports_v1(file_path: &std::path::Path, ast: &File) -> Vec<Stolen
                     out,
                 );
             }
-            _ => {}
         }
     }
 
@@ -276,32 +368,13 @@ pub fn extract_imports_v1(file_path: &std::path::Path, ast: &File) -> Vec<Stolen
     out
 }
 pub fn extract_imports(
+    crate_name: &str,
     file_path: &std::path::Path,
     ast: &syn::File,
 ) -> Vec<crate::model::StolenPath> {
-    use crate::model::{FileLocation, StolenPath, UseKind};
-
-    fn span_to_location(path: &std::path::Path, span: proc_macro2::Span) -> FileLocation {
-        #[cfg(feature = "span-locations")]
-        {
-            let start = span.start();
-            return FileLocation {
-                path: path.to_path_buf(),
-                line: Some(start.line as u32),
-                column: Some(start.column as u32),
-            };
-        }
-        #[cfg(not(feature = "span-locations"))]
-        {
-            let _ = span;
-            FileLocation {
-                path: path.to_path_buf(),
-                line: None,
-                column: None,
-            }
-        }
-    }
+    use crate::model::{StolenPath, UseKind};
 
+    #[allow(clippy::too_many_arguments)]
     fn emit(
         file_path: &std::path::Path,
         module_path: &[String],
@@ -334,7 +407,7 @@ pub fn extract_imports(
         out.push(StolenPath {
             root,
             segments: segs,
-            module_path: module_path.to_vec(),
+            module_path: module_path.into(),
             is_internal,
             is_public_use,
             kind,
@@ -342,6 +415,9 @@ pub fn extract_imports(
             location: span_to_location(file_path, span),
             origin: None,                   // classified later in KleptoBuilder::parse()
             is_absolute: Some(is_absolute), // tracked here
+            is_test_code: false,            // set by the visitor
+            enclosing_fn: None,             // ditto
+            expanded_from_glob: false,
         });
     }
 
@@ -446,39 +522,84 @@ pub fn extract_imports(
                     out,
                 );
             }
+        }
+    }
 
-            _ => {}
+    // `use` items anywhere, including function bodies
+    struct V<'a> {
+        file_path: &'a std::path::Path,
+        scope: Scope,
+        current_fn: Option<IStr>,
+        out: Vec<StolenPath>,
+    }
+
+    impl V<'_> {
+        fn in_fn(&mut self, info: FnInfo, attrs: &[Attribute], body: impl FnOnce(&mut Self)) {
+            let prev_fn = self.current_fn.replace(info.fq_name.into());
+            let saved = self.scope.enter_fn(attrs);
+            body(self);
+            self.scope.leave(saved);
+            self.current_fn = prev_fn;
         }
     }
 
-    fn walk_items(
-        file_path: &std::path::Path,
-        items: &[Item],
-        mod_stack: &mut Vec<String>,
-        out: &mut Vec<StolenPath>,
-    ) {
-        for item in items {
-            match item {
-                Item::Use(u) => {
-                    let is_pub = matches!(u.vis, Visibility::Public(_));
-                    let is_abs = u.leading_colon.is_some();
-                    walk_tree(file_path, mod_stack, &u.tree, Vec::new(), is_pub, is_abs, out);
-                }
-                Item::Mod(m) => {
-                    if let Some((_, inner)) = &m.content {
-                        mod_stack.push(m.ident.to_string());
-                        walk_items(file_path, inner, mod_stack, out);
-                        mod_stack.pop();
-                    }
+    impl<'ast> Visit<'ast> for V<'_> {
+        fn visit_item_use(&mut self, u: &'ast syn::ItemUse) {
+            let is_pub = matches!(u.vis, Visibility::Public(_));
+            let is_abs = u.leading_colon.is_some();
+            let start = self.out.len();
+            walk_tree(self.file_path, self.scope.mod_stack(), &u.tree, Vec::new(), is_pub, is_abs, &mut self.out);
+            let is_test_code = self.scope.in_test() || is_test_only(&u.attrs);
+            for i in &mut self.out[start..] {
+                i.is_test_code = is_test_code;
+                i.enclosing_fn = self.current_fn.clone();
+            }
+        }
+
+        fn visit_item_mod(&mut self, m: &'ast syn::ItemMod) {
+            if let Some((_, items)) = &m.content {
+                let saved = self.scope.enter_mod(m);
+                // a module in a fn body is a new scope
+                let prev_fn = self.current_fn.take();
+                for it in items {
+                    self.visit_item(it);
                 }
-                _ => {}
+                self.current_fn = prev_fn;
+                self.scope.leave(saved);
             }
         }
+
+        fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+            let saved = self.scope.enter_trait(i);
+            syn::visit::visit_item_trait(self, i);
+            self.scope.leave(saved);
+        }
+
+        fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+            let saved = self.scope.enter_impl(i);
+            syn::visit::visit_item_impl(self, i);
+            self.scope.leave(saved);
+        }
+
+        fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+            let info = self.scope.free_fn(&i.sig.ident.to_string(), &i.vis);
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_item_fn(v, i));
+        }
+
+        fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+            let info = self.scope.impl_fn(&i.sig.ident.to_string(), &i.vis);
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_impl_item_fn(v, i));
+        }
+
+        fn visit_trait_item_fn(&mut self, i: &'ast syn::TraitItemFn) {
+            let info = self.scope.trait_fn(&i.sig.ident.to_string());
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_trait_item_fn(v, i));
+        }
     }
 
-    let mut out = Vec::new();
-    let mut mod_stack = Vec::new();
-    walk_items(file_path, &ast.items, &mut mod_stack, &mut out);
+    let mut v = V { file_path, scope: Scope::new(crate_name), current_fn: None, out: Vec::new() };
+    v.visit_file(ast);
+    let out = v.out;
 //     let mut out = Vec::new();
 // 
 //     for item in &ast.items {
@@ -507,126 +628,222 @@ pub fn extract_functions(
     file_path: &std::path::Path,
     ast: &File,
 ) -> Vec<CapturedFn> {
-    let mut out = Vec::new();
-    let mut mod_stack: Vec<String> = Vec::new();
+    fn captured(
+        scope: &Scope,
+        info: FnInfo,
+        sig: &syn::Signature,
+        attrs: &[Attribute],
+        span: Span,
+        file_path: &std::path::Path,
+    ) -> CapturedFn {
+        CapturedFn {
+            name: sig.ident.to_string(),
+            fq_name: info.fq_name,
+            is_public: info.is_public,
+            has_docs: has_docs(attrs),
+
+            is_async: sig.asyncness.is_some(),
+            is_unsafe: sig.unsafety.is_some(),
+            is_const: sig.constness.is_some(),
+            is_generic: !sig.generics.params.is_empty(),
+
+            args: fn_args(sig),
+            return_ty: fn_return(sig),
+
+            kind: info.kind,
+            module_path: scope.mod_stack().into(),
+            attrs: attr_paths(attrs),
+            signature: sig.to_token_stream().to_string(),
+            location: span_to_location(file_path, span),
+            doc: doc_text(attrs),
+            is_test_code: scope.in_test() || is_test_only(attrs),
+        }
+    }
 
     fn walk_items(
-        crate_name: &str,
         file_path: &std::path::Path,
         items: &[Item],
-        mod_stack: &mut Vec<String>,
+        scope: &mut Scope,
         out: &mut Vec<CapturedFn>,
     ) {
         for item in items {
             match item {
                 Item::Fn(f) => {
-                    let kind = FnKind::FreeFn;
-                    let name = f.sig.ident.to_string();
-                    let fq = super::extract::fq_name(crate_name, mod_stack, &kind, &name);
-
-                    out.push(CapturedFn {
-                        name,
-                        fq_name: fq,
-                        is_public: vis_is_public(&f.vis),
-                        has_docs: has_docs(&f.attrs),
-
-                        is_async: f.sig.asyncness.is_some(),
-                        is_unsafe: f.sig.unsafety.is_some(),
-                        is_const: f.sig.constness.is_some(),
-                        is_generic: !f.sig.generics.params.is_empty(),
-
-                        args: fn_args(&f.sig),
-                        return_ty: fn_return(&f.sig),
-
-                        kind,
-                        module_path: mod_stack.clone(),
-                        attrs: attr_paths(&f.attrs),
-                        signature: f.sig.to_token_stream().to_string(),
-                        location: span_to_location(file_path, f.span()),
-                    });
+                    let info = scope.free_fn(&f.sig.ident.to_string(), &f.vis);
+                    out.push(captured(scope, info, &f.sig, &f.attrs, f.span(), file_path));
                 }
                 Item::Impl(imp) => {
-                    let self_ty = match &*imp.self_ty {
-                        syn::Type::Path(tp) => tp
-                            .path
-                            .segments
-                            .last()
-                            .map(|s| s.ident.to_string())
-                            .unwrap_or_else(|| type_to_string(&imp.self_ty)),
-                        _ => type_to_string(&imp.self_ty),
-                    };
-                    let trait_ty = imp.trait_.as_ref().map(|(_, path, _)| path_to_string(path));
-
+                    let saved = scope.enter_impl(imp);
                     for it in &imp.items {
                         if let syn::ImplItem::Fn(m) = it {
-                            let kind = FnKind::ImplMethod {
-                                self_ty: self_ty.clone(),
-                                trait_ty: trait_ty.clone(),
-                            };
-                            let name = m.sig.ident.to_string();
-                            let fq = super::extract::fq_name(crate_name, mod_stack, &kind, &name);
-
-                            out.push(CapturedFn {
-                                name,
-                                fq_name: fq,
-                                is_public: vis_is_public(&m.vis),
-                                has_docs: has_docs(&m.attrs),
-
-                                is_async: m.sig.asyncness.is_some(),
-                                is_unsafe: m.sig.unsafety.is_some(),
-                                is_const: m.sig.constness.is_some(),
-                                is_generic: !m.sig.generics.params.is_empty(),
-
-                                args: fn_args(&m.sig),
-                                return_ty: fn_return(&m.sig),
-
-                                kind,
-                                module_path: mod_stack.clone(),
-                                attrs: attr_paths(&m.attrs),
-                                signature: m.sig.to_token_stream().to_string(),
-                                location: span_to_location(file_path, m.span()),
-                            });
+                            let info = scope.impl_fn(&m.sig.ident.to_string(), &m.vis);
+                            out.push(captured(scope, info, &m.sig, &m.attrs, m.span(), file_path));
                         }
                     }
+                    scope.leave(saved);
                 }
                 Item::Trait(t) => {
-                    let trait_name = t.ident.to_string();
+                    let saved = scope.enter_trait(t);
                     for it in &t.items {
                         if let syn::TraitItem::Fn(tf) = it {
-                            let kind = FnKind::TraitMethod {
-                                trait_name: trait_name.clone(),
-                            };
-                            let name = tf.sig.ident.to_string();
-                            let fq = super::extract::fq_name(crate_name, mod_stack, &kind, &name);
-
-                            out.push(CapturedFn {
-                                name,
-                                fq_name: fq,
-                                is_public: true,
-                                has_docs: has_docs(&tf.attrs),
-
-                                is_async: tf.sig.asyncness.is_some(),
-                                is_unsafe: tf.sig.unsafety.is_some(),
-                                is_const: tf.sig.constness.is_some(),
-                                is_generic: !tf.sig.generics.params.is_empty(),
-
-                                args: fn_args(&tf.sig),
-                                return_ty: fn_return(&tf.sig),
-
-                                kind,
-                                module_path: mod_stack.clone(),
-                                attrs: attr_paths(&tf.attrs),
-                                signature: tf.sig.to_token_stream().to_string(),
-                                location: span_to_location(file_path, tf.span()),
-                            });
+                            let info = scope.trait_fn(&tf.sig.ident.to_string());
+                            out.push(captured(scope, info, &tf.sig, &tf.attrs, tf.span(), file_path));
                         }
                     }
+                    scope.leave(saved);
+                }
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        let saved = scope.enter_mod(m);
+                        walk_items(file_path, items, scope, out);
+                        scope.leave(saved);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk_items(file_path, &ast.items, &mut Scope::new(crate_name), &mut out);
+    out
+}
+
+/// Module path a file contributes by its location:
+/// `src/a/b/c.rs` -> [a, b, c]; `mod.rs`, `lib.rs` and `main.rs` name their parent.
+pub fn extract_modules(file_path: &std::path::Path, ast: &File) -> Vec<ModuleDecl> {
+    fn walk_items(file_path: &std::path::Path, items: &[Item], scope: &mut Scope, out: &mut Vec<ModuleDecl>) {
+        for it in items {
+            let Item::Mod(m) = it else { continue; };
+            out.push(ModuleDecl {
+                name: m.ident.to_string(),
+                module_path: scope.mod_stack().into(),
+                is_public: vis_is_public(&m.vis),
+                is_inline: m.content.is_some(),
+                location: span_to_location(file_path, m.ident.span()),
+                is_test_code: scope.in_test() || is_test_only(&m.attrs),
+            });
+            if let Some((_, items)) = &m.content {
+                let saved = scope.enter_mod(m);
+                walk_items(file_path, items, scope, out);
+                scope.leave(saved);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk_items(file_path, &ast.items, &mut Scope::default(), &mut out);
+    out
+}
+
+pub fn file_module_path(path: &std::path::Path) -> Vec<String> {
+    let comps: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let start = comps
+        .iter()
+        .rposition(|c| c == "src")
+        .map(|i| i + 1)
+        .unwrap_or(comps.len().saturating_sub(1));
+    let mut out: Vec<String> = comps[start..].to_vec();
+    if let Some(last) = out.pop() {
+        let stem = last.strip_suffix(".rs").unwrap_or(&last);
+        if !matches!(stem, "mod" | "lib" | "main") {
+            out.push(stem.to_string());
+        }
+    }
+    out
+}
+
+pub fn extract_types(
+    crate_name: &str,
+    file_path: &std::path::Path,
+    ast: &File,
+) -> Vec<CapturedType> {
+    fn walk_items(
+        crate_name: &str,
+        file_path: &std::path::Path,
+        items: &[Item],
+        scope: &mut Scope,
+        out: &mut Vec<CapturedType>,
+    ) {
+        for item in items {
+            let (ident, kind, vis, attrs, generics) = match item {
+                Item::Struct(i) => (&i.ident, TypeKind::Struct, &i.vis, &i.attrs, &i.generics),
+                Item::Enum(i) => (&i.ident, TypeKind::Enum, &i.vis, &i.attrs, &i.generics),
+                Item::Union(i) => (&i.ident, TypeKind::Union, &i.vis, &i.attrs, &i.generics),
+                Item::Type(i) => (&i.ident, TypeKind::TypeAlias, &i.vis, &i.attrs, &i.generics),
+                Item::Trait(i) => (&i.ident, TypeKind::Trait, &i.vis, &i.attrs, &i.generics),
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        let saved = scope.enter_mod(m);
+                        walk_items(crate_name, file_path, items, scope, out);
+                        scope.leave(saved);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let name = ident.to_string();
+            let mut parts = vec![crate_name.to_string()];
+            parts.extend(scope.mod_stack().iter().cloned());
+            parts.push(name.clone());
+
+            out.push(CapturedType {
+                name,
+                fq_name: parts.join("::"),
+                kind,
+                is_public: vis_is_public(vis),
+                has_docs: has_docs(attrs),
+                is_generic: !generics.params.is_empty(),
+                module_path: scope.mod_stack().into(),
+                attrs: attr_paths(attrs),
+                location: span_to_location(file_path, item.span()),
+                doc: doc_text(attrs),
+                derives: derive_paths(attrs),
+                is_test_code: scope.in_test() || is_test_only(attrs),
+            });
+        }
+    }
+
+    let mut out = Vec::new();
+    walk_items(crate_name, file_path, &ast.items, &mut Scope::new(crate_name), &mut out);
+    out
+}
+
+pub fn extract_impls(file_path: &std::path::Path, ast: &File) -> Vec<CapturedImpl> {
+    fn walk_items(
+        file_path: &std::path::Path,
+        items: &[Item],
+        scope: &mut Scope,
+        out: &mut Vec<CapturedImpl>,
+    ) {
+        for item in items {
+            match item {
+                Item::Impl(imp) => {
+                    let (self_ty, self_ty_args) = self_ty_parts(&imp.self_ty);
+                    out.push(CapturedImpl {
+                        self_ty,
+                        self_ty_args,
+                        trait_ty: imp.trait_.as_ref().map(|(_, path, _)| path_to_string(path)),
+                        trait_args: imp.trait_.as_ref()
+                            .and_then(|(_, path, _)| path.segments.last())
+                            .map(|s| generic_args(&s.arguments))
+                            .unwrap_or_default(),
+                        is_unsafe: imp.unsafety.is_some(),
+                        is_generic: !imp.generics.params.is_empty(),
+                        module_path: scope.mod_stack().into(),
+                        location: span_to_location(file_path, imp.span()),
+                        is_test_code: scope.in_test() || is_test_only(&imp.attrs),
+                    });
                 }
                 Item::Mod(m) => {
                     if let Some((_, items)) = &m.content {
-                        mod_stack.push(m.ident.to_string());
-                        walk_items(crate_name, file_path, items, mod_stack, out);
-                        mod_stack.pop();
+                        let saved = scope.enter_mod(m);
+                        walk_items(file_path, items, scope, out);
+                        scope.leave(saved);
                     }
                 }
                 _ => {}
@@ -634,7 +851,8 @@ pub fn extract_functions(
         }
     }
 
-    walk_items(crate_name, file_path, &ast.items, &mut mod_stack, &mut out);
+    let mut out = Vec::new();
+    walk_items(file_path, &ast.items, &mut Scope::default(), &mut out);
     out
 }
 
@@ -681,7 +899,7 @@ pub fn extract_occurrences_v1(
             if i.mac.path.is_ident("macro_rules") {
                 self.macros_def.push(MacroDef {
                     name,
-                    module_path: self.mod_stack.clone(),
+                    module_path: self.mod_stack.as_slice().into(),
                     location: span_to_location(&self.file_path, i.span()),
                 });
             } else {
@@ -754,8 +972,10 @@ pub fn extract_occurrences_v1(
         }
     }
 
-    let mut v = V::default();
-    v.file_path = file_path.to_path_buf();
+    let mut v = V {
+        file_path: file_path.to_path_buf(),
+        ..Default::default()
+    };
     v.visit_file(ast);
     (v.macros_def, v.macros_inv, v.paths, v.calls)
 }
@@ -769,23 +989,38 @@ pub fn extract_occurrences(
     Vec<MacroInvocation>,
     Vec<PathOccurrence>,
     Vec<CallOccurrence>,
+) {
+    extract_occurrences_with(crate_name, file_path, ast, PathCapture::All)
+}
+
+/// `extract_occurrences`, recording only the paths `capture` asks for.
+///
+/// With `ExternalOnly` this keeps every path that *may* be external (anything not
+/// rooted at `crate`/`self`/`super`/`Self`); `Klepto::parse` narrows that down with the imports.
+pub fn extract_occurrences_with(
+    crate_name: &str,
+    file_path: &std::path::Path,
+    ast: &syn::File,
+    capture: PathCapture,
+) -> (
+    Vec<MacroDef>,
+    Vec<MacroInvocation>,
+    Vec<PathOccurrence>,
+    Vec<CallOccurrence>,
 ) {
     use syn::visit::Visit;
 
     #[derive(Default)]
     struct V {
-        crate_name: String,
         file_path: std::path::PathBuf,
+        capture: PathCapture,
 
-        mod_stack: Vec<String>,
-
-        // impl / trait context
-        impl_self_ty: Option<String>,
-        impl_trait_ty: Option<String>,
-        in_trait: Option<String>,
+        scope: Scope,
+        // `scope.mod_stack()` as shared by every occurrence in the module
+        module: ModulePath,
 
         // current enclosing function
-        current_fn: Option<String>,
+        current_fn: Option<IStr>,
         current_fn_is_public: Option<bool>,
 
         macros_def: Vec<MacroDef>,
@@ -794,149 +1029,81 @@ pub fn extract_occurrences(
         calls: Vec<CallOccurrence>,
     }
 
-    fn vis_is_public(vis: &syn::Visibility) -> bool {
-        matches!(vis, syn::Visibility::Public(_))
-    }
-
-    fn path_to_string(p: &syn::Path) -> String {
-        p.segments
-            .iter()
-            .map(|s| s.ident.to_string())
-            .collect::<Vec<_>>()
-            .join("::")
-    }
+    impl V {
+        // visits a function's body with it as the enclosing fn
+        fn in_fn(&mut self, info: FnInfo, attrs: &[syn::Attribute], body: impl FnOnce(&mut Self)) {
+            let prev_fn = self.current_fn.replace(info.fq_name.into());
+            let prev_pub = self.current_fn_is_public.replace(info.is_public);
+            let saved = self.scope.enter_fn(attrs);
+            body(self);
+            self.scope.leave(saved);
+            self.current_fn = prev_fn;
+            self.current_fn_is_public = prev_pub;
+        }
 
-    fn type_to_string(ty: &syn::Type) -> String {
-        use quote::ToTokens;
-        ty.to_token_stream().to_string()
-    }
+        fn macro_call(&mut self, mac: &syn::Macro, span: Span) {
+            let segs: Vec<String> = mac.path.segments.iter().map(|s| s.ident.to_string()).collect();
+            self.macros_inv.push(MacroInvocation {
+                name: segs.last().map(|s| IStr::from(s.as_str())).unwrap_or_else(|| "<macro>".into()),
+                module_path: self.module.clone(),
+                path: (!segs.is_empty()).then(|| segs.join("::")),
+                location: span_to_location(&self.file_path, span),
+                enclosing_fn: self.current_fn.clone(),
+                enclosing_public: self.current_fn_is_public,
+                is_test_code: self.scope.in_test(),
+            });
+        }
 
-    fn fq_name(crate_name: &str, module_path: &[String], kind: &FnKind, name: &str) -> String {
-        let mut parts = Vec::new();
-        parts.push(crate_name.to_string());
-        parts.extend(module_path.iter().cloned());
-        match kind {
-            FnKind::FreeFn => parts.push(name.to_string()),
-            FnKind::TraitMethod { trait_name } => {
-                parts.push(trait_name.clone());
-                parts.push(name.to_string());
-            }
-            FnKind::ImplMethod { self_ty, .. } => {
-                parts.push(self_ty.clone());
-                parts.push(name.to_string());
+        // a test's calls are mostly inside `assert_eq!(..)` and friends; in test
+        // code, read arguments that parse as expressions (other results unchanged)
+        fn macro_args(&mut self, mac: &syn::Macro) {
+            if !self.scope.in_test() { return; }
+            if let Ok(args) = mac.parse_body_with(syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated) {
+                for a in &args {
+                    self.visit_expr(a);
+                }
             }
         }
-        parts.join("::")
     }
 
     impl<'ast> Visit<'ast> for V {
         fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
             if let Some((_, items)) = &i.content {
-                self.mod_stack.push(i.ident.to_string());
+                let saved = self.scope.enter_mod(i);
+                self.module = self.scope.mod_stack().into();
                 for it in items {
                     self.visit_item(it);
                 }
-                self.mod_stack.pop();
+                self.scope.leave(saved);
+                self.module = self.scope.mod_stack().into();
             }
         }
 
         fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
-            let prev = self.in_trait.take();
-            self.in_trait = Some(i.ident.to_string());
+            let saved = self.scope.enter_trait(i);
             syn::visit::visit_item_trait(self, i);
-            self.in_trait = prev;
+            self.scope.leave(saved);
         }
 
         fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
-            let prev_self = self.impl_self_ty.take();
-            let prev_trait = self.impl_trait_ty.take();
-
-            let self_ty = match &*i.self_ty {
-                syn::Type::Path(tp) => tp
-                    .path
-                    .segments
-                    .last()
-                    .map(|s| s.ident.to_string())
-                    .unwrap_or_else(|| type_to_string(&i.self_ty)),
-                _ => type_to_string(&i.self_ty),
-            };
-            self.impl_self_ty = Some(self_ty);
-
-            self.impl_trait_ty = i.trait_.as_ref().map(|(_, p, _)| path_to_string(p));
-
+            let saved = self.scope.enter_impl(i);
             syn::visit::visit_item_impl(self, i);
-
-            self.impl_self_ty = prev_self;
-            self.impl_trait_ty = prev_trait;
+            self.scope.leave(saved);
         }
 
         fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
-            let (kind, is_pub) = if let Some(tr) = &self.in_trait {
-                (
-                    FnKind::TraitMethod {
-                        trait_name: tr.clone(),
-                    },
-                    true,
-                )
-            } else {
-                (FnKind::FreeFn, vis_is_public(&i.vis))
-            };
-
-            let name = i.sig.ident.to_string();
-            let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-            let prev_fn = self.current_fn.take();
-            let prev_pub = self.current_fn_is_public.take();
-
-            self.current_fn = Some(fq);
-            self.current_fn_is_public = Some(is_pub);
-
-            // visit inside function body
-            syn::visit::visit_item_fn(self, i);
-
-            self.current_fn = prev_fn;
-            self.current_fn_is_public = prev_pub;
+            let info = self.scope.free_fn(&i.sig.ident.to_string(), &i.vis);
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_item_fn(v, i));
         }
 
         fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
-            let self_ty = self.impl_self_ty.clone().unwrap_or_else(|| "<impl>".into());
-            let trait_ty = self.impl_trait_ty.clone();
-            let kind = FnKind::ImplMethod { self_ty, trait_ty };
-
-            let name = i.sig.ident.to_string();
-            let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-            let is_pub = vis_is_public(&i.vis);
-
-            let prev_fn = self.current_fn.take();
-            let prev_pub = self.current_fn_is_public.take();
-
-            self.current_fn = Some(fq);
-            self.current_fn_is_public = Some(is_pub);
-
-            syn::visit::visit_impl_item_fn(self, i);
-
-            self.current_fn = prev_fn;
-            self.current_fn_is_public = prev_pub;
+            let info = self.scope.impl_fn(&i.sig.ident.to_string(), &i.vis);
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_impl_item_fn(v, i));
         }
 
         fn visit_trait_item_fn(&mut self, i: &'ast syn::TraitItemFn) {
-            let tr = self.in_trait.clone().unwrap_or_else(|| "<trait>".into());
-            let kind = FnKind::TraitMethod { trait_name: tr };
-
-            let name = i.sig.ident.to_string();
-            let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-            let prev_fn = self.current_fn.take();
-            let prev_pub = self.current_fn_is_public.take();
-
-            self.current_fn = Some(fq);
-            self.current_fn_is_public = Some(true);
-
-            syn::visit::visit_trait_item_fn(self, i);
-
-            self.current_fn = prev_fn;
-            self.current_fn_is_public = prev_pub;
+            let info = self.scope.trait_fn(&i.sig.ident.to_string());
+            self.in_fn(info, &i.attrs, |v| syn::visit::visit_trait_item_fn(v, i));
         }
 
         fn visit_item_macro(&mut self, i: &'ast syn::ItemMacro) {
@@ -948,76 +1115,33 @@ pub fn extract_occurrences(
             if i.mac.path.is_ident("macro_rules") {
                 self.macros_def.push(MacroDef {
                     name,
-                    module_path: self.mod_stack.clone(),
-                    location: super::extract::span_to_location(&self.file_path, i.span()),
+                    module_path: self.module.clone(),
+                    location: span_to_location(&self.file_path, i.span()),
                 });
             } else {
-                let full_path = {
-                    let segs: Vec<String> = i
-                        .mac
-                        .path
-                        .segments
-                        .iter()
-                        .map(|s| s.ident.to_string())
-                        .collect();
-                    if segs.is_empty() {
-                        None
-                    } else {
-                        Some(segs.join("::"))
-                    }
-                };
-
-                self.macros_inv.push(MacroInvocation {
-                    name: i
-                        .mac
-                        .path
-                        .segments
-                        .last()
-                        .map(|s| s.ident.to_string())
-                        .unwrap_or_else(|| "<macro>".into()),
-                    module_path: self.mod_stack.clone(),
-                    path: full_path,
-                    location: super::extract::span_to_location(&self.file_path, i.span()),
-                    enclosing_fn: self.current_fn.clone(),
-                    enclosing_public: self.current_fn_is_public,
-                });
+                self.macro_call(&i.mac, i.span());
             }
             syn::visit::visit_item_macro(self, i);
         }
 
         fn visit_expr_macro(&mut self, i: &'ast syn::ExprMacro) {
-            let full_path = {
-                let segs: Vec<String> = i
-                    .mac
-                    .path
-                    .segments
-                    .iter()
-                    .map(|s| s.ident.to_string())
-                    .collect();
-                if segs.is_empty() {
-                    None
-                } else {
-                    Some(segs.join("::"))
-                }
-            };
-            self.macros_inv.push(MacroInvocation {
-                name: i
-                    .mac
-                    .path
-                    .segments
-                    .last()
-                    .map(|s| s.ident.to_string())
-                    .unwrap_or_else(|| "<macro>".into()),
-                module_path: self.mod_stack.clone(),
-                path: full_path,
-                location: super::extract::span_to_location(&self.file_path, i.span()),
-                enclosing_fn: self.current_fn.clone(),
-                enclosing_public: self.current_fn_is_public,
-            });
+            self.macro_call(&i.mac, i.span());
             syn::visit::visit_expr_macro(self, i);
+            self.macro_args(&i.mac);
+        }
+
+        // `assert_eq!(..);` in statement position is a `Stmt::Macro`, not an `ExprMacro`
+        fn visit_stmt_macro(&mut self, i: &'ast syn::StmtMacro) {
+            self.macro_call(&i.mac, i.span());
+            syn::visit::visit_stmt_macro(self, i);
+            self.macro_args(&i.mac);
         }
 
         fn visit_path(&mut self, p: &'ast syn::Path) {
+            if self.capture == PathCapture::None {
+                syn::visit::visit_path(self, p);
+                return;
+            }
             let s = p
                 .segments
                 .iter()
@@ -1026,19 +1150,28 @@ pub fn extract_occurrences(
                 .join("::");
 
             // reduce noise: only record “real” paths
-            let keep = s.contains("::")
-                || matches!(
-                    s.as_str(),
-                    "std" | "core" | "alloc" | "crate" | "self" | "super"
-                );
+            let keep = match self.capture {
+                PathCapture::ExternalOnly => {
+                    s.contains("::") && !matches!(p.segments[0].ident.to_string().as_str(), "crate" | "self" | "super" | "Self")
+                }
+                _ => {
+                    s.contains("::")
+                        || matches!(
+                            s.as_str(),
+                            "std" | "core" | "alloc" | "crate" | "self" | "super"
+                        )
+                }
+            };
 
             if keep {
                 self.paths.push(PathOccurrence {
-                    path: s,
-                    module_path: self.mod_stack.clone(),
-                    location: super::extract::span_to_location(&self.file_path, p.span()),
+                    path: s.into(),
+                    resolved: None,
+                    module_path: self.module.clone(),
+                    location: span_to_location(&self.file_path, p.span()),
                     enclosing_fn: self.current_fn.clone(),
                     enclosing_public: self.current_fn_is_public,
+                    is_test_code: self.scope.in_test(),
                 });
             }
 
@@ -1047,11 +1180,13 @@ pub fn extract_occurrences(
 
         fn visit_expr_method_call(&mut self, m: &'ast syn::ExprMethodCall) {
             self.calls.push(CallOccurrence {
-                callee: m.method.to_string(),
-                module_path: self.mod_stack.clone(),
-                location: super::extract::span_to_location(&self.file_path, m.span()),
+                callee: m.method.to_string().into(),
+                module_path: self.module.clone(),
+                location: span_to_location(&self.file_path, m.span()),
                 enclosing_fn: self.current_fn.clone(),
                 enclosing_public: self.current_fn_is_public,
+                receiver: Some(expr_text(&m.receiver)),
+                is_test_code: self.scope.in_test(),
             });
             syn::visit::visit_expr_method_call(self, m);
         }
@@ -1059,20 +1194,122 @@ pub fn extract_occurrences(
         fn visit_expr_call(&mut self, c: &'ast syn::ExprCall) {
             let callee = c.func.to_token_stream().to_string();
             self.calls.push(CallOccurrence {
-                callee,
-                module_path: self.mod_stack.clone(),
-                location: super::extract::span_to_location(&self.file_path, c.span()),
+                callee: callee.into(),
+                module_path: self.module.clone(),
+                location: span_to_location(&self.file_path, c.span()),
                 enclosing_fn: self.current_fn.clone(),
                 enclosing_public: self.current_fn_is_public,
+                receiver: None,
+                is_test_code: self.scope.in_test(),
             });
             syn::visit::visit_expr_call(self, c);
         }
     }
 
-    let mut v = V::default();
-    v.crate_name = crate_name.to_string();
-    v.file_path = file_path.to_path_buf();
+    let mut v = V {
+        file_path: file_path.to_path_buf(),
+        capture,
+        scope: Scope::new(crate_name),
+        ..Default::default()
+    };
     v.visit_file(ast);
 
     (v.macros_def, v.macros_inv, v.paths, v.calls)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::klepto::Klepto;
+    use crate::rules::builtin::codes;
+
+    fn fn_attrs(src: &str) -> Vec<Attribute> {
+        match syn::parse_str::<File>(src).unwrap().items.into_iter().next() {
+            Some(Item::Fn(f)) => f.attrs,
+            _ => panic!("no fn in {src}"),
+        }
+    }
+
+    #[test]
+    fn doc_attribute_forms_count_as_docs() {
+        for src in [
+            "#[doc = \"x\"] pub fn f() {}",
+            "#[doc = include_str!(\"f.md\")] pub fn f() {}",
+            "#[cfg_attr(docsrs, doc = \"x\")] pub fn f() {}",
+            "#[cfg_attr(all(docsrs, feature = \"a\"), doc = include_str!(\"f.md\"))] pub fn f() {}",
+        ] {
+            assert!(has_docs(&fn_attrs(src)), "{src}");
+        }
+        for src in [
+            "pub fn f() {}",
+            "#[doc(alias = \"g\")] pub fn f() {}",
+            "#[cfg_attr(docsrs, doc(cfg(feature = \"a\")))] pub fn f() {}",
+        ] {
+            assert!(!has_docs(&fn_attrs(src)), "{src}");
+        }
+        assert_eq!(doc_text(&fn_attrs("#[doc = \" x\"] pub fn f() {}")).as_deref(), Some("x"));
+        assert_eq!(doc_text(&fn_attrs("#[doc = include_str!(\"f.md\")] pub fn f() {}")), None);
+    }
+
+    #[test]
+    fn receivers_read_as_written() {
+        for (src, want) in [
+            ("self.inner.clone()", "self.inner"),
+            ("shared_arc.clone()", "shared_arc"),
+            ("(*self.state).clone()", "(*self.state)"),
+            ("self.items[0].clone()", "self.items[0]"),
+            ("self.get()?.clone()", "self.get()?"),
+            ("self.0.clone()", "self.0"),
+            ("(&mut [1, 2]).clone()", "(&mut [1, 2])"),
+        ] {
+            let syn::Expr::MethodCall(m) = syn::parse_str::<syn::Expr>(src).unwrap() else { panic!("{src}") };
+            assert_eq!(expr_text(&m.receiver), want, "{src}");
+        }
+    }
+
+    #[test]
+    fn trait_methods_are_as_public_as_their_trait() {
+        let path = std::path::Path::new("src/lib.rs");
+        let ast = syn::parse_str::<File>("trait Hidden { fn a(&self) { todo!() } }\n\
+            pub trait Open { fn b(&self) { todo!() } }\n\
+            pub mod m { pub(crate) trait Inner { fn c(&self); } }\n").unwrap();
+        let public: Vec<(String, bool)> = extract_functions("t", path, &ast).into_iter().map(|f| (f.name, f.is_public)).collect();
+        assert_eq!(public, [("a".to_string(), false), ("b".to_string(), true), ("c".to_string(), false)]);
+
+        let index = crate::index::EnclosingIndex::build("t", path, &ast);
+        let spans: Vec<(&str, bool)> = index.spans(path).iter().map(|s| (s.fq_name.as_str(), s.is_public)).collect();
+        assert_eq!(spans, [("t::Hidden::a", false), ("t::Open::b", true), ("t::m::Inner::c", false)]);
+
+        let (_, macros, _, _) = extract_occurrences("t", path, &ast);
+        assert_eq!(macros.iter().map(|m| m.enclosing_public).collect::<Vec<_>>(), [Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn item_position_macros_record_their_path() {
+        let ast = syn::parse_str::<File>("macro_rules! m { () => {} }\nfoo::bar! { x }\nm!();\n").unwrap();
+        let (defs, macros, _, _) = extract_occurrences("t", std::path::Path::new("src/lib.rs"), &ast);
+        assert_eq!(defs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), ["m"]);
+        let invs: Vec<(&str, Option<&str>, Option<&str>)> = macros.iter()
+            .map(|m| (&*m.name, m.path.as_deref(), m.enclosing_fn.as_deref()))
+            .collect();
+        assert_eq!(invs, [("bar", Some("foo::bar"), None), ("m", Some("m"), None)]);
+    }
+
+    #[test]
+    fn undocumented_public_api_skips_included_and_cfg_attr_docs() {
+        let dir = std::env::temp_dir().join(format!("klepto-extract-docs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "#[doc = \"x\"]\npub fn plain() {}\n\
+            #[doc = include_str!(\"f.md\")]\npub fn included() {}\n\
+            #[cfg_attr(docsrs, doc = \"x\")]\npub fn gated() {}\n\
+            pub fn bare() {}\n").unwrap();
+        let k = Klepto::new("docs").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let flagged: Vec<String> = k.rules().with_default_rules().run().into_iter()
+            .filter(|f| f.code == codes::UNDOCUMENTED_PUBLIC_API)
+            .map(|f| f.message)
+            .collect();
+        assert_eq!(flagged.len(), 1, "{flagged:?}");
+        assert!(flagged[0].contains("bare"));
+    }
+}
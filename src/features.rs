@@ -0,0 +1,327 @@
+use crate::extract::{file_module_path, tidy_tokens};
+use crate::klepto::Klepto;
+use crate::model::{FileLocation, FnKind};
+use crate::xref::SymbolKind;
+use proc_macro2::{TokenStream, TokenTree};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+/// Where a `feature = "..."` predicate was written.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FeatureRefKind {
+    Cfg,      // #[cfg(..)]
+    CfgAttr,  // #[cfg_attr(.., ..)]
+    DocCfg,   // #[doc(cfg(..))]
+    CfgMacro, // cfg!(..)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FeatureRef {
+    pub feature: String,
+    pub kind: FeatureRefKind,
+    pub location: FileLocation,
+}
+
+/// Declared features joined with the ones code refers to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FeatureInventory {
+    pub declared: Option<BTreeMap<String, Vec<String>>>, // None when the scan had no Cargo.toml to read
+    pub referenced: BTreeMap<String, Vec<FeatureRef>>,
+}
+
+impl FeatureInventory {
+    /// Features named in `cfg`s but missing from `[features]` (empty when undeclared is unknown).
+    pub fn undeclared(&self) -> impl Iterator<Item = (&str, &[FeatureRef])> {
+        self.referenced.iter()
+            .filter(|(f, _)| self.declared.as_ref().is_some_and(|d| !d.contains_key(*f)))
+            .map(|(f, refs)| (f.as_str(), refs.as_slice()))
+    }
+
+    /// Declared features no `cfg` mentions. `default` and the implicit features of
+    /// optional dependencies (`rayon = ["dep:rayon"]`) are never reported.
+    ///
+    /// Features that only switch on dependency features are legitimately listed here.
+    pub fn unreferenced(&self) -> impl Iterator<Item = &str> {
+        self.declared.iter().flatten()
+            .filter(|(f, enables)| {
+                let implicit = enables.len() == 1 && enables[0].strip_prefix("dep:") == Some(f.as_str());
+                *f != "default" && !implicit && !self.referenced.contains_key(*f)
+            })
+            .map(|(f, _)| f.as_str())
+    }
+}
+
+/// A public item and the feature `cfg`s it's compiled under: its own and
+/// those of the modules around it, all of which must hold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FeatureGate {
+    pub item: String, // fq_name
+    pub kind: SymbolKind,
+    /// Predicates of the `#[cfg(..)]`s that mention a feature, outermost first.
+    pub cfgs: Vec<String>,
+    /// Features every predicate needs on (`feature = "x"`, or inside `all(..)`);
+    /// `any(..)` and `not(..)` add none.
+    pub required: BTreeSet<String>,
+    pub location: FileLocation,
+}
+
+// features an item under `cfg(<tokens>)` can't be built without
+fn required_features(tokens: &TokenStream, out: &mut BTreeSet<String>) {
+    fn walk(m: &syn::Meta, out: &mut BTreeSet<String>) {
+        match m {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("feature") => {
+                if let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) = &nv.value {
+                    out.insert(s.value());
+                }
+            }
+            syn::Meta::List(l) if l.path.is_ident("all") => {
+                let Ok(args) = l.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) else { return };
+                for a in &args { walk(a, out); }
+            }
+            _ => {}
+        }
+    }
+    if let Ok(m) = syn::parse2::<syn::Meta>(tokens.clone()) {
+        walk(&m, out);
+    }
+}
+
+// the `#[cfg(..)]` predicates of `attrs` that name a feature
+fn feature_cfgs(attrs: &[syn::Attribute]) -> Vec<TokenStream> {
+    attrs.iter()
+        .filter_map(|a| match &a.meta {
+            syn::Meta::List(l) if l.path.is_ident("cfg") => Some(l.tokens.clone()),
+            _ => None,
+        })
+        .filter(|t| {
+            let mut found = Vec::new();
+            feature_names(t.clone(), &mut found);
+            !found.is_empty()
+        })
+        .collect()
+}
+
+// byte ranges of feature-gated items in one file, and the gated `mod x;`
+// declarations (by full module path) whose files inherit the gate
+struct GateWalker {
+    module: Vec<String>,
+    ranges: Vec<(Range<usize>, TokenStream)>,
+    mods: Vec<(Vec<String>, Vec<TokenStream>)>,
+}
+
+impl GateWalker {
+    fn enter(&mut self, attrs: &[syn::Attribute], span: proc_macro2::Span) {
+        let Some(range) = crate::scope::span_to_location(Path::new(""), span).byte_range else { return };
+        for cfg in feature_cfgs(attrs) {
+            self.ranges.push((range.clone(), cfg));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for GateWalker {
+    fn visit_item(&mut self, i: &'ast syn::Item) {
+        let attrs = match i {
+            syn::Item::Const(x) => &x.attrs,
+            syn::Item::Enum(x) => &x.attrs,
+            syn::Item::Fn(x) => &x.attrs,
+            syn::Item::Impl(x) => &x.attrs,
+            syn::Item::Mod(x) => &x.attrs,
+            syn::Item::Struct(x) => &x.attrs,
+            syn::Item::Trait(x) => &x.attrs,
+            syn::Item::Type(x) => &x.attrs,
+            syn::Item::Union(x) => &x.attrs,
+            _ => return syn::visit::visit_item(self, i),
+        };
+        self.enter(attrs, i.span());
+        if let syn::Item::Mod(m) = i {
+            let mut full = self.module.clone();
+            full.push(m.ident.to_string());
+            if m.content.is_none() {
+                // the declaration's own gates and those of the items around it
+                let start = crate::scope::span_to_location(Path::new(""), m.span()).byte_range.map_or(0, |r| r.start);
+                let cfgs: Vec<TokenStream> = self.ranges.iter().filter(|(r, _)| r.contains(&start)).map(|(_, c)| c.clone()).collect();
+                if !cfgs.is_empty() { self.mods.push((full, cfgs)); }
+                return;
+            }
+            let outer = std::mem::replace(&mut self.module, full);
+            syn::visit::visit_item_mod(self, m);
+            self.module = outer;
+            return;
+        }
+        syn::visit::visit_item(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, f: &'ast syn::ImplItemFn) {
+        self.enter(&f.attrs, f.span());
+        syn::visit::visit_impl_item_fn(self, f);
+    }
+
+    fn visit_trait_item_fn(&mut self, f: &'ast syn::TraitItemFn) {
+        self.enter(&f.attrs, f.span());
+        syn::visit::visit_trait_item_fn(self, f);
+    }
+}
+
+// `feature = "x"` anywhere in the (nested) predicate tokens
+fn feature_names(tokens: TokenStream, out: &mut Vec<(String, proc_macro2::Span)>) {
+    let toks: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, t) in toks.iter().enumerate() {
+        match t {
+            TokenTree::Group(g) => feature_names(g.stream(), out),
+            TokenTree::Ident(id) if id == "feature" => {
+                let (Some(TokenTree::Punct(eq)), Some(TokenTree::Literal(lit))) = (toks.get(i + 1), toks.get(i + 2)) else { continue; };
+                if eq.as_char() != '=' { continue; }
+                if let Ok(syn::Lit::Str(s)) = syn::parse_str::<syn::Lit>(&lit.to_string()) {
+                    out.push((s.value(), lit.span()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Collector<'a> {
+    file: &'a Path,
+    out: Vec<FeatureRef>,
+}
+
+impl Collector<'_> {
+    fn push(&mut self, tokens: TokenStream, kind: FeatureRefKind) {
+        let mut found = Vec::new();
+        feature_names(tokens, &mut found);
+        for (feature, span) in found {
+            self.out.push(FeatureRef { feature, kind, location: crate::scope::span_to_location(self.file, span) });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_attribute(&mut self, a: &'ast syn::Attribute) {
+        let syn::Meta::List(list) = &a.meta else { return; };
+        let kind = if list.path.is_ident("cfg") {
+            FeatureRefKind::Cfg
+        } else if list.path.is_ident("cfg_attr") {
+            FeatureRefKind::CfgAttr
+        } else if list.path.is_ident("doc") && list.tokens.to_string().contains("cfg") {
+            FeatureRefKind::DocCfg
+        } else {
+            return;
+        };
+        self.push(list.tokens.clone(), kind);
+    }
+
+    fn visit_macro(&mut self, m: &'ast syn::Macro) {
+        if m.path.is_ident("cfg") {
+            self.push(m.tokens.clone(), FeatureRefKind::CfgMacro);
+        }
+        syn::visit::visit_macro(self, m);
+    }
+}
+
+impl Klepto {
+    /// Every `feature = "..."` predicate in `cfg`, `cfg_attr`, `doc(cfg)` and `cfg!`.
+    pub fn feature_refs(&self) -> Vec<FeatureRef> {
+        let mut out = Vec::new();
+        for pf in &self.files {
+            let mut c = Collector { file: &pf.path, out: Vec::new() };
+            c.visit_file(&pf.ast());
+            out.extend(c.out);
+        }
+        out
+    }
+
+    /// Referenced features joined with `declared_features` (from `scan_workspace_root`
+    /// or `KleptoBuilder::declared_features`). Members of a workspace share one set.
+    pub fn feature_inventory(&self) -> FeatureInventory {
+        let mut referenced: BTreeMap<String, Vec<FeatureRef>> = BTreeMap::new();
+        for r in self.feature_refs() {
+            referenced.entry(r.feature.clone()).or_default().push(r);
+        }
+        FeatureInventory { declared: self.declared_features.clone(), referenced }
+    }
+
+    /// Feature gates of every public function and type that has one. A file
+    /// inherits the gates on its `mod x;` declaration (and the modules above it),
+    /// and `#![cfg(..)]` at its top. Needs `span-locations` and the ASTs.
+    pub fn feature_gates(&self) -> Vec<FeatureGate> {
+        if !cfg!(feature = "span-locations") { return Vec::new(); }
+        let mut ranges: BTreeMap<&Path, Vec<(Range<usize>, TokenStream)>> = BTreeMap::new();
+        // (module, inner attribute?, cfgs)
+        let mut mods: Vec<(Vec<String>, bool, Vec<TokenStream>)> = Vec::new();
+        for pf in &self.files {
+            let module = file_module_path(&pf.path);
+            let mut w = GateWalker { module: module.clone(), ranges: Vec::new(), mods: Vec::new() };
+            w.visit_file(&pf.ast());
+            let inner = feature_cfgs(&pf.ast().attrs);
+            if !inner.is_empty() { mods.push((module, true, inner)); }
+            mods.extend(w.mods.into_iter().map(|(m, cfgs)| (m, false, cfgs)));
+            ranges.insert(&pf.path, w.ranges);
+        }
+        // shorter module paths are further out, and `mod x;` is outside x's `#![cfg]`
+        mods.sort_by_key(|(m, inner, _)| (m.len(), *inner));
+
+        let vis = &self.effective_visibility;
+        let items = self.functions.iter()
+            .filter(|f| vis.fn_public(f))
+            .map(|f| {
+                let kind = if f.kind == FnKind::FreeFn { SymbolKind::Fn } else { SymbolKind::Method };
+                (&f.fq_name, kind, &f.location)
+            })
+            .chain(self.types.iter().filter(|t| vis.type_public(t)).map(|t| (&t.fq_name, SymbolKind::Type(t.kind), &t.location)));
+
+        let mut out = Vec::new();
+        for (item, kind, location) in items {
+            let file_module = file_module_path(&location.path);
+            let mut gates: Vec<&TokenStream> = mods.iter()
+                .filter(|(m, _, _)| file_module.starts_with(m))
+                .flat_map(|(_, _, cfgs)| cfgs)
+                .collect();
+            if let Some(at) = location.byte_range.as_ref().map(|r| r.start) {
+                // ranges are pushed outermost first
+                gates.extend(ranges.get(location.path.as_path()).into_iter().flatten().filter(|(r, _)| r.contains(&at)).map(|(_, c)| c));
+            }
+            if gates.is_empty() { continue; }
+            let mut required = BTreeSet::new();
+            for g in &gates { required_features(g, &mut required); }
+            out.push(FeatureGate {
+                item: item.to_string(),
+                kind,
+                cfgs: gates.iter().map(|g| tidy_tokens(&g.to_string())).collect(),
+                required,
+                location: location.clone(),
+            });
+        }
+        out
+    }
+
+    /// Features `default` turns on, following features that enable others;
+    /// empty when the scan read no `[features]`.
+    pub fn default_features(&self) -> BTreeSet<String> {
+        let mut on = BTreeSet::new();
+        let Some(declared) = &self.declared_features else { return on };
+        let mut todo = vec!["default"];
+        while let Some(f) = todo.pop() {
+            for e in declared.get(f).into_iter().flatten() {
+                // `dep:x` and `x/feat` switch on dependencies, not features of ours
+                if e.starts_with("dep:") || e.contains('/') { continue; }
+                if on.insert(e.clone()) { todo.push(e); }
+            }
+        }
+        on
+    }
+
+    /// Public items that need a feature `default` doesn't turn on: missing from
+    /// a default build, and from docs.rs unless it's configured otherwise.
+    pub fn non_default_api(&self) -> Vec<FeatureGate> {
+        let defaults = self.default_features();
+        self.feature_gates().into_iter().filter(|g| !g.required.is_subset(&defaults)).collect()
+    }
+}
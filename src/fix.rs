@@ -0,0 +1,395 @@
+//! Suggested source edits (`Fix`), what produces them, and `apply`.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{parsed_offset, FileLocation, FilePath};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A suggested edit to one file: `byte_range` becomes `replacement` (an empty range inserts).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fix {
+    /// As the model stores it; see `Klepto::absolute_path`.
+    pub path: FilePath,
+    pub byte_range: Range<usize>,
+    pub replacement: String,
+    /// What the edit does, for previews.
+    pub description: String,
+}
+
+/// `///` templates for every undocumented public function and type: a summary
+/// line, plus `# Errors` for `Result`-returning fns, `# Panics` for fns with a
+/// panic site of their own (see `Klepto::panic_sites`), and `# Safety` for
+/// `unsafe fn`s. Each is inserted above the item's attributes.
+///
+/// Needs `span-locations` and retained sources; items without them get no fix.
+pub fn doc_stubs(k: &Klepto) -> Vec<Fix> {
+    let vis = &k.effective_visibility;
+    let sources: HashMap<&Path, &str> = k.files.iter().map(|pf| (pf.path.as_path(), pf.source.as_str())).collect();
+
+    let sites = k.panic_sites();
+    let panicking: HashSet<&str> = sites.iter().map(|s| s.fn_name.as_str()).collect();
+
+    let mut out = Vec::new();
+    for f in k.functions.iter().filter(|f| !f.has_docs && vis.fn_public(f)) {
+        let mut sections = Vec::new();
+        if f.return_ty.as_deref().is_some_and(|r| r.split('<').next().unwrap_or(r).trim().ends_with("Result")) {
+            sections.push(("Errors", "when this returns `Err`."));
+        }
+        if panicking.contains(f.fq_name.as_str()) {
+            sections.push(("Panics", "when this panics."));
+        }
+        if f.is_unsafe {
+            sections.push(("Safety", "what callers must uphold."));
+        }
+        out.extend(stub(&sources, &f.location, &f.name, &sections));
+    }
+    for t in k.types.iter().filter(|t| !t.has_docs && vis.type_public(t)) {
+        out.extend(stub(&sources, &t.location, &t.name, &[]));
+    }
+    out.sort_by(|a, b| (&a.path, a.byte_range.start).cmp(&(&b.path, b.byte_range.start)));
+    out
+}
+
+// the doc comment, indented like the item's first line, inserted at that line's start
+fn stub(sources: &HashMap<&Path, &str>, loc: &FileLocation, name: &str, sections: &[(&str, &str)]) -> Option<Fix> {
+    let source = sources.get(loc.path.as_path()).filter(|s| !s.is_empty())?;
+    let at = loc.byte_range.as_ref()?.start + parsed_offset(source);
+    let line_start = source.get(..at)?.rfind('\n').map_or(0, |i| i + 1);
+    let indent = &source[line_start..at];
+    if !indent.chars().all(char::is_whitespace) { return None; }
+
+    let mut text = format!("{}/// TODO: summarize `{}`.\n", indent, name);
+    for (heading, todo) in sections {
+        text.push_str(&format!("{i}///\n{i}/// # {}\n{i}///\n{i}/// TODO: {}\n", heading, todo, i = indent));
+    }
+    Some(Fix {
+        path: loc.path.clone(),
+        byte_range: line_start..line_start,
+        replacement: text,
+        description: format!("add a doc comment to `{}`", name),
+    })
+}
+
+/// Whether `apply` touches the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyMode {
+    /// Only compute the diff.
+    DryRun,
+    Write,
+}
+
+/// A fix `apply` left out, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FixConflict {
+    pub fix: Fix,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApplyReport {
+    pub applied: Vec<Fix>,
+    pub conflicts: Vec<FixConflict>,
+    /// Unified diff of every change, as `a/<path>` / `b/<path>`.
+    pub diff: String,
+    /// Files written (`Write`) or that would be (`DryRun`).
+    pub files_changed: Vec<PathBuf>,
+}
+
+/// Applies `fixes` to the files they name (paths as given; see
+/// `Klepto::apply_fixes` for paths relative to a workspace root).
+///
+/// Identical fixes are applied once. A fix whose range overlaps one applied
+/// before it (in order), inserts where another already does, or doesn't fit
+/// the file as it is now is left out and reported in `conflicts`.
+pub fn apply(fixes: &[Fix], mode: ApplyMode) -> Result<ApplyReport, KleptoError> {
+    apply_resolved(fixes, mode, Path::to_path_buf)
+}
+
+impl Klepto {
+    /// `fix::apply` with each path resolved by `absolute_path`.
+    pub fn apply_fixes(&self, fixes: &[Fix], mode: ApplyMode) -> Result<ApplyReport, KleptoError> {
+        apply_resolved(fixes, mode, |p| self.absolute_path(p))
+    }
+}
+
+fn apply_resolved(fixes: &[Fix], mode: ApplyMode, resolve: impl Fn(&Path) -> PathBuf) -> Result<ApplyReport, KleptoError> {
+    let mut by_file: BTreeMap<&FilePath, Vec<&Fix>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for f in fixes.iter().filter(|f| seen.insert(*f)) {
+        by_file.entry(&f.path).or_default().push(f);
+    }
+
+    let mut report = ApplyReport::default();
+    for (path, fixes) in by_file {
+        let full = resolve(path);
+        let old = std::fs::read_to_string(&full).map_err(|e| KleptoError::Io { path: full.clone(), source: e })?;
+
+        let mut accepted: Vec<&Fix> = Vec::new();
+        for f in fixes {
+            let r = &f.byte_range;
+            let reason = if r.start > r.end || !old.is_char_boundary(r.start) || !old.is_char_boundary(r.end) || r.end > old.len() {
+                Some("range doesn't fit the file (changed since the scan?)".to_string())
+            } else {
+                accepted.iter().find(|a| overlaps(&a.byte_range, r)).map(|a| format!("overlaps an earlier fix: {}", a.description))
+            };
+            match reason {
+                Some(reason) => report.conflicts.push(FixConflict { fix: f.clone(), reason }),
+                None => accepted.push(f),
+            }
+        }
+        if accepted.is_empty() { continue; }
+        accepted.sort_by_key(|f| (f.byte_range.start, f.byte_range.end));
+
+        report.diff.push_str(&unified_diff(&path.portable(), &old, &accepted));
+        if mode == ApplyMode::Write {
+            let new = splice(&old, 0..old.len(), &accepted);
+            std::fs::write(&full, new).map_err(|e| KleptoError::Io { path: full.clone(), source: e })?;
+        }
+        report.files_changed.push(full);
+        report.applied.extend(accepted.into_iter().cloned());
+    }
+    Ok(report)
+}
+
+// two insertions at one spot conflict too: which goes first is a guess
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return a.start == b.start || (a.start > b.start && a.start < b.end) || (b.start > a.start && b.start < a.end);
+    }
+    a.start < b.end && b.start < a.end
+}
+
+// `old[span]` with the (sorted, disjoint) fixes inside it applied
+fn splice(old: &str, span: Range<usize>, fixes: &[&Fix]) -> String {
+    let mut out = String::new();
+    let mut at = span.start;
+    for f in fixes {
+        out.push_str(&old[at..f.byte_range.start]);
+        out.push_str(&f.replacement);
+        at = f.byte_range.end;
+    }
+    out.push_str(&old[at..span.end]);
+    out
+}
+
+const CONTEXT: usize = 3;
+
+fn unified_diff(path: &str, old: &str, fixes: &[&Fix]) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let mut starts = vec![0];
+    starts.extend(old.match_indices('\n').map(|(i, _)| i + 1));
+    let line_of = |byte: usize| starts.partition_point(|&s| s <= byte) - 1;
+    let line_start = |line: usize| starts.get(line).copied().unwrap_or(old.len()).min(old.len());
+
+    // whole old lines [a, b) each group of fixes rewrites; fixes sharing a line share a group
+    let mut regions: Vec<(usize, usize, Vec<&Fix>)> = Vec::new();
+    for &f in fixes {
+        let r = &f.byte_range;
+        let a = line_of(r.start);
+        let mut b = if r.end > r.start { line_of(r.end - 1) + 1 } else if starts[a] == r.start { a } else { a + 1 };
+        // an edit ending at a line start without a `\n` of its own joins that line
+        let ends_line = match f.replacement.chars().last() {
+            Some(c) => c == '\n',
+            None => r.start == 0 || old.as_bytes()[r.start - 1] == b'\n',
+        };
+        if !ends_line && r.end == line_start(b) && b < old_lines.len() { b += 1; }
+        match regions.last_mut() {
+            Some(last) if a < last.1 => {
+                last.1 = last.1.max(b);
+                last.2.push(f);
+            }
+            _ => regions.push((a, b, vec![f])),
+        }
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut delta: isize = 0; // new line count minus old, before the current hunk
+    let mut i = 0;
+    while i < regions.len() {
+        // regions close enough to share context go in one hunk
+        let mut j = i;
+        while j + 1 < regions.len() && regions[j + 1].0 <= regions[j].1 + 2 * CONTEXT { j += 1; }
+
+        let from = regions[i].0.saturating_sub(CONTEXT);
+        let to = (regions[j].1 + CONTEXT).min(old_lines.len());
+        let mut body = String::new();
+        let (mut old_len, mut new_len) = (0, 0);
+        let mut line = from;
+        for (a, b, fs) in &regions[i..=j] {
+            for l in &old_lines[line..*a] { push_line(&mut body, ' ', l); }
+            let new = splice(old, line_start(*a)..line_start(*b), fs);
+            for l in &old_lines[*a..*b] { push_line(&mut body, '-', l); }
+            for l in new.split_inclusive('\n') { push_line(&mut body, '+', l); new_len += 1; }
+            old_len += b - a;
+            new_len += a - line;
+            old_len += a - line;
+            line = *b;
+        }
+        for l in &old_lines[line..to] { push_line(&mut body, ' ', l); }
+        old_len += to - line;
+        new_len += to - line;
+
+        let new_from = (from as isize + delta) as usize;
+        out.push_str(&format!("@@ -{} +{} @@\n", hunk_range(from, old_len), hunk_range(new_from, new_len)));
+        out.push_str(&body);
+        delta += new_len as isize - old_len as isize;
+        i = j + 1;
+    }
+    out
+}
+
+// a last line without `\n` gets the marker `patch` expects after it
+fn push_line(out: &mut String, mark: char, line: &str) {
+    out.push(mark);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+// `start,len` with a 1-based start; an empty range names the line before it
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 { format!("{},0", start) } else { format!("{},{}", start + 1, len) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(name: &str, lib: &str) -> Klepto {
+        let dir = std::env::temp_dir().join(format!("klepto-fix-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        let k = Klepto::new("fix").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        k
+    }
+
+    #[test]
+    fn stubs_get_panics_only_for_panic_sites() {
+        let k = scan("stubs", "pub fn or(x: Option<u8>) -> u8 { x.unwrap_or(0) }\n\
+            pub fn or_else(x: Result<u8, u8>) -> u8 { x.unwrap_or_else(|e| e) }\n\
+            pub fn err(x: Result<u8, u8>) -> u8 { x.expect_err(\"ok\") }\n\
+            pub fn bare(x: Option<u8>) -> u8 { x.unwrap() }\n\
+            pub fn todo() -> u8 { todo!() }\n\
+            pub fn at(v: &[u8]) -> u8 { v[0] }\n\
+            pub unsafe fn raw(p: *const u8) -> Result<u8, ()> { Ok(*p) }\n\
+            pub struct S;\n");
+        let fixes = doc_stubs(&k);
+        let stubs: HashMap<String, &str> = fixes.iter()
+            .map(|f| (f.description.clone(), f.replacement.as_str()))
+            .map(|(d, r)| (d.trim_start_matches("add a doc comment to `").trim_end_matches('`').to_string(), r))
+            .collect();
+        assert_eq!(stubs.len(), 8, "{stubs:?}");
+        for quiet in ["or", "or_else", "err", "S"] {
+            assert!(!stubs[quiet].contains("# Panics"), "{quiet}: {}", stubs[quiet]);
+        }
+        for loud in ["bare", "todo", "at"] {
+            assert!(stubs[loud].contains("# Panics"), "{loud}: {}", stubs[loud]);
+        }
+        assert!(stubs["raw"].contains("# Errors") && stubs["raw"].contains("# Safety"));
+        assert!(stubs["S"].starts_with("/// TODO: summarize `S`.\n"));
+    }
+
+    fn write_temp(name: &str, text: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("klepto-apply-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("f.rs");
+        std::fs::write(&file, text).unwrap();
+        (dir, file)
+    }
+
+    fn edit(file: &Path, range: Range<usize>, replacement: &str) -> Fix {
+        Fix { path: file.into(), byte_range: range, replacement: replacement.to_string(), description: replacement.trim().to_string() }
+    }
+
+    #[test]
+    fn overlapping_fixes_are_reported_not_applied() {
+        let (dir, file) = write_temp("overlap", "abcdef\n");
+        let fixes = [
+            edit(&file, 0..3, "X"),
+            edit(&file, 2..4, "Y"),
+            edit(&file, 0..3, "X"),
+            edit(&file, 6..6, "!"),
+            edit(&file, 6..6, "?"),
+            edit(&file, 5..40, "Z"),
+        ];
+        let report = apply(&fixes, ApplyMode::Write).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "Xdef!\n");
+        assert_eq!(report.applied.len(), 2);
+        let conflicts: Vec<&str> = report.conflicts.iter().map(|c| c.fix.replacement.as_str()).collect();
+        assert_eq!(conflicts, ["Y", "?", "Z"]);
+        assert!(report.conflicts[0].reason.contains("overlaps"));
+        assert!(report.conflicts[2].reason.contains("doesn't fit"));
+    }
+
+    #[test]
+    fn edits_land_where_the_scan_saw_them() {
+        let (dir, file) = write_temp("shift", "one\ntwo\nthree\n");
+        // given back to front, and each changing the length of what follows
+        let fixes = [edit(&file, 8..8, "// x\n"), edit(&file, 4..7, "2222"), edit(&file, 0..3, "1")];
+        let report = apply(&fixes, ApplyMode::Write).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "1\n2222\n// x\nthree\n");
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.files_changed, [file]);
+    }
+
+    #[test]
+    fn dry_runs_leave_files_alone() {
+        let (dir, file) = write_temp("dry", "a\nb\nc\n");
+        let report = apply(&[edit(&file, 2..3, "B")], ApplyMode::DryRun).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "a\nb\nc\n");
+        assert_eq!(report.applied.len(), 1);
+        assert_eq!(report.files_changed, std::slice::from_ref(&file));
+        let p = FilePath::from(&file).portable().into_owned();
+        assert_eq!(report.diff, format!("--- a/{p}\n+++ b/{p}\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"));
+    }
+
+    #[test]
+    fn diffs_keep_context_and_line_numbers_apart() {
+        let old: String = (1..=12).map(|n| format!("{n}\n")).collect();
+        let p = Path::new("f.rs");
+        let fixes = [edit(p, 0..0, "0\n"), edit(p, 21..23, "eleven")];
+        let refs: Vec<&Fix> = fixes.iter().collect();
+        assert_eq!(unified_diff("f.rs", &old, &refs), "--- a/f.rs\n+++ b/f.rs\n\
+            @@ -1,3 +1,4 @@\n+0\n 1\n 2\n 3\n\
+            @@ -8,5 +9,5 @@\n 8\n 9\n 10\n-11\n+eleven\n 12\n");
+
+        // edits that drop or never had a `\n` join the line after them
+        let joins = [edit(p, 0..0, "// "), edit(p, 20..21, " ")];
+        let refs: Vec<&Fix> = joins.iter().collect();
+        assert_eq!(unified_diff("f.rs", &old, &refs), "--- a/f.rs\n+++ b/f.rs\n\
+            @@ -1,4 +1,4 @@\n-1\n+// 1\n 2\n 3\n 4\n\
+            @@ -7,6 +7,5 @@\n 7\n 8\n 9\n-10\n-11\n+10 11\n 12\n");
+    }
+
+    #[test]
+    fn diffs_mark_a_missing_final_newline() {
+        let p = Path::new("f.rs");
+        let changed = [edit(p, 2..3, "B")];
+        let refs: Vec<&Fix> = changed.iter().collect();
+        assert_eq!(unified_diff("f.rs", "a\nb", &refs),
+            "--- a/f.rs\n+++ b/f.rs\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+B\n\\ No newline at end of file\n");
+
+        let added = [edit(p, 3..3, "\n")];
+        let refs: Vec<&Fix> = added.iter().collect();
+        assert_eq!(unified_diff("f.rs", "a\nb", &refs),
+            "--- a/f.rs\n+++ b/f.rs\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n");
+
+        let context = [edit(p, 0..1, "A")];
+        let refs: Vec<&Fix> = context.iter().collect();
+        assert_eq!(unified_diff("f.rs", "a\nb", &refs),
+            "--- a/f.rs\n+++ b/f.rs\n@@ -1,2 +1,2 @@\n-a\n+A\n b\n\\ No newline at end of file\n");
+    }
+}
@@ -0,0 +1,500 @@
+use crate::extract::file_module_path;
+use crate::klepto::Klepto;
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EdgeKind {
+    /// `foo()`, `Type::new()`, `crate::a::b()`
+    Call,
+    /// `self.helper()`, or `x.method()` when exactly one workspace method has that name
+    MethodCall,
+    /// fn named as a value, e.g. `.map(Self::helper)`
+    Reference,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    pub kind: EdgeKind,
+    pub location: FileLocation,
+}
+
+/// Intra-workspace call graph: `CallOccurrence`/`PathOccurrence` resolved against
+/// captured fn names.
+///
+/// Resolution is syntactic. A call is linked when its path matches the tail of
+/// exactly one captured `fq_name` (same-file candidates win ties); `Self::`
+/// and `self.` resolve through the enclosing impl. Calls into dependencies and
+/// ambiguous method calls are left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CallGraph {
+    edges: Vec<CallEdge>,
+    callees: BTreeMap<String, BTreeSet<String>>,
+    callers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CallGraph {
+    pub fn build(k: &Klepto) -> Self {
+        let r = Resolver::new(k);
+        let mut g = CallGraph::default();
+        let mut seen = BTreeSet::new();
+
+        for c in &k.calls {
+            let Some(caller) = c.enclosing_fn.as_deref() else { continue; };
+            let resolved = match c.receiver.as_deref() {
+                Some(recv) => r.method(caller, recv, &c.callee).map(|f| (f, EdgeKind::MethodCall)),
+                None => r.path(caller, &c.callee, &c.location).map(|f| (f, EdgeKind::Call)),
+            };
+            if let Some((callee, kind)) = resolved {
+                g.push(&mut seen, caller, callee, kind, &c.location);
+            }
+        }
+        for p in &k.paths {
+            let Some(caller) = p.enclosing_fn.as_deref() else { continue; };
+            if let Some(callee) = r.path(caller, &p.path, &p.location) {
+                g.push(&mut seen, caller, callee, EdgeKind::Reference, &p.location);
+            }
+        }
+        g
+    }
+
+    // a call site also shows up as a path occurrence; keep the first (more specific) edge
+    fn push(&mut self, seen: &mut BTreeSet<(String, String, u32, u32)>, caller: &str, callee: &str, kind: EdgeKind, location: &FileLocation) {
+        let key = (caller.to_string(), callee.to_string(), location.line.unwrap_or(0), location.column.unwrap_or(0));
+        if location.line.is_some() && !seen.insert(key) {
+            return;
+        }
+        self.callees.entry(caller.to_string()).or_default().insert(callee.to_string());
+        self.callers.entry(callee.to_string()).or_default().insert(caller.to_string());
+        self.edges.push(CallEdge { caller: caller.to_string(), callee: callee.to_string(), kind, location: location.clone() });
+    }
+
+    pub fn edges(&self) -> &[CallEdge] { &self.edges }
+
+    /// Every fn that calls or is called by something.
+    pub fn nodes(&self) -> BTreeSet<&str> {
+        self.callees.keys().chain(self.callers.keys()).map(String::as_str).collect()
+    }
+
+    /// Direct callers of `fq_name`.
+    pub fn callers_of(&self, fq_name: &str) -> Vec<&str> {
+        self.callers.get(fq_name).into_iter().flatten().map(String::as_str).collect()
+    }
+
+    /// Direct callees of `fq_name`.
+    pub fn callees_of(&self, fq_name: &str) -> Vec<&str> {
+        self.callees.get(fq_name).into_iter().flatten().map(String::as_str).collect()
+    }
+
+    /// Everything `fq_name` can (transitively) call, excluding itself unless recursive.
+    pub fn reachable_from(&self, fq_name: &str) -> BTreeSet<&str> {
+        walk(&self.callees, fq_name)
+    }
+
+    /// Everything that can (transitively) call `fq_name`.
+    pub fn reaching(&self, fq_name: &str) -> BTreeSet<&str> {
+        walk(&self.callers, fq_name)
+    }
+
+    pub fn is_reachable(&self, from: &str, to: &str) -> bool {
+        self.path_between(from, to).is_some()
+    }
+
+    /// Shortest call chain `from -> ... -> to`, both ends included. With `from == to`
+    /// it's the shortest cycle back to `from`, so only recursive fns reach themselves.
+    pub fn path_between<'g>(&'g self, from: &'g str, to: &str) -> Option<Vec<&'g str>> {
+        // `from` never gets a `prev`, which ends the walk back
+        let mut prev: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(cur) = queue.pop_front() {
+            for next in self.callees.get(cur).into_iter().flatten() {
+                let next = next.as_str();
+                if next == to {
+                    let mut path = vec![next, cur];
+                    let mut at = cur;
+                    while let Some(p) = prev.get(at) {
+                        path.push(p);
+                        at = p;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if next == from || prev.contains_key(next) { continue; }
+                prev.insert(next, cur);
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+}
+
+fn walk<'g>(adj: &'g BTreeMap<String, BTreeSet<String>>, start: &str) -> BTreeSet<&'g str> {
+    let mut out = BTreeSet::new();
+    let mut stack: Vec<&str> = adj.get(start).into_iter().flatten().map(String::as_str).collect();
+    while let Some(n) = stack.pop() {
+        if out.insert(n) {
+            stack.extend(adj.get(n).into_iter().flatten().map(String::as_str));
+        }
+    }
+    out
+}
+
+// `Foo :: < T > :: new` -> ["Foo", "new"]
+fn segments(path: &str) -> Vec<String> {
+    let mut s = String::with_capacity(path.len());
+    let mut depth = 0usize;
+    for ch in path.chars().filter(|c| !c.is_whitespace()) {
+        match ch {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => s.push(ch),
+            _ => {}
+        }
+    }
+    s.split("::").filter(|x| !x.is_empty()).map(str::to_string).collect()
+}
+
+fn owner(f: &CapturedFn) -> Option<&str> {
+    match &f.kind {
+        FnKind::FreeFn => None,
+        FnKind::ImplMethod { self_ty, .. } => Some(self_ty),
+        FnKind::TraitMethod { trait_name } => Some(trait_name),
+    }
+}
+
+struct Resolver<'k> {
+    crate_name: &'k str,
+    by_fq: HashMap<&'k str, &'k CapturedFn>,
+    by_name: HashMap<&'k str, Vec<&'k CapturedFn>>,
+}
+
+impl<'k> Resolver<'k> {
+    fn new(k: &'k Klepto) -> Self {
+        let mut by_name: HashMap<&str, Vec<&CapturedFn>> = HashMap::new();
+        for f in &k.functions {
+            by_name.entry(f.name.as_str()).or_default().push(f);
+        }
+        Resolver {
+            crate_name: &k.crate_name,
+            by_fq: k.functions.iter().map(|f| (f.fq_name.as_str(), f)).collect(),
+            by_name,
+        }
+    }
+
+    // exactly one candidate, or exactly one in the caller's file
+    fn pick(&self, cands: Vec<&'k CapturedFn>, at: &FileLocation) -> Option<&'k str> {
+        match cands.as_slice() {
+            [one] => Some(one.fq_name.as_str()),
+            [] => None,
+            _ => {
+                let local: Vec<_> = cands.iter().filter(|f| f.location.path == at.path).collect();
+                match local.as_slice() {
+                    [one] => Some(one.fq_name.as_str()),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn path(&self, caller: &str, callee: &str, at: &FileLocation) -> Option<&'k str> {
+        let mut segs = segments(callee);
+        while segs.len() > 1 && (matches!(segs[0].as_str(), "crate" | "self" | "super") || segs[0] == self.crate_name) {
+            segs.remove(0);
+        }
+        let enclosing = self.by_fq.get(caller);
+        if segs.first().is_some_and(|s| s == "Self") {
+            segs[0] = enclosing.and_then(|f| owner(f))?.to_string();
+        }
+        let name = segs.last()?;
+        let cands = self.by_name.get(name.as_str())?;
+
+        if segs.len() == 1 {
+            // bare `foo()`: a free fn, preferring the caller's module
+            let free: Vec<_> = cands.iter().copied().filter(|f| matches!(f.kind, FnKind::FreeFn)).collect();
+            if let Some(enc) = enclosing {
+                let same_mod: Vec<_> = free.iter().copied().filter(|f| f.module_path == enc.module_path).collect();
+                if !same_mod.is_empty() {
+                    return self.pick(same_mod, at);
+                }
+            }
+            return self.pick(free, at);
+        }
+
+        // qualified: the shorter of (call path, fq_name minus crate) must be a tail of the
+        // other; fq_names leave out file modules, so also try with them (`a::f` for src/a.rs)
+        let tail = |fq: &[&str]| {
+            let n = fq.len().min(segs.len());
+            n >= 2 && fq[fq.len() - n..].iter().zip(&segs[segs.len() - n..]).all(|(a, b)| a == b)
+        };
+        let matching = cands.iter().copied().filter(|f| {
+            let fq: Vec<&str> = f.fq_name.split("::").skip(1).collect();
+            let file_mods = file_module_path(&f.location.path);
+            tail(&fq) || (!file_mods.is_empty() && tail(&file_mods.iter().map(String::as_str).chain(fq.iter().copied()).collect::<Vec<_>>()))
+        }).collect();
+        self.pick(matching, at)
+    }
+
+    fn method(&self, caller: &str, receiver: &str, name: &str) -> Option<&'k str> {
+        let cands = self.by_name.get(name)?;
+        let methods: Vec<_> = cands.iter().copied().filter(|f| owner(f).is_some()).collect();
+        if receiver.trim() == "self" {
+            let own = self.by_fq.get(caller).and_then(|f| owner(f))?;
+            let mine: Vec<_> = methods.iter().copied().filter(|f| owner(f) == Some(own)).collect();
+            if let [one] = mine.as_slice() {
+                return Some(one.fq_name.as_str());
+            }
+        }
+        match methods.as_slice() {
+            [one] => Some(one.fq_name.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Crate-relative absolute readings of `segs` written in module `base`
+/// (`crate::`/`self::`/`super::` handled). A plain first segment may be a child
+/// module or an extern crate, so both readings are returned.
+pub(crate) fn resolve_path(segs: &[String], base: &[String], crate_name: &str) -> Vec<Vec<String>> {
+    let Some(first) = segs.first() else { return Vec::new(); };
+    match first.as_str() {
+        "crate" => vec![segs[1..].to_vec()],
+        s if s == crate_name => vec![segs[1..].to_vec()],
+        "self" => vec![[base, &segs[1..]].concat()],
+        "super" => {
+            let ups = segs.iter().take_while(|s| *s == "super").count();
+            let parent = &base[..base.len().saturating_sub(ups)];
+            vec![[parent, &segs[ups..]].concat()]
+        }
+        _ => vec![[base, segs].concat(), segs.to_vec()],
+    }
+}
+
+/// Crate-relative module of an item: file modules + inline modules.
+pub(crate) fn module_of(path: &std::path::Path, module_path: &[IStr]) -> Vec<String> {
+    let mut m = file_module_path(path);
+    m.extend(module_path.iter().map(|s| s.to_string()));
+    m
+}
+
+fn module_label(m: &[String]) -> String {
+    std::iter::once("crate").chain(m.iter().map(String::as_str)).collect::<Vec<_>>().join("::")
+}
+
+/// Module dependency graph: `a -> b` when module `a` imports or names a path in `b`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ModuleGraph {
+    pub modules: BTreeSet<String>,
+    pub edges: BTreeMap<String, BTreeMap<String, usize>>, // from -> to -> reference count
+}
+
+impl ModuleGraph {
+    pub fn build(k: &Klepto) -> Self {
+        let mut known: BTreeSet<Vec<String>> = BTreeSet::new();
+        for f in &k.files {
+            known.insert(file_module_path(&f.path));
+        }
+        for f in &k.functions { known.insert(module_of(&f.location.path, &f.module_path)); }
+        for t in &k.types { known.insert(module_of(&t.location.path, &t.module_path)); }
+
+        let mut g = ModuleGraph { modules: known.iter().map(|m| module_label(m)).collect(), ..Default::default() };
+        // only absolute (`crate::`/`self::`/`super::`) paths get here: longest known module prefix
+        let target = |segs: &[String], base: &[String]| -> Option<Vec<String>> {
+            let abs = resolve_path(segs, base, &k.crate_name).into_iter().next()?;
+            (0..=abs.len()).rev().map(|n| abs[..n].to_vec()).find(|m| known.contains(m))
+        };
+
+        let mut add = |from: Vec<String>, to: Vec<String>| {
+            if from != to {
+                *g.edges.entry(module_label(&from)).or_default().entry(module_label(&to)).or_default() += 1;
+            }
+        };
+        for imp in k.imports.iter().filter(|i| (i.is_internal || i.root == k.crate_name) && !i.expanded_from_glob) {
+            let base = module_of(&imp.location.path, &imp.module_path);
+            let mut segs = vec![imp.root.clone()];
+            segs.extend(imp.segments.iter().filter(|s| *s != "*").cloned());
+            if let Some(to) = target(&segs, &base) { add(base, to); }
+        }
+        for p in &k.paths {
+            let segs: Vec<String> = p.path.split("::").map(str::to_string).collect();
+            if !matches!(segs[0].as_str(), "crate" | "self" | "super") && segs[0] != k.crate_name { continue; }
+            let base = module_of(&p.location.path, &p.module_path);
+            if let Some(to) = target(&segs, &base) { add(base, to); }
+        }
+        g
+    }
+
+    /// Mermaid `graph LR` source, ready to paste into GitHub markdown.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("graph LR\n");
+        for m in &self.modules {
+            out.push_str(&format!("    {}[\"{}\"]\n", mermaid_id(m), m));
+        }
+        for (from, tos) in &self.edges {
+            for to in tos.keys() {
+                out.push_str(&format!("    {} --> {}\n", mermaid_id(from), mermaid_id(to)));
+            }
+        }
+        out
+    }
+}
+
+/// External crates used by the scanned code (std/core/alloc excluded).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DependencyGraph {
+    pub crate_name: String,
+    pub deps: BTreeMap<String, usize>, // crate root -> import count
+}
+
+impl DependencyGraph {
+    pub fn build(k: &Klepto) -> Self {
+        // `use context::X` (2018 relative path) looks external but names a local module
+        let local_modules: BTreeSet<String> = k.files.iter().flat_map(|f| file_module_path(&f.path)).collect();
+        let own = k.crate_name.replace('-', "_");
+
+        let mut deps = BTreeMap::new();
+        for imp in &k.imports {
+            let root = imp.root.replace('-', "_");
+            if root == own || local_modules.contains(&root) { continue; }
+            if matches!(imp.origin, Some(ImportOrigin::Dependency | ImportOrigin::WorkspaceMember | ImportOrigin::UnknownExternal)) {
+                *deps.entry(root).or_default() += 1;
+            }
+        }
+        DependencyGraph { crate_name: k.crate_name.clone(), deps }
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let root = mermaid_id(&self.crate_name);
+        let mut out = format!("graph LR\n    {}[\"{}\"]\n", root, self.crate_name);
+        for (dep, n) in &self.deps {
+            out.push_str(&format!("    {} -->|{}| {}[\"{}\"]\n", root, n, mermaid_id(&format!("dep::{}", dep)), dep));
+        }
+        out
+    }
+}
+
+impl CallGraph {
+    /// Mermaid `graph LR` of the resolved call edges (one arrow per caller/callee pair).
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("graph LR\n");
+        for n in self.nodes() {
+            out.push_str(&format!("    {}[\"{}\"]\n", mermaid_id(n), n));
+        }
+        for (from, tos) in &self.callees {
+            for to in tos {
+                out.push_str(&format!("    {} --> {}\n", mermaid_id(from), mermaid_id(to)));
+            }
+        }
+        out
+    }
+}
+
+// mermaid node ids must be plain identifiers, and not keywords like `end`: `n_`, then
+// the name with anything but ASCII letters and digits as `_<hex>_`, so names never share an id
+fn mermaid_id(s: &str) -> String {
+    let mut id = String::from("n_");
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c);
+        } else {
+            id.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(name: &str, files: &[(&str, &str)]) -> Klepto {
+        let dir = std::env::temp_dir().join(format!("klepto-graph-{}-{}", name, std::process::id()));
+        for (path, text) in files {
+            let path = dir.join("src").join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        let k = Klepto::new("g").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        k
+    }
+
+    fn edges(g: &CallGraph, caller: &str) -> Vec<(String, EdgeKind)> {
+        let mut out: Vec<_> = g.edges().iter().filter(|e| e.caller == caller).map(|e| (e.callee.clone(), e.kind)).collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    const LIB: &str = "mod a;\n\
+        pub fn top() { helper(); a::run(); crate::a::run(); Thing::new().go(); dep::call(); }\n\
+        fn helper() { helper(); }\n\
+        pub fn even(n: u32) -> bool { n == 0 || odd(n - 1) }\n\
+        fn odd(n: u32) -> bool { n != 0 && even(n - 1) }\n\
+        pub struct Thing;\n\
+        impl Thing {\n\
+            pub fn new() -> Self { Self::make() }\n\
+            fn make() -> Self { Thing }\n\
+            pub fn go(&self) { self.step(); [1].iter().map(Self::twice).count(); }\n\
+            fn step(&self) {}\n\
+            fn twice(x: &i32) -> i32 { x * 2 }\n\
+        }\n\
+        pub struct Other;\n\
+        impl Other { fn step(&self) {} }\n";
+    const A: &str = "pub fn run() { super::helper(); x.step(); }\n";
+
+    #[test]
+    fn calls_resolve_through_paths_self_and_receivers() {
+        let k = scan("resolve", &[("lib.rs", LIB), ("a.rs", A)]);
+        let g = CallGraph::build(&k);
+        let e = |callee: &str, kind| (callee.to_string(), kind);
+        assert_eq!(edges(&g, "g::top"), [
+            e("g::Thing::go", EdgeKind::MethodCall),
+            e("g::Thing::new", EdgeKind::Call),
+            e("g::helper", EdgeKind::Call),
+            e("g::run", EdgeKind::Call),
+        ]);
+        assert_eq!(edges(&g, "g::Thing::new"), [e("g::Thing::make", EdgeKind::Call)]);
+        assert_eq!(edges(&g, "g::Thing::go"), [e("g::Thing::step", EdgeKind::MethodCall), e("g::Thing::twice", EdgeKind::Reference)]);
+        // `x.step()` could be either type's `step`, so it isn't linked
+        assert_eq!(edges(&g, "g::run"), [e("g::helper", EdgeKind::Call)]);
+        assert_eq!(g.callers_of("g::helper"), ["g::helper", "g::run", "g::top"]);
+    }
+
+    #[test]
+    fn only_recursive_fns_reach_themselves() {
+        let k = scan("cycles", &[("lib.rs", LIB), ("a.rs", A)]);
+        let g = CallGraph::build(&k);
+        assert_eq!(g.path_between("g::helper", "g::helper"), Some(vec!["g::helper", "g::helper"]));
+        assert_eq!(g.path_between("g::even", "g::even"), Some(vec!["g::even", "g::odd", "g::even"]));
+        assert!(!g.is_reachable("g::top", "g::top"));
+        assert!(g.reachable_from("g::helper").contains("g::helper"));
+        assert!(!g.reachable_from("g::top").contains("g::top"));
+        assert_eq!(g.path_between("g::top", "g::make"), None);
+        assert_eq!(g.path_between("g::top", "g::Thing::make"), Some(vec!["g::top", "g::Thing::new", "g::Thing::make"]));
+    }
+
+    #[test]
+    fn mermaid_ids_keep_names_apart() {
+        assert_ne!(mermaid_id("a::b_c"), mermaid_id("a_b::c"));
+        assert_ne!(mermaid_id("a-b"), mermaid_id("a_b"));
+        assert_eq!(mermaid_id("end"), "n_end");
+        assert!(mermaid_id("g::x").chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+
+        let mut g = CallGraph::default();
+        let at = FileLocation { path: "src/lib.rs".into(), line: None, column: None, byte_range: None };
+        g.push(&mut BTreeSet::new(), "a::b_c", "x", EdgeKind::Call, &at);
+        g.push(&mut BTreeSet::new(), "a_b::c", "y", EdgeKind::Call, &at);
+        let m = g.to_mermaid();
+        assert_eq!(m.lines().filter(|l| l.contains("-->")).count(), 2);
+        assert!(m.contains(&format!("{} --> {}", mermaid_id("a::b_c"), mermaid_id("x"))));
+        assert!(m.contains(&format!("{} --> {}", mermaid_id("a_b::c"), mermaid_id("y"))));
+    }
+}
@@ -4,8 +4,9 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 /// Extension methods for `Vec<StolenPath>` / `&[StolenPath]`
 ///
 /// Usage:
-/// ```rust
-/// use klepto::ImportVecExt;
+/// ```rust,no_run
+/// use klepto::prelude::*;
+/// # let k = Klepto::new("crate").scan_in_folder("src").parse().unwrap();
 /// let imports = k.imports().collect().unique_prefer_pub_use();
 /// let by_origin = imports.group_by_origin_owned();
 /// ```
@@ -26,12 +27,14 @@ pub trait ImportVecExt {
     /// Group by root crate segment (`std`, `serde`, `crate`, etc.).
     fn group_by_root(&self) -> BTreeMap<String, Vec<&StolenPath>>;
 
-    /// A quick “at a glance” count breakdown.
+    /// A quick “at a glance” count breakdown of the `use`s as written (names
+    /// expanded from globs aren't counted).
     fn summary(&self) -> ImportSummary;
 }
 
 /// Simple import summary counts (post-dedup usually).
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ImportSummary {
     pub total: usize,
     pub by_origin: BTreeMap<ImportOrigin, usize>,
@@ -144,9 +147,9 @@ impl ImportVecExt for Vec<StolenPath> {
 
     fn summary(&self) -> ImportSummary {
         let mut s = ImportSummary::default();
-        s.total = self.len();
 
-        for i in self {
+        for i in self.iter().filter(|i| !i.expanded_from_glob) {
+            s.total += 1;
             *s.by_origin.entry(origin_or_unknown(i)).or_insert(0) += 1;
             *s.by_root.entry(i.root.clone()).or_insert(0) += 1;
 
@@ -166,3 +169,83 @@ impl ImportVecExt for Vec<StolenPath> {
         s
     }
 }
+
+/// Adds a `StolenPath` (flagged `expanded_from_glob`) for each name a glob of a
+/// scanned module brings in: `use crate::a::*`, `use self::b::*`, `use super::*`,
+/// `use <crate_name>::c::*` and `use child::*`. Names are the module's functions, types, child
+/// modules and non-glob imports; private ones only reach globs inside the module
+/// (`use super::*` in a test module). Globs of a glob aren't followed.
+pub(crate) fn expand_glob_imports(
+    crate_name: &str,
+    imports: &mut Vec<StolenPath>,
+    functions: &[crate::model::CapturedFn],
+    types: &[crate::model::CapturedType],
+    modules: &[crate::model::ModuleDecl],
+) {
+    use crate::visibility::full_module_path;
+
+    // module -> (name, is_public)
+    let mut names: HashMap<Vec<String>, Vec<(String, bool)>> = HashMap::new();
+    let mut add = |module: Vec<String>, name: &str, public: bool| names.entry(module).or_default().push((name.to_string(), public));
+    for f in functions.iter().filter(|f| f.kind == crate::model::FnKind::FreeFn) {
+        add(full_module_path(&f.location.path, &f.module_path), &f.name, f.is_public);
+    }
+    for t in types {
+        add(full_module_path(&t.location.path, &t.module_path), &t.name, t.is_public);
+    }
+    for m in modules {
+        add(full_module_path(&m.location.path, &m.module_path), &m.name, m.is_public);
+    }
+    for i in imports.iter() {
+        let name = match &i.kind {
+            UseKind::Glob => continue,
+            UseKind::Rename { alias } if alias == "_" => continue,
+            UseKind::Rename { alias } => alias.as_str(),
+            UseKind::Name => match i.segments.last().map(String::as_str) {
+                Some("self") => i.segments.iter().rev().nth(1).unwrap_or(&i.root).as_str(),
+                Some(last) => last,
+                None => i.root.as_str(),
+            },
+        };
+        add(full_module_path(&i.location.path, &i.module_path), name, i.is_public_use);
+    }
+
+    let own = crate_name.replace('-', "_");
+    let mut expanded = Vec::new();
+    for g in imports.iter().filter(|i| i.kind == UseKind::Glob && !i.expanded_from_glob) {
+        let here = full_module_path(&g.location.path, &g.module_path);
+        let mut target = match g.root.as_str() {
+            "crate" => Vec::new(),
+            "self" => here.clone(),
+            "super" => here[..here.len().saturating_sub(1)].to_vec(),
+            r if r.replace('-', "_") == own => Vec::new(),
+            // `use child::*`, relative to the module (2018)
+            r if names.contains_key(&[&here[..], &[r.to_string()]].concat()) => [&here[..], &[r.to_string()]].concat(),
+            _ => continue,
+        };
+        for s in &g.segments {
+            match s.as_str() {
+                "*" | "self" => {}
+                "super" => { target.pop(); }
+                _ => target.push(s.clone()),
+            }
+        }
+        let Some(found) = names.get(&target) else { continue };
+        let inside = here.starts_with(&target);
+        let prefix = g.full_path.strip_suffix('*').unwrap_or(&g.full_path);
+        let mut seen = HashSet::new();
+        for (name, public) in found {
+            if !(*public || inside) || !seen.insert(name) { continue; }
+            let mut segments: Vec<String> = g.segments.iter().filter(|s| *s != "*").cloned().collect();
+            segments.push(name.clone());
+            expanded.push(StolenPath {
+                segments,
+                kind: UseKind::Name,
+                full_path: format!("{}{}", prefix, name),
+                expanded_from_glob: true,
+                ..g.clone()
+            });
+        }
+    }
+    imports.extend(expanded);
+}
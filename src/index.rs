@@ -1,8 +1,11 @@
-use crate::model::{FileLocation, FnKind};
+use crate::model::{CallOccurrence, CapturedFn, CapturedImpl, CapturedType, FileLocation, FnKind, IStr, MacroInvocation, PathOccurrence, StolenPath};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::scope::{FnInfo, Scope, span_bytes, span_start_end};
 use proc_macro2::Span;
 use syn::{spanned::Spanned, visit::Visit};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FnSpan {
     pub fq_name: String,
     pub is_public: bool,
@@ -11,11 +14,16 @@ pub struct FnSpan {
     pub file: std::path::PathBuf,
     pub start: Option<(u32, u32)>, // (line, col)
     pub end: Option<(u32, u32)>,   // (line, col)
+    pub byte_range: Option<std::ops::Range<usize>>,
 }
 
 impl FnSpan {
     pub fn contains(&self, loc: &FileLocation) -> bool {
         if self.file != loc.path { return false; }
+        // byte ranges are exact; line/col only compares start positions
+        if let (Some(outer), Some(inner)) = (&self.byte_range, &loc.byte_range) {
+            return outer.start <= inner.start && inner.end <= outer.end;
+        }
         let (line, col) = match (loc.line, loc.column) {
             (Some(l), Some(c)) => (l, c),
             _ => return false,
@@ -30,216 +38,319 @@ impl FnSpan {
 
 #[derive(Debug, Default, Clone)]
 pub struct EnclosingIndex {
-    by_file: std::collections::HashMap<std::path::PathBuf, Vec<FnSpan>>,
+    by_file: std::collections::HashMap<std::path::PathBuf, FileSpans>,
+}
+
+// saved as the flat list of spans; the per-file forests are rebuilt on load
+impl serde::Serialize for EnclosingIndex {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.by_file.values().flat_map(|f| &f.spans))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for EnclosingIndex {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let mut by_file: HashMap<PathBuf, Vec<FnSpan>> = HashMap::new();
+        for s in Vec::<FnSpan>::deserialize(d)? {
+            by_file.entry(s.file.clone()).or_default().push(s);
+        }
+        Ok(EnclosingIndex { by_file: by_file.into_iter().map(|(k, v)| (k, FileSpans::new(v))).collect() })
+    }
+}
+
+// Fn spans nest (a fn body can hold items, never half of another fn), so the
+// spans of a file form a forest. Sorted by start, the innermost span holding a
+// position is the last span starting at or before it, or one of its ancestors.
+#[derive(Debug, Default, Clone)]
+struct FileSpans {
+    spans: Vec<FnSpan>,         // located spans only, sorted by (start, Reverse(end))
+    parent: Vec<Option<usize>>, // index of the innermost span enclosing spans[i]
+}
+
+impl FileSpans {
+    fn new(mut spans: Vec<FnSpan>) -> Self {
+        spans.retain(|f| f.start.is_some() && f.end.is_some());
+        // outer before inner when two spans start together
+        spans.sort_by_key(|f| (f.start, std::cmp::Reverse(f.end)));
+
+        let mut parent = Vec::with_capacity(spans.len());
+        let mut open: Vec<usize> = Vec::new();
+        for (i, f) in spans.iter().enumerate() {
+            while let Some(&top) = open.last() {
+                if spans[top].end >= f.end { break; }
+                open.pop();
+            }
+            parent.push(open.last().copied());
+            open.push(i);
+        }
+        FileSpans { spans, parent }
+    }
+
+    fn enclosing(&self, loc: &FileLocation) -> Option<&FnSpan> {
+        let pos = Some((loc.line?, loc.column?));
+        let mut i = self.spans.partition_point(|f| f.start <= pos).checked_sub(1)?;
+        loop {
+            let f = &self.spans[i];
+            if pos <= f.end { return Some(f); }
+            i = self.parent[i]?;
+        }
+    }
 }
 
 impl EnclosingIndex {
     pub fn build(crate_name: &str, file_path: &std::path::Path, ast: &syn::File) -> Self {
         let mut v = Builder {
-            crate_name: crate_name.to_string(),
             file_path: file_path.to_path_buf(),
-            mod_stack: Vec::new(),
+            scope: Scope::new(crate_name),
             out: Vec::new(),
-            impl_self_ty: None,
-            impl_trait_ty: None,
-            in_trait: None,
         };
         v.visit_file(ast);
 
-        // sort by start ascending (optional)
-        v.out.sort_by_key(|f| f.start.map(|x| x.0).unwrap_or(0));
-
         let mut idx = EnclosingIndex::default();
-        idx.by_file.insert(file_path.to_path_buf(), v.out);
+        idx.by_file.insert(file_path.to_path_buf(), FileSpans::new(v.out));
         idx
     }
 
     pub fn merge(mut self, other: EnclosingIndex) -> Self {
-        for (k, mut v) in other.by_file {
-            self.by_file.entry(k).or_default().append(&mut v);
+        for (k, v) in other.by_file {
+            match self.by_file.remove(&k) {
+                Some(mut mine) => {
+                    mine.spans.extend(v.spans);
+                    self.by_file.insert(k, FileSpans::new(mine.spans));
+                }
+                None => { self.by_file.insert(k, v); }
+            }
         }
         self
     }
 
+    // the same spans under rewritten file paths (see `Klepto::merge`)
+    pub(crate) fn map_paths(self, f: impl Fn(&Path) -> PathBuf) -> Self {
+        let by_file = self.by_file.into_iter().map(|(k, mut v)| {
+            for s in &mut v.spans { s.file = f(&s.file); }
+            (f(&k), v)
+        }).collect();
+        EnclosingIndex { by_file }
+    }
+
+    pub(crate) fn retain_files(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.by_file.retain(|k, _| keep(k));
+    }
+
+    /// Innermost function whose span contains `loc`.
+    ///
+    /// `O(log n + depth)` per lookup; needs `span-locations` (otherwise nothing is located).
     pub fn enclosing<'a>(&'a self, loc: &FileLocation) -> Option<&'a FnSpan> {
-        let v = self.by_file.get(&loc.path)?;
-        // smallest span that contains loc (best match)
-        v.iter()
-            .filter(|f| f.contains(loc))
-            .min_by_key(|f| {
-                // heuristic “size”: end-start (line range)
-                let sl = f.start.map(|x| x.0).unwrap_or(0);
-                let el = f.end.map(|x| x.0).unwrap_or(u32::MAX);
-                el.saturating_sub(sl)
-            })
+        self.by_file.get(loc.path.as_path())?.enclosing(loc)
+    }
+
+    /// All located fn spans of `path`, ordered by start.
+    pub fn spans(&self, path: &std::path::Path) -> &[FnSpan] {
+        self.by_file.get(path).map(|f| f.spans.as_slice()).unwrap_or(&[])
     }
 }
 
-struct Builder {
-    crate_name: String,
-    file_path: std::path::PathBuf,
-    mod_stack: Vec<String>,
-    out: Vec<FnSpan>,
+/// Positions into `Klepto`'s item vectors, keyed by `fq_name` and by file.
+///
+/// Built by `parse`; call `Klepto::reindex` after editing the vectors by hand.
+#[derive(Debug, Default, Clone)]
+pub struct ItemIndex {
+    fns_by_fq: HashMap<String, Vec<usize>>,
+    types_by_fq: HashMap<String, Vec<usize>>,
+    by_file: HashMap<PathBuf, FileItems>,
+}
 
-    impl_self_ty: Option<String>,
-    impl_trait_ty: Option<String>,
-    in_trait: Option<String>,
+/// Indices of the items captured from one file.
+#[derive(Debug, Default, Clone)]
+pub struct FileItems {
+    pub functions: Vec<usize>,
+    pub types: Vec<usize>,
+    pub impls: Vec<usize>,
+    pub imports: Vec<usize>,
 }
 
-fn span_start_end(span: Span) -> (Option<(u32, u32)>, Option<(u32, u32)>) {
-    #[cfg(feature = "span-locations")]
-    {
-        let s = span.start();
-        let e = span.end();
-        (Some((s.line as u32, s.column as u32)), Some((e.line as u32, e.column as u32)))
+impl ItemIndex {
+    pub fn build(functions: &[CapturedFn], types: &[CapturedType], impls: &[CapturedImpl], imports: &[StolenPath]) -> Self {
+        let mut idx = ItemIndex::default();
+        for (i, f) in functions.iter().enumerate() {
+            idx.fns_by_fq.entry(f.fq_name.clone()).or_default().push(i);
+            idx.file(&f.location.path).functions.push(i);
+        }
+        for (i, t) in types.iter().enumerate() {
+            idx.types_by_fq.entry(t.fq_name.clone()).or_default().push(i);
+            idx.file(&t.location.path).types.push(i);
+        }
+        for (i, x) in impls.iter().enumerate() {
+            idx.file(&x.location.path).impls.push(i);
+        }
+        for (i, x) in imports.iter().enumerate() {
+            idx.file(&x.location.path).imports.push(i);
+        }
+        idx
+    }
+
+    fn file(&mut self, path: &Path) -> &mut FileItems {
+        self.by_file.entry(path.to_path_buf()).or_default()
     }
-    #[cfg(not(feature = "span-locations"))]
-    {
-        let _ = span;
-        (None, None)
+
+    /// Positions in `functions` with this `fq_name` (several when files share module names).
+    pub fn fns(&self, fq_name: &str) -> &[usize] {
+        self.fns_by_fq.get(fq_name).map(Vec::as_slice).unwrap_or(&[])
     }
-}
 
-fn vis_is_public(vis: &syn::Visibility) -> bool {
-    matches!(vis, syn::Visibility::Public(_))
-}
+    pub fn types(&self, fq_name: &str) -> &[usize] {
+        self.types_by_fq.get(fq_name).map(Vec::as_slice).unwrap_or(&[])
+    }
 
-fn path_to_string(p: &syn::Path) -> String {
-    p.segments
-        .iter()
-        .map(|s| s.ident.to_string())
-        .collect::<Vec<_>>()
-        .join("::")
+    pub fn in_file(&self, path: &Path) -> Option<&FileItems> {
+        self.by_file.get(path)
+    }
 }
 
-fn type_to_string(ty: &syn::Type) -> String {
-    use quote::ToTokens;
-    ty.to_token_stream().to_string()
+/// Positions into `Klepto`'s occurrence vectors, keyed by path text, macro name and callee.
+///
+/// Built by `parse`; call `Klepto::reindex` after editing the vectors by hand.
+#[derive(Debug, Default, Clone)]
+pub struct OccurrenceIndex {
+    paths: HashMap<IStr, Vec<u32>>,
+    path_prefixes: HashMap<IStr, Vec<u32>>, // proper `::` prefixes of either form
+    macros: HashMap<IStr, Vec<u32>>,
+    calls: HashMap<IStr, Vec<u32>>,
 }
 
-fn fq_name(crate_name: &str, module_path: &[String], kind: &FnKind, name: &str) -> String {
-    let mut parts = Vec::new();
-    parts.push(crate_name.to_string());
-    parts.extend(module_path.iter().cloned());
-    match kind {
-        FnKind::FreeFn => parts.push(name.to_string()),
-        FnKind::TraitMethod { trait_name } => {
-            parts.push(trait_name.clone());
-            parts.push(name.to_string());
+impl OccurrenceIndex {
+    pub fn build(paths: &[PathOccurrence], macros_inv: &[MacroInvocation], calls: &[CallOccurrence]) -> Self {
+        fn group<'a>(keys: impl Iterator<Item = &'a IStr>) -> HashMap<IStr, Vec<u32>> {
+            let mut m: HashMap<IStr, Vec<u32>> = HashMap::new();
+            for (i, k) in keys.enumerate() {
+                m.entry(k.clone()).or_default().push(i as u32);
+            }
+            m
+        }
+        // written and resolved forms, each position once per key
+        let mut exact: HashMap<IStr, Vec<u32>> = HashMap::new();
+        let mut prefixes: HashMap<IStr, Vec<u32>> = HashMap::new();
+        for (i, p) in paths.iter().enumerate() {
+            let i = i as u32;
+            for form in std::iter::once(&p.path).chain(&p.resolved) {
+                let v = exact.entry(form.clone()).or_default();
+                if v.last() != Some(&i) { v.push(i); }
+                for (at, _) in form.match_indices("::").filter(|&(at, _)| at > 0) {
+                    let v = prefixes.entry(form[..at].into()).or_default();
+                    if v.last() != Some(&i) { v.push(i); }
+                }
+            }
         }
-        FnKind::ImplMethod { self_ty, .. } => {
-            parts.push(self_ty.clone());
-            parts.push(name.to_string());
+        OccurrenceIndex {
+            paths: exact,
+            path_prefixes: prefixes,
+            macros: group(macros_inv.iter().map(|m| &m.name)),
+            calls: group(calls.iter().map(|c| &c.callee)),
         }
     }
-    parts.join("::")
+
+    /// Positions in `paths` whose text, or import-resolved path, is exactly `path`.
+    pub fn paths(&self, path: &str) -> &[u32] {
+        self.paths.get(path).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Positions in `paths` naming `path` or something below it
+    /// (`std::sync::Arc` matches `Arc::new` after `use std::sync::Arc`), in order.
+    pub fn paths_under(&self, path: &str) -> Vec<u32> {
+        let mut out: Vec<u32> = self.paths(path).iter()
+            .chain(self.path_prefixes.get(path).map(Vec::as_slice).unwrap_or(&[]))
+            .copied()
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    pub fn macros(&self, name: &str) -> &[u32] {
+        self.macros.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn calls(&self, callee: &str) -> &[u32] {
+        self.calls.get(callee).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Positions in `calls` whose callee contains `needle`, in order; only the
+    /// distinct callees are searched.
+    pub fn calls_containing(&self, needle: &str) -> Vec<u32> {
+        let mut out: Vec<u32> = self.calls.iter()
+            .filter(|(callee, _)| callee.contains(needle))
+            .flat_map(|(_, ix)| ix.iter().copied())
+            .collect();
+        out.sort_unstable();
+        out
+    }
+}
+
+struct Builder {
+    file_path: std::path::PathBuf,
+    scope: Scope,
+    out: Vec<FnSpan>,
+}
+
+impl Builder {
+    fn push(&mut self, info: FnInfo, span: Span) {
+        let (start, end) = span_start_end(span);
+        self.out.push(FnSpan {
+            fq_name: info.fq_name,
+            is_public: info.is_public,
+            kind: info.kind,
+            file: self.file_path.clone(),
+            start,
+            end,
+            byte_range: span_bytes(span),
+        });
+    }
 }
 
 impl<'ast> Visit<'ast> for Builder {
     fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
         if let Some((_, items)) = &i.content {
-            self.mod_stack.push(i.ident.to_string());
+            let saved = self.scope.enter_mod(i);
             for it in items {
                 self.visit_item(it);
             }
-            self.mod_stack.pop();
+            self.scope.leave(saved);
         }
     }
 
     fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
-        let prev = self.in_trait.take();
-        self.in_trait = Some(i.ident.to_string());
+        let saved = self.scope.enter_trait(i);
         syn::visit::visit_item_trait(self, i);
-        self.in_trait = prev;
+        self.scope.leave(saved);
     }
 
     fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
-        let prev_self = self.impl_self_ty.take();
-        let prev_trait = self.impl_trait_ty.take();
-
-        let self_ty = match &*i.self_ty {
-            syn::Type::Path(tp) => tp
-                .path
-                .segments
-                .last()
-                .map(|s| s.ident.to_string())
-                .unwrap_or_else(|| type_to_string(&i.self_ty)),
-            _ => type_to_string(&i.self_ty),
-        };
-        self.impl_self_ty = Some(self_ty);
-
-        self.impl_trait_ty = i.trait_.as_ref().map(|(_, p, _)| path_to_string(p));
-
+        let saved = self.scope.enter_impl(i);
         syn::visit::visit_item_impl(self, i);
-
-        self.impl_self_ty = prev_self;
-        self.impl_trait_ty = prev_trait;
+        self.scope.leave(saved);
     }
 
     fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
-        let kind = if let Some(tr) = &self.in_trait {
-            FnKind::TraitMethod { trait_name: tr.clone() }
-        } else {
-            FnKind::FreeFn
-        };
-
-        let name = i.sig.ident.to_string();
-        let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-        let (start, end) = span_start_end(i.span());
-
-        self.out.push(FnSpan {
-            fq_name: fq,
-            is_public: vis_is_public(&i.vis) || matches!(kind, FnKind::TraitMethod { .. }),
-            kind,
-            file: self.file_path.clone(),
-            start,
-            end,
-        });
-
+        let info = self.scope.free_fn(&i.sig.ident.to_string(), &i.vis);
+        self.push(info, i.span());
+        let saved = self.scope.enter_fn(&i.attrs);
         syn::visit::visit_item_fn(self, i);
+        self.scope.leave(saved);
     }
 
     fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
-        let self_ty = self.impl_self_ty.clone().unwrap_or_else(|| "<impl>".into());
-        let trait_ty = self.impl_trait_ty.clone();
-
-        let kind = FnKind::ImplMethod { self_ty, trait_ty };
-        let name = i.sig.ident.to_string();
-        let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-        let (start, end) = span_start_end(i.span());
-
-        self.out.push(FnSpan {
-            fq_name: fq,
-            is_public: vis_is_public(&i.vis),
-            kind,
-            file: self.file_path.clone(),
-            start,
-            end,
-        });
-
+        let info = self.scope.impl_fn(&i.sig.ident.to_string(), &i.vis);
+        self.push(info, i.span());
+        let saved = self.scope.enter_fn(&i.attrs);
         syn::visit::visit_impl_item_fn(self, i);
+        self.scope.leave(saved);
     }
 
     fn visit_trait_item_fn(&mut self, i: &'ast syn::TraitItemFn) {
-        let tr = self.in_trait.clone().unwrap_or_else(|| "<trait>".into());
-        let kind = FnKind::TraitMethod { trait_name: tr };
-
-        let name = i.sig.ident.to_string();
-        let fq = fq_name(&self.crate_name, &self.mod_stack, &kind, &name);
-
-        let (start, end) = span_start_end(i.span());
-
-        self.out.push(FnSpan {
-            fq_name: fq,
-            is_public: true,
-            kind,
-            file: self.file_path.clone(),
-            start,
-            end,
-        });
-
+        let info = self.scope.trait_fn(&i.sig.ident.to_string());
+        self.push(info, i.span());
+        let saved = self.scope.enter_fn(&i.attrs);
         syn::visit::visit_trait_item_fn(self, i);
+        self.scope.leave(saved);
     }
 }
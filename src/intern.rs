@@ -0,0 +1,432 @@
+//! Shared strings for the model.
+//!
+//! Occurrences repeat the same module paths, callees, enclosing fn names and
+//! file paths thousands of times; `Klepto::parse` pools them so equal values
+//! share one allocation. `IStr`, `ModulePath` and `FilePath` serialize exactly
+//! like `String`, `Vec<String>` and `PathBuf`, except that a `FilePath` always
+//! serializes with `/` separators (see `portable_path`).
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An immutable, cheaply cloned string; compares and hashes like `str`.
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IStr(Arc<str>);
+
+impl IStr {
+    pub fn new(s: &str) -> Self { Self(Arc::from(s)) }
+    pub fn as_str(&self) -> &str { &self.0 }
+    /// Whether both share one allocation (i.e. came from the same `Interner`).
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool { Arc::ptr_eq(&a.0, &b.0) }
+}
+
+impl Deref for IStr {
+    type Target = str;
+    fn deref(&self) -> &str { &self.0 }
+}
+
+impl AsRef<str> for IStr {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl Borrow<str> for IStr {
+    fn borrow(&self) -> &str { &self.0 }
+}
+
+impl fmt::Debug for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(&*self.0, f) }
+}
+
+impl fmt::Display for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.pad(&self.0) }
+}
+
+impl From<&str> for IStr {
+    fn from(s: &str) -> Self { Self::new(s) }
+}
+
+impl From<String> for IStr {
+    fn from(s: String) -> Self { Self(Arc::from(s)) }
+}
+
+impl From<&String> for IStr {
+    fn from(s: &String) -> Self { Self::new(s) }
+}
+
+impl From<IStr> for String {
+    fn from(s: IStr) -> Self { s.0.to_string() }
+}
+
+impl PartialEq<str> for IStr {
+    fn eq(&self, other: &str) -> bool { &*self.0 == other }
+}
+
+impl PartialEq<&str> for IStr {
+    fn eq(&self, other: &&str) -> bool { &*self.0 == *other }
+}
+
+impl PartialEq<String> for IStr {
+    fn eq(&self, other: &String) -> bool { *self.0 == **other }
+}
+
+impl PartialEq<IStr> for str {
+    fn eq(&self, other: &IStr) -> bool { self == &*other.0 }
+}
+
+impl PartialEq<IStr> for &str {
+    fn eq(&self, other: &IStr) -> bool { *self == &*other.0 }
+}
+
+impl PartialEq<IStr> for String {
+    fn eq(&self, other: &IStr) -> bool { **self == *other.0 }
+}
+
+impl Serialize for IStr {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> { s.serialize_str(&self.0) }
+}
+
+impl<'de> Deserialize<'de> for IStr {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> { String::deserialize(d).map(Into::into) }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for IStr {
+    fn inline_schema() -> bool { true }
+    fn schema_name() -> std::borrow::Cow<'static, str> { String::schema_name() }
+    fn json_schema(g: &mut schemars::SchemaGenerator) -> schemars::Schema { String::json_schema(g) }
+}
+
+/// Module segments of an item (`["api", "v1"]`), shared between everything declared in that module.
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModulePath(Arc<[IStr]>);
+
+impl ModulePath {
+    pub fn to_vec(&self) -> Vec<String> { self.0.iter().map(|s| s.to_string()).collect() }
+}
+
+impl Deref for ModulePath {
+    type Target = [IStr];
+    fn deref(&self) -> &[IStr] { &self.0 }
+}
+
+impl fmt::Debug for ModulePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_list().entries(self.0.iter()).finish() }
+}
+
+impl From<Vec<String>> for ModulePath {
+    fn from(v: Vec<String>) -> Self { v.into_iter().collect() }
+}
+
+impl From<&[String]> for ModulePath {
+    fn from(v: &[String]) -> Self { v.iter().map(IStr::from).collect() }
+}
+
+impl From<Vec<IStr>> for ModulePath {
+    fn from(v: Vec<IStr>) -> Self { Self(v.into()) }
+}
+
+impl FromIterator<String> for ModulePath {
+    fn from_iter<I: IntoIterator<Item = String>>(it: I) -> Self { it.into_iter().map(IStr::from).collect() }
+}
+
+impl FromIterator<IStr> for ModulePath {
+    fn from_iter<I: IntoIterator<Item = IStr>>(it: I) -> Self { Self(it.into_iter().collect()) }
+}
+
+impl<'a> IntoIterator for &'a ModulePath {
+    type Item = &'a IStr;
+    type IntoIter = std::slice::Iter<'a, IStr>;
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+impl PartialEq<Vec<String>> for ModulePath {
+    fn eq(&self, other: &Vec<String>) -> bool { self.0.len() == other.len() && self.0.iter().zip(other).all(|(a, b)| a == b) }
+}
+
+impl Serialize for ModulePath {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> { s.collect_seq(self.0.iter()) }
+}
+
+impl<'de> Deserialize<'de> for ModulePath {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> { Vec::<String>::deserialize(d).map(Into::into) }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for ModulePath {
+    fn inline_schema() -> bool { true }
+    fn schema_name() -> std::borrow::Cow<'static, str> { Vec::<String>::schema_name() }
+    fn json_schema(g: &mut schemars::SchemaGenerator) -> schemars::Schema { Vec::<String>::json_schema(g) }
+}
+
+/// Pool that makes equal strings / module paths share one allocation.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strs: HashSet<IStr>,
+    paths: HashSet<ModulePath>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, s: &str) -> IStr {
+        if let Some(hit) = self.strs.get(s) {
+            return hit.clone();
+        }
+        let s = IStr::new(s);
+        self.strs.insert(s.clone());
+        s
+    }
+
+    /// Replaces `s` with the pooled copy.
+    pub fn share(&mut self, s: &mut IStr) {
+        match self.strs.get(s.as_str()) {
+            Some(hit) => *s = hit.clone(),
+            None => { self.strs.insert(s.clone()); }
+        }
+    }
+
+    /// Replaces `p` (and each of its segments) with the pooled copy.
+    pub fn share_path(&mut self, p: &mut ModulePath) {
+        if let Some(hit) = self.paths.get(p) {
+            *p = hit.clone();
+            return;
+        }
+        *p = p.iter().map(|seg| self.intern(seg)).collect();
+        self.paths.insert(p.clone());
+    }
+
+    /// Distinct strings and module paths held.
+    pub fn len(&self) -> (usize, usize) { (self.strs.len(), self.paths.len()) }
+    pub fn is_empty(&self) -> bool { self.strs.is_empty() && self.paths.is_empty() }
+}
+
+/// `path` with `/` separators on every platform: how paths are serialized and
+/// matched against globs, so snapshots and excludes work the same on Windows.
+pub fn portable_path(path: &Path) -> Cow<'_, str> {
+    with_slashes(path.to_string_lossy(), std::path::MAIN_SEPARATOR)
+}
+
+// `s` with its `sep` separators written as `/`
+pub(crate) fn with_slashes(s: Cow<'_, str>, sep: char) -> Cow<'_, str> {
+    if sep == '/' { s } else { Cow::Owned(s.replace(sep, "/")) }
+}
+
+// a drive prefix (`C:\`, `C:/`), or `\` separators and no `/`: a path written on
+// Windows. A `\` is a legal file name character elsewhere, so nothing else is rewritten
+fn looks_like_windows(s: &str) -> bool {
+    let b = s.as_bytes();
+    let drive = b.len() >= 3 && b[0].is_ascii_alphabetic() && b[1] == b':' && matches!(b[2], b'\\' | b'/');
+    drive || (s.contains('\\') && !s.contains('/'))
+}
+
+/// A source file path, shared by every location in that file.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FilePath(Arc<Path>);
+
+impl FilePath {
+    pub fn new(p: impl AsRef<Path>) -> Self { Self(Arc::from(p.as_ref())) }
+    pub fn as_path(&self) -> &Path { &self.0 }
+    /// See `portable_path`.
+    pub fn portable(&self) -> Cow<'_, str> { portable_path(&self.0) }
+}
+
+impl Default for FilePath {
+    fn default() -> Self { Self::new("") }
+}
+
+impl Deref for FilePath {
+    type Target = Path;
+    fn deref(&self) -> &Path { &self.0 }
+}
+
+impl AsRef<Path> for FilePath {
+    fn as_ref(&self) -> &Path { &self.0 }
+}
+
+impl Borrow<Path> for FilePath {
+    fn borrow(&self) -> &Path { &self.0 }
+}
+
+impl fmt::Debug for FilePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(&*self.0, f) }
+}
+
+impl From<&Path> for FilePath {
+    fn from(p: &Path) -> Self { Self::new(p) }
+}
+
+impl From<PathBuf> for FilePath {
+    fn from(p: PathBuf) -> Self { Self(Arc::from(p)) }
+}
+
+impl From<&PathBuf> for FilePath {
+    fn from(p: &PathBuf) -> Self { Self::new(p) }
+}
+
+impl From<&str> for FilePath {
+    fn from(p: &str) -> Self { Self::new(p) }
+}
+
+impl From<FilePath> for PathBuf {
+    fn from(p: FilePath) -> Self { p.0.to_path_buf() }
+}
+
+impl PartialEq<Path> for FilePath {
+    fn eq(&self, other: &Path) -> bool { &*self.0 == other }
+}
+
+impl PartialEq<&Path> for FilePath {
+    fn eq(&self, other: &&Path) -> bool { &*self.0 == *other }
+}
+
+impl PartialEq<PathBuf> for FilePath {
+    fn eq(&self, other: &PathBuf) -> bool { *self.0 == **other }
+}
+
+impl PartialEq<FilePath> for Path {
+    fn eq(&self, other: &FilePath) -> bool { self == &*other.0 }
+}
+
+impl PartialEq<FilePath> for PathBuf {
+    fn eq(&self, other: &FilePath) -> bool { **self == *other.0 }
+}
+
+impl Serialize for FilePath {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if self.0.to_str().is_none() {
+            return Err(serde::ser::Error::custom("path contains invalid UTF-8 characters"));
+        }
+        s.serialize_str(&self.portable())
+    }
+}
+
+impl<'de> Deserialize<'de> for FilePath {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        // written on Windows before paths were normalized
+        if std::path::MAIN_SEPARATOR == '/' && s.contains('\\') && looks_like_windows(&s) {
+            return Ok(s.replace('\\', "/").as_str().into());
+        }
+        Ok(s.as_str().into())
+    }
+}
+
+/// serde `with` for a path-keyed map, serialized like `FilePath`.
+pub(crate) mod portable_keys {
+    use super::FilePath;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    pub fn serialize<S: Serializer, V: Serialize>(m: &BTreeMap<PathBuf, V>, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(m.iter().map(|(k, v)| (FilePath::new(k), v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(d: D) -> Result<BTreeMap<PathBuf, V>, D::Error> {
+        Ok(BTreeMap::<FilePath, V>::deserialize(d)?.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+/// serde `with` for a list of paths, serialized like `FilePath`.
+pub(crate) mod portable_list {
+    use super::FilePath;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::PathBuf;
+
+    pub fn serialize<S: Serializer>(v: &[PathBuf], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(v.iter().map(FilePath::new))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<PathBuf>, D::Error> {
+        Ok(Vec::<FilePath>::deserialize(d)?.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FilePath {
+    fn inline_schema() -> bool { true }
+    fn schema_name() -> std::borrow::Cow<'static, str> { PathBuf::schema_name() }
+    fn json_schema(g: &mut schemars::SchemaGenerator) -> schemars::Schema { PathBuf::json_schema(g) }
+}
+
+/// Index of a file in a `FileTable`; stable for the life of one parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileId(pub u32);
+
+/// The files of one scan, each path stored once.
+#[derive(Debug, Clone, Default)]
+pub struct FileTable {
+    paths: Vec<FilePath>,
+    ids: HashMap<FilePath, FileId>,
+}
+
+impl FileTable {
+    /// Id of `path`, adding it if new.
+    pub fn intern(&mut self, path: &Path) -> FileId {
+        if let Some(&id) = self.ids.get(path) {
+            return id;
+        }
+        let id = FileId(self.paths.len() as u32);
+        let shared = FilePath::new(path);
+        self.paths.push(shared.clone());
+        self.ids.insert(shared, id);
+        id
+    }
+
+    pub fn id(&self, path: &Path) -> Option<FileId> { self.ids.get(path).copied() }
+    pub fn path(&self, id: FileId) -> Option<&FilePath> { self.paths.get(id.0 as usize) }
+
+    /// Replaces `p` with the table's copy (adding it if new).
+    pub fn share(&mut self, p: &mut FilePath) {
+        let id = self.intern(p);
+        *p = self.paths[id.0 as usize].clone();
+    }
+
+    pub fn len(&self) -> usize { self.paths.len() }
+    pub fn is_empty(&self) -> bool { self.paths.is_empty() }
+    pub fn iter(&self) -> impl Iterator<Item = (FileId, &FilePath)> {
+        self.paths.iter().enumerate().map(|(i, p)| (FileId(i as u32), p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(p: &str) -> FilePath {
+        let json = serde_json::to_string(&FilePath::from(p)).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn read(json_path: &str) -> FilePath {
+        serde_json::from_value(serde_json::json!(json_path)).unwrap()
+    }
+
+    #[test]
+    fn paths_round_trip_through_serde() {
+        for p in ["src/lib.rs", "crates/a b/src/é.rs", "", "/abs/src/main.rs"] {
+            assert_eq!(round_trip(p), FilePath::from(p));
+        }
+        assert_eq!(serde_json::to_string(&FilePath::from("src/lib.rs")).unwrap(), "\"src/lib.rs\"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_windows_looking_paths_get_their_backslashes_rewritten() {
+        assert_eq!(read(r"C:\work\src\lib.rs"), FilePath::from("C:/work/src/lib.rs"));
+        assert_eq!(read(r"src\model\mod.rs"), FilePath::from("src/model/mod.rs"));
+        // a legal Unix file name keeps its `\`
+        assert_eq!(read(r"src/we\ird.rs"), FilePath::from(r"src/we\ird.rs"));
+        assert_eq!(round_trip(r"src/we\ird.rs"), FilePath::from(r"src/we\ird.rs"));
+    }
+
+    #[test]
+    fn separators_become_slashes() {
+        assert_eq!(with_slashes(Cow::Borrowed(r"C:\w\src\lib.rs"), '\\'), "C:/w/src/lib.rs");
+        assert_eq!(with_slashes(Cow::Borrowed(r"src/we\ird.rs"), '/'), r"src/we\ird.rs");
+    }
+}
@@ -7,8 +7,11 @@ use crate::rules::*;
 
 use cargo_metadata::MetadataCommand;
 use globset::{Glob, GlobSet};
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
 use std::time::SystemTime;
 use thiserror::Error;
 use walkdir::WalkDir;
@@ -24,28 +27,168 @@ pub enum KleptoError {
     #[error("glob error: {0}")]
     Glob(#[from] globset::Error),
 
+    #[error("regex error: {0}")]
+    Regex(#[from] regex::Error),
+
     #[error("cargo metadata error: {0}")]
     CargoMeta(#[from] cargo_metadata::Error),
+
+    #[error("rule code {code} is already registered (by {existing})")]
+    DuplicateRuleCode { code: RuleCode, existing: String },
+
+    #[error("rule code {code} is reserved for builtin rules; use `override_builtin` to replace one")]
+    ReservedRuleCode { code: RuleCode },
+
+    #[error("rule code {code} isn't a builtin rule's; use `add_rule`")]
+    NotABuiltinRuleCode { code: RuleCode },
+
+    #[error("snapshot format error: {0}")]
+    SnapshotFormat(#[source] serde_json::Error),
+
+    #[error("saved analysis format error: {0}")]
+    AnalysisFormat(#[source] serde_json::Error),
+
+    #[error("saved analysis version {found} is not supported (this klepto reads {supported})")]
+    AnalysisVersion { found: u32, supported: u32 },
+
+    #[error("snapshot schema version {found} is not supported (this klepto reads up to {supported})")]
+    SnapshotVersion { found: u32, supported: u32 },
+
+    #[error("io error: {0}")]
+    Stream(#[source] std::io::Error),
+
+    #[error("json serialization error: {0}")]
+    Json(#[source] serde_json::Error),
+
+    #[error("git error: {0}")]
+    Git(String),
+
+    #[error("invalid query: {0}")]
+    Query(String),
+
+    #[error("invalid klepto.toml: {0}")]
+    Config(#[source] toml::de::Error),
+
+    #[error("invalid Rust version `{0}` (expected e.g. `1.70` or `1.70.1`)")]
+    RustVersion(String),
+
+    #[error("unsupported rustdoc JSON: {0}")]
+    RustdocFormat(String),
+
+    #[error("invalid compiler diagnostics JSON: {0}")]
+    DiagnosticsFormat(#[source] serde_json::Error),
+
+    #[cfg(feature = "binary-snapshots")]
+    #[error("binary snapshot error: {0}")]
+    SnapshotBinary(#[source] bincode::Error),
+
+    #[cfg(feature = "templates")]
+    #[error("template error: {0}")]
+    Template(#[from] minijinja::Error),
+
+    #[cfg(feature = "parquet")]
+    #[error("parquet export error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ParsedFile {
+    /// This file's entry in `Klepto::file_table`.
+    pub id: FileId,
     pub path: PathBuf,
     pub modified: SystemTime,
+    /// Empty when the builder had `retain_source(false)`.
     pub source: String,
-    pub ast: syn::File,
+    /// blake3 of the file's contents.
+    pub content_hash: String,
+    /// Not saved; names this file's tree in the per-thread table (see `ast()`).
+    #[serde(skip)]
+    pub(crate) ast_key: AstKey,
     pub is_no_std_crate_root: bool,
+    /// Edition of the file's package (see `KleptoBuilder::edition`); None when unknown.
+    #[serde(default)]
+    pub edition: Option<Edition>,
+}
+
+impl ParsedFile {
+    /// The file's syntax tree, parsed from `source` on first use on each thread
+    /// (syn's trees and, with `span-locations`, their spans are bound to the
+    /// thread that parsed them). A scan that dropped the source keeps the tree it
+    /// parsed, on the thread that ran it only. Empty when there's nothing to
+    /// parse, as after `Klepto::load` of a scan without sources.
+    pub fn ast(&self) -> Rc<syn::File> {
+        AST_TABLE.with(|t| {
+            if let Some(ast) = t.borrow().get(&self.ast_key) { return ast; }
+            let ast = Rc::new(syn::parse_file(&self.source).unwrap_or_else(|_| empty_ast()));
+            t.borrow_mut().insert(&self.ast_key, ast.clone());
+            ast
+        })
+    }
+
+    // keeps `ast` as this file's tree on the current thread
+    fn set_ast(&self, ast: syn::File) {
+        AST_TABLE.with(|t| t.borrow_mut().insert(&self.ast_key, Rc::new(ast)));
+    }
+}
+
+/// Identity of a `ParsedFile`'s tree; clones share it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AstKey(Arc<()>);
+
+// the trees parsed on this thread, by the `AstKey` of their file; an entry goes
+// once every `ParsedFile` holding its key is dropped (swept as the table grows)
+#[derive(Default)]
+struct AstTable {
+    trees: HashMap<usize, (Weak<()>, Rc<syn::File>)>,
+    live_at_sweep: usize,
+}
+
+impl AstTable {
+    fn get(&self, key: &AstKey) -> Option<Rc<syn::File>> {
+        // the `Weak` keeps the allocation, so an address isn't reused while its entry is here
+        self.trees.get(&(Arc::as_ptr(&key.0) as usize)).map(|(_, ast)| ast.clone())
+    }
+
+    fn insert(&mut self, key: &AstKey, ast: Rc<syn::File>) {
+        if self.trees.len() >= 2 * self.live_at_sweep.max(64) {
+            self.trees.retain(|_, (owner, _)| owner.strong_count() > 0);
+            self.live_at_sweep = self.trees.len();
+        }
+        self.trees.insert(Arc::as_ptr(&key.0) as usize, (Arc::downgrade(&key.0), ast));
+    }
+}
+
+thread_local! {
+    static AST_TABLE: RefCell<AstTable> = RefCell::default();
 }
 
-#[derive(Debug, Clone)]
+pub(crate) fn empty_ast() -> syn::File {
+    syn::File { shebang: None, attrs: Vec::new(), items: Vec::new() }
+}
+
+/// Version of the `Klepto::save` format; `load` reads only this one.
+pub const ANALYSIS_SCHEMA_VERSION: u32 = 1;
+
+/// Serializes without ASTs and lookup indexes; read it back with `Klepto::load` /
+/// `from_reader`, which rebuild them (plain deserializing leaves them empty).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Klepto {
     pub crate_name: String,
+    /// What file paths in the model are relative to (see `KleptoBuilder::workspace_root`);
+    /// None when they are stored as scanned.
+    pub root: Option<PathBuf>,
     pub files: Vec<ParsedFile>,
+    // every location path points into this, so each file's path is stored once
+    #[serde(skip)]
+    pub file_table: FileTable,
 
     // extracted caches (so queries are fast)
     pub functions: Vec<CapturedFn>,
+    pub types: Vec<CapturedType>,
+    pub impls: Vec<CapturedImpl>,
     pub imports: Vec<StolenPath>,
     pub exports: Vec<ExportedSymbol>,
+    pub modules: Vec<ModuleDecl>,
 
     pub macros_def: Vec<MacroDef>,
     pub macros_inv: Vec<MacroInvocation>,
@@ -55,10 +198,32 @@ pub struct Klepto {
     pub no_std_detected: bool,
 
     pub index: crate::index::EnclosingIndex,
+    #[serde(skip)]
+    pub items: crate::index::ItemIndex,
+    #[serde(skip)]
+    pub occurrences: crate::index::OccurrenceIndex,
+    #[serde(skip)]
+    pub effective_visibility: crate::visibility::EffectiveVisibility,
+    // normalized names from `[dependencies]` of the scanned packages (not dev/build)
+    pub normal_dependencies: std::collections::BTreeSet<String>,
+    // `[features]` of the scanned packages (name -> what it enables); None when they weren't read
+    pub declared_features: Option<std::collections::BTreeMap<String, Vec<String>>>,
+    // normalized in-code name -> package, for `name = { package = "..." }` dependencies
+    pub dependency_renames: std::collections::BTreeMap<String, String>,
+    // rustdoc-derived APIs of dependencies, by normalized crate name
+    pub dependency_apis: std::collections::BTreeMap<String, crate::rustdoc::DepApi>,
+    pub code_owners: Option<crate::owners::CodeOwners>,
+    /// Candidates `parse` left out: over `max_file_size`, or unparsable under `ignore_parse_errors`.
+    #[serde(default)]
+    pub skipped_files: Vec<PathBuf>,
+}
 
+fn pick<'a, T>(idx: Option<&'a [usize]>, items: &'a [T]) -> impl Iterator<Item = &'a T> + 'a {
+    idx.unwrap_or(&[]).iter().map(move |&i| &items[i])
 }
 
 impl Klepto {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(crate_name: impl Into<String>) -> KleptoBuilder {
         KleptoBuilder::new(crate_name)
     }
@@ -67,36 +232,283 @@ impl Klepto {
     pub fn functions(&self) -> FnQuery<'_> { FnQuery::new(self) }
     pub fn imports(&self) -> ImportQuery<'_> { ImportQuery::new(self) }
 
+    /// The parsed file with this id.
+    pub fn file(&self, id: FileId) -> Option<&ParsedFile> { self.files.iter().find(|f| f.id == id) }
+    pub fn file_id(&self, path: &Path) -> Option<FileId> { self.file_table.id(path) }
+
     // Presets
+    /// Functions reachable from outside the crate (see `visibility::EffectiveVisibility`).
     pub fn public_api(&self) -> FnQuery<'_> { self.functions().public_only() }
     pub fn undocumented_public_api(&self) -> FnQuery<'_> { self.functions().public_only().no_docs() }
 
     // Public surface (pub use)
     pub fn public_surface(&self) -> PublicSurface { PublicSurface { exports: self.exports.clone() } }
 
-    // Finders (fast, uses cached occurrences)
+    // Finders (indexed by `parse`)
+    /// Paths naming `needle` or an item below it, as written or through the
+    /// file's imports: `"std::sync::Arc"` finds `Arc::new` after `use std::sync::Arc`
+    /// and `Shared::new` after `use std::sync::Arc as Shared`.
     pub fn find_paths(&self, needle: &str) -> Vec<PathOccurrence> {
-        self.paths.iter().cloned().filter(|p| p.path == needle).collect()
+        self.occurrences.paths_under(needle).into_iter().map(|i| self.paths[i as usize].clone()).collect()
     }
 
     pub fn find_macro_invocations(&self, name: &str) -> Vec<MacroInvocation> {
-        self.macros_inv.iter().cloned().filter(|m| m.name == name).collect()
+        self.macro_invocations_named(name).cloned().collect()
     }
 
     pub fn find_calls(&self, callee_contains: &str) -> Vec<CallOccurrence> {
-        self.calls.iter().cloned().filter(|c| c.callee.contains(callee_contains)).collect()
+        self.calls_containing(callee_contains).cloned().collect()
+    }
+
+    /// Paths that are exactly `needle`, as written or resolved through imports; no clones.
+    pub fn paths_eq<'a>(&'a self, needle: &str) -> impl Iterator<Item = &'a PathOccurrence> + 'a {
+        self.occurrences.paths(needle).iter().map(|&i| &self.paths[i as usize])
+    }
+
+    pub fn macro_invocations_named<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a MacroInvocation> + 'a {
+        self.occurrences.macros(name).iter().map(|&i| &self.macros_inv[i as usize])
+    }
+
+    pub fn calls_containing<'a>(&'a self, callee_contains: &str) -> impl Iterator<Item = &'a CallOccurrence> + 'a {
+        self.occurrences.calls_containing(callee_contains).into_iter().map(|i| &self.calls[i as usize])
+    }
+
+    // Lookups (prebuilt by `parse`)
+    /// First function with this `fq_name`, e.g. `"my_crate::Parser::parse"`.
+    pub fn fn_by_name(&self, fq_name: &str) -> Option<&CapturedFn> {
+        self.items.fns(fq_name).first().map(|&i| &self.functions[i])
+    }
+
+    /// Every function with this `fq_name`; file modules aren't part of it, so it can repeat.
+    pub fn fns_by_name<'a>(&'a self, fq_name: &str) -> impl Iterator<Item = &'a CapturedFn> + 'a {
+        self.items.fns(fq_name).iter().map(|&i| &self.functions[i])
+    }
+
+    pub fn type_by_name(&self, fq_name: &str) -> Option<&CapturedType> {
+        self.items.types(fq_name).first().map(|&i| &self.types[i])
+    }
+
+    pub fn functions_in_file<'a>(&'a self, path: &Path) -> impl Iterator<Item = &'a CapturedFn> + 'a {
+        pick(self.items.in_file(path).map(|f| f.functions.as_slice()), &self.functions)
+    }
+
+    pub fn types_in_file<'a>(&'a self, path: &Path) -> impl Iterator<Item = &'a CapturedType> + 'a {
+        pick(self.items.in_file(path).map(|f| f.types.as_slice()), &self.types)
+    }
+
+    pub fn impls_in_file<'a>(&'a self, path: &Path) -> impl Iterator<Item = &'a CapturedImpl> + 'a {
+        pick(self.items.in_file(path).map(|f| f.impls.as_slice()), &self.impls)
+    }
+
+    pub fn imports_in_file<'a>(&'a self, path: &Path) -> impl Iterator<Item = &'a StolenPath> + 'a {
+        pick(self.items.in_file(path).map(|f| f.imports.as_slice()), &self.imports)
+    }
+
+    /// Package a crate root names: the real package for a renamed dependency
+    /// (`json = { package = "serde_json" }`), else `root` itself.
+    pub fn dependency_package<'a>(&'a self, root: &'a str) -> &'a str {
+        self.dependency_renames.get(root).map_or(root, String::as_str)
+    }
+
+    /// Rebuilds the lookup maps; needed only after mutating the item vectors directly.
+    pub fn reindex(&mut self) {
+        self.items = crate::index::ItemIndex::build(&self.functions, &self.types, &self.impls, &self.imports);
+        self.occurrences = crate::index::OccurrenceIndex::build(&self.paths, &self.macros_inv, &self.calls);
+        self.effective_visibility = crate::visibility::EffectiveVisibility::build(&self.modules, &self.exports, &self.types);
+        self.effective_visibility.resolve_exports(&mut self.exports, &self.functions, &self.types, &self.macros_def);
+    }
+
+    /// Where a path from the model is on disk: joined onto `root`, if there is one.
+    pub fn absolute_path(&self, path: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Writes the analysis as JSON (see `ANALYSIS_SCHEMA_VERSION`), for `load` in
+    /// another job or tool; ASTs aren't included.
+    pub fn to_writer<W: std::io::Write>(&self, mut w: W) -> Result<(), KleptoError> {
+        #[derive(serde::Serialize)]
+        struct Saved<'a> { schema_version: u32, analysis: &'a Klepto }
+        serde_json::to_writer(&mut w, &Saved { schema_version: ANALYSIS_SCHEMA_VERSION, analysis: self })
+            .map_err(KleptoError::AnalysisFormat)?;
+        w.flush().map_err(KleptoError::Stream)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KleptoError> {
+        let path = path.as_ref();
+        let f = std::fs::File::create(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        self.to_writer(std::io::BufWriter::new(f))
+    }
+
+    /// Reads what `to_writer` wrote and rebuilds the indexes; ASTs are parsed
+    /// from the saved sources when first needed (empty when the scan had
+    /// `retain_source(false)`).
+    pub fn from_reader<R: std::io::Read>(r: R) -> Result<Self, KleptoError> {
+        #[derive(serde::Deserialize)]
+        struct Saved { schema_version: u32, analysis: serde_json::Value }
+        let saved: Saved = serde_json::from_reader(std::io::BufReader::new(r)).map_err(KleptoError::AnalysisFormat)?;
+        if saved.schema_version != ANALYSIS_SCHEMA_VERSION {
+            return Err(KleptoError::AnalysisVersion { found: saved.schema_version, supported: ANALYSIS_SCHEMA_VERSION });
+        }
+        let mut k: Klepto = serde_json::from_value(saved.analysis).map_err(KleptoError::AnalysisFormat)?;
+        k.rebuild_tables();
+        Ok(k)
+    }
+
+    /// Folds `others` into this analysis, e.g. per-crate scans from parallel jobs
+    /// into one workspace view; `crate_name` stays this one's.
+    ///
+    /// Files are deduped by path: the first analysis of a file wins, and what a
+    /// later one extracted from it is dropped. Paths of an instance with another
+    /// `root` are re-rooted onto this one's (kept absolute when outside it).
+    pub fn merge(mut self, others: Vec<Klepto>) -> Klepto {
+        for mut other in others {
+            if other.root != self.root {
+                other.rebase(self.root.as_deref());
+            }
+            let seen: HashSet<PathBuf> = self.files.iter().map(|pf| pf.path.clone()).collect();
+            let fresh = |loc: &FileLocation| !seen.contains(loc.path.as_path());
+            other.files.retain(|pf| !seen.contains(&pf.path));
+            other.functions.retain(|x| fresh(&x.location));
+            other.types.retain(|x| fresh(&x.location));
+            other.impls.retain(|x| fresh(&x.location));
+            other.imports.retain(|x| fresh(&x.location));
+            other.exports.retain(|x| fresh(&x.location));
+            other.modules.retain(|x| fresh(&x.location));
+            other.macros_def.retain(|x| fresh(&x.location));
+            other.macros_inv.retain(|x| fresh(&x.location));
+            other.paths.retain(|x| fresh(&x.location));
+            other.calls.retain(|x| fresh(&x.location));
+            other.index.retain_files(|p| !seen.contains(p));
+
+            self.files.append(&mut other.files);
+            self.functions.append(&mut other.functions);
+            self.types.append(&mut other.types);
+            self.impls.append(&mut other.impls);
+            self.imports.append(&mut other.imports);
+            self.exports.append(&mut other.exports);
+            self.modules.append(&mut other.modules);
+            self.macros_def.append(&mut other.macros_def);
+            self.macros_inv.append(&mut other.macros_inv);
+            self.paths.append(&mut other.paths);
+            self.calls.append(&mut other.calls);
+            self.index = std::mem::take(&mut self.index).merge(other.index);
+
+            self.no_std_detected |= other.no_std_detected;
+            self.normal_dependencies.append(&mut other.normal_dependencies);
+            if let Some(features) = other.declared_features {
+                self.declared_features.get_or_insert_default().extend(features);
+            }
+            self.dependency_renames.append(&mut other.dependency_renames);
+            for (name, api) in other.dependency_apis {
+                self.dependency_apis.entry(name).or_insert(api);
+            }
+            if self.code_owners.is_none() {
+                self.code_owners = other.code_owners;
+            }
+            other.skipped_files.retain(|p| !seen.contains(p));
+            self.skipped_files.append(&mut other.skipped_files);
+        }
+        self.file_table = FileTable::default();
+        self.rebuild_tables();
+        self
+    }
+
+    // re-roots every stored path from `self.root` onto `root`
+    fn rebase(&mut self, root: Option<&Path>) {
+        let old = self.root.take();
+        let map = |p: &Path| {
+            let abs = match &old {
+                Some(r) if p.is_relative() => r.join(p),
+                _ => p.to_path_buf(),
+            };
+            match root {
+                Some(r) => relative_to_root(r, &abs),
+                None => abs,
+            }
+        };
+        for pf in &mut self.files { pf.path = map(&pf.path); }
+        for p in &mut self.skipped_files { *p = map(p); }
+        let locations = self.functions.iter_mut().map(|x| &mut x.location)
+            .chain(self.types.iter_mut().map(|x| &mut x.location))
+            .chain(self.impls.iter_mut().map(|x| &mut x.location))
+            .chain(self.imports.iter_mut().map(|x| &mut x.location))
+            .chain(self.exports.iter_mut().map(|x| &mut x.location))
+            .chain(self.modules.iter_mut().map(|x| &mut x.location))
+            .chain(self.macros_def.iter_mut().map(|x| &mut x.location))
+            .chain(self.macros_inv.iter_mut().map(|x| &mut x.location))
+            .chain(self.paths.iter_mut().map(|x| &mut x.location))
+            .chain(self.calls.iter_mut().map(|x| &mut x.location));
+        for loc in locations { loc.path = map(&loc.path).into(); }
+        self.index = std::mem::take(&mut self.index).map_paths(map);
+        self.root = root.map(Path::to_path_buf);
+    }
+
+    // file ids, shared strings and indexes for a model that didn't come from `build`
+    pub(crate) fn rebuild_tables(&mut self) {
+        for pf in &mut self.files {
+            pf.id = self.file_table.intern(&pf.path);
+        }
+        intern_all(&mut self.file_table, &mut self.functions, &mut self.types, &mut self.impls, &mut self.imports, &mut self.exports, &mut self.modules, &mut self.macros_def, &mut self.macros_inv, &mut self.paths, &mut self.calls);
+        self.reindex();
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let f = std::fs::File::open(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        Self::from_reader(f)
+    }
+
+    /// Source text at `loc` (needs `span-locations`; files must be part of this scan).
+    pub fn snippet(&self, loc: &FileLocation) -> Option<&str> {
+        let file = self.files.iter().find(|f| f.path == loc.path)?;
+        loc.snippet(&file.source)
     }
 
     pub fn doc_coverage(&self) -> DocCoverage {
-        let public_total = self.functions.iter().filter(|f| f.is_public).count();
-        let public_documented = self.functions.iter().filter(|f| f.is_public && f.has_docs).count();
+        let vis = &self.effective_visibility;
+        let public_total = self.functions.iter().filter(|f| vis.fn_public(f)).count();
+        let public_documented = self.functions.iter().filter(|f| vis.fn_public(f) && f.has_docs).count();
         let percent = if public_total == 0 { 100.0 } else { (public_documented as f64) * 100.0 / (public_total as f64) };
         DocCoverage { public_total, public_documented, percent }
     }
 
+    /// Counts of the non-test imports by origin and root; a `use` and a `pub use`
+    /// of the same path count once.
+    pub fn import_summary(&self) -> crate::imports_ext::ImportSummary {
+        use crate::imports_ext::ImportVecExt;
+        self.imports.iter().filter(|i| !i.is_test_code).cloned().collect::<Vec<_>>().unique_prefer_pub_use().summary()
+    }
+
+    pub fn doc_coverage_report(&self) -> crate::coverage::DocCoverageReport {
+        crate::coverage::DocCoverageReport::from_klepto(self)
+    }
+
+    pub fn metrics(&self, findings: &[Finding]) -> crate::metrics::MetricsSnapshot {
+        crate::metrics::MetricsSnapshot::from_klepto(self, findings)
+    }
+
     // Snapshot / diff
     pub fn snapshot(&self) -> Snapshot { Snapshot::from_klepto(self) }
+    pub fn public_snapshot(&self) -> Snapshot { Snapshot::public_api_only(self) }
+    /// Files added/removed/modified since `old` was taken (by content hash).
+    /// Both sides must use the same path form (see `Snapshot::relative_to`).
+    pub fn changed_files_since(&self, old: &Snapshot) -> FileChanges { old.file_changes(&crate::snapshot::file_hashes(self)) }
+    pub fn snapshot_with_findings(&self, findings: &[Finding]) -> Snapshot { self.snapshot().with_findings(findings) }
     pub fn diff_snapshot(&self, old: &Snapshot) -> SnapshotDiff { self.snapshot().diff(old) }
+    /// Full comparison against `old` (API, occurrences, import origins, doc coverage).
+    pub fn diff(&self, old: &Klepto) -> crate::diff::KleptoDiff { crate::diff::KleptoDiff::between(old, self) }
+
+    /// Intra-workspace call graph (see `graph::CallGraph` for how calls are resolved).
+    pub fn call_graph(&self) -> crate::graph::CallGraph { crate::graph::CallGraph::build(self) }
+    pub fn module_graph(&self) -> crate::graph::ModuleGraph { crate::graph::ModuleGraph::build(self) }
+    /// Afferent/efferent coupling and instability per module, from `module_graph`.
+    pub fn module_coupling(&self) -> Vec<crate::metrics::ModuleCoupling> { crate::metrics::module_coupling(&self.module_graph()) }
+    /// Definition/reference cross-reference with stable symbol ids.
+    pub fn xref(&self) -> crate::xref::Xref { crate::xref::Xref::build(self) }
+    pub fn dependency_graph(&self) -> crate::graph::DependencyGraph { crate::graph::DependencyGraph::build(self) }
 
     // Rules
     pub fn rules(&self) -> RuleRunner<'_> { RuleRunner::new(self) }
@@ -104,10 +516,11 @@ impl Klepto {
 
 
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct KleptoBuilder {
     crate_name: String,
     roots: Vec<PathBuf>,
+    workspace_root: Option<PathBuf>,
     include: KleptoGlobSetBuilder,
     exclude: KleptoGlobSetBuilder,
     follow_links: bool,
@@ -120,7 +533,19 @@ pub struct KleptoBuilder {
     add_benches: bool,
     workspace_members: HashSet<String>,
     dependency_crates: HashSet<String>,
-
+    normal_dependencies: std::collections::BTreeSet<String>,
+    declared_features: Option<std::collections::BTreeMap<String, Vec<String>>>,
+    dependency_renames: std::collections::BTreeMap<String, String>,
+    dependency_apis: std::collections::BTreeMap<String, crate::rustdoc::DepApi>,
+    code_owners: Option<crate::owners::CodeOwners>,
+    // package directory -> its edition, from `scan_workspace_root`
+    package_editions: Vec<(PathBuf, Edition)>,
+    edition: Option<Edition>,
+    path_capture: PathCapture,
+    // inverted so `Default` keeps sources
+    drop_source: bool,
+    #[cfg(feature = "mmap")]
+    mmap_threshold: Option<u64>,
 }
 
 impl KleptoBuilder {
@@ -183,6 +608,7 @@ impl KleptoBuilder {
     pub fn scan_workspace_root(mut self, root: impl Into<PathBuf>) -> Result<Self, KleptoError> {
         let root = root.into();
         let manifest = root.join("Cargo.toml");
+        self.workspace_root.get_or_insert_with(|| root.clone());
         let meta = MetadataCommand::new().manifest_path(manifest).exec()?;
 
         // Choose which members + collect workspace/deps (normalized)
@@ -196,16 +622,28 @@ impl KleptoBuilder {
             // Always collect workspace member roots + deps (regardless of member_filter)
             self.workspace_members.insert(norm_crate_root(&pkg.name));
             for dep in &pkg.dependencies {
-                self.dependency_crates.insert(norm_crate_root(&dep.name));
+                // code names a renamed dependency by its key, not its package
+                let code_name = norm_crate_root(dep.rename.as_deref().unwrap_or(&dep.name));
+                if dep.rename.is_some() {
+                    self.dependency_renames.insert(code_name.clone(), norm_crate_root(&dep.name));
+                }
+                self.dependency_crates.insert(code_name);
             }
 
             // Optional filter: only scan selected members
-            if let Some(f) = &self.member_filter {
-                if !f.iter().any(|x| x == &pkg.name) {
-                    continue;
-                }
+            if let Some(f) = &self.member_filter
+                && !f.iter().any(|x| x == &pkg.name)
+            {
+                continue;
             }
 
+            for dep in pkg.dependencies.iter().filter(|d| d.kind == cargo_metadata::DependencyKind::Normal) {
+                self.normal_dependencies.insert(norm_crate_root(dep.rename.as_deref().unwrap_or(&dep.name)));
+            }
+            self.declared_features.get_or_insert_with(Default::default).extend(pkg.features.clone());
+            if let (Some(dir), Some(edition)) = (pkg.manifest_path.parent(), Edition::parse(pkg.edition.as_str())) {
+                self.package_editions.push((dir.as_std_path().to_path_buf(), edition));
+            }
             members.push(pkg.manifest_path.clone().into_std_path_buf());
         }
 
@@ -221,6 +659,11 @@ impl KleptoBuilder {
         Ok(self)
     }
 
+    /// Stores file paths relative to `root` (`scan_workspace_root` sets it to the
+    /// workspace), so locations, snapshots and baselines don't depend on where the
+    /// checkout is. Files outside it keep the path they were scanned under.
+    pub fn workspace_root(mut self, root: impl Into<PathBuf>) -> Self { self.workspace_root = Some(root.into()); self }
+
     pub fn only_members(mut self, names: &[&str]) -> Self {
         self.member_filter = Some(names.iter().map(|s| s.to_string()).collect());
         self
@@ -247,14 +690,60 @@ impl KleptoBuilder {
 
     pub fn follow_links(mut self, yes: bool) -> Self { self.follow_links = yes; self }
     pub fn max_file_size(mut self, bytes: u64) -> Self { self.max_file_size = Some(bytes); self }
+    /// Leave files that don't parse out of the scan (see `Klepto::skipped_files`) instead of failing it.
     pub fn ignore_parse_errors(mut self, yes: bool) -> Self { self.ignore_parse_errors = yes; self }
     pub fn only_newest(mut self, n: usize) -> Self { self.only_newest = Some(n); self }
+    /// Which path occurrences to keep (default `All`); path-based rules and `usages_of` only see what's kept.
+    pub fn capture_paths(mut self, capture: PathCapture) -> Self { self.path_capture = capture; self }
+    /// Keep each file's text in `ParsedFile::source` (default). Snippets, report
+    /// excerpts and line metrics need it; content hashes don't. Without it each
+    /// AST is kept from the scan, on the scanning thread only (the `parallel`
+    /// scan parses it a second time there).
+    pub fn retain_source(mut self, yes: bool) -> Self { self.drop_source = !yes; self }
+    /// Memory-map files of at least `bytes` instead of reading them; with
+    /// `retain_source(false)` their text is never copied onto the heap.
+    #[cfg(feature = "mmap")]
+    pub fn mmap_files_over(mut self, bytes: u64) -> Self { self.mmap_threshold = Some(bytes); self }
 
     pub fn include_tests(mut self, yes: bool) -> Self { self.add_tests = yes; self }
     pub fn include_examples(mut self, yes: bool) -> Self { self.add_examples = yes; self }
     pub fn include_benches(mut self, yes: bool) -> Self { self.add_benches = yes; self }
 
-    pub fn parse(self) -> Result<Klepto, KleptoError> {
+    /// Declares `[dependencies]` entries for scans that don't go through `scan_workspace_root`.
+    pub fn normal_dependencies(mut self, names: &[&str]) -> Self {
+        self.normal_dependencies.extend(names.iter().map(|n| norm_crate_root(n)));
+        self
+    }
+
+    /// Declares `[features]` names for scans that don't go through `scan_workspace_root`.
+    pub fn declared_features(mut self, names: &[&str]) -> Self {
+        self.declared_features.get_or_insert_with(Default::default).extend(names.iter().map(|n| (n.to_string(), Vec::new())));
+        self
+    }
+
+    /// Declares a renamed dependency (`code_name = { package = "package" }`) for scans
+    /// that don't go through `scan_workspace_root`.
+    pub fn dependency_rename(mut self, code_name: &str, package: &str) -> Self {
+        self.dependency_renames.insert(norm_crate_root(code_name), norm_crate_root(package));
+        self
+    }
+
+    /// Adds a dependency's API (see `DepApi::from_rustdoc_json`) so `dep_item_uses`
+    /// can tell its documented items from `#[doc(hidden)]` ones.
+    pub fn dependency_api(mut self, api: crate::rustdoc::DepApi) -> Self {
+        self.dependency_apis.insert(api.crate_name.clone(), api);
+        self
+    }
+
+    /// Edition for files outside the packages `scan_workspace_root` read (which
+    /// get their package's).
+    pub fn edition(mut self, edition: Edition) -> Self { self.edition = Some(edition); self }
+
+    /// Attributes files to owners (see `CodeOwners::discover`); rule findings get `extra.owners`.
+    pub fn code_owners(mut self, owners: crate::owners::CodeOwners) -> Self { self.code_owners = Some(owners); self }
+
+    // files `parse` would read, newest first, with their mtimes
+    pub(crate) fn candidates(&self) -> Result<Vec<(PathBuf, SystemTime)>, KleptoError> {
         let include: GlobSet = self.include.build()?;
         let exclude: GlobSet = self.exclude.build()?;
 
@@ -279,65 +768,87 @@ impl KleptoBuilder {
         candidates.sort_by_key(|(_, m)| *m);
         candidates.reverse();
         if let Some(n) = self.only_newest { candidates.truncate(n); }
+        Ok(candidates)
+    }
 
-        // parse files
+    pub fn parse(self) -> Result<Klepto, KleptoError> {
+        let candidates = self.candidates()?;
+
+        // parse + extract each file
         #[cfg(feature = "parallel")]
-        let parsed: Result<Vec<ParsedFile>, KleptoError> = {
+        let parsed: Result<Vec<Parsed>, KleptoError> = {
             use rayon::prelude::*;
-            candidates
+            // syn's AST isn't Send (and with span-locations its spans point into a
+            // thread-local source map), so workers parse and extract and only the
+            // plain results cross threads; `ParsedFile::ast` parses again on first
+            // use, unless the source is dropped and the tree is all that's left
+            let done: Result<Vec<_>, KleptoError> = candidates
                 .par_iter()
-                .map(|(path, modified)| parse_one(path, *modified, self.max_file_size))
-                .filter_map(|r| match r {
-                    Ok(Some(pf)) => Some(Ok(pf)),
-                    Ok(None) => None,
-                    Err(e) => Some(Err(e)),
+                .map(|(path, modified)| {
+                    let skipped = || Ok(Err(self.stored_path(path)));
+                    let Some(contents) = self.read_source(path)? else { return skipped() };
+                    let Some(mut pf) = self.parse_candidate(path, *modified, contents.text(path)?)? else { return skipped() };
+                    pf.path = self.stored_path(path);
+                    let extract = self.extract_file(&pf.path, &pf.ast());
+                    Ok(Ok((Unparsed::from(pf), contents, extract)))
                 })
-                .collect()
+                .collect();
+            done.and_then(|v| {
+                v.into_iter()
+                    .map(|r| match r {
+                        Ok((unparsed, contents, extract)) => {
+                            let mut pf = unparsed.into_parsed();
+                            if self.drop_source {
+                                let ast = syn::parse_file(contents.text(&pf.path)?)
+                                    .map_err(|e| KleptoError::Parse { path: pf.path.clone(), source: e })?;
+                                pf.set_ast(ast);
+                            }
+                            self.keep_source(&mut pf, contents)?;
+                            Ok(Ok((pf, extract)))
+                        }
+                        Err(skipped) => Ok(Err(skipped)),
+                    })
+                    .collect()
+            })
         };
 
         #[cfg(not(feature = "parallel"))]
-        let parsed: Result<Vec<ParsedFile>, KleptoError> = {
+        let parsed: Result<Vec<Parsed>, KleptoError> = {
             let mut v = Vec::new();
             for (path, modified) in candidates {
-                match parse_one(&path, modified, self.max_file_size)? {
-                    Some(pf) => v.push(pf),
-                    None => {}
-                }
+                let Some(contents) = self.read_source(&path)? else {
+                    v.push(Err(self.stored_path(&path)));
+                    continue;
+                };
+                let Some(mut pf) = self.parse_candidate(&path, modified, contents.text(&path)?)? else {
+                    v.push(Err(self.stored_path(&path)));
+                    continue;
+                };
+                pf.path = self.stored_path(&path);
+                let extract = self.extract_file(&pf.path, &pf.ast());
+                self.keep_source(&mut pf, contents)?;
+                v.push(Ok((pf, extract)));
             }
             Ok(v)
         };
 
-        let mut files = Vec::new();
-        match parsed {
-            Ok(v) => files = v,
-            Err(e) if self.ignore_parse_errors => { /* keep empty */ }
-            Err(e) => return Err(e),
+        let mut per_file = Vec::new();
+        let mut skipped_files = Vec::new();
+        for p in parsed? {
+            match p {
+                Ok(f) => per_file.push(f),
+                Err(path) => skipped_files.push(path),
+            }
         }
 
-        // extract caches
+        // merge the per-file caches
+        let mut files = Vec::with_capacity(per_file.len());
         let mut functions = Vec::new();
+        let mut types = Vec::new();
+        let mut impls = Vec::new();
         let mut imports = Vec::new();
-        // for imp in &mut imports {
-        //     use ImportOrigin::*;
-        //     let origin = if imp.is_internal {
-        //         Internal
-        //     } else {
-        //         match imp.root.as_str() {
-        //             "std" => Std,
-        //             "core" => Core,
-        //             "alloc" => Alloc,
-        //             r if workspace_members.contains(r) => WorkspaceMember,
-        //             r if dependency_crates.contains(r) => Dependency,
-        //             _ => UnknownExternal,
-        //         }
-        //     };
-        //     imp.origin = Some(origin);
-        // }
-        classify_imports(&mut imports, &self.workspace_members, &self.dependency_crates);
-
-
-
         let mut exports = Vec::new();
+        let mut modules = Vec::new();
 
         let mut macros_def = Vec::new();
         let mut macros_inv = Vec::new();
@@ -345,81 +856,465 @@ impl KleptoBuilder {
         let mut calls = Vec::new();
 
         let mut no_std_detected = false;
+        let mut index = crate::index::EnclosingIndex::default();
+        let mut file_table = FileTable::default();
 
-        for pf in &files {
+        let package_editions = self.stored_package_editions();
+        for (mut pf, ex) in per_file {
             if pf.is_no_std_crate_root { no_std_detected = true; }
-
-            functions.extend(extract_functions(&self.crate_name, &pf.path, &pf.ast));
-            imports.extend(extract_imports(&pf.path, &pf.ast));
-            exports.extend(extract_public_surface(&pf.path, &pf.ast));
-
-            // let (md, mi, po, co) = extract_occurrences_v1(&pf.path, &pf.ast);
-            let (md, mi, po, co) = extract_occurrences(&self.crate_name, &pf.path, &pf.ast);
-            macros_def.extend(md);
-            macros_inv.extend(mi);
-            paths.extend(po);
-            calls.extend(co);
+            pf.id = file_table.intern(&pf.path);
+            // the innermost package holding the file decides
+            pf.edition = package_editions.iter()
+                .filter(|(dir, _)| pf.path.starts_with(dir) && (pf.path.is_relative() || dir.is_absolute()))
+                .max_by_key(|(dir, _)| dir.components().count())
+                .map(|(_, edition)| *edition)
+                .or(self.edition);
+
+            functions.extend(ex.functions);
+            types.extend(ex.types);
+            impls.extend(ex.impls);
+            imports.extend(ex.imports);
+            exports.extend(ex.exports);
+            modules.extend(ex.modules);
+            macros_def.extend(ex.macros_def);
+            macros_inv.extend(ex.macros_inv);
+            paths.extend(ex.paths);
+            calls.extend(ex.calls);
+            index = index.merge(ex.index);
+            files.push(pf);
         }
 
-        let mut index = crate::index::EnclosingIndex::default();
-        for pf in &files {
-            index = index.merge(crate::index::EnclosingIndex::build(&self.crate_name, &pf.path, &pf.ast));
+        // `#[cfg(test)] mod x;` makes x's file, and the files below it, test code
+        let test_mods: Vec<Vec<String>> = modules.iter()
+            .filter(|m| m.is_test_code && !m.is_inline)
+            .map(|m| {
+                let mut full = crate::visibility::full_module_path(&m.location.path, &m.module_path);
+                full.push(m.name.clone());
+                full
+            })
+            .collect();
+        if !test_mods.is_empty() {
+            let test_files: HashSet<PathBuf> = files.iter()
+                .filter(|pf| {
+                    let m = crate::extract::file_module_path(&pf.path);
+                    test_mods.iter().any(|t| m.starts_with(t))
+                })
+                .map(|pf| pf.path.clone())
+                .collect();
+            let in_test = |loc: &FileLocation| test_files.contains(loc.path.as_path());
+            for f in &mut functions { f.is_test_code |= in_test(&f.location); }
+            for t in &mut types { t.is_test_code |= in_test(&t.location); }
+            for i in &mut impls { i.is_test_code |= in_test(&i.location); }
+            for i in &mut imports { i.is_test_code |= in_test(&i.location); }
+            for m in &mut modules { m.is_test_code |= in_test(&m.location); }
+            for m in &mut macros_inv { m.is_test_code |= in_test(&m.location); }
+            for p in &mut paths { p.is_test_code |= in_test(&p.location); }
+            for c in &mut calls { c.is_test_code |= in_test(&c.location); }
         }
+        crate::imports_ext::expand_glob_imports(&self.crate_name, &mut imports, &functions, &types, &modules);
+        intern_all(&mut file_table, &mut functions, &mut types, &mut impls, &mut imports, &mut exports, &mut modules, &mut macros_def, &mut macros_inv, &mut paths, &mut calls);
+
+        let items = crate::index::ItemIndex::build(&functions, &types, &impls, &imports);
+        let occurrences = crate::index::OccurrenceIndex::build(&paths, &macros_inv, &calls);
+        let effective_visibility = crate::visibility::EffectiveVisibility::build(&modules, &exports, &types);
+        effective_visibility.resolve_exports(&mut exports, &functions, &types, &macros_def);
 
         Ok(Klepto {
             crate_name: self.crate_name,
+            root: self.workspace_root,
             files,
+            file_table,
             functions,
+            types,
+            impls,
             imports,
             exports,
+            modules,
             macros_def,
             macros_inv,
             paths,
             calls,
             no_std_detected,
-            index
+            index,
+            items,
+            occurrences,
+            effective_visibility,
+            normal_dependencies: self.normal_dependencies,
+            declared_features: self.declared_features,
+            dependency_renames: self.dependency_renames,
+            dependency_apis: self.dependency_apis,
+            code_owners: self.code_owners,
+            skipped_files,
         })
     }
 }
 
+// `path` below `root`, relative to it; as it is otherwise (and for `root` itself)
+fn relative_to_root(root: &Path, path: &Path) -> PathBuf {
+    let rel = path.strip_prefix(root).ok().map(Path::to_path_buf).or_else(|| {
+        // `scan_in_folder("src")` with an absolute root, or the other way round
+        let (root, path) = (root.canonicalize().ok()?, path.canonicalize().ok()?);
+        path.strip_prefix(root).ok().map(Path::to_path_buf)
+    });
+    match rel {
+        Some(rel) if !rel.as_os_str().is_empty() => rel,
+        _ => path.to_path_buf(),
+    }
+}
+
+// equal module paths / occurrence strings / file paths share one allocation after this
+#[allow(clippy::too_many_arguments)]
+fn intern_all(
+    table: &mut FileTable,
+    functions: &mut [CapturedFn],
+    types: &mut [CapturedType],
+    impls: &mut [CapturedImpl],
+    imports: &mut [StolenPath],
+    exports: &mut [ExportedSymbol],
+    modules: &mut [ModuleDecl],
+    macros_def: &mut [MacroDef],
+    macros_inv: &mut [MacroInvocation],
+    paths: &mut [PathOccurrence],
+    calls: &mut [CallOccurrence],
+) {
+    let mut pool = crate::intern::Interner::default();
+    for f in functions {
+        pool.share_path(&mut f.module_path);
+        table.share(&mut f.location.path);
+    }
+    for t in types {
+        pool.share_path(&mut t.module_path);
+        table.share(&mut t.location.path);
+    }
+    for i in impls {
+        pool.share_path(&mut i.module_path);
+        table.share(&mut i.location.path);
+    }
+    for i in imports {
+        pool.share_path(&mut i.module_path);
+        table.share(&mut i.location.path);
+        if let Some(f) = &mut i.enclosing_fn { pool.share(f); }
+    }
+    for e in exports {
+        pool.share_path(&mut e.module_path);
+        table.share(&mut e.location.path);
+    }
+    for m in modules {
+        pool.share_path(&mut m.module_path);
+        table.share(&mut m.location.path);
+    }
+    for m in macros_def {
+        pool.share_path(&mut m.module_path);
+        table.share(&mut m.location.path);
+    }
+    for m in macros_inv {
+        pool.share_path(&mut m.module_path);
+        table.share(&mut m.location.path);
+        pool.share(&mut m.name);
+        if let Some(f) = &mut m.enclosing_fn { pool.share(f); }
+    }
+    for p in paths {
+        pool.share_path(&mut p.module_path);
+        if let Some(r) = &mut p.resolved { pool.share(r); }
+        table.share(&mut p.location.path);
+        pool.share(&mut p.path);
+        if let Some(f) = &mut p.enclosing_fn { pool.share(f); }
+    }
+    for c in calls {
+        pool.share_path(&mut c.module_path);
+        table.share(&mut c.location.path);
+        pool.share(&mut c.callee);
+        if let Some(f) = &mut c.enclosing_fn { pool.share(f); }
+    }
+}
+
 fn push_if_match(p: &Path, include: &GlobSet, exclude: &GlobSet, out: &mut Vec<(PathBuf, SystemTime)>) {
-    if !include.is_match(p) { return; }
-    if exclude.is_match(p) { return; }
+    if !globs_match(p, std::path::MAIN_SEPARATOR, include, exclude) { return; }
     if p.extension().and_then(|e| e.to_str()) != Some("rs") { return; }
     let modified = std::fs::metadata(p).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
     out.push((p.to_path_buf(), modified));
 }
 
-fn parse_one(path: &Path, modified: SystemTime, max_size: Option<u64>) -> Result<Option<ParsedFile>, KleptoError> {
-    let meta = std::fs::metadata(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
-    if let Some(max) = max_size {
-        if meta.len() > max { return Ok(None); }
+// `**/target/**` is written with `/`; match paths with `sep` separators in that form too
+fn globs_match(p: &Path, sep: char, include: &GlobSet, exclude: &GlobSet) -> bool {
+    let portable = crate::intern::with_slashes(p.to_string_lossy(), sep);
+    let candidate = Path::new(portable.as_ref());
+    include.is_match(candidate) && !exclude.is_match(candidate)
+}
+
+// a file's text, read onto the heap or mapped
+enum Contents {
+    Read(String),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Contents {
+    // `path` is only for the error on a mapped non-UTF-8 file
+    #[cfg_attr(not(feature = "mmap"), allow(unused_variables))]
+    fn text(&self, path: &Path) -> Result<&str, KleptoError> {
+        match self {
+            Contents::Read(s) => Ok(s),
+            #[cfg(feature = "mmap")]
+            Contents::Mapped(m) => std::str::from_utf8(m).map_err(|e| KleptoError::Io {
+                path: path.to_path_buf(),
+                source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            }),
+        }
+    }
+
+    #[cfg_attr(not(feature = "mmap"), allow(unused_variables))]
+    fn into_string(self, path: &Path) -> Result<String, KleptoError> {
+        match self {
+            Contents::Read(s) => Ok(s),
+            #[cfg(feature = "mmap")]
+            mapped => mapped.text(path).map(str::to_owned),
+        }
+    }
+}
+
+impl KleptoBuilder {
+    // `None` when the file is over `max_file_size`
+    fn read_source(&self, path: &Path) -> Result<Option<Contents>, KleptoError> {
+        let io = |e| KleptoError::Io { path: path.to_path_buf(), source: e };
+        let meta = std::fs::metadata(path).map_err(io)?;
+        if let Some(max) = self.max_file_size
+            && meta.len() > max
+        {
+            return Ok(None);
+        }
+        #[cfg(feature = "mmap")]
+        if let Some(min) = self.mmap_threshold
+            && meta.len() >= min
+        {
+            let file = std::fs::File::open(path).map_err(io)?;
+            // SAFETY: the map is read-only and dropped once the file is parsed; a
+            // file truncated meanwhile by another process is the documented hazard
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io)?;
+            return Ok(Some(Contents::Mapped(map)));
+        }
+        let source = std::fs::read_to_string(path).map_err(io)?;
+        Ok(Some(Contents::Read(source)))
     }
-    let source = std::fs::read_to_string(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
-    let ast = syn::parse_file(&source).map_err(|e| KleptoError::Parse { path: path.to_path_buf(), source: e })?;
+
+    fn parse_candidate(&self, path: &Path, modified: SystemTime, text: &str) -> Result<Option<ParsedFile>, KleptoError> {
+        match parse_source(path, modified, text) {
+            Ok(pf) => Ok(Some(pf)),
+            Err(KleptoError::Parse { .. }) if self.ignore_parse_errors => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // package dirs as stored paths; the workspace's own package is the empty path
+    fn stored_package_editions(&self) -> Vec<(PathBuf, Edition)> {
+        self.package_editions.iter()
+            .map(|(dir, edition)| {
+                let is_root = self.workspace_root.as_ref().is_some_and(|root| {
+                    root == dir || root.canonicalize().ok().is_some_and(|r| Some(r) == dir.canonicalize().ok())
+                });
+                (if is_root { PathBuf::new() } else { self.stored_path(dir) }, *edition)
+            })
+            .collect()
+    }
+
+    // `path` as the model stores it: relative to `workspace_root` when it's under it
+    fn stored_path(&self, path: &Path) -> PathBuf {
+        match &self.workspace_root {
+            Some(root) => relative_to_root(root, path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    fn keep_source(&self, pf: &mut ParsedFile, contents: Contents) -> Result<(), KleptoError> {
+        if !self.drop_source {
+            pf.source = contents.into_string(&pf.path)?;
+        }
+        Ok(())
+    }
+}
+
+// `source` is left empty; `KleptoBuilder::keep_source` fills it in
+fn parse_source(path: &Path, modified: SystemTime, text: &str) -> Result<ParsedFile, KleptoError> {
+    let ast = syn::parse_file(text).map_err(|e| KleptoError::Parse { path: path.to_path_buf(), source: e })?;
     let is_no_std_crate_root = crate_is_no_std(&ast);
-    Ok(Some(ParsedFile {
+    let pf = ParsedFile {
+        // assigned by `parse` once the file set is known
+        id: FileId(0),
         path: path.to_path_buf(),
         modified,
-        source,
-        ast,
+        source: String::new(),
+        content_hash: crate::snapshot::hash_bytes(text.as_bytes()),
+        ast_key: AstKey::default(),
         is_no_std_crate_root,
-    }))
+        edition: None,
+    };
+    pf.set_ast(ast);
+    Ok(pf)
+}
+
+// a `ParsedFile` without its AST, so it can leave the worker that parsed it
+#[cfg(feature = "parallel")]
+struct Unparsed {
+    path: PathBuf,
+    modified: SystemTime,
+    content_hash: String,
+    is_no_std_crate_root: bool,
+}
+
+#[cfg(feature = "parallel")]
+impl From<ParsedFile> for Unparsed {
+    fn from(pf: ParsedFile) -> Self {
+        Unparsed { path: pf.path, modified: pf.modified, content_hash: pf.content_hash, is_no_std_crate_root: pf.is_no_std_crate_root }
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl Unparsed {
+    fn into_parsed(self) -> ParsedFile {
+        ParsedFile {
+            id: FileId(0),
+            path: self.path,
+            modified: self.modified,
+            source: String::new(),
+            content_hash: self.content_hash,
+            ast_key: AstKey::default(),
+            is_no_std_crate_root: self.is_no_std_crate_root,
+            edition: None,
+        }
+    }
+}
+
+// a parsed candidate, or the stored path of one that was skipped
+type Parsed = Result<(ParsedFile, FileExtract), PathBuf>;
+
+// everything `parse` caches for one file; plain data, so it can leave a worker thread
+struct FileExtract {
+    functions: Vec<CapturedFn>,
+    types: Vec<CapturedType>,
+    impls: Vec<CapturedImpl>,
+    imports: Vec<StolenPath>,
+    exports: Vec<ExportedSymbol>,
+    modules: Vec<ModuleDecl>,
+    macros_def: Vec<MacroDef>,
+    macros_inv: Vec<MacroInvocation>,
+    paths: Vec<PathOccurrence>,
+    calls: Vec<CallOccurrence>,
+    index: crate::index::EnclosingIndex,
+}
+
+impl KleptoBuilder {
+    fn extract_file(&self, path: &Path, ast: &syn::File) -> FileExtract {
+        let crate_name = self.crate_name.as_str();
+        let mut imports = extract_imports(crate_name, path, ast);
+        classify_imports(&mut imports, &self.workspace_members, &self.dependency_crates, &self.dependency_renames);
+        // let (md, mi, po, co) = extract_occurrences_v1(path, ast);
+        let (macros_def, macros_inv, mut paths, calls) = extract_occurrences_with(crate_name, path, ast, self.path_capture);
+        if self.path_capture == PathCapture::ExternalOnly {
+            self.retain_external_paths(&mut paths, &imports);
+        }
+        resolve_paths(&mut paths, &imports);
+        FileExtract {
+            functions: extract_functions(crate_name, path, ast),
+            types: extract_types(crate_name, path, ast),
+            impls: extract_impls(path, ast),
+            imports,
+            exports: extract_public_surface(path, ast),
+            modules: extract_modules(path, ast),
+            macros_def,
+            macros_inv,
+            paths,
+            calls,
+            index: crate::index::EnclosingIndex::build(crate_name, path, ast),
+        }
+    }
+
+    // external = the first segment names another crate, or a name `use`d from one
+    // in the same module; everything else (local types, variants, generics) goes
+    fn retain_external_paths(&self, paths: &mut Vec<PathOccurrence>, imports: &[StolenPath]) {
+        let mut bound: Bindings<'_, bool> = std::collections::HashMap::new();
+        for imp in imports {
+            let name = match &imp.kind {
+                UseKind::Glob => continue,
+                UseKind::Rename { alias } => alias.as_str(),
+                UseKind::Name => imp.segments.last().unwrap_or(&imp.root).as_str(),
+            };
+            bound.insert((&imp.module_path, imp.enclosing_fn.as_deref(), name), imp.origin.as_ref().is_some_and(|o| *o != ImportOrigin::Internal));
+        }
+        let own = norm_crate_root(&self.crate_name);
+        paths.retain(|p| {
+            let first = p.path.split("::").next().unwrap_or_default();
+            match binding(&bound, p, first) {
+                Some(external) => *external,
+                None => {
+                    let root = norm_crate_root(first);
+                    root != own
+                        && (matches!(root.as_str(), "std" | "core" | "alloc")
+                            || self.workspace_members.contains(&root)
+                            || self.dependency_crates.contains(&root))
+                }
+            }
+        });
+    }
+}
+
+// fills `PathOccurrence::resolved` from the imports of the same file
+// names a `use` brings in, keyed by (module, enclosing fn, name)
+type Bindings<'a, T> = std::collections::HashMap<(&'a ModulePath, Option<&'a str>, &'a str), T>;
+
+// a `use` in the path's own function shadows one at module level
+fn binding<'a, T>(bound: &'a Bindings<'a, T>, p: &'a PathOccurrence, name: &'a str) -> Option<&'a T> {
+    p.enclosing_fn.as_deref()
+        .and_then(|f| bound.get(&(&p.module_path, Some(f), name)))
+        .or_else(|| bound.get(&(&p.module_path, None, name)))
+}
+
+fn resolve_paths(paths: &mut [PathOccurrence], imports: &[StolenPath]) {
+    let mut bound: Bindings<'_, String> = std::collections::HashMap::new();
+    for imp in imports {
+        let name = match &imp.kind {
+            UseKind::Glob => continue,
+            UseKind::Rename { alias } => alias.as_str(),
+            UseKind::Name => imp.segments.last().unwrap_or(&imp.root).as_str(),
+        };
+        let mut full = imp.root.clone();
+        for s in &imp.segments {
+            full.push_str("::");
+            full.push_str(s);
+        }
+        bound.insert((&imp.module_path, imp.enclosing_fn.as_deref(), name), full);
+    }
+    if bound.is_empty() { return; }
+    for p in paths {
+        let (first, rest) = match p.path.split_once("::") {
+            Some((f, r)) => (f, Some(r)),
+            None => (p.path.as_str(), None),
+        };
+        let Some(full) = binding(&bound, p, first) else { continue; };
+        let resolved = match rest {
+            Some(r) => format!("{}::{}", full, r),
+            None => full.clone(),
+        };
+        if resolved != p.path.as_str() {
+            p.resolved = Some(resolved.into());
+        }
+    }
 }
 
 fn norm_crate_root(s: &str) -> String {
     s.replace('-', "_")
 }
 
-fn classify_imports(
+pub(crate) fn classify_imports(
     imports: &mut [crate::model::StolenPath],
     workspace_members: &std::collections::HashSet<String>,
     dependency_crates: &std::collections::HashSet<String>,
+    renames: &std::collections::BTreeMap<String, String>,
 ) {
     use crate::model::ImportOrigin::*;
 
     for imp in imports {
         let root = norm_crate_root(&imp.root);
+        let package = renames.get(&root).unwrap_or(&root);
 
         let origin = if imp.is_internal {
             Internal
@@ -428,8 +1323,8 @@ fn classify_imports(
                 "std" => Std,
                 "core" => Core,
                 "alloc" => Alloc,
-                r if workspace_members.contains(r) => WorkspaceMember,
-                r if dependency_crates.contains(r) => Dependency,
+                _ if workspace_members.contains(package) => WorkspaceMember,
+                r if dependency_crates.contains(r) || renames.contains_key(r) => Dependency,
                 _ => UnknownExternal,
             }
         };
@@ -437,3 +1332,96 @@ fn classify_imports(
         imp.origin = Some(origin);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_paths_hit_slash_written_excludes() {
+        let b = Klepto::new("w").exclude_generated().unwrap();
+        let (include, exclude) = (b.include.build().unwrap(), b.exclude.build().unwrap());
+        let windows = |p: &str| globs_match(Path::new(p), '\\', &include, &exclude);
+        assert!(!windows(r"C:\repo\target\debug\build\x-1\out\gen.rs"));
+        assert!(!windows(r"crates\a\target\release\gen.rs"));
+        assert!(windows(r"C:\repo\src\lib.rs"));
+        assert!(!globs_match(Path::new("repo/target/debug/gen.rs"), '/', &include, &exclude));
+    }
+
+    #[cfg(feature = "span-locations")]
+    #[test]
+    fn crlf_sources_keep_offsets_and_docs_straight() {
+        let dir = std::env::temp_dir().join(format!("klepto-crlf-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let lib = "/// é doc\r\n/// more\r\npub fn f() {}\r\n\r\n/* é😀 */ pub fn g() {\r\n    let _x = 1;\r\n}\r\n\
+            /** block\r\n doc */\r\npub fn h() {}\r\n";
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        let k = Klepto::new("crlf").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let source = &k.files[0].source;
+        let func = |name: &str| k.functions.iter().find(|f| f.name == name).unwrap();
+
+        assert_eq!(func("f").doc.as_deref(), Some("é doc\nmore"));
+        assert_eq!(func("h").doc.as_deref(), Some("block\n doc "));
+        assert_eq!(func("f").location.snippet(source), Some("/// é doc\r\n/// more\r\npub fn f() {}"));
+        let g = &func("g").location;
+        assert_eq!((g.line, g.column), (Some(5), Some(9)));
+        assert_eq!(g.byte_column(source), Some(13));
+        assert_eq!(g.utf16_column(source), Some(10));
+        assert_eq!(g.snippet(source), Some("pub fn g() {\r\n    let _x = 1;\r\n}"));
+        assert_eq!(func("h").location.line, Some(8));
+    }
+
+    fn write_crate(dir: &Path, lib: &str) {
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+    }
+
+    fn paths(k: &Klepto) -> Vec<String> {
+        let mut v: Vec<String> = k.files.iter().map(|f| f.path.display().to_string()).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn merges_rebase_other_roots_and_keep_the_first_copy_of_a_file() {
+        let ws = std::env::temp_dir().join(format!("klepto-merge-{}", std::process::id()));
+        let outside = std::env::temp_dir().join(format!("klepto-merge-outside-{}", std::process::id()));
+        write_crate(&ws.join("a"), "pub fn a() {}\n");
+        write_crate(&ws.join("b"), "pub fn b() { crate::b() }\n");
+        write_crate(&outside, "pub fn far() {}\n");
+        let scan = |name: &str, root: Option<&Path>, src: PathBuf| {
+            let b = Klepto::new(name);
+            let b = match root { Some(r) => b.workspace_root(r), None => b };
+            b.scan_in_folder(src).parse().unwrap()
+        };
+        let a = scan("ws", Some(&ws), ws.join("a/src"));
+        let b = scan("b", Some(&ws.join("b")), ws.join("b/src"));
+        let b_again = scan("again", None, ws.join("b/src"));
+        let far = scan("far", Some(&outside), outside.join("src"));
+        let unrooted = scan("u", None, ws.join("a/src"));
+        let also_a = scan("ws", Some(&ws), ws.join("a/src"));
+        std::fs::remove_dir_all(&ws).unwrap();
+        std::fs::remove_dir_all(&outside).unwrap();
+
+        assert_eq!(paths(&b), ["src/lib.rs"]);
+        let m = a.merge(vec![b, b_again, far]);
+        assert_eq!(m.crate_name, "ws");
+        assert_eq!(m.root.as_deref(), Some(ws.as_path()));
+        let far_path = outside.join("src/lib.rs").display().to_string();
+        assert_eq!(paths(&m), [far_path.clone(), "a/src/lib.rs".to_string(), "b/src/lib.rs".to_string()]);
+        // `b/src/lib.rs` came twice; the second copy's extractions are dropped with it
+        let fns: Vec<(&str, String)> = m.functions.iter().map(|f| (f.fq_name.as_str(), f.location.path.display().to_string())).collect();
+        assert_eq!(fns, [("ws::a", "a/src/lib.rs".to_string()), ("b::b", "b/src/lib.rs".to_string()), ("far::far", far_path)]);
+        assert!(m.calls.iter().all(|c| c.location.path == Path::new("b/src/lib.rs")));
+        assert_eq!(m.calls.len(), 1);
+        assert!(m.fn_by_name("b::b").is_some());
+        assert!(m.index.enclosing(&m.calls[0].location).is_some_and(|f| f.fq_name == "b::b"));
+
+        // an unrooted analysis takes rooted paths absolute
+        let u = unrooted.merge(vec![also_a]);
+        assert_eq!(u.root, None);
+        assert_eq!(paths(&u), [ws.join("a/src/lib.rs").display().to_string()]);
+        assert_eq!(u.functions.len(), 1);
+    }
+}
@@ -1,18 +1,84 @@
 pub mod model;
+pub mod intern;
+pub mod config;
 pub mod extract;
+mod scope;
 pub mod klepto;
 pub mod query;
 pub mod find;
 pub mod snapshot;
+pub mod public_api;
+pub mod fix;
 pub mod report;
 pub mod rules;
 pub mod index;
 pub mod imports_ext;
 pub mod use_sites;
+pub mod usages;
+pub mod traits;
+pub mod features;
+pub mod dsl;
+pub mod xref;
+pub mod coverage;
+pub mod doc_links;
+pub mod unsafety;
+pub mod msrv;
+pub mod edition;
+pub mod const_fn;
+pub mod panics;
+pub mod test_map;
+pub mod examples;
+pub mod stats;
+pub mod metrics;
+pub mod diff;
+pub mod graph;
+pub mod rustdoc;
+pub mod diagnostics;
+pub mod owners;
+pub mod churn;
+pub mod sbom;
+pub mod visibility;
+pub mod watch;
+pub mod view;
+pub mod scoped;
+pub mod prelude;
+#[cfg(feature = "templates")]
+pub mod template;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub use crate::imports_ext::{ImportSummary, ImportVecExt};
 
-pub use crate::index::{EnclosingIndex, FnSpan};
-pub use crate::klepto::{Klepto, KleptoBuilder, KleptoError};
+pub use crate::config::{Baseline, Config, RuleLevel};
+pub use crate::intern::Interner;
+pub use crate::coverage::{CoverageCounts, DocCoverageReport, KindCoverage};
+pub use crate::doc_links::BrokenDocLink;
+pub use crate::unsafety::{UnsafeCounts, UnsafeStats};
+pub use crate::edition::{EditionIdiom, EditionIdiomKind};
+pub use crate::panics::{PanicKind, PanicPath, PanicReachability, PanicSite};
+pub use crate::test_map::{TestMap, TestTarget};
+pub use crate::examples::{ExampleCoverage, ExampleTarget};
+pub use crate::msrv::{MsrvEntry, MsrvPattern, MsrvTable, MsrvViolation, RustVersion, SyntaxFeature};
+pub use crate::stats::{FnCounts, WorkspaceStats};
+pub use crate::fix::{ApplyMode, ApplyReport, Fix, FixConflict};
+pub use crate::diff::{CountChange, KleptoDiff};
+pub use crate::graph::{CallEdge, CallGraph, DependencyGraph, EdgeKind, ModuleGraph};
+pub use crate::dsl::{Query, QueryResults};
+pub use crate::features::{FeatureGate, FeatureInventory, FeatureRef, FeatureRefKind};
+pub use crate::traits::TraitImpl;
+pub use crate::usages::Usage;
+pub use crate::watch::{Watch, WatchEvent};
+pub use crate::view::KleptoView;
+pub use crate::owners::CodeOwners;
+pub use crate::visibility::EffectiveVisibility;
+pub use crate::churn::FnChurn;
+pub use crate::rustdoc::{DepApi, DepItemUse, DepItemUseKind, DepItemVisibility};
+pub use crate::use_sites::{AttributeCrates, UseSiteVecExt, grouped_use_sites_to_markdown, use_sites_to_markdown};
+pub use crate::xref::{Definition, Reference, ReferenceKind, SymbolId, SymbolKind, Xref};
+pub use crate::index::{EnclosingIndex, FileItems, FnSpan, ItemIndex, OccurrenceIndex};
+pub use crate::metrics::{MetricChange, MetricsDiff, MetricsSnapshot, ModuleCoupling};
+pub use crate::klepto::{ANALYSIS_SCHEMA_VERSION, Klepto, KleptoBuilder, KleptoError};
 pub use crate::model::*;
 pub use crate::query::*;
 pub use crate::find::*;
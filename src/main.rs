@@ -1,83 +1,526 @@
-use klepto::{Klepto, findings_to_table};
-
-#[allow(unused)]
-fn main_v1() -> Result<(), Box<dyn std::error::Error>> {
-    let k = Klepto::new("crate")
-        .scan_workspace_root(".")?
-        .exclude_generated()?
-        .only_newest(200)
-        .parse()?;
-
-    // Ergonomic queries
-    let api = k.public_api()
-        .returns("Result")
-        .collect();
-
-    let std_uses = k.imports()
-        .root("std")
-        .collect();
-
-    // Finders
-    let printlns = k.find_macro_invocations("println");
-    let arc_paths = k.find_paths("std::sync::Arc");
-
-    // Snapshot + diff
-    let snap = k.snapshot();
-    std::fs::write("klepto_snapshot.json", snap.to_json_string())?;
-
-    // Rules + report
-    let findings = k.rules().with_default_rules().run();
-    println!("{}", findings_to_table(&findings));
-
-    println!("public api fns returning Result: {}", api.len());
-    println!("std imports: {}", std_uses.len());
-    println!("println! invocations: {}", printlns.len());
-    println!("Arc paths: {}", arc_paths.len());
-
-    let k2 = Klepto::new("crate")
-        .scan_workspace_root(".")?
-        .exclude_generated()?
-        .parse()?;
-
-    let findings2 = k2.rules().with_default_rules().run();
-    println!("{}", klepto::findings_to_table(&findings2));
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use klepto::config::{CONFIG_FILE_NAME, DEFAULT_BASELINE};
+use klepto::{Config, Finding, Klepto, KleptoBuilder, KleptoError, Report, Severity, Snapshot, Watch, WatchEvent};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-    Ok(())
+#[derive(Parser)]
+#[command(name = "klepto", version, about = "Scan Rust sources: API surface, imports, rules and snapshots")]
+struct Cli {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// `json` wraps every command's output in `{command, schema_version, results}`
+    #[arg(long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Directory to scan (the workspace root with --workspace)
+    #[arg(long, global = true, default_value = ".")]
+    path: PathBuf,
+
+    /// Find sources through `cargo metadata` instead of walking --path
+    #[arg(long, global = true)]
+    workspace: bool,
+
+    /// Only scan these workspace members (comma-separated)
+    #[arg(long, global = true, value_delimiter = ',')]
+    members: Vec<String>,
+
+    /// Skip files matching this glob (repeatable)
+    #[arg(long, global = true)]
+    exclude: Vec<String>,
+
+    /// Crate name used in fq names (defaults to the directory name)
+    #[arg(long, global = true)]
+    crate_name: Option<String>,
+
+    /// Config file (defaults to `klepto.toml` in --path, if present)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Merge `cargo clippy --message-format=json` output, run in --path, into the findings (repeatable)
+    #[arg(long, global = true)]
+    diagnostics: Vec<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a starter klepto.toml and a baseline snapshot with the current findings
+    Init {
+        /// Overwrite an existing klepto.toml and baseline snapshot
+        #[arg(long)]
+        force: bool,
+    },
+    /// Parse everything and print what was captured
+    Scan,
+    /// Run the builtin rules; exits 1 when any finding is `deny`
+    Rules,
+    /// Write a snapshot of the current API (stdout unless --out)
+    Snapshot {
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Public API only
+        #[arg(long)]
+        public: bool,
+        /// Embed the current findings as a baseline for `diff --fail-on new-findings`
+        #[arg(long)]
+        with_findings: bool,
+        /// Embed import counts by origin and root crate
+        #[arg(long)]
+        with_imports: bool,
+    },
+    /// Compare the current API against a saved snapshot (the configured baseline by default)
+    Diff {
+        old: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = DiffFormat::Table)]
+        format: DiffFormat,
+        /// Exit 1 when any of these hold (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fail_on: Vec<FailOn>,
+    },
+    /// Run a textual query, e.g. `fn pub returns:Result in:crate::api`
+    Query {
+        query: String,
+        #[arg(long, value_enum, default_value_t = QueryFormat::Table)]
+        format: QueryFormat,
+    },
+    /// Render the rule findings as a report
+    Report {
+        #[arg(long, value_enum, default_value_t = ReportFormat::Md)]
+        format: ReportFormat,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Keep the index warm and print findings that appear/disappear as files are saved
+    Watch {
+        /// Poll interval in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+        /// Serve the latest HTML report (`/`) and findings (`/findings.json`) on this address
+        #[arg(long)]
+        serve: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Output { Text, Json }
+
+/// Bumped when any command's `results` shape changes incompatibly.
+const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
+/// `--output json` wrapper; exactly one of `results` / `error` is set.
+///
+/// `results` per command: `init` `{config, baseline, rules, findings}`, `scan` counts, `rules` findings, `snapshot` the snapshot,
+/// `diff` `{diff, semver_impact, breaking_changes, failed}`, `query` `{kind, items}`,
+/// `report` the report; `watch` prints one compact envelope per event.
+#[derive(serde::Serialize)]
+struct Envelope<'a> {
+    command: &'a str,
+    schema_version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<'a> Envelope<'a> {
+    fn results(command: &'a str, results: serde_json::Value) -> Self {
+        Self { command, schema_version: OUTPUT_SCHEMA_VERSION, results: Some(results), error: None }
+    }
+
+    fn error(command: &'a str, error: String) -> Self {
+        Self { command, schema_version: OUTPUT_SCHEMA_VERSION, results: None, error: Some(error) }
+    }
+}
+
+fn to_value(v: impl serde::Serialize) -> serde_json::Value {
+    serde_json::to_value(v).expect("cli output serializes")
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Init { .. } => "init",
+            Command::Scan => "scan",
+            Command::Rules => "rules",
+            Command::Snapshot { .. } => "snapshot",
+            Command::Diff { .. } => "diff",
+            Command::Query { .. } => "query",
+            Command::Report { .. } => "report",
+            Command::Watch { .. } => "watch",
+        }
+    }
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum DiffFormat { Table, Md }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let k = Klepto::new("crate")
-        .scan_workspace_root(".")?
-        .exclude_generated()?
-        .only_newest(200)
-        .parse()?;
+#[derive(Clone, Copy, ValueEnum)]
+enum QueryFormat { Table, Json }
 
-    // Ergonomic queries
-    let api = k.public_api()
-        .returns("Result")
-        .collect();
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat { Md, Html, Sarif }
 
-    let std_uses = k.imports()
-        .root("std")
-        .collect();
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum FailOn {
+    /// Semver-major changes to the public API
+    Breaking,
+    /// Any `pub use` re-export went away
+    RemovedExports,
+    /// Findings not in the old snapshot's baseline
+    NewFindings,
+}
 
-    // Finders
-    let printlns = k.find_macro_invocations("println");
-    let arc_paths = k.find_paths("std::sync::Arc");
+impl ScanArgs {
+    fn crate_name(&self) -> String {
+        let dir = std::fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+        let name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "crate".into());
+        self.crate_name.clone().unwrap_or(name).replace('-', "_")
+    }
 
-    // Snapshot + diff
-    let snap = k.snapshot();
-    std::fs::write("klepto_snapshot.json", snap.to_json_string())?;
+    fn config_path(&self) -> PathBuf {
+        self.config.clone().unwrap_or_else(|| self.path.join(CONFIG_FILE_NAME))
+    }
 
-    // Rules + report
-    let findings = k.rules().with_default_rules().run();
-    println!("{}", findings_to_table(&findings));
+    // an explicit --config must exist; the default one is optional
+    fn load_config(&self) -> Result<Config, KleptoError> {
+        match &self.config {
+            Some(path) => Config::load(path),
+            None => Config::discover(&self.path),
+        }
+    }
 
-    println!("public api fns returning Result: {}", api.len());
-    println!("std imports: {}", std_uses.len());
-    println!("println! invocations: {}", printlns.len());
-    println!("Arc paths: {}", arc_paths.len());
+    fn diagnostics(&self) -> Result<Vec<Finding>, KleptoError> {
+        let mut all = Vec::new();
+        for file in &self.diagnostics {
+            // cargo ran in --path, which locations are relative to
+            all.extend(klepto::diagnostics::from_cargo_json_file(file, "")?);
+        }
+        Ok(all)
+    }
+
+    fn parse(&self) -> Result<Klepto, KleptoError> {
+        self.builder()?.parse()
+    }
+
+    fn builder(&self) -> Result<KleptoBuilder, KleptoError> {
+        let mut b = Klepto::new(self.crate_name());
+        if !self.members.is_empty() {
+            let members: Vec<&str> = self.members.iter().map(String::as_str).collect();
+            b = b.only_members(&members);
+        }
+        b = if self.workspace { b.scan_workspace_root(&self.path)? } else { b.scan_in_folder(&self.path) };
+        b = b.workspace_root(&self.path);
+        b = b.exclude_generated()?;
+        if let Some(owners) = klepto::CodeOwners::discover(&self.path)? {
+            b = b.code_owners(owners);
+        }
+        for g in &self.exclude {
+            b = b.exclude_glob(g)?;
+        }
+        Ok(b)
+    }
+}
 
+fn emit(out: Option<&Path>, text: &str) -> Result<(), KleptoError> {
+    use std::io::Write;
+    match out {
+        Some(path) => std::fs::write(path, text).map_err(|source| KleptoError::Io { path: path.to_path_buf(), source }),
+        // `klepto query .. | head` closing the pipe early is not an error
+        None => match std::io::stdout().lock().write_all(text.as_bytes()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(KleptoError::Stream(e)),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn loc(l: &klepto::FileLocation) -> String {
+    match l.line {
+        Some(line) => format!("{}:{}", l.path.display(), line),
+        None => l.path.display().to_string(),
+    }
+}
+
+fn finding_line(sign: char, code: &str, l: &klepto::FileLocation, message: &str) -> String {
+    format!("{} {} {}  {}", sign, code, loc(l), message)
+}
+
+// (html report, findings json), replaced after every re-scan
+type Served = Arc<Mutex<(String, String)>>;
+
+// findings plus every optional section, for `report` and `serve`
+fn full_report(k: &Klepto, findings: Vec<Finding>) -> Report {
+    Report::new(findings)
+        .with_coupling(k.module_coupling())
+        .with_panics(k.panic_reachability())
+        .with_non_default_api(k.non_default_api())
+        .with_import_summary(k.import_summary())
+}
+
+fn served(k: &Klepto, findings: &[Finding]) -> (String, String) {
+    let html = full_report(k, findings.to_vec()).render_html();
+    (html, serde_json::to_string_pretty(findings).unwrap_or_default())
+}
+
+// one request per connection, GET only; enough for a browser tab or `curl`
+fn serve(addr: &str, state: Served) -> Result<(), KleptoError> {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind(addr).map_err(KleptoError::Stream)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut request_line = String::new();
+            if BufReader::new(&stream).read_line(&mut request_line).is_err() { continue; }
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let (status, ctype, body) = {
+                let state = state.lock().unwrap_or_else(|e| e.into_inner());
+                match path {
+                    "/" => ("200 OK", "text/html; charset=utf-8", state.0.clone()),
+                    "/findings.json" => ("200 OK", "application/json", state.1.clone()),
+                    _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+                }
+            };
+            let mut stream = stream;
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, ctype, body.len(), body);
+        }
+    });
     Ok(())
 }
+
+// json lines: the first event lists every current finding as new
+fn watch_json(event: &WatchEvent, total: usize) -> String {
+    let mut results = to_value(event);
+    results["total_findings"] = total.into();
+    serde_json::to_string(&Envelope::results("watch", results)).expect("cli output serializes")
+}
+
+fn run_rules(k: &Klepto, config: &Config, imported: &[Finding]) -> Vec<Finding> {
+    k.rules().with_default_rules().with_config(config).with_external_findings(imported.iter().cloned()).run()
+}
+
+fn watch(scan: &ScanArgs, config: Config, output: Output, interval: u64, addr: Option<&str>) -> Result<ExitCode, KleptoError> {
+    // read once: the imported diagnostics don't follow edits
+    let imported = scan.diagnostics()?;
+    let mut w = Watch::with_rules(scan.builder()?, move |k| run_rules(k, &config, &imported))?;
+    let state: Served = Arc::new(Mutex::new(served(w.klepto(), w.findings())));
+    if let Some(addr) = addr {
+        serve(addr, state.clone())?;
+        eprintln!("serving report on http://{}/", addr);
+    }
+    eprintln!("watching {} ({} files, {} findings); Ctrl-C to stop", scan.path.display(), w.klepto().files.len(), w.findings().len());
+    if output == Output::Json {
+        let initial = WatchEvent { new_findings: w.findings().iter().map(Into::into).collect(), ..Default::default() };
+        println!("{}", watch_json(&initial, w.findings().len()));
+    } else {
+        for f in w.findings() {
+            println!("{}", finding_line(' ', f.code.as_str(), &f.location, &f.message));
+        }
+    }
+    w.run(
+        Duration::from_millis(interval),
+        |w, event| {
+            if output == Output::Json {
+                println!("{}", watch_json(event, w.findings().len()));
+            } else {
+                let c = &event.changes;
+                println!("-- {} modified, {} added, {} removed; {} findings", c.modified.len(), c.added.len(), c.removed.len(), w.findings().len());
+                for f in &event.new_findings { println!("{}", finding_line('+', f.code.as_str(), &f.location, &f.message)); }
+                for f in &event.resolved_findings { println!("{}", finding_line('-', f.code.as_str(), &f.location, &f.message)); }
+            }
+            *state.lock().unwrap_or_else(|e| e.into_inner()) = served(w.klepto(), w.findings());
+            true
+        },
+        |e| match output {
+            Output::Json => println!("{}", serde_json::to_string(&Envelope::error("watch", e.to_string())).expect("cli output serializes")),
+            Output::Text => eprintln!("error: {}", e),
+        },
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+// what a command produced: human text, the `results` value for `--output json`, and where to write it
+type Produced = (String, serde_json::Value, Option<PathBuf>);
+
+fn run(cli: Cli) -> Result<ExitCode, KleptoError> {
+    let name = cli.command.name();
+    let config = cli.scan.load_config()?;
+    if let Command::Watch { interval, serve } = &cli.command {
+        return watch(&cli.scan, config, cli.output, *interval, serve.as_deref());
+    }
+    let k = cli.scan.parse()?;
+    let root = &cli.scan.path;
+    let imported = cli.scan.diagnostics()?;
+
+    let mut code = ExitCode::SUCCESS;
+    let (text, results, out): Produced = match cli.command {
+        Command::Init { force } => {
+            let config_path = cli.scan.config_path();
+            let baseline_path = config_path.parent().unwrap_or(Path::new(".")).join(DEFAULT_BASELINE);
+            // checked before writing either, so a refused run leaves both as they were
+            if let Some(existing) = [&config_path, &baseline_path].into_iter().find(|p| p.exists() && !force) {
+                return Ok(fail(cli.output, name, format!("{} already exists (use --force to overwrite)", existing.display())));
+            }
+            let starter = Config::starter();
+            let findings = run_rules(&k, &starter, &imported);
+            let snap = k.snapshot().with_findings(&findings).relative_to(root);
+            std::fs::write(&config_path, starter.to_toml_string()).map_err(|source| KleptoError::Io { path: config_path.clone(), source })?;
+            snap.save(&baseline_path)?;
+            let text = format!(
+                "wrote {} ({} rules)\nwrote {} ({} functions, {} findings)\n",
+                config_path.display(), starter.rules.len(),
+                baseline_path.display(), snap.functions.len(), findings.len(),
+            );
+            let results = serde_json::json!({
+                "config": config_path,
+                "baseline": baseline_path,
+                "rules": starter.rules.len(),
+                "findings": findings.len(),
+            });
+            (text, results, None)
+        }
+        Command::Scan => {
+            let public = k.functions.iter().filter(|f| k.effective_visibility.fn_public(f)).count();
+            let coverage = k.doc_coverage().percent;
+            let text = format!(
+                "crate {}: {} files\n  {} functions ({} public), {} types, {} impls\n  {} imports, {} exports, {} macro definitions\n  {} paths, {} calls, {} macro invocations\n  doc coverage {:.1}%\n",
+                k.crate_name, k.files.len(),
+                k.functions.len(), public, k.types.len(), k.impls.len(),
+                k.imports.len(), k.exports.len(), k.macros_def.len(),
+                k.paths.len(), k.calls.len(), k.macros_inv.len(),
+                coverage,
+            );
+            let results = serde_json::json!({
+                "crate_name": k.crate_name,
+                "files": k.files.len(),
+                "functions": k.functions.len(),
+                "public_functions": public,
+                "types": k.types.len(),
+                "impls": k.impls.len(),
+                "imports": k.imports.len(),
+                "exports": k.exports.len(),
+                "macro_definitions": k.macros_def.len(),
+                "paths": k.paths.len(),
+                "calls": k.calls.len(),
+                "macro_invocations": k.macros_inv.len(),
+                "doc_coverage_percent": coverage,
+            });
+            (text, results, None)
+        }
+        Command::Rules => {
+            let findings = run_rules(&k, &config, &imported);
+            if findings.iter().any(|f| f.severity == Severity::Deny) {
+                code = ExitCode::from(1);
+            }
+            let results = to_value(&findings);
+            (Report::new(findings).render_table(), results, None)
+        }
+        Command::Snapshot { out, public, with_findings, with_imports } => {
+            let mut snap = if public { k.public_snapshot() } else { k.snapshot() };
+            if with_findings {
+                snap = snap.with_findings(&run_rules(&k, &config, &imported));
+            }
+            if with_imports {
+                snap = snap.with_import_summary(k.import_summary());
+            }
+            let snap = snap.relative_to(root);
+            (snap.to_json_string()?, to_value(&snap), out)
+        }
+        Command::Diff { old, format, fail_on } => {
+            let old = match (old, &config.baseline) {
+                (Some(path), _) => path,
+                // the baseline path is relative to the config file
+                (None, Some(b)) => cli.scan.config_path().parent().unwrap_or(Path::new(".")).join(&b.snapshot),
+                (None, None) => return Ok(fail(cli.output, name, "no snapshot given and the config has no [baseline]".into())),
+            };
+            let old = Snapshot::load(&old)?;
+            if fail_on.contains(&FailOn::NewFindings) && old.findings.is_none() {
+                return Ok(fail(cli.output, name, "old snapshot has no findings baseline; create it with `klepto snapshot --with-findings`".into()));
+            }
+            let mut current = k.snapshot();
+            if old.findings.is_some() {
+                current = current.with_findings(&run_rules(&k, &config, &imported));
+            }
+            let diff = current.relative_to(root).diff(&old);
+
+            let mut text = match format {
+                DiffFormat::Table => diff.to_table(),
+                DiffFormat::Md => diff.to_markdown(),
+            };
+            text.push_str(&format!("\nsemver impact: {:?}\n", diff.semver_impact()));
+
+            let mut failed = Vec::new();
+            for f in &fail_on {
+                let reasons: Vec<String> = match f {
+                    FailOn::Breaking => diff.breaking_changes(),
+                    FailOn::RemovedExports => diff.removed_exports.iter().map(|e| format!("removed export {}", e.exported_as)).collect(),
+                    FailOn::NewFindings => diff.new_findings.iter().map(|x| format!("new finding {}: {}", x.code, x.message)).collect(),
+                };
+                failed.extend(reasons);
+            }
+            if !failed.is_empty() {
+                failed.dedup();
+                failed.iter().for_each(|r| eprintln!("fail: {}", r));
+                code = ExitCode::from(1);
+            }
+            let results = serde_json::json!({
+                "diff": diff,
+                "semver_impact": diff.semver_impact(),
+                "breaking_changes": diff.breaking_changes(),
+                "failed": failed,
+            });
+            (text, results, None)
+        }
+        Command::Query { query, format } => {
+            let results = k.query(&query)?.collect();
+            let text = match format {
+                QueryFormat::Table => results.to_table(),
+                QueryFormat::Json => results.to_json_string()? + "\n",
+            };
+            (text, to_value(&results), None)
+        }
+        Command::Report { format, out } => {
+            let report = full_report(&k, run_rules(&k, &config, &imported));
+            let text = match format {
+                ReportFormat::Md => report.render_markdown(),
+                ReportFormat::Html => report.render_html(),
+                ReportFormat::Sarif => klepto::findings_to_sarif(&report.findings)?,
+            };
+            (text, to_value(&report), out)
+        }
+        Command::Watch { .. } => unreachable!("handled before the scan"),
+    };
+    let text = match cli.output {
+        Output::Text => text,
+        Output::Json => serde_json::to_string_pretty(&Envelope::results(name, results)).map_err(KleptoError::Json)? + "\n",
+    };
+    emit(out.as_deref(), &text)?;
+    Ok(code)
+}
+
+// usage-level failure: exit 2, reported as an error envelope under `--output json`
+fn fail(output: Output, command: &str, error: String) -> ExitCode {
+    match output {
+        Output::Text => eprintln!("error: {}", error),
+        Output::Json => {
+            let text = serde_json::to_string_pretty(&Envelope::error(command, error)).expect("cli output serializes") + "\n";
+            let _ = emit(None, &text);
+        }
+    }
+    ExitCode::from(2)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let (output, name) = (cli.output, cli.command.name());
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => fail(output, name, e.to_string()),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::graph::ModuleGraph;
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::*;
+use crate::report::{Report, SeverityCounts};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Compact quality numbers for charting over time; much smaller than a `Snapshot`.
+/// Field order and map ordering are fixed, so the JSON is stable across runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetricsSnapshot {
+    pub crate_name: String,
+    pub files: usize,
+    pub lines: usize,
+    pub code_lines: usize, // non-blank, non-`//` lines
+    pub functions: usize,
+    pub public_functions: usize,
+    pub types: usize,
+    pub doc_coverage_percent: f64,
+    pub unsafe_fns: usize,
+    #[serde(default)]
+    pub unsafe_blocks: usize,
+    #[serde(default)]
+    pub unsafe_impls: usize,
+    #[serde(default)]
+    pub unsafe_traits: usize,
+    pub findings: SeverityCounts,
+    pub findings_by_rule: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub coupling: Vec<ModuleCoupling>,
+}
+
+/// Coupling of one module within the crate, counted in distinct modules.
+///
+/// `instability` is `efferent / (afferent + efferent)`: 0 for modules everything
+/// leans on, 1 for leaves that only depend on others (0 when isolated).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ModuleCoupling {
+    pub module: String,
+    pub afferent: usize, // fan-in: modules that use this one
+    pub efferent: usize, // fan-out: modules this one uses
+    pub instability: f64,
+}
+
+/// Fan-in/fan-out and instability for every module of `g`, in module order.
+pub fn module_coupling(g: &ModuleGraph) -> Vec<ModuleCoupling> {
+    let mut fan_in: BTreeMap<&str, usize> = BTreeMap::new();
+    for tos in g.edges.values() {
+        for to in tos.keys() {
+            *fan_in.entry(to).or_default() += 1;
+        }
+    }
+    g.modules.iter()
+        .map(|m| {
+            let afferent = fan_in.get(m.as_str()).copied().unwrap_or(0);
+            let efferent = g.edges.get(m).map_or(0, |tos| tos.len());
+            let total = afferent + efferent;
+            let instability = if total == 0 { 0.0 } else { efferent as f64 / total as f64 };
+            ModuleCoupling { module: m.clone(), afferent, efferent, instability }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetricChange {
+    pub metric: String,
+    pub old: f64,
+    pub new: f64,
+    pub regression: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetricsDiff {
+    pub changes: Vec<MetricChange>,
+}
+
+impl MetricsDiff {
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricChange> {
+        self.changes.iter().filter(|c| c.regression)
+    }
+
+    pub fn has_regressions(&self) -> bool { self.regressions().next().is_some() }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Better { Lower, Higher, Neutral }
+
+impl MetricsSnapshot {
+    pub fn from_klepto(k: &Klepto, findings: &[Finding]) -> Self {
+        let mut lines = 0;
+        let mut code_lines = 0;
+        for f in &k.files {
+            for l in f.source.lines() {
+                lines += 1;
+                let t = l.trim();
+                if !t.is_empty() && !t.starts_with("//") { code_lines += 1; }
+            }
+        }
+
+        let report = Report::new(findings.to_vec());
+        let findings_by_rule = report.group_by_rule().into_iter()
+            .map(|(code, fs)| (code.to_string(), fs.len()))
+            .collect();
+
+        let unsafe_stats = k.unsafe_stats();
+
+        MetricsSnapshot {
+            crate_name: k.crate_name.clone(),
+            files: k.files.len(),
+            lines,
+            code_lines,
+            functions: k.functions.len(),
+            public_functions: k.functions.iter().filter(|f| f.is_public).count(),
+            types: k.types.len(),
+            doc_coverage_percent: k.doc_coverage().percent,
+            unsafe_fns: unsafe_stats.total.fns,
+            unsafe_blocks: unsafe_stats.total.blocks,
+            unsafe_impls: unsafe_stats.total.impls,
+            unsafe_traits: unsafe_stats.total.traits,
+            findings: report.severity_counts(),
+            findings_by_rule,
+            coupling: k.module_coupling(),
+        }
+    }
+
+    pub fn to_json_string(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+
+    pub fn from_json_str(s: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+
+    fn values(&self) -> BTreeMap<String, (f64, Better)> {
+        let mut m = BTreeMap::new();
+        let mut put = |name: &str, v: usize, b: Better| { m.insert(name.to_string(), (v as f64, b)); };
+        put("files", self.files, Better::Neutral);
+        put("lines", self.lines, Better::Neutral);
+        put("code_lines", self.code_lines, Better::Neutral);
+        put("functions", self.functions, Better::Neutral);
+        put("public_functions", self.public_functions, Better::Neutral);
+        put("types", self.types, Better::Neutral);
+        put("unsafe_fns", self.unsafe_fns, Better::Lower);
+        put("unsafe_blocks", self.unsafe_blocks, Better::Lower);
+        put("unsafe_impls", self.unsafe_impls, Better::Lower);
+        put("unsafe_traits", self.unsafe_traits, Better::Lower);
+        put("findings.deny", self.findings.deny, Better::Lower);
+        put("findings.warn", self.findings.warn, Better::Lower);
+        put("findings.info", self.findings.info, Better::Lower);
+        for (code, n) in &self.findings_by_rule {
+            put(&format!("findings.{}", code), *n, Better::Lower);
+        }
+        m.insert("doc_coverage_percent".into(), (self.doc_coverage_percent, Better::Higher));
+        m
+    }
+
+    /// Every metric that changed since `old`, flagging the ones that moved the wrong way.
+    pub fn diff(&self, old: &MetricsSnapshot) -> MetricsDiff {
+        let new_v = self.values();
+        let old_v = old.values();
+
+        let mut changes = Vec::new();
+        let names: std::collections::BTreeSet<&String> = new_v.keys().chain(old_v.keys()).collect();
+        for name in names {
+            let (n, better) = new_v.get(name).copied().unwrap_or((0.0, Better::Lower));
+            let (o, _) = old_v.get(name).copied().unwrap_or((0.0, better));
+            if (n - o).abs() < f64::EPSILON { continue; }
+            let regression = match better {
+                Better::Lower => n > o,
+                Better::Higher => n < o,
+                Better::Neutral => false,
+            };
+            changes.push(MetricChange { metric: name.clone(), old: o, new: n, regression });
+        }
+        MetricsDiff { changes }
+    }
+}
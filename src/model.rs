@@ -1,15 +1,88 @@
 use globset::GlobSetBuilder;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+pub use crate::intern::{FileId, FilePath, FileTable, IStr, ModulePath};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct FileLocation {
-    pub path: PathBuf,
+    pub path: FilePath,
+    /// 1-based line.
     pub line: Option<u32>,
+    /// 0-based column in characters (Unicode scalar values), as proc-macro2 counts;
+    /// see `column_1based`, `byte_column` and `utf16_column` for other conventions.
     pub column: Option<u32>,
+    #[serde(default)]
+    pub byte_range: Option<std::ops::Range<usize>>, // whole span, counted after any BOM/shebang syn strips; `\r\n` counts 2
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Bytes `syn::parse_file` cuts off the front of `source` before parsing (a BOM,
+/// then a `#!` line that isn't an inner attribute); scanned byte ranges count
+/// from there.
+pub(crate) fn parsed_offset(source: &str) -> usize {
+    let bom = if source.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+    let rest = &source[bom..];
+    let shebang = match rest.strip_prefix("#!") {
+        Some(after) if !after.trim_start().starts_with('[') => rest.find('\n').unwrap_or(rest.len()),
+        _ => 0,
+    };
+    bom + shebang
+}
+
+impl FileLocation {
+    /// The source text this location covers, given the file's contents.
+    pub fn snippet<'s>(&self, source: &'s str) -> Option<&'s str> {
+        let offset = parsed_offset(source);
+        let r = self.byte_range.clone()?;
+        source.get(r.start + offset..r.end + offset)
+    }
+
+    /// 1-based character column, as rustc and most editors show it.
+    pub fn column_1based(&self) -> Option<u32> {
+        self.column.map(|c| c + 1)
+    }
+
+    /// 0-based column in UTF-8 bytes, given the file's contents.
+    pub fn byte_column(&self, source: &str) -> Option<u32> {
+        Some(self.line_prefix(source)?.len() as u32)
+    }
+
+    /// 0-based column in UTF-16 code units (LSP's default position encoding),
+    /// given the file's contents.
+    pub fn utf16_column(&self, source: &str) -> Option<u32> {
+        Some(self.line_prefix(source)?.encode_utf16().count() as u32)
+    }
+
+    // the text of `line` before `column`; None if the source doesn't have it
+    fn line_prefix<'s>(&self, source: &'s str) -> Option<&'s str> {
+        let line_no = self.line?.checked_sub(1)? as usize;
+        let text = source.lines().nth(line_no)?;
+        // proc-macro2 never sees a BOM, so it doesn't count toward the column
+        let text = if line_no == 0 { text.strip_prefix('\u{feff}').unwrap_or(text) } else { text };
+        let col = self.column? as usize;
+        match text.char_indices().nth(col) {
+            Some((i, _)) => Some(&text[..i]),
+            None if text.chars().count() == col => Some(text),
+            None => None,
+        }
+    }
+}
+
+/// `path:line:col`, with a 1-based column as rustc prints it; `path` alone without a line.
+impl std::fmt::Display for FileLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(col) = self.column_1based() {
+                write!(f, ":{}", col)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum FnKind {
     FreeFn,
     ImplMethod {
@@ -21,7 +94,20 @@ pub enum FnKind {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where the function is declared: `fn`, `impl Foo`, `impl Display for Foo` or `trait Foo`.
+impl std::fmt::Display for FnKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FnKind::FreeFn => f.write_str("fn"),
+            FnKind::ImplMethod { self_ty, trait_ty: None } => write!(f, "impl {}", self_ty),
+            FnKind::ImplMethod { self_ty, trait_ty: Some(t) } => write!(f, "impl {} for {}", t, self_ty),
+            FnKind::TraitMethod { trait_name } => write!(f, "trait {}", trait_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CapturedFn {
     pub name: String,
     pub fq_name: String, // crate::mod::Type::method
@@ -37,10 +123,16 @@ pub struct CapturedFn {
     pub return_ty: Option<String>,
 
     pub kind: FnKind,
-    pub module_path: Vec<String>,
+    pub module_path: ModulePath,
     pub attrs: Vec<String>,
     pub signature: String,
     pub location: FileLocation,
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Compiled only for tests: inside `#[cfg(test)]` (item, module or
+    /// `mod x;` file) or a `#[test]` function.
+    #[serde(default)]
+    pub is_test_code: bool,
 }
 
 impl CapturedFn {
@@ -48,7 +140,55 @@ impl CapturedFn {
     pub fn has_docs(&self) -> bool { self.has_docs }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TypeKind {
+    Struct,
+    Enum,
+    Union,
+    TypeAlias,
+    Trait,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CapturedType {
+    pub name: String,
+    pub fq_name: String, // crate::mod::Type
+    pub kind: TypeKind,
+    pub is_public: bool,
+    pub has_docs: bool,
+    pub is_generic: bool,
+    pub module_path: ModulePath,
+    pub attrs: Vec<String>,
+    pub location: FileLocation,
+    #[serde(default)]
+    pub doc: Option<String>,
+    #[serde(default)]
+    pub derives: Vec<String>, // `#[derive(..)]` paths as written: "Debug", "serde::Serialize"
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CapturedImpl {
+    pub self_ty: String, // base name, refs and generics stripped: "Vec" for `impl<T> X for &Vec<T>`
+    #[serde(default)]
+    pub self_ty_args: Vec<String>, // its generic arguments, lifetimes left out: ["T"]
+    pub trait_ty: Option<String>,
+    #[serde(default)]
+    pub trait_args: Vec<String>, // the trait's generic arguments, as `self_ty_args`: ["u8"] for `From<u8>`
+    pub is_unsafe: bool,
+    pub is_generic: bool,
+    pub module_path: ModulePath,
+    pub location: FileLocation,
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum UseKind {
     Name,
     Glob,
@@ -56,6 +196,7 @@ pub enum UseKind {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StolenPathV1 {
     pub root: String,
     pub segments: Vec<String>,
@@ -66,12 +207,13 @@ pub struct StolenPathV1 {
     pub location: FileLocation,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StolenPath {
     pub root: String,
     pub segments: Vec<String>,
 
-    pub module_path: Vec<String>,
+    pub module_path: ModulePath,
     pub is_internal: bool,
     pub is_public_use: bool,
     pub kind: UseKind,
@@ -82,93 +224,203 @@ pub struct StolenPath {
     pub origin: Option<ImportOrigin>,
     #[serde(default)]
     pub is_absolute: Option<bool>,
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
+    /// fq_name of the function whose body holds the `use`; None at module level.
+    #[serde(default)]
+    pub enclosing_fn: Option<IStr>,
+    /// A name a scanned module brings in through this `use`'s glob, recorded
+    /// at the glob's location; the glob itself stays listed too.
+    #[serde(default)]
+    pub expanded_from_glob: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ExportedSymbol {
     pub exported_as: String,     // name visible in public surface
     pub source_path: String,     // crate::x::y or external::path::Thing
-    pub module_path: Vec<String>,
+    pub module_path: ModulePath,
     pub location: FileLocation,
+    /// The item the `pub use` chain ends at, as `crate::<module path>::Name`; None
+    /// for globs, other crates' items and items the scan didn't capture.
+    #[serde(default)]
+    pub resolved_target: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A `mod` item, inline (`mod a { .. }`) or declaring a file (`mod a;`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ModuleDecl {
+    pub name: String,
+    pub module_path: ModulePath, // the module it's declared in
+    pub is_public: bool,
+    pub is_inline: bool,
+    pub location: FileLocation,
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MacroDef {
     pub name: String,
-    pub module_path: Vec<String>,
+    pub module_path: ModulePath,
     pub location: FileLocation,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MacroInvocationV1 {
     pub name: String,
     pub module_path: Vec<String>,
     pub location: FileLocation,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MacroInvocation {
-    pub name: String,
-    pub module_path: Vec<String>,
+    pub name: IStr,
+    pub module_path: ModulePath,
         #[serde(default)]
     pub path: Option<String>,
     pub location: FileLocation,
     #[serde(default)]
-    pub enclosing_fn: Option<String>,
+    pub enclosing_fn: Option<IStr>,
     #[serde(default)]
     pub enclosing_public: Option<bool>,
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PathOccurrenceV1 {
     pub path: String,            // std::sync::Arc
     pub module_path: Vec<String>,
     pub location: FileLocation,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PathOccurrence {
-    pub path: String,
-    pub module_path: Vec<String>,
+    pub path: IStr,
+    /// `path` with its first segment replaced by the same-module `use` it names
+    /// (`Arc::new` -> `std::sync::Arc::new`); None when it names no import.
+    #[serde(default)]
+    pub resolved: Option<IStr>,
+    pub module_path: ModulePath,
     pub location: FileLocation,
     #[serde(default)]
-    pub enclosing_fn: Option<String>,
+    pub enclosing_fn: Option<IStr>,
     #[serde(default)]
     pub enclosing_public: Option<bool>,
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
+}
+
+/// Which `PathOccurrence`s a scan records (`KleptoBuilder::capture_paths`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PathCapture {
+    /// Every multi-segment path, plus bare `std` / `crate` / `self` / ...
+    #[default]
+    All,
+    /// Only paths into other crates: `serde::Serialize`, or `Value::Null` after `use serde_json::Value`.
+    ExternalOnly,
+    /// No path occurrences; imports and calls are still captured.
+    None,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CallOccurrenceV1 {
     pub callee: String,          // unwrap / expect / foo / bar::baz
     pub module_path: Vec<String>,
     pub location: FileLocation,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CallOccurrence {
-    pub callee: String,
-    pub module_path: Vec<String>,
+    pub callee: IStr,
+    pub module_path: ModulePath,
     pub location: FileLocation,
     #[serde(default)]
-    pub enclosing_fn: Option<String>,
+    pub enclosing_fn: Option<IStr>,
     #[serde(default)]
     pub enclosing_public: Option<bool>,
+    #[serde(default)]
+    pub receiver: Option<String>, // method calls only: `self.inner` in `self.inner.clone()`
+    #[serde(default)]
+    pub is_test_code: bool, // see `CapturedFn::is_test_code`
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Severity {
     Info,
     Warn,
     Deny,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// `info`, `warn` or `deny`, as the config spells levels.
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Deny => "deny",
+        })
+    }
+}
+
+/// Stable rule identifier (`KLEP001`, or a user rule's own code).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct RuleCode(std::borrow::Cow<'static, str>);
+
+impl RuleCode {
+    pub const fn from_static(code: &'static str) -> Self { Self(std::borrow::Cow::Borrowed(code)) }
+    pub fn new(code: impl Into<String>) -> Self { Self(std::borrow::Cow::Owned(code.into())) }
+    pub fn as_str(&self) -> &str { &self.0 }
+    /// `KLEP*`: reserved for klepto's own rules.
+    pub fn is_builtin(&self) -> bool { self.0.starts_with("KLEP") }
+}
+
+impl std::fmt::Display for RuleCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.pad(&self.0) }
+}
+
+impl From<&'static str> for RuleCode {
+    fn from(code: &'static str) -> Self { Self::from_static(code) }
+}
+
+impl PartialEq<str> for RuleCode {
+    fn eq(&self, other: &str) -> bool { self.0 == other }
+}
+
+impl PartialEq<&str> for RuleCode {
+    fn eq(&self, other: &&str) -> bool { self.0 == *other }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Finding {
     pub severity: Severity,
-    pub code: String,           // stable rule code: KLEP001
+    pub code: RuleCode,
     pub message: String,
     pub location: FileLocation,
     pub extra: serde_json::Value,
 }
 
+/// `path:line:col: warn[KLEP001] message`, on one line.
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}[{}] {}", self.location, self.severity, self.code, self.message.replace('\n', " "))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DocCoverage {
     pub public_total: usize,
     pub public_documented: usize,
@@ -176,13 +428,65 @@ pub struct DocCoverage {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PublicSurface {
     pub exports: Vec<ExportedSymbol>,
 }
 
+/// Re-exports that make a public surface ambiguous (see `PublicSurface::conflicts`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ExportConflict {
+    /// One name re-exported for different items.
+    NameReused { exported_as: String, exports: Vec<ExportedSymbol> },
+    /// One item re-exported under different names.
+    ItemRenamed { target: String, exports: Vec<ExportedSymbol> },
+}
 
+impl ExportConflict {
+    pub fn exports(&self) -> &[ExportedSymbol] {
+        match self {
+            ExportConflict::NameReused { exports, .. } | ExportConflict::ItemRenamed { exports, .. } => exports,
+        }
+    }
+}
+
+impl PublicSurface {
+    /// Names re-exported for more than one item, and items re-exported under more
+    /// than one name, crate-wide. An item is its `resolved_target`, else its
+    /// `source_path`; globs are left out. The same item under the same name in
+    /// several modules (a prelude) is fine.
+    pub fn conflicts(&self) -> Vec<ExportConflict> {
+        use std::collections::{BTreeMap, BTreeSet};
+        let target = |e: &ExportedSymbol| e.resolved_target.clone().unwrap_or_else(|| e.source_path.clone());
+        let named = self.exports.iter().filter(|e| e.exported_as != "*");
+
+        let mut by_name: BTreeMap<&str, Vec<&ExportedSymbol>> = BTreeMap::new();
+        let mut by_target: BTreeMap<String, Vec<&ExportedSymbol>> = BTreeMap::new();
+        for e in named {
+            by_name.entry(e.exported_as.as_str()).or_default().push(e);
+            by_target.entry(target(e)).or_default().push(e);
+        }
+
+        let mut out = Vec::new();
+        for (name, exports) in by_name {
+            if exports.iter().map(|e| target(e)).collect::<BTreeSet<_>>().len() > 1 {
+                out.push(ExportConflict::NameReused { exported_as: name.to_string(), exports: exports.into_iter().cloned().collect() });
+            }
+        }
+        for (target, exports) in by_target {
+            if exports.iter().map(|e| e.exported_as.as_str()).collect::<BTreeSet<_>>().len() > 1 {
+                out.push(ExportConflict::ItemRenamed { target, exports: exports.into_iter().cloned().collect() });
+            }
+        }
+        out
+    }
+}
 
 
+
+
+#[derive(Clone)]
 pub struct KleptoGlobSetBuilder(pub GlobSetBuilder);
 
 impl Default for KleptoGlobSetBuilder {
@@ -212,6 +516,7 @@ impl std::ops::DerefMut for KleptoGlobSetBuilder {
 //     UnknownExternal,   // anything else
 // }
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ImportOrigin {
     Internal,
     Std,
@@ -225,9 +530,10 @@ pub enum ImportOrigin {
 
 
 
-#[derive(Debug, Clone, Serialize, Deserialize,
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
 // added Copy for the scanning module
 Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum UseSiteKind {
     UseStmt,       // `use dep::foo`
     ExternCrate,   // `extern crate dep;`
@@ -236,7 +542,8 @@ pub enum UseSiteKind {
     Path,          // everything else `dep::foo::bar`
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct UseSite {
     pub dep: String,     // "regex"
     pub path: String,    // "regex::RegexSet"
@@ -245,6 +552,172 @@ pub struct UseSite {
     pub location: FileLocation,     // 1-based
     pub scope: String,   // "fn run" / "impl Foo" / "file"
 }
+/// Rust edition of the package a file belongs to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    /// `"2015"` .. `"2024"`; None for editions this klepto doesn't know.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "2015" => Some(Edition::E2015),
+            "2018" => Some(Edition::E2018),
+            "2021" => Some(Edition::E2021),
+            "2024" => Some(Edition::E2024),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+impl std::fmt::Display for Edition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.pad(self.as_str()) }
+}
+
+/// Cargo target a source file belongs to, going by the standard layout.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TargetKind {
+    Lib,
+    Bin,         // src/main.rs, src/bin/**
+    Test,        // tests/**
+    Example,     // examples/**
+    Bench,       // benches/**
+    BuildScript, // build.rs
+}
+
+impl TargetKind {
+    /// Classifies by the nearest `src`/`tests`/`examples`/`benches` directory above `path`.
+    pub fn of_path(path: &std::path::Path) -> Self {
+        if path.file_name().is_some_and(|n| n == "build.rs") {
+            return TargetKind::BuildScript;
+        }
+        let dirs: Vec<&std::ffi::OsStr> = path.parent().map(|p| p.iter().collect()).unwrap_or_default();
+        for (i, d) in dirs.iter().enumerate().rev() {
+            match d.to_str() {
+                Some("tests") => return TargetKind::Test,
+                Some("examples") => return TargetKind::Example,
+                Some("benches") => return TargetKind::Bench,
+                Some("src") => {
+                    let in_bin = dirs.get(i + 1).is_some_and(|c| *c == "bin");
+                    let is_main = i + 1 == dirs.len() && path.file_name().is_some_and(|n| n == "main.rs");
+                    return if in_bin || is_main { TargetKind::Bin } else { TargetKind::Lib };
+                }
+                _ => {}
+            }
+        }
+        TargetKind::Lib
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Test => "test",
+            TargetKind::Example => "example",
+            TargetKind::Bench => "bench",
+            TargetKind::BuildScript => "build script",
+        }
+    }
+
+    /// Tests, examples and benches: targets built only with dev-dependencies available.
+    pub fn is_dev(self) -> bool {
+        matches!(self, TargetKind::Test | TargetKind::Example | TargetKind::Bench)
+    }
+}
+
+/// A `[dependencies]` entry only ever used from dev targets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DevOnlyDependency {
+    pub dep: String,
+    pub targets: std::collections::BTreeSet<TargetKind>, // where it is used
+    pub sites: Vec<UseSite>,
+}
+
 use ::std::collections::BTreeMap;
 pub type UseSites = BTreeMap<String, BTreeMap<String, usize>>;
 pub type UseSitesCount = UseSites;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, column: u32) -> FileLocation {
+        FileLocation { path: std::path::PathBuf::from("src/lib.rs").into(), line: Some(line), column: Some(column), byte_range: None }
+    }
+
+    #[test]
+    fn columns_after_multibyte_and_astral_chars() {
+        // `é` is 2 bytes and 1 UTF-16 unit, `😀` 4 bytes and 2 units
+        let src = "fn a() {}\n/* é😀 */ pub fn f() {}\n";
+        let loc = at(2, 9);
+        assert_eq!(loc.column_1based(), Some(10));
+        assert_eq!(loc.byte_column(src), Some(13));
+        assert_eq!(loc.utf16_column(src), Some(10));
+        assert_eq!(&src.lines().nth(1).unwrap()[13..], "pub fn f() {}");
+        assert_eq!(loc.to_string(), "src/lib.rs:2:10");
+    }
+
+    #[test]
+    fn bom_does_not_count_on_line_one() {
+        let src = "\u{feff}/* é */ fn f() {}\nfn g() {}\n";
+        assert_eq!(at(1, 8).byte_column(src), Some(9));
+        assert_eq!(at(1, 8).utf16_column(src), Some(8));
+        assert_eq!(at(2, 3).byte_column(src), Some(3));
+    }
+
+    #[test]
+    fn column_at_end_of_line() {
+        let src = "fn f() {} // é\n";
+        assert_eq!(at(1, 14).byte_column(src), Some(15));
+        assert_eq!(at(1, 14).utf16_column(src), Some(14));
+        assert_eq!(at(1, 15).byte_column(src), None);
+        assert_eq!(at(2, 0).byte_column(src), None);
+    }
+
+    #[cfg(feature = "span-locations")]
+    #[test]
+    fn scanned_columns_count_chars_and_skip_the_bom() {
+        let dir = std::env::temp_dir().join(format!("klepto-model-columns-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let lib = "\u{feff}/* é */ pub fn f() {}\n/* é😀 */ pub fn g() {}\n";
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        let k = crate::klepto::Klepto::new("cols").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let source = &k.files[0].source;
+        let loc = |name: &str| k.functions.iter().find(|f| f.name == name).unwrap().location.clone();
+        assert_eq!((loc("f").column, loc("f").byte_column(source)), (Some(8), Some(9)));
+        assert_eq!((loc("g").column, loc("g").byte_column(source), loc("g").utf16_column(source)), (Some(9), Some(13), Some(10)));
+        assert_eq!(loc("g").snippet(source), Some("pub fn g() {}"));
+    }
+
+    #[test]
+    fn snippets_skip_what_syn_strips() {
+        let loc = FileLocation { byte_range: Some(3..5), ..at(2, 3) };
+        assert_eq!(loc.snippet("fn f() {}"), Some("f("));
+        assert_eq!(loc.snippet("\u{feff}fn f() {}"), Some("f("));
+        // syn keeps the shebang line's `\n`
+        let loc = FileLocation { byte_range: Some(4..6), ..loc };
+        assert_eq!(loc.snippet("#!/bin/x\nfn f() {}"), Some("f("));
+        assert_eq!(parsed_offset("#![allow(x)]\nfn f() {}"), 0);
+    }
+}
@@ -0,0 +1,352 @@
+//! Syntax and std items newer than a minimum supported Rust version, found
+//! from a table of patterns instead of a rustc per version.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::FileLocation;
+use crate::scope::span_to_location;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+/// `1.70` or `1.70.1`, as `rust-version` spells it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RustVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl RustVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self { Self { major, minor, patch } }
+}
+
+impl FromStr for RustVersion {
+    type Err = KleptoError;
+
+    fn from_str(s: &str) -> Result<Self, KleptoError> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        let nums: Option<Vec<u32>> = parts.iter().map(|p| p.parse().ok()).collect();
+        match nums.as_deref() {
+            Some(&[major, minor]) => Ok(Self::new(major, minor, 0)),
+            Some(&[major, minor, patch]) => Ok(Self::new(major, minor, patch)),
+            _ => Err(KleptoError::RustVersion(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.patch == 0 { write!(f, "{}.{}", self.major, self.minor) } else { write!(f, "{}.{}.{}", self.major, self.minor, self.patch) }
+    }
+}
+
+impl Serialize for RustVersion {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> { s.collect_str(self) }
+}
+
+impl<'de> Deserialize<'de> for RustVersion {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for RustVersion {
+    fn inline_schema() -> bool { true }
+    fn schema_name() -> std::borrow::Cow<'static, str> { String::schema_name() }
+    fn json_schema(g: &mut schemars::SchemaGenerator) -> schemars::Schema { String::json_schema(g) }
+}
+
+/// Language features found in the ASTs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SyntaxFeature {
+    LetElse,
+    LabeledBlockBreak,
+    GenericAssociatedTypes,
+    AsyncFnInTrait,
+    ImplTraitInTrait,
+    CStrLiteral,
+    InlineConst,
+    ExclusiveRangePattern,
+    RawRef,
+    UnsafeExternBlock,
+    PreciseCapturing,
+    AsyncClosure,
+    LetChains,
+}
+
+/// How a table entry is recognized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MsrvPattern {
+    Syntax(SyntaxFeature),
+    /// A std item (`std::sync::OnceLock`), matched in imports and in paths that
+    /// don't go through one; `core::`/`alloc::` spellings match too.
+    Path(String),
+    /// A method call by name (`is_some_and`); the receiver's type isn't known.
+    Method(String),
+    /// A macro invocation by name, without `!`.
+    Macro(String),
+}
+
+/// One table row: what stabilized in which release.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MsrvEntry {
+    pub name: String,
+    pub since: RustVersion,
+    #[serde(flatten)]
+    pub pattern: MsrvPattern,
+}
+
+impl MsrvEntry {
+    pub fn new(name: impl Into<String>, since: RustVersion, pattern: MsrvPattern) -> Self {
+        Self { name: name.into(), since, pattern }
+    }
+}
+
+/// The patterns `msrv_violations` looks for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MsrvTable {
+    pub entries: Vec<MsrvEntry>,
+}
+
+impl MsrvTable {
+    /// Common syntax and std additions from 1.63 on; not exhaustive.
+    pub fn builtin() -> Self {
+        use MsrvPattern::*;
+        use SyntaxFeature::*;
+        let v = |minor| RustVersion::new(1, minor, 0);
+        let rows = [
+            ("`std::thread::scope`", v(63), Path("std::thread::scope".into())),
+            ("`std::array::from_fn`", v(63), Path("std::array::from_fn".into())),
+            ("let-else", v(65), Syntax(LetElse)),
+            ("labeled block `break`", v(65), Syntax(LabeledBlockBreak)),
+            ("generic associated types", v(65), Syntax(GenericAssociatedTypes)),
+            ("`std::backtrace::Backtrace`", v(65), Path("std::backtrace".into())),
+            ("`std::hint::black_box`", v(66), Path("std::hint::black_box".into())),
+            ("`std::os::fd`", v(66), Path("std::os::fd".into())),
+            ("`ilog2`/`ilog10`", v(67), Method("ilog2".into())),
+            ("`std::sync::OnceLock`", v(70), Path("std::sync::OnceLock".into())),
+            ("`std::cell::OnceCell`", v(70), Path("std::cell::OnceCell".into())),
+            ("`std::io::IsTerminal`", v(70), Path("std::io::IsTerminal".into())),
+            ("`Option::is_some_and`", v(70), Method("is_some_and".into())),
+            ("`div_ceil`", v(73), Method("div_ceil".into())),
+            ("`async fn` in traits", v(75), Syntax(AsyncFnInTrait)),
+            ("`impl Trait` in trait methods", v(75), Syntax(ImplTraitInTrait)),
+            ("`c\"..\"` literals", v(77), Syntax(CStrLiteral)),
+            ("`std::mem::offset_of!`", v(77), Macro("offset_of".into())),
+            ("inline `const { .. }`", v(79), Syntax(InlineConst)),
+            ("`std::num::NonZero`", v(79), Path("std::num::NonZero".into())),
+            ("exclusive range patterns", v(80), Syntax(ExclusiveRangePattern)),
+            ("`std::sync::LazyLock`", v(80), Path("std::sync::LazyLock".into())),
+            ("`std::cell::LazyCell`", v(80), Path("std::cell::LazyCell".into())),
+            ("`&raw const`/`&raw mut`", v(82), Syntax(RawRef)),
+            ("`unsafe extern` blocks", v(82), Syntax(UnsafeExternBlock)),
+            ("`use<..>` precise capturing", v(82), Syntax(PreciseCapturing)),
+            ("`Option::is_none_or`", v(82), Method("is_none_or".into())),
+            ("`std::iter::repeat_n`", v(82), Path("std::iter::repeat_n".into())),
+            ("async closures", v(85), Syntax(AsyncClosure)),
+            ("let chains", v(88), Syntax(LetChains)),
+        ];
+        Self { entries: rows.into_iter().map(|(name, since, pattern)| MsrvEntry::new(name, since, pattern)).collect() }
+    }
+
+    pub fn with(mut self, entry: MsrvEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+}
+
+/// A use of something newer than the MSRV.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MsrvViolation {
+    pub entry: MsrvEntry,
+    pub location: FileLocation,
+}
+
+// syntax features and where they occur, in one pass over the ASTs
+struct Walker<'a> {
+    file: &'a Path,
+    in_trait: bool,
+    out: Vec<(SyntaxFeature, FileLocation)>,
+}
+
+impl Walker<'_> {
+    fn hit(&mut self, feature: SyntaxFeature, span: proc_macro2::Span) {
+        self.out.push((feature, span_to_location(self.file, span)));
+    }
+}
+
+fn has_let(e: &syn::Expr) -> bool {
+    match e {
+        syn::Expr::Let(_) => true,
+        syn::Expr::Binary(b) if matches!(b.op, syn::BinOp::And(_)) => has_let(&b.left) || has_let(&b.right),
+        _ => false,
+    }
+}
+
+impl<'ast> Visit<'ast> for Walker<'_> {
+    fn visit_local(&mut self, l: &'ast syn::Local) {
+        if l.init.as_ref().is_some_and(|i| i.diverge.is_some()) { self.hit(SyntaxFeature::LetElse, l.span()); }
+        syn::visit::visit_local(self, l);
+    }
+
+    fn visit_expr_block(&mut self, e: &'ast syn::ExprBlock) {
+        if let Some(label) = &e.label { self.hit(SyntaxFeature::LabeledBlockBreak, label.span()); }
+        syn::visit::visit_expr_block(self, e);
+    }
+
+    fn visit_item_trait(&mut self, t: &'ast syn::ItemTrait) {
+        let outer = std::mem::replace(&mut self.in_trait, true);
+        syn::visit::visit_item_trait(self, t);
+        self.in_trait = outer;
+    }
+
+    // trait bodies only; impls of such traits are flagged at the trait
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        let outer = std::mem::replace(&mut self.in_trait, false);
+        syn::visit::visit_item_impl(self, i);
+        self.in_trait = outer;
+    }
+
+    fn visit_trait_item_type(&mut self, t: &'ast syn::TraitItemType) {
+        if !t.generics.params.is_empty() { self.hit(SyntaxFeature::GenericAssociatedTypes, t.span()); }
+        syn::visit::visit_trait_item_type(self, t);
+    }
+
+    fn visit_trait_item_fn(&mut self, f: &'ast syn::TraitItemFn) {
+        if self.in_trait {
+            if f.sig.asyncness.is_some() { self.hit(SyntaxFeature::AsyncFnInTrait, f.sig.span()); }
+            if let syn::ReturnType::Type(_, ty) = &f.sig.output
+                && matches!(**ty, syn::Type::ImplTrait(_))
+            {
+                self.hit(SyntaxFeature::ImplTraitInTrait, ty.span());
+            }
+        }
+        syn::visit::visit_trait_item_fn(self, f);
+    }
+
+    fn visit_lit(&mut self, l: &'ast syn::Lit) {
+        if let syn::Lit::CStr(c) = l { self.hit(SyntaxFeature::CStrLiteral, c.span()); }
+        syn::visit::visit_lit(self, l);
+    }
+
+    fn visit_expr_const(&mut self, e: &'ast syn::ExprConst) {
+        self.hit(SyntaxFeature::InlineConst, e.span());
+        syn::visit::visit_expr_const(self, e);
+    }
+
+    fn visit_pat(&mut self, p: &'ast syn::Pat) {
+        if let syn::Pat::Range(r) = p
+            && matches!(r.limits, syn::RangeLimits::HalfOpen(_))
+            && r.end.is_some()
+        {
+            self.hit(SyntaxFeature::ExclusiveRangePattern, r.span());
+        }
+        syn::visit::visit_pat(self, p);
+    }
+
+    fn visit_expr_raw_addr(&mut self, e: &'ast syn::ExprRawAddr) {
+        self.hit(SyntaxFeature::RawRef, e.span());
+        syn::visit::visit_expr_raw_addr(self, e);
+    }
+
+    fn visit_item_foreign_mod(&mut self, i: &'ast syn::ItemForeignMod) {
+        if let Some(u) = &i.unsafety { self.hit(SyntaxFeature::UnsafeExternBlock, u.span()); }
+        syn::visit::visit_item_foreign_mod(self, i);
+    }
+
+    fn visit_precise_capture(&mut self, p: &'ast syn::PreciseCapture) {
+        self.hit(SyntaxFeature::PreciseCapturing, p.span());
+        syn::visit::visit_precise_capture(self, p);
+    }
+
+    fn visit_expr_closure(&mut self, c: &'ast syn::ExprClosure) {
+        if let Some(a) = &c.asyncness { self.hit(SyntaxFeature::AsyncClosure, a.span()); }
+        syn::visit::visit_expr_closure(self, c);
+    }
+
+    fn visit_expr_if(&mut self, e: &'ast syn::ExprIf) {
+        if !matches!(*e.cond, syn::Expr::Let(_)) && has_let(&e.cond) { self.hit(SyntaxFeature::LetChains, e.cond.span()); }
+        syn::visit::visit_expr_if(self, e);
+    }
+
+    fn visit_expr_while(&mut self, e: &'ast syn::ExprWhile) {
+        if !matches!(*e.cond, syn::Expr::Let(_)) && has_let(&e.cond) { self.hit(SyntaxFeature::LetChains, e.cond.span()); }
+        syn::visit::visit_expr_while(self, e);
+    }
+}
+
+// `core::cell::OnceCell` names `std::cell::OnceCell`; true for the item and paths under it
+fn path_matches(path: &str, pattern: &str) -> bool {
+    let strip = |p: &'_ str| -> String {
+        let p = p.trim_start_matches("::");
+        match p.split_once("::") {
+            Some(("std" | "core" | "alloc", rest)) => rest.to_string(),
+            _ => format!("\0{}", p), // not std: never equal to a std item
+        }
+    };
+    let (path, pattern) = (strip(path), strip(pattern));
+    path == pattern || path.starts_with(&format!("{}::", pattern))
+}
+
+impl Klepto {
+    /// Uses of the entries in `table` that stabilized after `msrv`, ordered by
+    /// location. Test code counts, since it has to build on the MSRV too.
+    pub fn msrv_violations(&self, msrv: RustVersion, table: &MsrvTable) -> Vec<MsrvViolation> {
+        let newer: Vec<&MsrvEntry> = table.entries.iter().filter(|e| e.since > msrv).collect();
+        let mut out = Vec::new();
+        let mut hit = |e: &MsrvEntry, location: &FileLocation| out.push(MsrvViolation { entry: e.clone(), location: location.clone() });
+
+        let syntax: HashSet<SyntaxFeature> = newer.iter()
+            .filter_map(|e| match e.pattern { MsrvPattern::Syntax(s) => Some(s), _ => None })
+            .collect();
+        if !syntax.is_empty() {
+            for pf in &self.files {
+                let mut w = Walker { file: &pf.path, in_trait: false, out: Vec::new() };
+                w.visit_file(&pf.ast());
+                for (feature, loc) in w.out.iter().filter(|(f, _)| syntax.contains(f)) {
+                    for e in newer.iter().filter(|e| e.pattern == MsrvPattern::Syntax(*feature)) {
+                        hit(e, loc);
+                    }
+                }
+            }
+        }
+
+        for e in &newer {
+            match &e.pattern {
+                MsrvPattern::Syntax(_) => {}
+                MsrvPattern::Path(p) => {
+                    for i in self.imports.iter().filter(|i| path_matches(&i.full_path, p)) {
+                        hit(e, &i.location);
+                    }
+                    // a path through a `use` was flagged at the `use`
+                    for o in self.paths.iter().filter(|o| o.resolved.is_none() && path_matches(&o.path, p)) {
+                        hit(e, &o.location);
+                    }
+                }
+                MsrvPattern::Method(m) => {
+                    for c in self.calls.iter().filter(|c| c.receiver.is_some() && c.callee == m.as_str()) {
+                        hit(e, &c.location);
+                    }
+                }
+                MsrvPattern::Macro(m) => {
+                    for i in self.macros_inv.iter().filter(|i| i.name == m.as_str()) {
+                        hit(e, &i.location);
+                    }
+                }
+            }
+        }
+        out.sort_by(|a, b| (&a.location.path, a.location.line, a.location.column).cmp(&(&b.location.path, b.location.line, b.location.column)));
+        out
+    }
+}
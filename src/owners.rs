@@ -0,0 +1,114 @@
+//! CODEOWNERS: who owns which files, for routing findings and items to teams.
+//!
+//! GitHub's syntax: `pattern owner...` per line, gitignore-style patterns
+//! relative to the repository root, the last matching line wins, and a line
+//! without owners leaves its paths unowned.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{FileLocation, Finding};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+/// Where GitHub looks for the file, in its order.
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+#[derive(Debug, Clone)]
+pub struct CodeOwners {
+    root: PathBuf,
+    text: String, // as parsed, so a saved analysis can rebuild the globs
+    owners: Vec<Vec<String>>, // per line, in file order
+    globs: GlobSet,
+    glob_line: Vec<usize>, // glob index -> line index
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedOwners {
+    root: PathBuf,
+    text: String,
+}
+
+impl serde::Serialize for CodeOwners {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        SavedOwners { root: self.root.clone(), text: self.text.clone() }.serialize(s)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CodeOwners {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let saved = SavedOwners::deserialize(d)?;
+        Self::parse(&saved.text, saved.root).map_err(serde::de::Error::custom)
+    }
+}
+
+impl CodeOwners {
+    /// Parses CODEOWNERS text; paths are matched relative to `root`.
+    pub fn parse(text: &str, root: impl Into<PathBuf>) -> Result<Self, KleptoError> {
+        let mut owners = Vec::new();
+        let mut builder = GlobSetBuilder::new();
+        let mut glob_line = Vec::new();
+        for line in text.lines() {
+            let line = line.split_once(" #").map_or(line, |(l, _)| l).trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let mut parts = line.split_whitespace();
+            let Some(pattern) = parts.next() else { continue; };
+
+            // gitignore: a slash anywhere but the end anchors to the root
+            let dir_only = pattern.ends_with('/');
+            let core = pattern.trim_matches('/');
+            let base = if pattern.trim_end_matches('/').contains('/') { core.to_string() } else { format!("**/{}", core) };
+            let mut globs = vec![format!("{}/**", base)];
+            if !dir_only { globs.push(base); }
+            for g in globs {
+                builder.add(GlobBuilder::new(&g).literal_separator(true).build()?);
+                glob_line.push(owners.len());
+            }
+            owners.push(parts.map(str::to_string).collect());
+        }
+        Ok(Self { root: root.into(), text: text.to_string(), owners, globs: builder.build()?, glob_line })
+    }
+
+    pub fn load(path: impl AsRef<Path>, root: impl Into<PathBuf>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        Self::parse(&text, root)
+    }
+
+    /// The first of `CODEOWNERS_LOCATIONS` under `root`, if any.
+    pub fn discover(root: impl AsRef<Path>) -> Result<Option<Self>, KleptoError> {
+        let root = root.as_ref();
+        match CODEOWNERS_LOCATIONS.iter().map(|l| root.join(l)).find(|p| p.is_file()) {
+            Some(path) => Self::load(path, root).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Owners of a file (as scanned, or relative to the root); empty when unowned.
+    pub fn owners_of(&self, path: &Path) -> &[String] {
+        let rel = path.strip_prefix(&self.root).unwrap_or(path);
+        let rel = rel.strip_prefix(".").unwrap_or(rel);
+        self.globs.matches(rel).into_iter()
+            .map(|g| self.glob_line[g])
+            .max()
+            .map_or(&[], |line| &self.owners[line])
+    }
+
+    /// Sets `extra.owners` on every finding in an owned file.
+    pub fn annotate(&self, findings: &mut [Finding]) {
+        for f in findings {
+            let owners = self.owners_of(&f.location.path);
+            if owners.is_empty() { continue; }
+            if !f.extra.is_object() {
+                f.extra = serde_json::Value::Object(Default::default());
+            }
+            f.extra["owners"] = owners.into();
+        }
+    }
+}
+
+impl Klepto {
+    /// Owners of the file `loc` is in, from `KleptoBuilder::code_owners`; empty when
+    /// unowned or no CODEOWNERS was given.
+    pub fn owners_of(&self, loc: &FileLocation) -> &[String] {
+        self.code_owners.as_ref().map_or(&[], |co| co.owners_of(&loc.path))
+    }
+}
@@ -0,0 +1,155 @@
+//! Where code can panic, and which public functions can reach a panic site
+//! through the workspace call graph.
+
+use crate::klepto::Klepto;
+use crate::model::FileLocation;
+use crate::scope::span_to_location;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use syn::visit::Visit;
+
+// macros that panic in release builds too
+const PANIC_MACROS: &[&str] = &["panic", "todo", "unimplemented", "unreachable", "assert", "assert_eq", "assert_ne"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PanicKind {
+    Macro { name: String },
+    Unwrap,
+    Expect,
+    /// `a[i]` or `a[from..to]`; may panic on slices, `Vec`s and maps alike.
+    Index,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PanicSite {
+    pub kind: PanicKind,
+    /// fq_name of the function it's in.
+    pub fn_name: String,
+    pub location: FileLocation,
+}
+
+/// A public function and the shortest call chain from it to a panic site.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PanicPath {
+    pub fn_name: String,
+    /// `fn_name` first, then each callee down to the one holding `site`.
+    pub path: Vec<String>,
+    pub site: PanicSite,
+}
+
+/// Panic sites and, per non-test public function, whether one is reachable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PanicReachability {
+    pub sites: Vec<PanicSite>,
+    pub reachable: Vec<PanicPath>,
+    /// Public functions no panic site is reachable from, by fq_name.
+    pub panic_free: Vec<String>,
+}
+
+impl PanicReachability {
+    pub fn for_fn(&self, fq_name: &str) -> Option<&PanicPath> {
+        self.reachable.iter().find(|p| p.fn_name == fq_name)
+    }
+
+    pub fn is_panic_free(&self, fq_name: &str) -> bool {
+        self.panic_free.iter().any(|f| f == fq_name)
+    }
+}
+
+struct IndexWalker<'a> {
+    file: &'a Path,
+    out: Vec<FileLocation>,
+}
+
+impl<'ast> Visit<'ast> for IndexWalker<'_> {
+    fn visit_expr_index(&mut self, e: &'ast syn::ExprIndex) {
+        self.out.push(span_to_location(self.file, e.bracket_token.span.join()));
+        syn::visit::visit_expr_index(self, e);
+    }
+}
+
+impl Klepto {
+    /// Panic sites in non-test functions: `panic!`-like macros (not
+    /// `debug_assert!`s), `.unwrap()`/`.expect()` calls, and indexing. Indexing
+    /// needs `span-locations` and the ASTs to be placed in a function, so it's
+    /// missing without them.
+    pub fn panic_sites(&self) -> Vec<PanicSite> {
+        let mut out = Vec::new();
+        let in_fn = |f: Option<&str>| f.filter(|f| self.fn_by_name(f).is_some_and(|f| !f.is_test_code)).map(str::to_string);
+        for m in self.macros_inv.iter().filter(|m| !m.is_test_code && PANIC_MACROS.contains(&m.name.as_str())) {
+            if let Some(fn_name) = in_fn(m.enclosing_fn.as_deref()) {
+                out.push(PanicSite { kind: PanicKind::Macro { name: m.name.to_string() }, fn_name, location: m.location.clone() });
+            }
+        }
+        for c in self.calls.iter().filter(|c| !c.is_test_code && c.receiver.is_some()) {
+            let kind = match c.callee.as_str() {
+                "unwrap" => PanicKind::Unwrap,
+                "expect" => PanicKind::Expect,
+                _ => continue,
+            };
+            if let Some(fn_name) = in_fn(c.enclosing_fn.as_deref()) {
+                out.push(PanicSite { kind, fn_name, location: c.location.clone() });
+            }
+        }
+        if cfg!(feature = "span-locations") {
+            for pf in &self.files {
+                let mut w = IndexWalker { file: &pf.path, out: Vec::new() };
+                w.visit_file(&pf.ast());
+                for location in w.out {
+                    if let Some(fn_name) = in_fn(self.index.enclosing(&location).map(|f| f.fq_name.as_str())) {
+                        out.push(PanicSite { kind: PanicKind::Index, fn_name, location });
+                    }
+                }
+            }
+        }
+        out.sort_by(|a, b| (&a.location.path, a.location.line, a.location.column).cmp(&(&b.location.path, b.location.line, b.location.column)));
+        out
+    }
+
+    /// For each non-test public function, the shortest chain through the
+    /// call graph to a panic site (possibly in the function itself). Calls the
+    /// graph can't resolve, into dependencies included, aren't followed.
+    pub fn panic_reachability(&self) -> PanicReachability {
+        let sites = self.panic_sites();
+        let graph = self.call_graph();
+
+        // the first site of each panicking fn, then a reverse BFS over callers:
+        // `next` is one step closer to a site
+        let mut first: BTreeMap<&str, &PanicSite> = BTreeMap::new();
+        for s in &sites {
+            first.entry(s.fn_name.as_str()).or_insert(s);
+        }
+        let mut next: HashMap<&str, Option<&str>> = first.keys().map(|f| (*f, None)).collect();
+        let mut queue: VecDeque<&str> = first.keys().copied().collect();
+        while let Some(cur) = queue.pop_front() {
+            for caller in graph.callers_of(cur) {
+                if !next.contains_key(caller) {
+                    next.insert(caller, Some(cur));
+                    queue.push_back(caller);
+                }
+            }
+        }
+
+        let mut reachable = Vec::new();
+        let mut panic_free = Vec::new();
+        for f in self.functions.iter().filter(|f| !f.is_test_code && self.effective_visibility.fn_public(f)) {
+            if !next.contains_key(f.fq_name.as_str()) {
+                panic_free.push(f.fq_name.to_string());
+                continue;
+            }
+            let mut path = vec![f.fq_name.to_string()];
+            let mut at = f.fq_name.as_str();
+            while let Some(Some(n)) = next.get(at) {
+                path.push(n.to_string());
+                at = n;
+            }
+            reachable.push(PanicPath { fn_name: f.fq_name.to_string(), path, site: first[at].clone() });
+        }
+        PanicReachability { sites, reachable, panic_free }
+    }
+}
@@ -0,0 +1,185 @@
+//! Parquet export of the extracted tables, for loading into a data warehouse.
+//!
+//! One file per table (`functions`, `imports`, `paths`, `calls`, `macros`), one
+//! row per captured item; every table has `crate`, `file`, `line` and `column`
+//! columns so they can be joined and stacked across crates. Enum columns hold
+//! the variant names the JSON output uses (`ImplMethod`, `Dependency`, ...).
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::*;
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt32Array};
+use parquet::arrow::ArrowWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// columns of one table, in order
+struct Table {
+    cols: Vec<(&'static str, ArrayRef)>,
+}
+
+impl Table {
+    fn new(crate_name: &str, rows: usize) -> Self {
+        let mut t = Table { cols: Vec::new() };
+        t.str("crate", vec![crate_name; rows]);
+        t
+    }
+
+    fn str(&mut self, name: &'static str, v: Vec<&str>) {
+        self.cols.push((name, Arc::new(StringArray::from(v))));
+    }
+
+    fn opt_str(&mut self, name: &'static str, v: Vec<Option<&str>>) {
+        self.cols.push((name, Arc::new(StringArray::from(v))));
+    }
+
+    fn bool(&mut self, name: &'static str, v: Vec<bool>) {
+        self.cols.push((name, Arc::new(BooleanArray::from(v))));
+    }
+
+    fn opt_bool(&mut self, name: &'static str, v: Vec<Option<bool>>) {
+        self.cols.push((name, Arc::new(BooleanArray::from(v))));
+    }
+
+    fn module_path<'a>(&mut self, v: impl Iterator<Item = &'a ModulePath>) {
+        let joined: Vec<String> = v.map(|m| m.join("::")).collect();
+        self.str("module_path", joined.iter().map(String::as_str).collect());
+    }
+
+    fn location<'a>(&mut self, v: impl Iterator<Item = &'a FileLocation> + Clone) {
+        let files: Vec<String> = v.clone().map(|l| l.path.portable().into_owned()).collect();
+        self.str("file", files.iter().map(String::as_str).collect());
+        self.cols.push(("line", Arc::new(UInt32Array::from(v.clone().map(|l| l.line).collect::<Vec<_>>()))));
+        self.cols.push(("column", Arc::new(UInt32Array::from(v.map(|l| l.column).collect::<Vec<_>>()))));
+    }
+
+    fn write(self, path: &Path) -> Result<(), KleptoError> {
+        let batch = RecordBatch::try_from_iter(self.cols).map_err(parquet::errors::ParquetError::from)?;
+        let file = std::fs::File::create(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        let mut w = ArrowWriter::try_new(file, batch.schema(), None)?;
+        w.write(&batch)?;
+        w.close()?;
+        Ok(())
+    }
+}
+
+fn functions(k: &Klepto) -> Table {
+    let fns = &k.functions;
+    let mut t = Table::new(&k.crate_name, fns.len());
+    t.str("fq_name", fns.iter().map(|f| f.fq_name.as_str()).collect());
+    t.str("name", fns.iter().map(|f| f.name.as_str()).collect());
+    t.str("kind", fns.iter().map(|f| match f.kind {
+        FnKind::FreeFn => "FreeFn",
+        FnKind::ImplMethod { .. } => "ImplMethod",
+        FnKind::TraitMethod { .. } => "TraitMethod",
+    }).collect());
+    // the impl's self type, or the trait of a trait method
+    t.opt_str("owner", fns.iter().map(|f| match &f.kind {
+        FnKind::FreeFn => None,
+        FnKind::ImplMethod { self_ty, .. } => Some(self_ty.as_str()),
+        FnKind::TraitMethod { trait_name } => Some(trait_name.as_str()),
+    }).collect());
+    t.opt_str("trait_ty", fns.iter().map(|f| match &f.kind {
+        FnKind::ImplMethod { trait_ty, .. } => trait_ty.as_deref(),
+        _ => None,
+    }).collect());
+    t.module_path(fns.iter().map(|f| &f.module_path));
+    t.bool("is_public", fns.iter().map(|f| f.is_public).collect());
+    t.bool("has_docs", fns.iter().map(|f| f.has_docs).collect());
+    t.bool("is_test_code", fns.iter().map(|f| f.is_test_code).collect());
+    t.bool("is_async", fns.iter().map(|f| f.is_async).collect());
+    t.bool("is_unsafe", fns.iter().map(|f| f.is_unsafe).collect());
+    t.bool("is_const", fns.iter().map(|f| f.is_const).collect());
+    t.bool("is_generic", fns.iter().map(|f| f.is_generic).collect());
+    t.opt_str("return_ty", fns.iter().map(|f| f.return_ty.as_deref()).collect());
+    t.str("signature", fns.iter().map(|f| f.signature.as_str()).collect());
+    t.location(fns.iter().map(|f| &f.location));
+    t
+}
+
+fn imports(k: &Klepto) -> Table {
+    let imps = &k.imports;
+    let mut t = Table::new(&k.crate_name, imps.len());
+    t.str("full_path", imps.iter().map(|i| i.full_path.as_str()).collect());
+    t.str("root", imps.iter().map(|i| i.root.as_str()).collect());
+    t.str("kind", imps.iter().map(|i| match i.kind {
+        UseKind::Name => "Name",
+        UseKind::Glob => "Glob",
+        UseKind::Rename { .. } => "Rename",
+    }).collect());
+    t.opt_str("alias", imps.iter().map(|i| match &i.kind {
+        UseKind::Rename { alias } => Some(alias.as_str()),
+        _ => None,
+    }).collect());
+    let origins: Vec<Option<String>> = imps.iter().map(|i| i.origin.as_ref().map(|o| format!("{o:?}"))).collect();
+    t.opt_str("origin", origins.iter().map(Option::as_deref).collect());
+    t.module_path(imps.iter().map(|i| &i.module_path));
+    t.opt_str("enclosing_fn", imps.iter().map(|i| i.enclosing_fn.as_deref()).collect());
+    t.bool("is_public_use", imps.iter().map(|i| i.is_public_use).collect());
+    t.bool("is_internal", imps.iter().map(|i| i.is_internal).collect());
+    t.bool("expanded_from_glob", imps.iter().map(|i| i.expanded_from_glob).collect());
+    t.location(imps.iter().map(|i| &i.location));
+    t
+}
+
+fn paths(k: &Klepto) -> Table {
+    let ps = &k.paths;
+    let mut t = Table::new(&k.crate_name, ps.len());
+    t.str("path", ps.iter().map(|p| p.path.as_str()).collect());
+    t.opt_str("resolved", ps.iter().map(|p| p.resolved.as_deref()).collect());
+    t.module_path(ps.iter().map(|p| &p.module_path));
+    t.opt_str("enclosing_fn", ps.iter().map(|p| p.enclosing_fn.as_deref()).collect());
+    t.opt_bool("enclosing_public", ps.iter().map(|p| p.enclosing_public).collect());
+    t.bool("is_test_code", ps.iter().map(|p| p.is_test_code).collect());
+    t.location(ps.iter().map(|p| &p.location));
+    t
+}
+
+fn calls(k: &Klepto) -> Table {
+    let cs = &k.calls;
+    let mut t = Table::new(&k.crate_name, cs.len());
+    t.str("callee", cs.iter().map(|c| c.callee.as_str()).collect());
+    t.opt_str("receiver", cs.iter().map(|c| c.receiver.as_deref()).collect());
+    t.module_path(cs.iter().map(|c| &c.module_path));
+    t.opt_str("enclosing_fn", cs.iter().map(|c| c.enclosing_fn.as_deref()).collect());
+    t.opt_bool("enclosing_public", cs.iter().map(|c| c.enclosing_public).collect());
+    t.bool("is_test_code", cs.iter().map(|c| c.is_test_code).collect());
+    t.location(cs.iter().map(|c| &c.location));
+    t
+}
+
+fn macros(k: &Klepto) -> Table {
+    let ms = &k.macros_inv;
+    let mut t = Table::new(&k.crate_name, ms.len());
+    t.str("name", ms.iter().map(|m| m.name.as_str()).collect());
+    t.opt_str("path", ms.iter().map(|m| m.path.as_deref()).collect());
+    t.module_path(ms.iter().map(|m| &m.module_path));
+    t.opt_str("enclosing_fn", ms.iter().map(|m| m.enclosing_fn.as_deref()).collect());
+    t.opt_bool("enclosing_public", ms.iter().map(|m| m.enclosing_public).collect());
+    t.bool("is_test_code", ms.iter().map(|m| m.is_test_code).collect());
+    t.location(ms.iter().map(|m| &m.location));
+    t
+}
+
+impl Klepto {
+    /// Writes `functions.parquet`, `imports.parquet`, `paths.parquet`,
+    /// `calls.parquet` and `macros.parquet` into `dir` (created if missing);
+    /// returns the files written.
+    pub fn write_parquet(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, KleptoError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| KleptoError::Io { path: dir.to_path_buf(), source: e })?;
+        let tables = [
+            ("functions", functions(self)),
+            ("imports", imports(self)),
+            ("paths", paths(self)),
+            ("calls", calls(self)),
+            ("macros", macros(self)),
+        ];
+        let mut written = Vec::new();
+        for (name, table) in tables {
+            let path = dir.join(format!("{name}.parquet"));
+            table.write(&path)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
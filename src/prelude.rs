@@ -0,0 +1,24 @@
+//! What a typical audit script needs, in one `use`:
+//!
+//! ```rust,no_run
+//! use klepto::prelude::*;
+//!
+//! let k = Klepto::new("my_crate").scan_in_folder("src").parse()?;
+//! let findings = k.rules().with_default_rules().run();
+//! println!("{}", Report::new(findings).render_table());
+//! println!("{:#?}", k.imports().collect().summary());
+//! # Ok::<(), KleptoError>(())
+//! ```
+
+pub use crate::config::Config;
+pub use crate::find::Finder;
+pub use crate::imports_ext::{ImportSummary, ImportVecExt};
+pub use crate::klepto::{Klepto, KleptoBuilder, KleptoError};
+pub use crate::model::{FileLocation, Finding, RuleCode, Severity};
+pub use crate::query::{FnQuery, ImportQuery};
+pub use crate::report::{
+    Report, findings_to_json, findings_to_markdown, findings_to_sarif, findings_to_table, findings_to_terminal,
+};
+pub use crate::rules::{Rule, RuleContext, RuleRunner};
+pub use crate::snapshot::Snapshot;
+pub use crate::use_sites::UseSiteVecExt;
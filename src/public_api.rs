@@ -0,0 +1,102 @@
+//! The public surface as sorted text, one item per line, in the spirit of
+//! cargo-public-api: small diffs when the API changes, none when it doesn't.
+
+use crate::extract::tidy_tokens;
+use crate::klepto::Klepto;
+use crate::model::{CapturedFn, FnKind, TypeKind};
+use crate::visibility::full_module_path;
+use std::collections::BTreeSet;
+
+impl Klepto {
+    /// Every public function (with its signature), type, trait impl on a public
+    /// type (derived ones included), and re-export, as `pub fn krate::module::Type::name(..) -> ..`
+    /// lines, sorted and deduplicated. Items are named by the module they're
+    /// declared in; re-exports add `pub use krate::module::Name = source` lines.
+    /// Traits go by their last segment and without generic arguments, so
+    /// `impl From<A>` and `impl From<B>` share a line.
+    pub fn public_api_text(&self) -> String {
+        let vis = &self.effective_visibility;
+        let mut lines = BTreeSet::new();
+
+        for t in self.types.iter().filter(|t| vis.type_public(t)) {
+            let kind = match t.kind {
+                TypeKind::Struct => "struct",
+                TypeKind::Enum => "enum",
+                TypeKind::Union => "union",
+                TypeKind::TypeAlias => "type",
+                TypeKind::Trait => "trait",
+            };
+            let m = full_module_path(&t.location.path, &t.module_path);
+            let path = self.item_path(&m, &t.name);
+            for d in &t.derives {
+                lines.insert(format!("impl {} for {}", last_segment(d), path));
+            }
+            lines.insert(format!("pub {} {}", kind, path));
+        }
+
+        for f in self.functions.iter().filter(|f| vis.fn_public(f)) {
+            lines.insert(format!("pub {}", self.api_signature(f)));
+        }
+
+        for i in self.impls.iter().filter(|i| !i.is_test_code) {
+            let Some(tr) = &i.trait_ty else { continue };
+            let m = full_module_path(&i.location.path, &i.module_path);
+            let Some(ty) = self.type_path(&i.self_ty, &m) else { continue };
+            lines.insert(format!("impl {} for {}", last_segment(tr), ty));
+        }
+
+        for e in &self.exports {
+            let m = full_module_path(&e.location.path, &e.module_path);
+            if !vis.module_public(&m) { continue; }
+            lines.insert(format!("pub use {} = {}", self.item_path(&m, &e.exported_as), e.source_path));
+        }
+
+        lines.into_iter().map(|l| l + "\n").collect()
+    }
+
+    fn item_path(&self, module: &[String], name: &str) -> String {
+        let mut parts = vec![self.crate_name.as_str()];
+        parts.extend(module.iter().map(String::as_str));
+        parts.push(name);
+        parts.join("::")
+    }
+
+    // the public type a method or impl names, preferring one declared in `m`; None
+    // for private types and types the scan didn't capture
+    fn type_path(&self, name: &str, m: &[String]) -> Option<String> {
+        let name = name.split('<').next().unwrap_or(name).trim();
+        let mut found: Vec<_> = self.types.iter().filter(|t| t.name == name).collect();
+        found.sort_by_key(|t| full_module_path(&t.location.path, &t.module_path) != m);
+        let t = found.into_iter().find(|t| self.effective_visibility.type_public(t))?;
+        Some(self.item_path(&full_module_path(&t.location.path, &t.module_path), &t.name))
+    }
+
+    // the signature with the function's name replaced by its path
+    fn api_signature(&self, f: &CapturedFn) -> String {
+        let m = full_module_path(&f.location.path, &f.module_path);
+        let owner = match &f.kind {
+            FnKind::FreeFn => None,
+            FnKind::ImplMethod { self_ty, .. } => Some(self_ty),
+            FnKind::TraitMethod { trait_name } => Some(trait_name),
+        };
+        let path = match owner {
+            Some(ty) => {
+                let ty = self.type_path(ty, &m).unwrap_or_else(|| self.item_path(&m, ty));
+                format!("{}::{}", ty, f.name)
+            }
+            None => self.item_path(&m, &f.name),
+        };
+        // rustfmt's trailing comma on a wrapped parameter list isn't part of the API
+        let sig = tidy_tokens(&f.signature).replace(",)", ")");
+        let needle = format!("fn {}", f.name);
+        match sig.find(&needle) {
+            Some(at) => format!("{}fn {}{}", &sig[..at], path, &sig[at + needle.len()..]),
+            None => format!("fn {}", path),
+        }
+    }
+}
+
+// `std::fmt::Display` and `fmt::Display` are the same trait
+fn last_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path).trim()
+}
@@ -1,4 +1,4 @@
-use crate::klepto::Klepto;
+use crate::klepto::{Klepto, KleptoError};
 use crate::model::*;
 use regex::Regex;
 
@@ -34,6 +34,8 @@ impl<'k> ImportQuery<'k> {
         if self.internal_only { v.retain(|p| p.is_internal); }
         if self.public_use_only { v.retain(|p| p.is_public_use); }
         if let Some(pref) = &self.full_prefix { v.retain(|p| p.full_path.starts_with(pref)); }
+        if let Some(o) = &self.origin { v.retain(|p| p.origin.as_ref() == Some(o)); }
+        if self.stdish_only { v.retain(|p| matches!(p.root.as_str(), "std" | "core" | "alloc")); }
         v
     }
 
@@ -72,6 +74,8 @@ pub struct FnQuery<'k> {
     is_generic: Option<bool>,
 
     has_attr: Option<String>,
+    uses_dep: Option<String>,
+    in_module: Option<Vec<String>>,
 }
 
 impl<'k> FnQuery<'k> {
@@ -92,6 +96,8 @@ impl<'k> FnQuery<'k> {
             is_const: None,
             is_generic: None,
             has_attr: None,
+            uses_dep: None,
+            in_module: None,
         }
     }
 
@@ -104,10 +110,20 @@ impl<'k> FnQuery<'k> {
     pub fn impls_trait(mut self, tr: impl Into<String>) -> Self { self.impls_trait = Some(tr.into()); self }
     pub fn in_trait(mut self, tr: impl Into<String>) -> Self { self.in_trait = Some(tr.into()); self }
 
+    /// Functions in module `m` or below it, file modules included (`"crate::api"` or `"api"`).
+    pub fn in_module(mut self, m: &str) -> Self {
+        let m = m.strip_prefix("crate").unwrap_or(m).trim_start_matches("::");
+        self.in_module = Some(m.split("::").filter(|s| !s.is_empty()).map(str::to_string).collect());
+        self
+    }
+
     // name filters
     pub fn named(mut self, n: impl Into<String>) -> Self { self.name_contains = Some(n.into()); self }
     pub fn name_contains(mut self, s: impl Into<String>) -> Self { self.name_contains = Some(s.into()); self }
-    pub fn name_matches(mut self, re: &str) -> Self { self.name_regex = Some(Regex::new(re).unwrap()); self }
+    pub fn name_matches(mut self, re: &str) -> Result<Self, KleptoError> {
+        self.name_regex = Some(Regex::new(re)?);
+        Ok(self)
+    }
 
     // signature helpers
     pub fn returns(mut self, s: impl Into<String>) -> Self { self.returns_contains = Some(s.into()); self }
@@ -121,6 +137,9 @@ impl<'k> FnQuery<'k> {
 
     pub fn has_attr(mut self, a: impl Into<String>) -> Self { self.has_attr = Some(a.into()); self }
 
+    /// Functions whose body touches crate `dep` (see `Klepto::fn_dep_profile`).
+    pub fn uses_dep(mut self, dep: impl Into<String>) -> Self { self.uses_dep = Some(dep.into().replace('-', "_")); self }
+
     pub fn filter<F>(self, f: F) -> Vec<CapturedFn>
     where F: Fn(&CapturedFn) -> bool
     {
@@ -130,7 +149,7 @@ impl<'k> FnQuery<'k> {
     pub fn collect(self) -> Vec<CapturedFn> {
         let mut v = self.k.functions.clone();
 
-        if self.public_only { v.retain(|f| f.is_public); }
+        if self.public_only { v.retain(|f| self.k.effective_visibility.fn_public(f)); }
         if self.no_docs { v.retain(|f| !f.has_docs); }
 
         if let Some(ty) = &self.in_impl {
@@ -162,6 +181,15 @@ impl<'k> FnQuery<'k> {
             v.retain(|f| f.attrs.iter().any(|x| x == a));
         }
 
+        if let Some(m) = &self.in_module {
+            v.retain(|f| crate::graph::module_of(&f.location.path, &f.module_path).starts_with(m));
+        }
+
+        if let Some(dep) = &self.uses_dep {
+            let profile = self.k.fn_dep_profile();
+            v.retain(|f| profile.get(&f.fq_name).is_some_and(|deps| deps.contains(dep)));
+        }
+
         v
     }
 }
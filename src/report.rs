@@ -1,40 +1,288 @@
+use crate::klepto::KleptoError;
+use crate::features::FeatureGate;
+use crate::imports_ext::ImportSummary;
+use crate::metrics::ModuleCoupling;
+use crate::panics::{PanicKind, PanicPath, PanicReachability, PanicSite};
 use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 
-pub fn findings_to_json(findings: &[Finding]) -> String {
-    serde_json::to_string_pretty(findings).unwrap()
+pub fn findings_to_json(findings: &[Finding]) -> Result<String, KleptoError> {
+    serde_json::to_string_pretty(findings).map_err(KleptoError::Json)
 }
 
+/// GitHub-flavored markdown suitable for a PR comment: a summary table, then one
+/// collapsible `<details>` section per file.
 pub fn findings_to_markdown(findings: &[Finding]) -> String {
+    markdown_report(findings, None)
+}
+
+/// Like `findings_to_markdown`, with a fenced snippet of the offending lines taken from
+/// the sources retained in `k.files`.
+pub fn findings_to_markdown_with_source(findings: &[Finding], k: &crate::klepto::Klepto) -> String {
+    let sources: BTreeMap<&Path, &str> = k.files.iter().map(|f| (f.path.as_path(), f.source.as_str())).collect();
+    markdown_report(findings, Some(&sources))
+}
+
+const SNIPPET_CONTEXT: usize = 2;
+
+fn markdown_report(findings: &[Finding], sources: Option<&BTreeMap<&Path, &str>>) -> String {
+    let report = Report::new(findings.to_vec());
+    let c = report.severity_counts();
+
     let mut s = String::new();
     s.push_str("# Klepto Report\n\n");
-    for f in findings {
+    s.push_str("| Severity | Count |\n|---|---:|\n");
+    s.push_str(&format!("| Deny | {} |\n| Warn | {} |\n| Info | {} |\n| **Total** | **{}** |\n\n", c.deny, c.warn, c.info, c.total()));
+
+    if findings.is_empty() {
+        s.push_str("No findings.\n");
+        return s;
+    }
+
+    s.push_str("| Rule | Name | Count |\n|---|---|---:|\n");
+    for (code, n) in report.top_rules(usize::MAX) {
+        s.push_str(&format!("| `{}` | {} | {} |\n", code, crate::rules::builtin::rule_name(code).unwrap_or(""), n));
+    }
+
+    for (path, fs) in report.group_by_file() {
         s.push_str(&format!(
-            "- **{:?} {}**: {} (`{}`:{}:{})\n",
-            f.severity,
-            f.code,
-            f.message,
-            f.location.path.display(),
-            f.location.line.unwrap_or(0),
-            f.location.column.unwrap_or(0),
+            "\n<details>\n<summary><code>{}</code> ({} finding{})</summary>\n\n",
+            path.display(), fs.len(), if fs.len() == 1 { "" } else { "s" }
         ));
+        let src = sources.and_then(|m| m.get(path).copied());
+        for f in fs {
+            s.push_str(&format!(
+                "- **{:?} {}**: {} (`{}`:{}:{})\n",
+                f.severity,
+                f.code,
+                f.message,
+                f.location.path.display(),
+                f.location.line.unwrap_or(0),
+                f.location.column_1based().unwrap_or(0),
+            ));
+            if let (Some(src), Some(line)) = (src, f.location.line.filter(|l| *l > 0)) {
+                let line = line as usize;
+                let first = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+                let snippet: Vec<&str> = src.lines().skip(first - 1).take(line - first + 1 + SNIPPET_CONTEXT).collect();
+                if !snippet.is_empty() {
+                    s.push_str("\n  ```rust\n");
+                    for l in snippet {
+                        s.push_str("  ");
+                        s.push_str(l);
+                        s.push('\n');
+                    }
+                    s.push_str("  ```\n\n");
+                }
+            }
+        }
+        s.push_str("\n</details>\n");
     }
     s
 }
 
+/// Writes one compact JSON document per line; nothing is buffered beyond the current item.
+pub fn write_jsonl<'a, T, W>(items: impl IntoIterator<Item = &'a T>, mut w: W) -> std::io::Result<()>
+where
+    T: serde::Serialize + 'a,
+    W: std::io::Write,
+{
+    for item in items {
+        write_jsonl_line(&mut w, item)?;
+    }
+    w.flush()
+}
+
+fn write_jsonl_line<T: serde::Serialize, W: std::io::Write>(w: &mut W, item: &T) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *w, item)?;
+    w.write_all(b"\n")
+}
+
+pub fn findings_to_jsonl<W: std::io::Write>(findings: &[Finding], w: W) -> std::io::Result<()> {
+    write_jsonl(findings, w)
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum OccurrenceLine<'a> {
+    Path(&'a PathOccurrence),
+    Call(&'a CallOccurrence),
+    Macro(&'a MacroInvocation),
+}
+
+/// Path, call and macro occurrences as JSON Lines, each tagged with `"kind"`.
+pub fn occurrences_to_jsonl<W: std::io::Write>(k: &crate::klepto::Klepto, mut w: W) -> std::io::Result<()> {
+    let lines = k.paths.iter().map(OccurrenceLine::Path)
+        .chain(k.calls.iter().map(OccurrenceLine::Call))
+        .chain(k.macros_inv.iter().map(OccurrenceLine::Macro));
+    for line in lines {
+        write_jsonl_line(&mut w, &line)?;
+    }
+    w.flush()
+}
+
+fn sarif_level(s: &Severity) -> &'static str {
+    match s {
+        Severity::Info => "note",
+        Severity::Warn => "warning",
+        Severity::Deny => "error",
+    }
+}
+
+/// SARIF 2.1.0 log with one run, for GitHub code scanning and other SARIF consumers.
+pub fn findings_to_sarif(findings: &[Finding]) -> Result<String, KleptoError> {
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    // rule index in `tool.driver.rules`, in order of first appearance
+    let mut rule_index: BTreeMap<&RuleCode, usize> = BTreeMap::new();
+    let mut rules = Vec::new();
+    for f in findings {
+        if rule_index.contains_key(&f.code) { continue; }
+        rule_index.insert(&f.code, rules.len());
+        let name = crate::rules::builtin::rule_name(&f.code).unwrap_or(f.code.as_str());
+        rules.push(json!({
+            "id": f.code.as_str(),
+            "name": name,
+            "shortDescription": { "text": name },
+            "defaultConfiguration": { "level": sarif_level(&f.severity) },
+        }));
+    }
+
+    let results: Vec<serde_json::Value> = findings.iter().map(|f| {
+        let mut physical = json!({
+            "artifactLocation": { "uri": f.location.path.portable() },
+        });
+        // SARIF lines/columns are 1-based; columns count characters (`columnKind` below)
+        if let Some(line) = f.location.line.filter(|l| *l > 0) {
+            let mut region = json!({ "startLine": line });
+            if let Some(col) = f.location.column_1based() {
+                region["startColumn"] = json!(col);
+            }
+            physical["region"] = region;
+        }
+        // property bags must be objects
+        let properties = if f.extra.is_object() { f.extra.clone() } else { json!({ "extra": f.extra }) };
+        json!({
+            "ruleId": f.code.as_str(),
+            "ruleIndex": rule_index[&f.code],
+            "level": sarif_level(&f.severity),
+            "message": { "text": f.message },
+            "locations": [{ "physicalLocation": physical }],
+            "properties": properties,
+        })
+    }).collect();
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "klepto",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+            // the default is UTF-16 code units
+            "columnKind": "unicodeCodePoints",
+        }],
+    });
+    serde_json::to_string_pretty(&log).map_err(KleptoError::Json)
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+fn severity_label(s: &Severity) -> (&'static str, &'static str) {
+    match s {
+        Severity::Info => ("info", "\x1b[1;36m"),
+        Severity::Warn => ("warning", "\x1b[1;33m"),
+        Severity::Deny => ("error", "\x1b[1;31m"),
+    }
+}
+
+/// rustc-style diagnostics: severity header, `-->` location and, when the source is
+/// retained in `k.files`, the offending line with a caret under the column.
+pub fn findings_to_terminal(findings: &[Finding], k: &crate::klepto::Klepto, color: bool) -> String {
+    let paint = |code: &'static str| if color { code } else { "" };
+    let sources: BTreeMap<&Path, &str> = k.files.iter().map(|f| (f.path.as_path(), f.source.as_str())).collect();
+
+    let mut out = String::new();
+    for f in findings {
+        let (label, sev_color) = severity_label(&f.severity);
+        out.push_str(&format!(
+            "{}{}[{}]{}{}: {}{}\n",
+            paint(sev_color), label, f.code, paint(RESET), paint(BOLD), f.message, paint(RESET)
+        ));
+
+        let line_no = f.location.line.filter(|l| *l > 0);
+        let src_line = line_no.and_then(|l| {
+            sources.get(f.location.path.as_path()).and_then(|src| src.lines().nth(l as usize - 1))
+        });
+        let gutter = " ".repeat(line_no.map(|l| l.to_string().len()).unwrap_or(1));
+
+        match line_no {
+            Some(l) => out.push_str(&format!(
+                "{}{}-->{} {}:{}:{}\n",
+                gutter, paint(BLUE), paint(RESET), f.location.path.display(), l, f.location.column_1based().unwrap_or(1)
+            )),
+            None => out.push_str(&format!("{}{}-->{} {}\n", gutter, paint(BLUE), paint(RESET), f.location.path.display())),
+        }
+
+        if let (Some(l), Some(text)) = (line_no, src_line) {
+            let col = f.location.column.unwrap_or(0) as usize;
+            // keep tabs so the caret lines up with the source line
+            let pad: String = text.chars().take(col).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            out.push_str(&format!("{} {}|{}\n", gutter, paint(BLUE), paint(RESET)));
+            out.push_str(&format!("{}{} |{} {}\n", paint(BLUE), l, paint(RESET), text));
+            out.push_str(&format!(
+                "{} {}|{} {}{}^{}\n",
+                gutter, paint(BLUE), paint(RESET), pad, paint(sev_color), paint(RESET)
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `path:line:col: CODE message` lines for Vim's quickfix list and Emacs compilation-mode.
+/// Lines and columns are 1-based; findings without a line point at the top of the file.
+pub fn findings_to_quickfix(findings: &[Finding]) -> String {
+    let mut out = String::new();
+    for f in findings {
+        out.push_str(&format!(
+            "{}:{}:{}: {} {}\n",
+            f.location.path.display(),
+            f.location.line.filter(|l| *l > 0).unwrap_or(1),
+            f.location.column_1based().unwrap_or(1),
+            f.code,
+            f.message.replace('\n', " "),
+        ));
+    }
+    out
+}
+
 // simple ASCII table (no deps)
 pub fn findings_to_table(findings: &[Finding]) -> String {
+    // imported compiler codes (`clippy::unwrap_used`) are wider than `KLEP001`
+    let w = findings.iter().map(|f| f.code.as_str().len()).max().unwrap_or(0).max(7);
     let mut out = String::new();
-    out.push_str("SEV  CODE    LOCATION                         MESSAGE\n");
-    out.push_str("---- ------- -------------------------------  ------------------------------\n");
+    out.push_str(&format!("SEV  {:<w$} LOCATION                         MESSAGE\n", "CODE"));
+    out.push_str(&format!("---- {} -------------------------------  ------------------------------\n", "-".repeat(w)));
     for f in findings {
         let loc = format!(
             "{}:{}:{}",
             f.location.path.display(),
             f.location.line.unwrap_or(0),
-            f.location.column.unwrap_or(0)
+            f.location.column_1based().unwrap_or(0)
         );
         out.push_str(&format!(
-            "{:<4} {:<7} {:<31}  {}\n",
+            "{:<4} {:<w$} {:<31}  {}\n",
             format!("{:?}", f.severity),
             f.code,
             truncate(&loc, 31),
@@ -50,3 +298,417 @@ fn truncate(s: &str, max: usize) -> String {
     t.push_str("...");
     t
 }
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SeverityCounts {
+    pub info: usize,
+    pub warn: usize,
+    pub deny: usize,
+}
+
+impl SeverityCounts {
+    pub fn total(&self) -> usize { self.info + self.warn + self.deny }
+}
+
+/// Findings plus the summaries needed to review them (per rule, per file, worst offenders).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    #[serde(default)]
+    pub coupling: Vec<ModuleCoupling>, // rendered when set, see `with_coupling`
+    #[serde(default)]
+    pub panics: Option<PanicReachability>, // see `with_panics`
+    #[serde(default)]
+    pub non_default_api: Vec<FeatureGate>, // see `with_non_default_api`
+    #[serde(default)]
+    pub imports: Option<ImportSummary>, // see `with_import_summary`
+}
+
+impl From<Vec<Finding>> for Report {
+    fn from(findings: Vec<Finding>) -> Self { Self::new(findings) }
+}
+
+impl Report {
+    pub fn new(findings: Vec<Finding>) -> Self { Self { findings, coupling: Vec::new(), panics: None, non_default_api: Vec::new(), imports: None } }
+
+    /// Adds a module coupling section (usually `k.module_coupling()`) to the markdown report.
+    pub fn with_coupling(mut self, coupling: Vec<ModuleCoupling>) -> Self { self.coupling = coupling; self }
+
+    /// Adds a section listing the public functions that can reach a panic
+    /// (usually `k.panic_reachability()`).
+    pub fn with_panics(mut self, panics: PanicReachability) -> Self { self.panics = Some(panics); self }
+
+    /// Adds a section listing the public items a default build leaves out
+    /// (usually `k.non_default_api()`).
+    pub fn with_non_default_api(mut self, gates: Vec<FeatureGate>) -> Self { self.non_default_api = gates; self }
+
+    /// Adds an imports section, by origin and root crate (usually `k.import_summary()`).
+    pub fn with_import_summary(mut self, summary: ImportSummary) -> Self { self.imports = Some(summary); self }
+
+    pub fn group_by_rule(&self) -> BTreeMap<&RuleCode, Vec<&Finding>> {
+        let mut m: BTreeMap<&RuleCode, Vec<&Finding>> = BTreeMap::new();
+        for f in &self.findings { m.entry(&f.code).or_default().push(f); }
+        m
+    }
+
+    pub fn group_by_file(&self) -> BTreeMap<&Path, Vec<&Finding>> {
+        let mut m: BTreeMap<&Path, Vec<&Finding>> = BTreeMap::new();
+        for f in &self.findings { m.entry(f.location.path.as_path()).or_default().push(f); }
+        m
+    }
+
+    /// By `extra.owners` (set when the scan had `code_owners`); a finding with several
+    /// owners is under each, unowned ones under `None`.
+    pub fn group_by_owner(&self) -> BTreeMap<Option<&str>, Vec<&Finding>> {
+        let mut m: BTreeMap<Option<&str>, Vec<&Finding>> = BTreeMap::new();
+        for f in &self.findings {
+            let owners: Vec<&str> = f.extra.get("owners").and_then(|o| o.as_array()).into_iter().flatten()
+                .filter_map(|o| o.as_str())
+                .collect();
+            if owners.is_empty() {
+                m.entry(None).or_default().push(f);
+            }
+            for o in owners { m.entry(Some(o)).or_default().push(f); }
+        }
+        m
+    }
+
+    pub fn severity_counts(&self) -> SeverityCounts {
+        let mut c = SeverityCounts::default();
+        for f in &self.findings {
+            match f.severity {
+                Severity::Info => c.info += 1,
+                Severity::Warn => c.warn += 1,
+                Severity::Deny => c.deny += 1,
+            }
+        }
+        c
+    }
+
+    /// Files with the most findings, most first (ties by path).
+    pub fn top_files(&self, n: usize) -> Vec<(&Path, usize)> {
+        top_n(self.group_by_file().into_iter().map(|(k, v)| (k, v.len())), n)
+    }
+
+    /// Rules with the most findings, most first (ties by code).
+    pub fn top_rules(&self, n: usize) -> Vec<(&RuleCode, usize)> {
+        top_n(self.group_by_rule().into_iter().map(|(k, v)| (k, v.len())), n)
+    }
+
+    fn summary_lines(&self) -> Vec<String> {
+        let c = self.severity_counts();
+        let mut lines = vec![format!(
+            "{} findings: {} deny, {} warn, {} info",
+            c.total(), c.deny, c.warn, c.info
+        )];
+        let rules = self.top_rules(usize::MAX);
+        let w = rules.iter().map(|(code, _)| code.as_str().len()).max().unwrap_or(0).max(8);
+        for (code, n) in rules {
+            let name = crate::rules::builtin::rule_name(code).unwrap_or("");
+            lines.push(format!("  {:<w$} {:>5}  {}", code, n, name).trim_end().to_string());
+        }
+        lines
+    }
+
+    pub fn render_table(&self) -> String {
+        let mut out = String::new();
+        for l in self.summary_lines() {
+            out.push_str(&l);
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(&findings_to_table(&self.findings));
+        out
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let c = self.severity_counts();
+        let mut s = String::new();
+        s.push_str("# Klepto Report\n\n");
+        s.push_str(&format!(
+            "**{}** findings: **{}** deny, **{}** warn, **{}** info\n\n",
+            c.total(), c.deny, c.warn, c.info
+        ));
+
+        s.push_str("| Rule | Name | Count |\n|------|------|------:|\n");
+        for (code, n) in self.top_rules(usize::MAX) {
+            let name = crate::rules::builtin::rule_name(code).unwrap_or("");
+            s.push_str(&format!("| {} | {} | {} |\n", code, name, n));
+        }
+
+        s.push_str("\n## Top files\n\n");
+        for (path, n) in self.top_files(10) {
+            s.push_str(&format!("- `{}`: {}\n", path.display(), n));
+        }
+
+        let by_owner = self.group_by_owner();
+        if by_owner.keys().any(Option::is_some) {
+            s.push_str("\n## By owner\n\n");
+            for (owner, fs) in &by_owner {
+                s.push_str(&format!("- {}: {}\n", owner.unwrap_or("_unowned_"), fs.len()));
+            }
+        }
+
+        if !self.coupling.is_empty() {
+            s.push_str("\n## Module coupling\n\n");
+            s.push_str("| Module | Fan-in | Fan-out | Instability |\n|--------|-------:|--------:|------------:|\n");
+            for c in &self.coupling {
+                s.push_str(&format!("| `{}` | {} | {} | {:.2} |\n", c.module, c.afferent, c.efferent, c.instability));
+            }
+        }
+
+        if let Some(i) = &self.imports {
+            s.push_str("\n## Imports\n\n");
+            s.push_str(&format!("{}\n\n", import_totals(i)));
+            s.push_str("| Origin | Count |\n|--------|------:|\n");
+            for (origin, n) in &i.by_origin {
+                s.push_str(&format!("| {:?} | {} |\n", origin, n));
+            }
+            s.push_str("\n| Root | Count |\n|------|------:|\n");
+            for (root, n) in top_n(i.by_root.iter().map(|(r, n)| (r.as_str(), *n)), 10) {
+                s.push_str(&format!("| `{}` | {} |\n", root, n));
+            }
+        }
+
+        if let Some(p) = &self.panics {
+            s.push_str("\n## Panic reachability\n\n");
+            s.push_str(&format!(
+                "{} of {} public functions can reach one of {} panic sites\n",
+                p.reachable.len(), p.reachable.len() + p.panic_free.len(), p.sites.len()
+            ));
+            if !p.reachable.is_empty() {
+                s.push_str("\n| Function | Panic | Via |\n|----------|-------|-----|\n");
+                for r in &p.reachable {
+                    s.push_str(&format!("| `{}` | {} | {} |\n", r.fn_name, panic_site_label(&r.site), panic_via(r)));
+                }
+            }
+        }
+
+        if !self.non_default_api.is_empty() {
+            s.push_str("\n## APIs behind non-default features\n\n");
+            s.push_str("| Item | Features | cfg |\n|------|----------|-----|\n");
+            for g in &self.non_default_api {
+                s.push_str(&format!("| `{}` | {} | `{}` |\n", g.item, gate_features(g), g.cfgs.join("` and `")));
+            }
+        }
+
+        s.push_str("\n## Findings\n");
+        for (path, fs) in self.group_by_file() {
+            s.push_str(&format!("\n### `{}`\n\n", path.display()));
+            for f in fs {
+                s.push_str(&format!(
+                    "- **{:?} {}**: {} (line {})\n",
+                    f.severity,
+                    f.code,
+                    f.message,
+                    f.location.line.unwrap_or(0),
+                ));
+            }
+        }
+        s
+    }
+
+    /// Self-contained HTML page (inline CSS, no scripts) with the same sections as `render_markdown`.
+    pub fn render_html(&self) -> String {
+        let c = self.severity_counts();
+        let mut s = String::from(concat!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Klepto Report</title>\n",
+            "<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse}",
+            "td,th{border:1px solid #ccc;padding:2px 8px;text-align:left}.num{text-align:right}",
+            ".Deny{color:#b00}.Warn{color:#a60}.Info{color:#06a}</style></head><body>\n",
+            "<h1>Klepto Report</h1>\n",
+        ));
+        s.push_str(&format!(
+            "<p><b>{}</b> findings: <b>{}</b> deny, <b>{}</b> warn, <b>{}</b> info</p>\n",
+            c.total(), c.deny, c.warn, c.info
+        ));
+
+        s.push_str("<table><tr><th>Rule</th><th>Name</th><th>Count</th></tr>\n");
+        for (code, n) in self.top_rules(usize::MAX) {
+            let name = crate::rules::builtin::rule_name(code).unwrap_or("");
+            s.push_str(&format!("<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>\n", escape_html(code.as_str()), escape_html(name), n));
+        }
+        s.push_str("</table>\n");
+
+        let by_owner = self.group_by_owner();
+        if by_owner.keys().any(Option::is_some) {
+            s.push_str("<h2>By owner</h2>\n<table><tr><th>Owner</th><th>Count</th></tr>\n");
+            for (owner, fs) in &by_owner {
+                let owner = owner.map_or("<i>unowned</i>".to_string(), escape_html);
+                s.push_str(&format!("<tr><td>{}</td><td class=\"num\">{}</td></tr>\n", owner, fs.len()));
+            }
+            s.push_str("</table>\n");
+        }
+
+        if !self.coupling.is_empty() {
+            s.push_str("<h2>Module coupling</h2>\n<table><tr><th>Module</th><th>Fan-in</th><th>Fan-out</th><th>Instability</th></tr>\n");
+            for m in &self.coupling {
+                s.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td></tr>\n",
+                    escape_html(&m.module), m.afferent, m.efferent, m.instability
+                ));
+            }
+            s.push_str("</table>\n");
+        }
+
+        if let Some(i) = &self.imports {
+            s.push_str(&format!("<h2>Imports</h2>\n<p>{}</p>\n", import_totals(i)));
+            s.push_str("<table><tr><th>Origin</th><th>Count</th></tr>\n");
+            for (origin, n) in &i.by_origin {
+                s.push_str(&format!("<tr><td>{:?}</td><td class=\"num\">{}</td></tr>\n", origin, n));
+            }
+            s.push_str("</table>\n<table><tr><th>Root</th><th>Count</th></tr>\n");
+            for (root, n) in top_n(i.by_root.iter().map(|(r, n)| (r.as_str(), *n)), 10) {
+                s.push_str(&format!("<tr><td><code>{}</code></td><td class=\"num\">{}</td></tr>\n", escape_html(root), n));
+            }
+            s.push_str("</table>\n");
+        }
+
+        if let Some(p) = &self.panics {
+            s.push_str(&format!(
+                "<h2>Panic reachability</h2>\n<p>{} of {} public functions can reach one of {} panic sites</p>\n",
+                p.reachable.len(), p.reachable.len() + p.panic_free.len(), p.sites.len()
+            ));
+            if !p.reachable.is_empty() {
+                s.push_str("<table><tr><th>Function</th><th>Panic</th><th>Via</th></tr>\n");
+                for r in &p.reachable {
+                    s.push_str(&format!(
+                        "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                        escape_html(&r.fn_name), escape_html(&panic_site_label(&r.site)), escape_html(&panic_via(r))
+                    ));
+                }
+                s.push_str("</table>\n");
+            }
+        }
+
+        if !self.non_default_api.is_empty() {
+            s.push_str("<h2>APIs behind non-default features</h2>\n<table><tr><th>Item</th><th>Features</th><th>cfg</th></tr>\n");
+            for g in &self.non_default_api {
+                let cfgs: Vec<String> = g.cfgs.iter().map(|c| format!("<code>{}</code>", escape_html(c))).collect();
+                s.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&g.item), escape_html(&gate_features(g)), cfgs.join(" and ")
+                ));
+            }
+            s.push_str("</table>\n");
+        }
+
+        s.push_str("<h2>Findings</h2>\n");
+        for (path, fs) in self.group_by_file() {
+            s.push_str(&format!("<h3><code>{}</code></h3>\n<ul>\n", escape_html(&path.display().to_string())));
+            for f in fs {
+                s.push_str(&format!(
+                    "<li><b class=\"{:?}\">{:?} {}</b>: {} (line {})</li>\n",
+                    f.severity, f.severity, escape_html(f.code.as_str()), escape_html(&f.message), f.location.line.unwrap_or(0),
+                ));
+            }
+            s.push_str("</ul>\n");
+        }
+        s.push_str("</body></html>\n");
+        s
+    }
+}
+
+fn import_totals(i: &ImportSummary) -> String {
+    format!(
+        "{} imports: {} pub use, {} glob, {} renamed, {} absolute",
+        i.total, i.pub_use_count, i.glob_count, i.rename_count, i.absolute_count
+    )
+}
+
+fn gate_features(g: &FeatureGate) -> String {
+    g.required.iter().map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+// `unwrap at src/a.rs:12`
+fn panic_site_label(site: &PanicSite) -> String {
+    let what = match &site.kind {
+        PanicKind::Macro { name } => format!("{}!", name),
+        PanicKind::Unwrap => "unwrap".to_string(),
+        PanicKind::Expect => "expect".to_string(),
+        PanicKind::Index => "indexing".to_string(),
+    };
+    format!("{} at {}:{}", what, site.location.path.display(), site.location.line.unwrap_or(0))
+}
+
+// the callees after the function itself, or `-` when it panics directly
+fn panic_via(p: &PanicPath) -> String {
+    if p.path.len() <= 1 { return "-".to_string(); }
+    p.path[1..].join(" -> ")
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn top_n<K: Ord>(counts: impl Iterator<Item = (K, usize)>, n: usize) -> Vec<(K, usize)> {
+    let mut v: Vec<(K, usize)> = counts.collect();
+    v.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    v.truncate(n);
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn finding(code: &'static str, severity: Severity, line: Option<u32>, column: Option<u32>, extra: Value) -> Finding {
+        Finding {
+            severity,
+            code: RuleCode::from_static(code),
+            message: format!("{code} here"),
+            location: FileLocation { path: "src/lib.rs".into(), line, column, byte_range: None },
+            extra,
+        }
+    }
+
+    #[test]
+    fn sarif_results_index_rules_and_count_from_one() {
+        let findings = [
+            finding("KLEP001", Severity::Warn, Some(3), Some(0), json!({ "signature": "fn f()" })),
+            finding("MY001", Severity::Deny, Some(7), Some(4), json!([1, 2])),
+            finding("KLEP001", Severity::Warn, None, None, Value::Null),
+        ];
+        let log: Value = serde_json::from_str(&findings_to_sarif(&findings).unwrap()).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        let ids: Vec<&str> = rules.iter().map(|r| r["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["KLEP001", "MY001"]);
+        assert_eq!(rules[0]["name"], "Undocumented public API");
+        assert_eq!(rules[1]["name"], "MY001");
+        assert_eq!(rules[1]["defaultConfiguration"]["level"], "error");
+
+        let results = run["results"].as_array().unwrap();
+        let indexes: Vec<u64> = results.iter().map(|r| r["ruleIndex"].as_u64().unwrap()).collect();
+        assert_eq!(indexes, [0, 1, 0]);
+        for r in results {
+            assert_eq!(rules[r["ruleIndex"].as_u64().unwrap() as usize]["id"], r["ruleId"]);
+            assert!(r["properties"].is_object(), "{r}");
+        }
+        assert_eq!(results[0]["properties"], json!({ "signature": "fn f()" }));
+        assert_eq!(results[1]["properties"], json!({ "extra": [1, 2] }));
+        assert_eq!(results[1]["level"], "error");
+
+        let loc = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(loc["artifactLocation"]["uri"], "src/lib.rs");
+        assert_eq!(loc["region"], json!({ "startLine": 3, "startColumn": 1 }));
+        assert_eq!(results[1]["locations"][0]["physicalLocation"]["region"]["startColumn"], 5);
+        assert!(results[2]["locations"][0]["physicalLocation"].get("region").is_none());
+    }
+}
@@ -1,19 +1,114 @@
-use crate::klepto::Klepto;
+use crate::config::{AbsolutePaths, DocCoverageConfig, ImportGroup, ImportStyleConfig, MsrvConfig};
+use crate::extract::file_module_path;
+use crate::msrv::{MsrvTable, RustVersion};
 use crate::model::*;
-use crate::rules::Rule;
+use crate::rules::{Rule, RuleContext};
 use serde_json::json;
 
+/// Stable codes of the builtin rules. These are reserved: `RuleRunner::add_rule`
+/// refuses them, and `RuleRunner::override_builtin` replaces the rule behind one.
+pub mod codes {
+    use crate::model::RuleCode;
+
+    pub const UNDOCUMENTED_PUBLIC_API: RuleCode = RuleCode::from_static("KLEP001");
+    pub const UNWRAP_IN_PUBLIC_API: RuleCode = RuleCode::from_static("KLEP002");
+    pub const PANIC_MACROS_IN_PUBLIC_API: RuleCode = RuleCode::from_static("KLEP003");
+    pub const STD_IN_NO_STD_CRATE: RuleCode = RuleCode::from_static("KLEP004");
+    pub const UNUSED_IMPORTS: RuleCode = RuleCode::from_static("KLEP005");
+    pub const DEEP_MODULE_NESTING: RuleCode = RuleCode::from_static("KLEP006");
+    pub const GOD_MODULE: RuleCode = RuleCode::from_static("KLEP007");
+    pub const EXCESSIVE_CLONES: RuleCode = RuleCode::from_static("KLEP008");
+    pub const DEV_ONLY_DEPENDENCY: RuleCode = RuleCode::from_static("KLEP009");
+    pub const UNDECLARED_FEATURE: RuleCode = RuleCode::from_static("KLEP010");
+    pub const UNREFERENCED_FEATURE: RuleCode = RuleCode::from_static("KLEP011");
+    pub const DOC_HIDDEN_DEPENDENCY_API: RuleCode = RuleCode::from_static("KLEP012");
+    pub const CONFLICTING_REEXPORTS: RuleCode = RuleCode::from_static("KLEP013");
+    pub const BROKEN_INTRA_DOC_LINKS: RuleCode = RuleCode::from_static("KLEP014");
+    pub const MSRV_INCOMPATIBLE: RuleCode = RuleCode::from_static("KLEP015");
+    pub const EDITION_IDIOMS: RuleCode = RuleCode::from_static("KLEP016");
+    pub const CONST_FN_CANDIDATE: RuleCode = RuleCode::from_static("KLEP017");
+    // the `guidelines` pack
+    pub const GUIDELINES_CASE: RuleCode = RuleCode::from_static("KLEP018");
+    pub const GUIDELINES_GETTER: RuleCode = RuleCode::from_static("KLEP019");
+    pub const GUIDELINES_CONV: RuleCode = RuleCode::from_static("KLEP020");
+    pub const GUIDELINES_ITER: RuleCode = RuleCode::from_static("KLEP021");
+    pub const IMPORT_STYLE: RuleCode = RuleCode::from_static("KLEP022");
+    pub const IMPORT_SHADOWING: RuleCode = RuleCode::from_static("KLEP023");
+    pub const DOC_COVERAGE_BELOW_THRESHOLD: RuleCode = RuleCode::from_static("KLEP024");
+    pub const UNTESTED_PUBLIC_FN: RuleCode = RuleCode::from_static("KLEP025");
+
+    pub const ALL: &[RuleCode] = &[
+        UNDOCUMENTED_PUBLIC_API,
+        UNWRAP_IN_PUBLIC_API,
+        PANIC_MACROS_IN_PUBLIC_API,
+        STD_IN_NO_STD_CRATE,
+        UNUSED_IMPORTS,
+        DEEP_MODULE_NESTING,
+        GOD_MODULE,
+        EXCESSIVE_CLONES,
+        DEV_ONLY_DEPENDENCY,
+        UNDECLARED_FEATURE,
+        UNREFERENCED_FEATURE,
+        DOC_HIDDEN_DEPENDENCY_API,
+        CONFLICTING_REEXPORTS,
+        BROKEN_INTRA_DOC_LINKS,
+        MSRV_INCOMPATIBLE,
+        EDITION_IDIOMS,
+        CONST_FN_CANDIDATE,
+        GUIDELINES_CASE,
+        GUIDELINES_GETTER,
+        GUIDELINES_CONV,
+        GUIDELINES_ITER,
+        IMPORT_STYLE,
+        IMPORT_SHADOWING,
+        DOC_COVERAGE_BELOW_THRESHOLD,
+        UNTESTED_PUBLIC_FN,
+    ];
+}
+
+/// Every builtin rule with its default configuration.
+pub fn defaults() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(UndocumentedPublicApi),
+        Box::new(UnwrapInPublicApi),
+        Box::new(StdInNoStdCrate),
+        Box::new(PanicMacrosInPublicApi),
+        Box::new(UnusedImports),
+        Box::new(DeepModuleNesting::default()),
+        Box::new(GodModule::default()),
+        Box::new(ExcessiveClones::default()),
+        Box::new(DevOnlyDependency),
+        Box::new(UndeclaredFeature),
+        Box::new(UnreferencedFeature),
+        Box::new(DocHiddenDependencyApi),
+        Box::new(ConflictingReexports),
+        Box::new(BrokenIntraDocLinks),
+        Box::new(MsrvIncompatible::default()),
+        Box::new(EditionIdioms),
+        Box::new(ConstFnCandidate),
+        Box::new(ImportStyle::default()),
+        Box::new(ImportShadowing),
+        Box::new(DocCoverageBelowThreshold::default()),
+        Box::new(UntestedPublicFn),
+    ]
+}
+
+/// Human-readable name of a builtin rule code (the `guidelines` pack's included).
+pub fn rule_name(code: &RuleCode) -> Option<&'static str> {
+    defaults().into_iter().chain(super::guidelines::pack()).find(|r| &r.code() == code).map(|r| r.name())
+}
+
 pub struct UndocumentedPublicApi;
 impl Rule for UndocumentedPublicApi {
-    fn code(&self) -> &'static str { "KLEP001" }
+    fn code(&self) -> RuleCode { codes::UNDOCUMENTED_PUBLIC_API }
     fn name(&self) -> &'static str { "Undocumented public API" }
 
-    fn run(&self, k: &Klepto) -> Vec<Finding> {
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
         k.functions.iter()
-            .filter(|f| f.is_public && !f.has_docs)
+            .filter(|f| !f.has_docs && k.effective_visibility.fn_public(f))
             .map(|f| Finding {
                 severity: Severity::Warn,
-                code: self.code().into(),
+                code: self.code(),
                 message: format!("public function missing docs: {}", f.fq_name),
                 location: f.location.clone(),
                 extra: json!({ "signature": f.signature }),
@@ -24,32 +119,17 @@ impl Rule for UndocumentedPublicApi {
 
 pub struct UnwrapInPublicApi;
 impl Rule for UnwrapInPublicApi {
-    fn code(&self) -> &'static str { "KLEP002" }
+    fn code(&self) -> RuleCode { codes::UNWRAP_IN_PUBLIC_API }
     fn name(&self) -> &'static str { "unwrap/expect in public API" }
 
-    fn run(&self, k: &Klepto) -> Vec<Finding> {
-        // best-effort: if file contains unwrap calls, flag them if in same module as any public fn
-        // (You can tighten this later by mapping call spans -> enclosing fn.)
-        let pub_mods: std::collections::BTreeSet<Vec<String>> =
-            k.functions.iter().filter(|f| f.is_public).map(|f| f.module_path.clone()).collect();
-
-        // k.calls.iter()
-        //     .filter(|c| pub_mods.contains(&c.module_path))
-        //     .filter(|c| c.callee.contains("unwrap") || c.callee.contains("expect"))
-        //     .map(|c| Finding {
-        //         severity: Severity::Warn,
-        //         code: self.code().into(),
-        //         message: format!("potential panic path in public module: {}", c.callee),
-        //         location: c.location.clone(),
-        //         extra: json!({ "module": c.module_path }),
-        //     })
-        //     .collect()
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
         k.calls.iter()
-            .filter(|c| c.enclosing_public == Some(true))
+            .filter(|c| !c.is_test_code)
+            .filter(|c| k.in_public_fn(c.enclosing_fn.as_deref(), &c.location))
             .filter(|c| c.callee.contains("unwrap") || c.callee.contains("expect"))
             .map(|c| Finding {
                 severity: Severity::Warn,
-                code: self.code().into(),
+                code: self.code(),
                 message: format!(
                     "panic-ish call inside public fn {}: {}",
                     c.enclosing_fn.clone().unwrap_or_else(|| "<unknown>".into()),
@@ -65,30 +145,17 @@ impl Rule for UnwrapInPublicApi {
 
 pub struct PanicMacrosInPublicApi;
 impl Rule for PanicMacrosInPublicApi {
-    fn code(&self) -> &'static str { "KLEP003" }
+    fn code(&self) -> RuleCode { codes::PANIC_MACROS_IN_PUBLIC_API }
     fn name(&self) -> &'static str { "panic/todo/unreachable in public modules" }
 
-    fn run(&self, k: &Klepto) -> Vec<Finding> {
-        let pub_mods: std::collections::BTreeSet<Vec<String>> =
-            k.functions.iter().filter(|f| f.is_public).map(|f| f.module_path.clone()).collect();
-
-        // k.macros_inv.iter()
-        //     .filter(|m| pub_mods.contains(&m.module_path))
-        //     .filter(|m| matches!(m.name.as_str(), "panic" | "todo" | "unreachable"))
-        //     .map(|m| Finding {
-        //         severity: Severity::Warn,
-        //         code: self.code().into(),
-        //         message: format!("macro in public module: {}!", m.name),
-        //         location: m.location.clone(),
-        //         extra: json!({ "module": m.module_path }),
-        //     })
-        //     .collect()
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
         k.macros_inv.iter()
-            .filter(|m| m.enclosing_public == Some(true))
+            .filter(|m| !m.is_test_code)
+            .filter(|m| k.in_public_fn(m.enclosing_fn.as_deref(), &m.location))
             .filter(|m| matches!(m.name.as_str(), "panic" | "todo" | "unreachable"))
             .map(|m| Finding {
                 severity: Severity::Warn,
-                code: self.code().into(),
+                code: self.code(),
                 message: format!(
                     "macro {}! inside public fn {}",
                     m.name,
@@ -104,10 +171,11 @@ impl Rule for PanicMacrosInPublicApi {
 
 pub struct StdInNoStdCrate;
 impl Rule for StdInNoStdCrate {
-    fn code(&self) -> &'static str { "KLEP004" }
+    fn code(&self) -> RuleCode { codes::STD_IN_NO_STD_CRATE }
     fn name(&self) -> &'static str { "std usage in no_std crate" }
+    fn default_severity(&self) -> Severity { Severity::Deny }
 
-    fn run(&self, k: &Klepto) -> Vec<Finding> {
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
         if !k.no_std_detected { return Vec::new(); }
 
         // flag std:: imports and std paths
@@ -117,7 +185,7 @@ impl Rule for StdInNoStdCrate {
             if i.root == "std" {
                 out.push(Finding {
                     severity: Severity::Deny,
-                    code: self.code().into(),
+                    code: self.code(),
                     message: format!("std import in no_std crate: {}", i.full_path),
                     location: i.location.clone(),
                     extra: json!({ "import": i.full_path }),
@@ -129,7 +197,7 @@ impl Rule for StdInNoStdCrate {
             if p.path.starts_with("std::") || p.path == "std" {
                 out.push(Finding {
                     severity: Severity::Deny,
-                    code: self.code().into(),
+                    code: self.code(),
                     message: format!("std path in no_std crate: {}", p.path),
                     location: p.location.clone(),
                     extra: json!({ "path": p.path, "module": p.module_path }),
@@ -140,3 +208,699 @@ impl Rule for StdInNoStdCrate {
         out
     }
 }
+
+/// Traits that are routinely imported only for their methods; an import of one of
+/// these never shows up as a path, so it can't be judged by occurrences alone.
+const METHOD_TRAITS: &[&str] = &[
+    "Read", "Write", "BufRead", "Seek", "FromStr", "Hash", "Hasher", "Borrow", "BorrowMut",
+    "Deref", "DerefMut", "Iterator", "IntoIterator", "FromIterator", "Extend", "AsRef", "AsMut",
+    "ToTokens", "Spanned", "Visit", "VisitMut", "Parse", "Context", "Rng", "Digest",
+];
+
+fn collect_idents(ts: proc_macro2::TokenStream, out: &mut std::collections::HashSet<String>) {
+    for tt in ts {
+        match tt {
+            proc_macro2::TokenTree::Ident(i) => { out.insert(i.to_string()); }
+            proc_macro2::TokenTree::Group(g) => collect_idents(g.stream(), out),
+            _ => {}
+        }
+    }
+}
+
+// every identifier mentioned outside `use` items, keyed by module path
+fn idents_by_module(
+    items: &[syn::Item],
+    mod_stack: &mut Vec<String>,
+    out: &mut std::collections::HashMap<Vec<String>, std::collections::HashSet<String>>,
+) {
+    use quote::ToTokens;
+    for it in items {
+        match it {
+            syn::Item::Use(_) => {}
+            syn::Item::Mod(m) if m.content.is_some() => {
+                if let Some((_, inner)) = &m.content {
+                    mod_stack.push(m.ident.to_string());
+                    idents_by_module(inner, mod_stack, out);
+                    mod_stack.pop();
+                }
+            }
+            other => collect_idents(other.to_token_stream(), out.entry(mod_stack.clone()).or_default()),
+        }
+    }
+}
+
+fn first_segment(p: &str) -> &str {
+    let p = p.trim().trim_start_matches("::");
+    p.split("::").next().unwrap_or(p).trim()
+}
+
+pub struct UnusedImports;
+impl Rule for UnusedImports {
+    fn code(&self) -> RuleCode { codes::UNUSED_IMPORTS }
+    fn name(&self) -> &'static str { "unused import (heuristic)" }
+    fn default_severity(&self) -> Severity { Severity::Info }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        use std::collections::{HashMap, HashSet};
+        use std::path::PathBuf;
+
+        let mut used: HashMap<(PathBuf, ModulePath), HashSet<String>> = HashMap::new();
+        let mut mark = |path: &std::path::Path, module: &ModulePath, name: &str| {
+            used.entry((path.to_path_buf(), module.clone())).or_default().insert(name.to_string());
+        };
+
+        for p in &k.paths { mark(&p.location.path, &p.module_path, first_segment(&p.path)); }
+        for c in &k.calls { mark(&c.location.path, &c.module_path, first_segment(&c.callee)); }
+        for m in &k.macros_inv {
+            let name = m.path.as_deref().map(first_segment).unwrap_or(&m.name);
+            mark(&m.location.path, &m.module_path, name);
+        }
+
+        // single-segment uses (`HashMap<K, V>`, `Arc`) are not recorded as occurrences,
+        // so fall back to the identifiers of each module's non-use items
+        for pf in &k.files {
+            let mut by_mod = HashMap::new();
+            idents_by_module(&pf.ast().items, &mut Vec::new(), &mut by_mod);
+            for (module, idents) in by_mod {
+                used.entry((pf.path.clone(), module.into())).or_default().extend(idents);
+            }
+        }
+
+        // traits declared or implemented in the scan are likely imported for their methods
+        let mut traits: HashSet<&str> = METHOD_TRAITS.iter().copied().collect();
+        for f in &k.functions {
+            match &f.kind {
+                FnKind::TraitMethod { trait_name } => { traits.insert(trait_name); }
+                FnKind::ImplMethod { trait_ty: Some(t), .. } => { traits.insert(t.rsplit("::").next().unwrap_or(t)); }
+                _ => {}
+            }
+        }
+        traits.extend(k.types.iter().filter(|t| t.kind == TypeKind::Trait).map(|t| t.name.as_str()));
+
+        k.imports.iter()
+            .filter(|i| !i.is_public_use && !i.expanded_from_glob)
+            .filter_map(|i| {
+                let name = match &i.kind {
+                    UseKind::Glob => return None,
+                    UseKind::Rename { alias } => alias.as_str(),
+                    UseKind::Name => i.segments.last().unwrap_or(&i.root).as_str(),
+                };
+                if name == "_" || traits.contains(name) { return None; }
+
+                let key = (i.location.path.to_path_buf(), i.module_path.clone());
+                if used.get(&key).is_some_and(|s| s.contains(name)) { return None; }
+
+                Some(Finding {
+                    severity: Severity::Info,
+                    code: self.code(),
+                    message: format!("import appears unused: {}", i.full_path),
+                    location: i.location.clone(),
+                    extra: json!({ "import": i.full_path, "name": name, "module": i.module_path }),
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct DeepModuleNesting {
+    pub max_depth: usize,
+}
+impl Default for DeepModuleNesting {
+    fn default() -> Self { Self { max_depth: 4 } }
+}
+impl Rule for DeepModuleNesting {
+    fn code(&self) -> RuleCode { codes::DEEP_MODULE_NESTING }
+    fn name(&self) -> &'static str { "deeply nested module" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        // first location seen for each (file, inline module path)
+        let mut seen: std::collections::BTreeMap<Vec<String>, FileLocation> = std::collections::BTreeMap::new();
+        let locs = k.functions.iter().map(|f| (&f.module_path, &f.location))
+            .chain(k.types.iter().map(|t| (&t.module_path, &t.location)))
+            .chain(k.imports.iter().map(|i| (&i.module_path, &i.location)))
+            .chain(k.macros_def.iter().map(|m| (&m.module_path, &m.location)));
+        for (module, loc) in locs {
+            let mut full = file_module_path(&loc.path);
+            full.extend(module.iter().map(|s| s.to_string()));
+            seen.entry(full).or_insert_with(|| loc.clone());
+        }
+
+        seen.into_iter()
+            .filter(|(m, _)| m.len() > self.max_depth)
+            .map(|(m, loc)| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("module crate::{} is nested {} levels deep (max {})", m.join("::"), m.len(), self.max_depth),
+                location: loc,
+                extra: json!({ "module": m, "depth": m.len(), "max_depth": self.max_depth }),
+            })
+            .collect()
+    }
+}
+
+pub struct GodModule {
+    pub max_items: usize,
+}
+impl Default for GodModule {
+    fn default() -> Self { Self { max_items: 60 } }
+}
+impl Rule for GodModule {
+    fn code(&self) -> RuleCode { codes::GOD_MODULE }
+    fn name(&self) -> &'static str { "file with too many items" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let mut counts: std::collections::BTreeMap<&std::path::Path, usize> = std::collections::BTreeMap::new();
+        for f in &k.functions { *counts.entry(&f.location.path).or_default() += 1; }
+        for t in &k.types { *counts.entry(&t.location.path).or_default() += 1; }
+        for m in &k.macros_def { *counts.entry(&m.location.path).or_default() += 1; }
+
+        counts.into_iter()
+            .filter(|(_, n)| *n > self.max_items)
+            .map(|(path, n)| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("{} defines {} items (max {})", path.display(), n, self.max_items),
+                location: FileLocation { path: path.into(), line: None, column: None, byte_range: None },
+                extra: json!({ "items": n, "max_items": self.max_items }),
+            })
+            .collect()
+    }
+}
+
+// `name : Arc < T >` / `name : & Rc < T >` -> Some("name")
+fn refcounted_arg(arg: &str) -> Option<&str> {
+    let (pat, ty) = arg.split_once(" : ")?;
+    let ty = ty.trim_start_matches('&').trim_start_matches("mut ").trim();
+    let base = ty.split('<').next()?.trim().rsplit("::").next()?.trim();
+    matches!(base, "Arc" | "Rc").then(|| pat.trim_start_matches("mut ").trim())
+}
+
+pub struct ExcessiveClones {
+    pub max_clones: usize,
+    pub flag_refcounted: bool,
+}
+impl Default for ExcessiveClones {
+    fn default() -> Self { Self { max_clones: 8, flag_refcounted: true } }
+}
+impl Rule for ExcessiveClones {
+    fn code(&self) -> RuleCode { codes::EXCESSIVE_CLONES }
+    fn name(&self) -> &'static str { "excessive .clone() calls" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let mut out = Vec::new();
+        for (fq, calls) in k.calls_by_fn() {
+            let clones: Vec<&CallOccurrence> = calls.iter().copied()
+                .filter(|c| c.callee == "clone" && c.receiver.is_some())
+                .collect();
+            let captured = k.fn_named(fq);
+
+            if clones.len() > self.max_clones {
+                out.push(Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message: format!("{} calls .clone() {} times (max {})", fq, clones.len(), self.max_clones),
+                    location: captured.map(|f| f.location.clone()).unwrap_or_else(|| clones[0].location.clone()),
+                    extra: json!({ "enclosing_fn": fq, "clones": clones.len(), "max_clones": self.max_clones }),
+                });
+            }
+
+            if !self.flag_refcounted { continue; }
+            // best-effort: only receivers we can tie to an `Arc`/`Rc` parameter or an `_arc`/`_rc` name
+            let rc_args: Vec<&str> = captured.map(|f| f.args.iter().filter_map(|a| refcounted_arg(a)).collect()).unwrap_or_default();
+            for c in &clones {
+                let recv = c.receiver.as_deref().unwrap_or("");
+                let is_rc = rc_args.contains(&recv) || recv.ends_with("_arc") || recv.ends_with("_rc");
+                if !is_rc { continue; }
+                out.push(Finding {
+                    severity: Severity::Info,
+                    code: self.code(),
+                    message: format!("prefer Arc::clone(&{0}) / Rc::clone(&{0}) over {0}.clone()", recv),
+                    location: c.location.clone(),
+                    extra: json!({ "enclosing_fn": fq, "receiver": recv }),
+                });
+            }
+        }
+        out
+    }
+}
+
+pub struct DevOnlyDependency;
+impl Rule for DevOnlyDependency {
+    fn code(&self) -> RuleCode { codes::DEV_ONLY_DEPENDENCY }
+    fn name(&self) -> &'static str { "dependency only used by tests/examples" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.dev_only_dependencies().into_iter()
+            .map(|d| {
+                let targets: Vec<&str> = d.targets.iter().map(|t| t.as_str()).collect();
+                Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message: format!("dependency `{}` is only used from {} code; move it to [dev-dependencies]", d.dep, targets.join("/")),
+                    location: d.sites[0].location.clone(),
+                    extra: json!({ "dep": d.dep, "targets": targets, "use_sites": d.sites.len() }),
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct UndeclaredFeature;
+impl Rule for UndeclaredFeature {
+    fn code(&self) -> RuleCode { codes::UNDECLARED_FEATURE }
+    fn name(&self) -> &'static str { "cfg on an undeclared feature" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let inv = k.feature_inventory();
+        inv.undeclared()
+            .flat_map(|(feature, refs)| refs.iter().map(move |r| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("feature `{}` is not declared in [features]", feature),
+                location: r.location.clone(),
+                extra: json!({ "feature": feature, "kind": r.kind }),
+            }))
+            .collect()
+    }
+}
+
+pub struct UnreferencedFeature;
+impl Rule for UnreferencedFeature {
+    fn code(&self) -> RuleCode { codes::UNREFERENCED_FEATURE }
+    fn name(&self) -> &'static str { "declared feature never referenced" }
+    fn default_severity(&self) -> Severity { Severity::Info }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.feature_inventory().unreferenced()
+            .map(|feature| Finding {
+                severity: Severity::Info,
+                code: self.code(),
+                message: format!("feature `{}` is declared but no cfg refers to it", feature),
+                location: FileLocation { path: "Cargo.toml".into(), line: None, column: None, byte_range: None },
+                extra: json!({ "feature": feature }),
+            })
+            .collect()
+    }
+}
+
+/// Needs the dependency's API from `KleptoBuilder::dependency_api`; silent otherwise.
+pub struct DocHiddenDependencyApi;
+impl Rule for DocHiddenDependencyApi {
+    fn code(&self) -> RuleCode { codes::DOC_HIDDEN_DEPENDENCY_API }
+    fn name(&self) -> &'static str { "use of a dependency's doc(hidden) item" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let mut seen = std::collections::HashSet::new();
+        k.dep_item_uses().into_iter()
+            // uses through a flagged `use` add nothing; a call's path is also a path occurrence
+            .filter(|u| u.visibility == crate::rustdoc::DepItemVisibility::Hidden && !u.via_import)
+            .filter(|u| seen.insert((u.location.path.clone(), u.location.line, u.location.column, u.path.clone())))
+            .map(|u| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("`{}::{}` is #[doc(hidden)]: not part of `{}`'s public API and may change in any release", u.dep, u.path, u.dep),
+                location: u.location,
+                extra: json!({ "dep": u.dep, "path": u.path, "kind": u.kind }),
+            })
+            .collect()
+    }
+}
+
+pub struct ConflictingReexports;
+impl Rule for ConflictingReexports {
+    fn code(&self) -> RuleCode { codes::CONFLICTING_REEXPORTS }
+    fn name(&self) -> &'static str { "conflicting re-exports" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        // only `pub use`s outside code can reach make the API confusing
+        let exports = k.exports.iter()
+            .filter(|e| k.effective_visibility.module_public(&crate::visibility::full_module_path(&e.location.path, &e.module_path)))
+            .cloned()
+            .collect();
+        PublicSurface { exports }.conflicts().into_iter()
+            .map(|c| {
+                let exports = c.exports();
+                let listed = exports.iter()
+                    .map(|e| format!("`{}` from `{}` ({})", e.exported_as, e.source_path, e.location))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let message = match &c {
+                    ExportConflict::NameReused { exported_as, .. } => format!("`{}` is re-exported for different items: {}", exported_as, listed),
+                    ExportConflict::ItemRenamed { target, .. } => format!("`{}` is re-exported under different names: {}", target, listed),
+                };
+                Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message,
+                    location: exports[0].location.clone(),
+                    extra: json!({ "conflict": c }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Catches what `rustdoc::broken_intra_doc_links` would, without a doc build.
+pub struct BrokenIntraDocLinks;
+impl Rule for BrokenIntraDocLinks {
+    fn code(&self) -> RuleCode { codes::BROKEN_INTRA_DOC_LINKS }
+    fn name(&self) -> &'static str { "broken intra-doc link" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.broken_doc_links().into_iter()
+            .map(|l| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("doc link `{}` on {} doesn't resolve", l.link, l.item),
+                location: l.location,
+                extra: json!({ "item": l.item, "link": l.link }),
+            })
+            .collect()
+    }
+}
+
+/// Silent without a target version: set `msrv`, or `[msrv] version` in klepto.toml.
+pub struct MsrvIncompatible {
+    pub msrv: Option<RustVersion>,
+    pub table: MsrvTable,
+}
+impl Default for MsrvIncompatible {
+    fn default() -> Self { Self { msrv: None, table: MsrvTable::builtin() } }
+}
+impl MsrvIncompatible {
+    /// The builtin table plus the configured patterns.
+    pub fn from_config(c: &MsrvConfig) -> Self {
+        let mut table = MsrvTable::builtin();
+        table.entries.extend(c.patterns.iter().cloned());
+        Self { msrv: Some(c.version), table }
+    }
+}
+impl Rule for MsrvIncompatible {
+    fn code(&self) -> RuleCode { codes::MSRV_INCOMPATIBLE }
+    fn name(&self) -> &'static str { "newer than the MSRV" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let Some(msrv) = self.msrv else { return Vec::new() };
+        k.msrv_violations(msrv, &self.table).into_iter()
+            .map(|v| Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("{} needs Rust {} (MSRV is {})", v.entry.name, v.entry.since, msrv),
+                location: v.location,
+                extra: json!({ "feature": v.entry.name, "since": v.entry.since, "msrv": msrv, "pattern": v.entry.pattern }),
+            })
+            .collect()
+    }
+}
+
+/// Needs editions (from `scan_workspace_root` or `KleptoBuilder::edition`); silent otherwise.
+pub struct EditionIdioms;
+impl Rule for EditionIdioms {
+    fn code(&self) -> RuleCode { codes::EDITION_IDIOMS }
+    fn name(&self) -> &'static str { "idiom retired by the edition" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.edition_idioms().into_iter()
+            .map(|i| {
+                let message = match &i.kind {
+                    crate::edition::EditionIdiomKind::ExternCrate { name } =>
+                        format!("`extern crate {}` isn't needed in the {} edition", name, i.edition),
+                    crate::edition::EditionIdiomKind::BareTraitObject { ty } =>
+                        format!("trait object `{}` needs `dyn` in the {} edition", ty, i.edition),
+                };
+                Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message,
+                    location: i.location,
+                    extra: json!({ "idiom": i.kind, "edition": i.edition }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Public functions only: the point is a larger const API.
+pub struct ConstFnCandidate;
+impl Rule for ConstFnCandidate {
+    fn code(&self) -> RuleCode { codes::CONST_FN_CANDIDATE }
+    fn name(&self) -> &'static str { "could be const fn" }
+    fn default_severity(&self) -> Severity { Severity::Info }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.const_fn_candidates().into_iter()
+            .filter(|f| k.effective_visibility.fn_public(f))
+            .map(|f| Finding {
+                severity: Severity::Info,
+                code: self.code(),
+                message: format!("{} looks like it could be a `const fn`", f.fq_name),
+                location: f.location.clone(),
+                extra: json!({ "signature": f.signature }),
+            })
+            .collect()
+    }
+}
+
+/// Silent until `[imports]` in klepto.toml (or `style`) asks for a leading-`::`
+/// convention or a group order. `pub use`s are re-exports and may sit anywhere.
+#[derive(Default)]
+pub struct ImportStyle {
+    pub style: ImportStyleConfig,
+}
+impl ImportStyle {
+    pub fn from_config(c: &ImportStyleConfig) -> Self { Self { style: c.clone() } }
+}
+
+// file, module and enclosing fn of a `use`
+type ImportScope<'a> = (&'a std::path::Path, &'a [IStr], Option<&'a str>);
+
+fn import_group(i: &StolenPath) -> ImportGroup {
+    match i.origin {
+        Some(ImportOrigin::Internal) => ImportGroup::Internal,
+        Some(ImportOrigin::Std | ImportOrigin::Core | ImportOrigin::Alloc) => ImportGroup::Std,
+        Some(_) => ImportGroup::External,
+        None if i.is_internal => ImportGroup::Internal,
+        None if matches!(i.root.as_str(), "std" | "core" | "alloc") => ImportGroup::Std,
+        None => ImportGroup::External,
+    }
+}
+
+impl Rule for ImportStyle {
+    fn code(&self) -> RuleCode { codes::IMPORT_STYLE }
+    fn name(&self) -> &'static str { "import style" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let imports: Vec<&StolenPath> = k.imports.iter().filter(|i| !i.is_test_code && !i.expanded_from_glob).collect();
+        let mut out = Vec::new();
+
+        if let Some(absolute) = self.style.absolute {
+            for i in imports.iter().filter(|i| import_group(i) != ImportGroup::Internal) {
+                let is_absolute = i.is_absolute == Some(true);
+                let message = match absolute {
+                    AbsolutePaths::Require if !is_absolute => format!("`use {}` should start with `::`", i.full_path),
+                    AbsolutePaths::Forbid if is_absolute => format!("`use {}` shouldn't start with `::`", i.full_path),
+                    _ => continue,
+                };
+                out.push(Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message,
+                    location: i.location.clone(),
+                    extra: json!({ "import": i.full_path, "absolute": absolute }),
+                });
+            }
+        }
+
+        if let Some(order) = &self.style.groups {
+            let rank = |g: ImportGroup| order.iter().position(|o| *o == g);
+            // the `use`s of one module (or fn body), in source order
+            let mut scopes: std::collections::BTreeMap<ImportScope, Vec<&StolenPath>> = std::collections::BTreeMap::new();
+            for i in imports.iter().filter(|i| !i.is_public_use) {
+                scopes.entry((i.location.path.as_path(), &i.module_path[..], i.enclosing_fn.as_deref())).or_default().push(i);
+            }
+            let expected = order.iter().map(|g| g.as_str()).collect::<Vec<_>>().join(", ");
+            for mut uses in scopes.into_values() {
+                uses.sort_by_key(|i| (i.location.line, i.location.column));
+                let mut latest: Option<(usize, ImportGroup)> = None;
+                let mut flagged_root: Option<&str> = None;
+                for i in uses {
+                    let group = import_group(i);
+                    let Some(r) = rank(group) else { continue };
+                    match latest {
+                        Some((seen, seen_group)) if r < seen => {
+                            // one finding per `use` tree, whose leaves share a root
+                            if flagged_root != Some(i.root.as_str()) {
+                                out.push(Finding {
+                                    severity: Severity::Warn,
+                                    code: self.code(),
+                                    message: format!(
+                                        "{} import `{}` comes after {} imports (expected order: {})",
+                                        group.as_str(), i.full_path, seen_group.as_str(), expected
+                                    ),
+                                    location: i.location.clone(),
+                                    extra: json!({ "import": i.full_path, "group": group, "after": seen_group, "order": order }),
+                                });
+                            }
+                            flagged_root = Some(i.root.as_str());
+                        }
+                        _ => {
+                            latest = Some((r, group));
+                            flagged_root = None;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+// std prelude names a `use` can shadow, with the modules (under std/core/alloc)
+// that really define them
+pub(crate) const PRELUDE_ITEMS: &[(&str, &[&str])] = &[
+    ("Option", &["option"]), ("Some", &["option::Option"]), ("None", &["option::Option"]),
+    ("Result", &["result"]), ("Ok", &["result::Result"]), ("Err", &["result::Result"]),
+    ("Vec", &["vec"]), ("String", &["string"]), ("Box", &["boxed"]),
+    ("ToString", &["string"]), ("ToOwned", &["borrow"]),
+    ("Clone", &["clone"]), ("Copy", &["marker"]), ("Send", &["marker"]), ("Sync", &["marker"]),
+    ("Sized", &["marker"]), ("Unpin", &["marker"]), ("Drop", &["ops"]),
+    ("Fn", &["ops"]), ("FnMut", &["ops"]), ("FnOnce", &["ops"]), ("Default", &["default"]),
+    ("Iterator", &["iter"]), ("IntoIterator", &["iter"]), ("Extend", &["iter"]), ("FromIterator", &["iter"]),
+    ("DoubleEndedIterator", &["iter"]), ("ExactSizeIterator", &["iter"]),
+    ("Eq", &["cmp"]), ("PartialEq", &["cmp"]), ("Ord", &["cmp"]), ("PartialOrd", &["cmp"]),
+    ("AsRef", &["convert"]), ("AsMut", &["convert"]), ("Into", &["convert"]), ("From", &["convert"]),
+    ("TryFrom", &["convert"]), ("TryInto", &["convert"]),
+];
+
+// the name a `use` binds; None for globs and `as _`
+fn bound_name(i: &StolenPath) -> Option<&str> {
+    match &i.kind {
+        UseKind::Glob => None,
+        UseKind::Rename { alias } => (alias != "_").then_some(alias.as_str()),
+        UseKind::Name => {
+            // `use a::b::{self}` binds `b`
+            let mut segs = i.full_path.rsplit("::");
+            segs.next().filter(|s| *s != "self").or_else(|| segs.next())
+        }
+    }
+}
+
+/// `use`s binding a std prelude name to something else (`use std::fmt::Result`,
+/// `use anyhow::Error as Result`) or a name a function or type of the same
+/// module already has.
+pub struct ImportShadowing;
+impl Rule for ImportShadowing {
+    fn code(&self) -> RuleCode { codes::IMPORT_SHADOWING }
+    fn name(&self) -> &'static str { "import shadows prelude or local item" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        use crate::visibility::full_module_path;
+
+        let mut local: std::collections::HashMap<(Vec<String>, &str), &str> = std::collections::HashMap::new();
+        for f in k.functions.iter().filter(|f| f.kind == FnKind::FreeFn && !f.is_test_code) {
+            local.entry((full_module_path(&f.location.path, &f.module_path), f.name.as_str())).or_insert(&f.fq_name);
+        }
+        for t in k.types.iter().filter(|t| !t.is_test_code) {
+            local.entry((full_module_path(&t.location.path, &t.module_path), t.name.as_str())).or_insert(&t.fq_name);
+        }
+
+        let mut out = Vec::new();
+        for i in k.imports.iter().filter(|i| !i.is_test_code && !i.expanded_from_glob) {
+            let Some(name) = bound_name(i) else { continue };
+            let path = i.full_path.trim_start_matches("::");
+            let module = full_module_path(&i.location.path, &i.module_path);
+
+            let shadowed = if let Some((_, homes)) = PRELUDE_ITEMS.iter().find(|(n, _)| *n == name) {
+                let (root, rest) = path.split_once("::").unwrap_or((path, ""));
+                let genuine = matches!(root, "std" | "core" | "alloc")
+                    && (rest.starts_with("prelude::") || homes.iter().any(|h| rest == format!("{}::{}", h, name)));
+                (!genuine).then(|| (format!("the prelude's `{}`", name), None))
+            } else {
+                // `use self::x` or `use crate::this::x` re-import the item itself
+                let own = [format!("self::{}", name), format!("crate::{}", module.iter().map(String::as_str).chain([name]).collect::<Vec<_>>().join("::"))];
+                local.get(&(module.clone(), name))
+                    .filter(|_| !own.iter().any(|o| *o == path))
+                    .map(|fq| (format!("`{}` of the same module", fq), Some(*fq)))
+            };
+            let Some((what, local_item)) = shadowed else { continue };
+            out.push(Finding {
+                severity: Severity::Warn,
+                code: self.code(),
+                message: format!("`use {}` shadows {}", i.full_path, what),
+                location: i.location.clone(),
+                extra: json!({ "import": i.full_path, "name": name, "shadows": local_item.unwrap_or("prelude") }),
+            });
+        }
+        out
+    }
+}
+
+/// Silent until `[doc_coverage]` in klepto.toml (or `thresholds`) sets a
+/// minimum. One finding per module under its minimum, at its first
+/// undocumented public item.
+#[derive(Default)]
+pub struct DocCoverageBelowThreshold {
+    pub thresholds: DocCoverageConfig,
+}
+impl DocCoverageBelowThreshold {
+    pub fn from_config(c: &DocCoverageConfig) -> Self { Self { thresholds: c.clone() } }
+}
+impl Rule for DocCoverageBelowThreshold {
+    fn code(&self) -> RuleCode { codes::DOC_COVERAGE_BELOW_THRESHOLD }
+    fn name(&self) -> &'static str { "module doc coverage below threshold" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        use crate::coverage::module_key;
+
+        let vis = &k.effective_visibility;
+        let mut first_undocumented: std::collections::BTreeMap<String, &FileLocation> = std::collections::BTreeMap::new();
+        let undocumented = k.functions.iter()
+            .filter(|f| vis.fn_public(f) && !f.has_docs)
+            .map(|f| (&f.location, &f.module_path))
+            .chain(k.types.iter().filter(|t| vis.type_public(t) && !t.has_docs).map(|t| (&t.location, &t.module_path)));
+        for (loc, module_path) in undocumented {
+            let first = first_undocumented.entry(module_key(&loc.path, module_path)).or_insert(loc);
+            if (&loc.path, loc.line) < (&first.path, first.line) { *first = loc; }
+        }
+
+        k.doc_coverage_report().by_module.iter()
+            .filter_map(|(module, c)| {
+                let min = self.thresholds.threshold(module)?;
+                let all = c.all();
+                let percent = all.percent();
+                if percent >= min { return None; }
+                let location = (*first_undocumented.get(module)?).clone();
+                Some(Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message: format!(
+                        "`{}` documents {}/{} public items ({:.1}%, minimum {}%)",
+                        module, all.documented, all.total, percent, min
+                    ),
+                    location,
+                    extra: json!({ "module": module, "documented": all.documented, "total": all.total, "percent": percent, "min": min }),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Heuristic (see `test_map`), hence Info; silent when the scan has no tests.
+pub struct UntestedPublicFn;
+impl Rule for UntestedPublicFn {
+    fn code(&self) -> RuleCode { codes::UNTESTED_PUBLIC_FN }
+    fn name(&self) -> &'static str { "public fn without tests" }
+    fn default_severity(&self) -> Severity { Severity::Info }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        k.untested_public_fns_with(k.call_graph()).into_iter()
+            .map(|f| Finding {
+                severity: Severity::Info,
+                code: self.code(),
+                message: format!("no test calls {}", f.fq_name),
+                location: f.location.clone(),
+                extra: json!({ "fn": f.fq_name }),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,107 @@
+use crate::graph::CallGraph;
+use crate::klepto::Klepto;
+use crate::model::*;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Shared, lazily computed artifacts handed to every rule in a run.
+///
+/// Derefs to the underlying `Klepto`, so rules keep reading `k.functions`,
+/// `k.calls`, ... directly and only pay for the precomputed views they ask for.
+pub struct RuleContext<'k> {
+    k: &'k Klepto,
+    public_modules: OnceCell<BTreeSet<ModulePath>>,
+    calls_by_fn: OnceCell<BTreeMap<&'k str, Vec<&'k CallOccurrence>>>,
+    call_graph: OnceCell<CallGraph>,
+}
+
+impl<'k> RuleContext<'k> {
+    pub fn new(k: &'k Klepto) -> Self {
+        Self {
+            k,
+            public_modules: OnceCell::new(),
+            calls_by_fn: OnceCell::new(),
+            call_graph: OnceCell::new(),
+        }
+    }
+
+    pub fn klepto(&self) -> &'k Klepto { self.k }
+
+    /// Module paths containing at least one function reachable from outside the crate.
+    pub fn public_modules(&self) -> &BTreeSet<ModulePath> {
+        self.public_modules.get_or_init(|| {
+            let vis = &self.k.effective_visibility;
+            self.k.functions.iter().filter(|f| vis.fn_public(f)).map(|f| f.module_path.clone()).collect()
+        })
+    }
+
+    pub fn fn_named(&self, fq_name: &str) -> Option<&'k CapturedFn> {
+        self.k.fn_by_name(fq_name)
+    }
+
+    /// Whether `enclosing_fn`, the fn around a call or macro at `at`, is reachable from outside the crate.
+    pub fn in_public_fn(&self, enclosing_fn: Option<&str>, at: &FileLocation) -> bool {
+        let Some(name) = enclosing_fn else { return false; };
+        self.k.fns_by_name(name)
+            .find(|f| f.location.path == at.path)
+            .is_some_and(|f| self.k.effective_visibility.fn_public(f))
+    }
+
+    /// Raw call edges: enclosing fn `fq_name` -> calls made from its body.
+    pub fn calls_by_fn(&self) -> &BTreeMap<&'k str, Vec<&'k CallOccurrence>> {
+        self.calls_by_fn.get_or_init(|| {
+            let mut m: BTreeMap<&str, Vec<&CallOccurrence>> = BTreeMap::new();
+            for c in &self.k.calls {
+                if let Some(f) = c.enclosing_fn.as_deref() {
+                    m.entry(f).or_default().push(c);
+                }
+            }
+            m
+        })
+    }
+
+    /// Resolved call graph; prefer it over `calls_by_fn` when rules need callee identities.
+    pub fn call_graph(&self) -> &CallGraph {
+        self.call_graph.get_or_init(|| CallGraph::build(self.k))
+    }
+
+}
+
+impl std::ops::Deref for RuleContext<'_> {
+    type Target = Klepto;
+    fn deref(&self) -> &Klepto { self.k }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::builtin::codes;
+
+    #[test]
+    fn public_modules_follow_effective_visibility() {
+        let dir = std::env::temp_dir().join(format!("klepto-context-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "mod hidden { pub fn f() {} }\npub mod open { pub fn g() {} }\n").unwrap();
+        let k = Klepto::new("ctx").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let ctx = RuleContext::new(&k);
+        let modules: Vec<Vec<String>> = ctx.public_modules().iter().map(|m| m.to_vec()).collect();
+        assert_eq!(modules, vec![vec!["open".to_string()]]);
+    }
+
+    #[test]
+    fn panic_rules_skip_pub_fns_in_private_modules() {
+        let dir = std::env::temp_dir().join(format!("klepto-context-panics-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "mod hidden {\n    pub fn f(x: Option<u8>) -> u8 { if x.is_none() { todo!() } x.unwrap() }\n}\n\
+            pub mod open {\n    pub fn g(x: Option<u8>) -> u8 { if x.is_none() { todo!() } x.unwrap() }\n}\n").unwrap();
+        let k = Klepto::new("ctx").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let findings = k.rules().with_default_rules().run();
+        for code in [codes::UNWRAP_IN_PUBLIC_API, codes::PANIC_MACROS_IN_PUBLIC_API] {
+            let hits: Vec<&str> = findings.iter().filter(|f| f.code == code).map(|f| f.message.as_str()).collect();
+            assert_eq!(hits.len(), 1, "{code}: {hits:?}");
+            assert!(hits[0].contains("open::g"), "{code}: {hits:?}");
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Checks from the Rust API Guidelines (<https://rust-lang.github.io/api-guidelines/>)
+//! on the public API. Not among the defaults: add the pack with
+//! `RuleRunner::with_guidelines`, or one rule by giving its code a level in
+//! klepto.toml (`KLEP018 = "warn"`).
+
+use crate::model::*;
+use crate::rules::builtin::codes;
+use crate::rules::{Rule, RuleContext};
+use serde_json::json;
+
+/// Every guideline rule.
+pub fn pack() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(Naming),
+        Box::new(GetterPrefix),
+        Box::new(ConversionNames),
+        Box::new(IterNames),
+    ]
+}
+
+// how a method takes `self`; None for associated functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Receiver {
+    Ref,
+    RefMut,
+    Value,
+}
+
+fn receiver(f: &CapturedFn) -> Option<Receiver> {
+    if matches!(f.kind, FnKind::FreeFn) { return None; }
+    let first = f.args.first()?;
+    let tokens: Vec<&str> = first.split_whitespace().collect();
+    // `self: &Self`, `self: Box<Self>`, .. are judged by the type
+    let ty = match tokens.iter().position(|t| *t == ":") {
+        Some(at) if tokens[..at].contains(&"self") => &tokens[at + 1..],
+        Some(_) => return None,
+        None if tokens.contains(&"self") => &tokens[..],
+        None => return None,
+    };
+    Some(match (ty.first(), ty.contains(&"mut")) {
+        (Some(&"&"), true) => Receiver::RefMut,
+        (Some(&"&"), false) => Receiver::Ref,
+        _ => Receiver::Value,
+    })
+}
+
+fn public_methods<'k>(k: &'k RuleContext<'_>) -> impl Iterator<Item = &'k CapturedFn> {
+    // trait impls take their names from the trait
+    k.klepto().functions.iter()
+        .filter(|f| !matches!(f.kind, FnKind::ImplMethod { trait_ty: Some(_), .. }))
+        .filter(|f| k.effective_visibility.fn_public(f))
+}
+
+fn finding(code: RuleCode, f: &CapturedFn, message: String, suggestion: Option<String>) -> Finding {
+    Finding {
+        severity: Severity::Warn,
+        code,
+        message,
+        location: f.location.clone(),
+        extra: json!({ "fn": f.fq_name, "suggestion": suggestion }),
+    }
+}
+
+/// `UpperCamelCase` with acronyms as one word: `Uuid`, not `UUID`.
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let prev_upper = i > 0 && chars[i - 1].is_uppercase();
+        let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+        // the last capital of `HTTPServer` starts the next word
+        if c.is_uppercase() && prev_upper && !next_lower {
+            out.extend(c.to_lowercase());
+        } else if *c != '_' {
+            out.push(if i == 0 || chars[i - 1] == '_' { c.to_ascii_uppercase() } else { *c });
+        }
+    }
+    out
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let prev_lower = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if i > 0 && chars[i - 1] != '_' && (prev_lower || next_lower) { out.push('_'); }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(*c);
+        }
+    }
+    out
+}
+
+/// C-CASE: `UpperCamelCase` types and traits (acronyms as one word),
+/// `snake_case` functions and modules.
+pub struct Naming;
+impl Rule for Naming {
+    fn code(&self) -> RuleCode { codes::GUIDELINES_CASE }
+    fn name(&self) -> &'static str { "C-CASE: naming follows RFC 430" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let vis = &k.effective_visibility;
+        let mut out = Vec::new();
+        for t in k.types.iter().filter(|t| vis.type_public(t)) {
+            let want = camel_case(&t.name);
+            if want != t.name {
+                out.push(Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message: format!("type `{}` should be `{}` (C-CASE)", t.fq_name, want),
+                    location: t.location.clone(),
+                    extra: json!({ "type": t.fq_name, "suggestion": want }),
+                });
+            }
+        }
+        for f in public_methods(k) {
+            let want = snake_case(&f.name);
+            if want != f.name {
+                out.push(finding(self.code(), f, format!("function `{}` should be `{}` (C-CASE)", f.fq_name, want), Some(want)));
+            }
+        }
+        for m in k.modules.iter().filter(|m| m.is_public && !m.is_test_code) {
+            let mut full = crate::visibility::full_module_path(&m.location.path, &m.module_path);
+            full.push(m.name.clone());
+            let want = snake_case(&m.name);
+            if want != m.name && vis.module_public(&full) {
+                out.push(Finding {
+                    severity: Severity::Warn,
+                    code: self.code(),
+                    message: format!("module `{}` should be `{}` (C-CASE)", full.join("::"), want),
+                    location: m.location.clone(),
+                    extra: json!({ "module": full, "suggestion": want }),
+                });
+            }
+        }
+        out
+    }
+}
+
+/// C-GETTER: `&self` methods without arguments aren't named `get_*`
+/// (`get`, `get_mut`, `get_ref` and `get_unchecked*` are the exceptions).
+pub struct GetterPrefix;
+impl Rule for GetterPrefix {
+    fn code(&self) -> RuleCode { codes::GUIDELINES_GETTER }
+    fn name(&self) -> &'static str { "C-GETTER: getters have no get_ prefix" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        public_methods(k)
+            .filter(|f| f.args.len() == 1 && matches!(receiver(f), Some(Receiver::Ref | Receiver::RefMut)))
+            .filter(|f| !matches!(f.name.as_str(), "get_mut" | "get_ref") && !f.name.starts_with("get_unchecked"))
+            .filter_map(|f| {
+                let want = f.name.strip_prefix("get_")?;
+                // `get_type` can't lose its prefix
+                let want = if syn::parse_str::<syn::Ident>(want).is_ok() { want.to_string() } else { format!("{}_", want) };
+                Some(finding(self.code(), f, format!("getter `{}` should be `{}` (C-GETTER)", f.fq_name, want), Some(want)))
+            })
+            .collect()
+    }
+}
+
+/// C-CONV: `as_*` borrows and is cheap, `to_*` takes `&self`, `into_*` takes `self`.
+/// `as_*` may take `self` when its type derives `Copy`.
+pub struct ConversionNames;
+impl Rule for ConversionNames {
+    fn code(&self) -> RuleCode { codes::GUIDELINES_CONV }
+    fn name(&self) -> &'static str { "C-CONV: as_/to_/into_ conversions" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        let copy_types: std::collections::HashSet<&str> = k.types.iter()
+            .filter(|t| t.derives.iter().any(|d| d.rsplit("::").next() == Some("Copy")))
+            .map(|t| t.name.as_str())
+            .collect();
+        public_methods(k)
+            .filter_map(|f| {
+                let r = receiver(f)?;
+                let is_copy = matches!(&f.kind, FnKind::ImplMethod { self_ty, .. } if copy_types.contains(self_ty.as_str()));
+                let ret = f.return_ty.as_deref().unwrap_or("").split('<').next().unwrap_or("").trim().to_string();
+                let why = if f.name.starts_with("into_") && r != Receiver::Value {
+                    "`into_` conversions take `self` by value"
+                } else if f.name.starts_with("as_") && r == Receiver::Value && !is_copy {
+                    "`as_` conversions borrow `self`"
+                } else if f.name.starts_with("as_") && matches!(ret.as_str(), "String" | "Vec" | "Box") {
+                    "`as_` conversions are free; an allocating one is `to_`"
+                } else if f.name.starts_with("to_") && r == Receiver::RefMut {
+                    "`to_` conversions take `&self`"
+                } else {
+                    return None;
+                };
+                Some(finding(self.code(), f, format!("`{}`: {} (C-CONV)", f.fq_name, why), None))
+            })
+            .collect()
+    }
+}
+
+/// C-ITER: iterator methods are `iter` (`&self`), `iter_mut` (`&mut self`) and
+/// `into_iter` (`self`); other `iter`-ish names (`get_iter`, `iterator`, ..) are flagged.
+pub struct IterNames;
+impl Rule for IterNames {
+    fn code(&self) -> RuleCode { codes::GUIDELINES_ITER }
+    fn name(&self) -> &'static str { "C-ITER: iter/iter_mut/into_iter" }
+
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding> {
+        public_methods(k)
+            .filter_map(|f| {
+                let r = receiver(f)?;
+                let expected = match r {
+                    Receiver::Ref => "iter",
+                    Receiver::RefMut => "iter_mut",
+                    Receiver::Value => "into_iter",
+                };
+                let iterish = matches!(f.name.as_str(), "iter" | "iter_mut" | "into_iter" | "iterator" | "iterate" | "mut_iter" | "iter_ref" | "get_iter" | "as_iter" | "to_iter");
+                if !iterish || f.name == expected || f.args.len() != 1 { return None; }
+                let message = format!("`{}` takes {} and should be `{}` (C-ITER)", f.fq_name, match r {
+                    Receiver::Ref => "`&self`",
+                    Receiver::RefMut => "`&mut self`",
+                    Receiver::Value => "`self`",
+                }, expected);
+                Some(finding(self.code(), f, message, Some(expected.to_string())))
+            })
+            .collect()
+    }
+}
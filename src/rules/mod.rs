@@ -1,41 +1,188 @@
-use crate::klepto::Klepto;
+use crate::config::{Config, RuleLevel};
+use crate::klepto::{Klepto, KleptoError};
 use crate::model::*;
+use std::collections::BTreeMap;
 pub mod builtin;
+pub mod context;
+pub mod guidelines;
+pub use context::RuleContext;
 
 pub trait Rule {
-    fn code(&self) -> &'static str;
+    fn code(&self) -> RuleCode;
     fn name(&self) -> &'static str;
-    fn run(&self, k: &Klepto) -> Vec<Finding>;
+    fn run(&self, k: &RuleContext<'_>) -> Vec<Finding>;
+    /// What the rule reports at; the most severe one if it reports at several.
+    fn default_severity(&self) -> Severity { Severity::Warn }
+}
+
+/// Codes registered with a runner, so two rules can never report under the same code.
+#[derive(Debug, Clone, Default)]
+pub struct RuleRegistry {
+    codes: BTreeMap<RuleCode, &'static str>,
+}
+
+impl RuleRegistry {
+    pub fn register(&mut self, code: RuleCode, name: &'static str) -> Result<(), KleptoError> {
+        if let Some(existing) = self.codes.get(&code) {
+            return Err(KleptoError::DuplicateRuleCode { code, existing: existing.to_string() });
+        }
+        self.codes.insert(code, name);
+        Ok(())
+    }
+
+    pub fn contains(&self, code: &RuleCode) -> bool { self.codes.contains_key(code) }
+    pub fn iter(&self) -> impl Iterator<Item = (&RuleCode, &&'static str)> { self.codes.iter() }
 }
 
 pub struct RuleRunner<'k> {
     k: &'k Klepto,
     rules: Vec<Box<dyn Rule>>,
+    registry: RuleRegistry,
+    levels: BTreeMap<RuleCode, RuleLevel>,
+    external: Vec<Finding>,
 }
 
 impl<'k> RuleRunner<'k> {
     pub fn new(k: &'k Klepto) -> Self {
-        Self { k, rules: Vec::new() }
+        Self { k, rules: Vec::new(), registry: RuleRegistry::default(), levels: BTreeMap::new(), external: Vec::new() }
+    }
+
+    /// Applies `[rules]` from a `klepto.toml`: `off` drops a rule, a severity other
+    /// than the rule's `default_severity` re-labels all of its findings. `[msrv]`
+    /// configures the MSRV rule, replacing one already added. A `guidelines` rule
+    /// given a level other than `off` is added.
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.levels.extend(config.rules.iter().map(|(code, level)| (RuleCode::new(code.clone()), *level)));
+        for r in guidelines::pack() {
+            if self.levels.get(&r.code()).is_some_and(|l| *l != RuleLevel::Off) {
+                self.add_if_new(r);
+            }
+        }
+        if let Some(m) = &config.msrv {
+            self.replace_builtin(Box::new(builtin::MsrvIncompatible::from_config(m)));
+        }
+        if let Some(i) = &config.imports {
+            self.replace_builtin(Box::new(builtin::ImportStyle::from_config(i)));
+        }
+        if let Some(d) = &config.doc_coverage {
+            self.replace_builtin(Box::new(builtin::DocCoverageBelowThreshold::from_config(d)));
+        }
+        self
+    }
+
+    // a configured builtin takes the place of its default
+    fn replace_builtin(&mut self, rule: Box<dyn Rule>) {
+        self.rules.retain(|r| r.code() != rule.code());
+        // already registered when the defaults came first
+        let _ = self.registry.register(rule.code(), rule.name());
+        self.rules.push(rule);
     }
 
     pub fn with_default_rules(mut self) -> Self {
-        self.rules.push(Box::new(builtin::UndocumentedPublicApi));
-        self.rules.push(Box::new(builtin::UnwrapInPublicApi));
-        self.rules.push(Box::new(builtin::StdInNoStdCrate));
-        self.rules.push(Box::new(builtin::PanicMacrosInPublicApi));
+        for r in builtin::defaults() {
+            self.add_if_new(r);
+        }
+        self
+    }
+
+    /// The API guidelines checks (see `guidelines`), off by default.
+    pub fn with_guidelines(mut self) -> Self {
+        for r in guidelines::pack() {
+            self.add_if_new(r);
+        }
         self
     }
 
-    pub fn add_rule<R: Rule + 'static>(mut self, r: R) -> Self {
+    // a builtin already added (e.g. with custom thresholds via `override_builtin`) wins
+    fn add_if_new(&mut self, r: Box<dyn Rule>) {
+        if self.registry.register(r.code(), r.name()).is_ok() {
+            self.rules.push(r);
+        }
+    }
+
+    /// Adds a rule, failing if another rule already registered its code.
+    /// `KLEP*` codes belong to the builtin rules (see `builtin::codes`) and are
+    /// refused; `override_builtin` replaces one.
+    pub fn add_rule<R: Rule + 'static>(mut self, r: R) -> Result<Self, KleptoError> {
+        if r.code().is_builtin() {
+            return Err(KleptoError::ReservedRuleCode { code: r.code() });
+        }
+        self.registry.register(r.code(), r.name())?;
         self.rules.push(Box::new(r));
+        Ok(self)
+    }
+
+    /// Runs `r` in place of the builtin rule with its `KLEP*` code (e.g. one built
+    /// with other thresholds), whether the defaults were added before or after.
+    pub fn override_builtin<R: Rule + 'static>(mut self, r: R) -> Result<Self, KleptoError> {
+        if !r.code().is_builtin() {
+            return Err(KleptoError::NotABuiltinRuleCode { code: r.code() });
+        }
+        self.replace_builtin(Box::new(r));
+        Ok(self)
+    }
+
+    /// Findings from outside klepto (e.g. `diagnostics::from_cargo_json`) to report
+    /// with the rules'. `[rules]` levels apply to them by code too.
+    pub fn with_external_findings(mut self, findings: impl IntoIterator<Item = Finding>) -> Self {
+        self.external.extend(findings);
         self
     }
 
+    pub fn registry(&self) -> &RuleRegistry { &self.registry }
+
     pub fn run(self) -> Vec<Finding> {
+        let ctx = RuleContext::new(self.k);
         let mut all = Vec::new();
         for r in self.rules {
-            all.extend(r.run(self.k));
+            let level = self.levels.get(&r.code());
+            if level == Some(&RuleLevel::Off) { continue; }
+            let relabel = level.and_then(|l| l.severity()).filter(|s| *s != r.default_severity());
+            let mut found = r.run(&ctx);
+            if let Some(s) = relabel {
+                found.iter_mut().for_each(|f| f.severity = s);
+            }
+            all.extend(found);
+        }
+        for mut f in self.external {
+            match self.levels.get(&f.code).map(|l| l.severity()) {
+                Some(None) => continue,
+                Some(Some(s)) => f.severity = s,
+                None => {}
+            }
+            all.push(f);
+        }
+        if let Some(owners) = &self.k.code_owners {
+            owners.annotate(&mut all);
         }
         all
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Quiet(&'static str);
+    impl Rule for Quiet {
+        fn code(&self) -> RuleCode { RuleCode::from_static(self.0) }
+        fn name(&self) -> &'static str { "quiet" }
+        fn run(&self, _: &RuleContext<'_>) -> Vec<Finding> { Vec::new() }
+    }
+
+    #[test]
+    fn builtin_codes_need_an_explicit_override() {
+        let k = Klepto::new("r").parse().unwrap();
+        assert!(matches!(k.rules().add_rule(Quiet("KLEP001")), Err(KleptoError::ReservedRuleCode { .. })));
+        assert!(matches!(k.rules().override_builtin(Quiet("MY001")), Err(KleptoError::NotABuiltinRuleCode { .. })));
+
+        let before = k.rules().override_builtin(Quiet("KLEP001")).unwrap().with_default_rules();
+        let after = k.rules().with_default_rules().override_builtin(Quiet("KLEP001")).unwrap();
+        for runner in [before, after] {
+            let klep001: Vec<_> = runner.rules.iter().filter(|r| r.code() == "KLEP001").collect();
+            assert_eq!(klep001.len(), 1);
+            assert_eq!(klep001[0].name(), "quiet");
+        }
+        assert!(k.rules().add_rule(Quiet("MY001")).unwrap().add_rule(Quiet("MY001")).is_err());
+    }
+}
@@ -0,0 +1,635 @@
+//! Interop with rustdoc's JSON output (`cargo +nightly rustdoc -- -Z unstable-options --output-format json`).
+//!
+//! Only the parts a `Snapshot` models are read: functions/methods, types, impls
+//! and `pub use` re-exports. rustdoc doesn't give us klepto's signature strings,
+//! so imported functions carry an empty `sig_hash` and `Snapshot::diff` only
+//! compares their names. Imports aren't part of rustdoc's output either.
+//!
+//! Both the current (`"inner": {"function": {..}}`) and the older
+//! (`"kind": "function", "inner": {..}`) item layouts are accepted.
+
+use crate::extract::file_module_path;
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::*;
+use crate::snapshot::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// `format_version` written by `Snapshot::to_rustdoc_json`.
+pub const RUSTDOC_FORMAT_VERSION: u32 = 57;
+
+// (kind, payload) for either item layout
+fn item_kind(item: &Value) -> Option<(&str, &Value)> {
+    match item.get("inner")? {
+        Value::Object(m) if m.len() == 1 && item.get("kind").is_none() => {
+            let (k, v) = m.iter().next()?;
+            Some((k.as_str(), v))
+        }
+        inner => Some((item.get("kind")?.as_str()?, inner)),
+    }
+}
+
+fn is_local(item: &Value) -> bool {
+    item.get("crate_id").and_then(Value::as_u64) == Some(0)
+}
+
+fn is_public(item: &Value) -> bool {
+    item.get("visibility").and_then(Value::as_str) == Some("public")
+}
+
+// attrs are plain strings in older formats and tagged objects in newer ones
+fn has_attr(item: &Value, name: &str) -> bool {
+    item.get("attrs").and_then(Value::as_array).is_some_and(|a| a.iter().any(|x| x.to_string().contains(name)))
+}
+
+fn location(item: &Value) -> FileLocation {
+    let span = item.get("span");
+    let path = span
+        .and_then(|s| s.get("filename"))
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let begin = span.and_then(|s| s.get("begin"));
+    let at = |i: usize| begin.and_then(|b| b.get(i)).and_then(Value::as_u64).map(|n| n as u32);
+    FileLocation { path: path.into(), line: at(0), column: at(1), byte_range: None }
+}
+
+// name of a `resolved_path` type (either layout), e.g. `Foo` for `crate::x::Foo<T>`
+fn resolved_path(ty: &Value) -> Option<&Value> {
+    ty.get("resolved_path").or_else(|| {
+        (ty.get("kind").and_then(Value::as_str) == Some("resolved_path")).then(|| ty.get("inner")).flatten()
+    })
+}
+
+fn resolved_name(ty: &Value) -> Option<(&str, Option<&Value>)> {
+    let rp = resolved_path(ty)?;
+    let name = rp.get("path").or_else(|| rp.get("name")).and_then(Value::as_str)?;
+    Some((name.rsplit("::").next().unwrap_or(name), rp.get("id")))
+}
+
+// (kind, payload) of a type, in either layout
+fn type_kind(ty: &Value) -> Option<(&str, &Value)> {
+    match ty {
+        Value::Object(m) if m.len() == 1 => m.iter().next().map(|(k, v)| (k.as_str(), v)),
+        _ => Some((ty.get("kind")?.as_str()?, ty.get("inner")?)),
+    }
+}
+
+// a type as klepto's tidied tokens write it (`Vec<u8>`, `&'a str`, `(A, B)`);
+// None for kinds klepto doesn't model
+fn type_text(ty: &Value) -> Option<String> {
+    let (kind, inner) = type_kind(ty)?;
+    Some(match kind {
+        "primitive" | "generic" => inner.as_str()?.to_string(),
+        "resolved_path" => {
+            let name = inner.get("path").or_else(|| inner.get("name")).and_then(Value::as_str)?;
+            let args = path_args(inner);
+            if args.is_empty() { name.to_string() } else { format!("{}<{}>", name, args.join(", ")) }
+        }
+        "borrowed_ref" => {
+            let lifetime = inner.get("lifetime").and_then(Value::as_str).map(|l| format!("{} ", l)).unwrap_or_default();
+            let mutable = inner.get("is_mutable").or_else(|| inner.get("mutable")).and_then(Value::as_bool) == Some(true);
+            format!("&{}{}{}", lifetime, if mutable { "mut " } else { "" }, type_text(inner.get("type")?)?)
+        }
+        "tuple" => format!("({})", inner.as_array()?.iter().map(type_text).collect::<Option<Vec<_>>>()?.join(", ")),
+        "slice" => format!("[{}]", type_text(inner)?),
+        "array" => format!("[{}; {}]", type_text(inner.get("type")?)?, inner.get("len")?.as_str()?),
+        _ => return None,
+    })
+}
+
+// a path's `<..>` type arguments, lifetimes left out (as `CapturedImpl::self_ty_args`)
+fn path_args(path: &Value) -> Vec<String> {
+    path.get("args")
+        .and_then(|a| a.get("angle_bracketed"))
+        .and_then(|a| a.get("args"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|a| a.get("type"))
+        .filter_map(type_text)
+        .collect()
+}
+
+fn id_key(id: &Value) -> String {
+    match id {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct Doc<'a> {
+    crate_name: String,
+    index: &'a Map<String, Value>,
+    paths: &'a Map<String, Value>,
+}
+
+impl<'a> Doc<'a> {
+    fn item(&self, id: &Value) -> Option<&'a Value> {
+        self.index.get(&id_key(id))
+    }
+
+    // full rustdoc path of a local item, e.g. ["klepto", "snapshot", "Snapshot"]
+    fn path_of(&self, id: &Value) -> Option<Vec<String>> {
+        let p = self.paths.get(&id_key(id))?.get("path")?.as_array()?;
+        Some(p.iter().filter_map(|s| s.as_str().map(str::to_string)).collect())
+    }
+
+    // klepto's module_path: the rustdoc module path minus the modules implied by the file
+    fn inline_modules(&self, full: &[String], file: &Path) -> Vec<String> {
+        let modules = &full[1.min(full.len())..full.len().saturating_sub(1)];
+        let from_file = file_module_path(file);
+        match modules.strip_prefix(from_file.as_slice()) {
+            Some(rest) => rest.to_vec(),
+            None => modules.to_vec(),
+        }
+    }
+
+    fn fq(&self, module_path: &[String], owner: Option<&str>, name: &str) -> String {
+        let mut parts = vec![self.crate_name.clone()];
+        parts.extend(module_path.iter().cloned());
+        parts.extend(owner.map(str::to_string));
+        parts.push(name.to_string());
+        parts.join("::")
+    }
+
+    fn function(&self, fq_name: String, item: &Value) -> FnFinger {
+        FnFinger { fq_name, sig_hash: String::new(), signature: String::new(), location: location(item), is_public: None }
+    }
+}
+
+impl Snapshot {
+    /// Builds a snapshot from a rustdoc JSON file. `fq_name`s use the crate name
+    /// rustdoc reports, so scan the live tree with the same name to compare.
+    pub fn from_rustdoc_json(path: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        let value: Value = serde_json::from_slice(&bytes).map_err(KleptoError::SnapshotFormat)?;
+        Self::from_rustdoc_value(&value)
+    }
+
+    pub fn from_rustdoc_value(value: &Value) -> Result<Self, KleptoError> {
+        let bad = |what: &str| KleptoError::RustdocFormat(format!("missing `{}`", what));
+        let index = value.get("index").and_then(Value::as_object).ok_or_else(|| bad("index"))?;
+        let paths = value.get("paths").and_then(Value::as_object).ok_or_else(|| bad("paths"))?;
+        let root = value.get("root").ok_or_else(|| bad("root"))?;
+        let crate_name = index
+            .get(&id_key(root))
+            .and_then(|r| r.get("name"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| bad("root item name"))?
+            .to_string();
+        let doc = Doc { crate_name, index, paths };
+
+        let mut functions = BTreeMap::new();
+        let mut types = Vec::new();
+        let mut impls = Vec::new();
+        let mut exports = Vec::new();
+        let mut no_std = false;
+
+        for (id, item) in index {
+            if !is_local(item) { continue; }
+            let Some((kind, inner)) = item_kind(item) else { continue; };
+            let id = Value::String(id.clone());
+            let loc = location(item);
+
+            match kind {
+                "module" if item.get("name") == Some(&Value::String(doc.crate_name.clone())) => {
+                    no_std |= has_attr(item, "no_std");
+                }
+                "function" => {
+                    let Some(full) = doc.path_of(&id) else { continue; };
+                    let Some(name) = full.last() else { continue; };
+                    let fq = doc.fq(&doc.inline_modules(&full, &loc.path), None, name);
+                    functions.insert(fq.clone(), doc.function(fq, item));
+                }
+                "struct" | "enum" | "union" | "trait" | "type_alias" | "typedef" => {
+                    let Some(full) = doc.path_of(&id) else { continue; };
+                    let Some(name) = full.last() else { continue; };
+                    let module_path = doc.inline_modules(&full, &loc.path);
+                    let kind = match kind {
+                        "struct" => TypeKind::Struct,
+                        "enum" => TypeKind::Enum,
+                        "union" => TypeKind::Union,
+                        "trait" => TypeKind::Trait,
+                        _ => TypeKind::TypeAlias,
+                    };
+                    if kind == TypeKind::Trait {
+                        for m in inner.get("items").and_then(Value::as_array).into_iter().flatten() {
+                            let Some(mi) = doc.item(m) else { continue; };
+                            if !matches!(item_kind(mi), Some(("function" | "method", _))) { continue; }
+                            let Some(mname) = mi.get("name").and_then(Value::as_str) else { continue; };
+                            let fq = doc.fq(&module_path, Some(name), mname);
+                            functions.insert(fq.clone(), doc.function(fq, mi));
+                        }
+                    }
+                    types.push(TypeFinger { fq_name: doc.fq(&module_path, None, name), kind, is_public: is_public(item), location: loc });
+                }
+                "impl" => {
+                    // auto-trait, blanket and derive-generated impls have no `impl` block in the source
+                    let synthetic = inner.get("is_synthetic").or_else(|| inner.get("synthetic")).and_then(Value::as_bool);
+                    if synthetic == Some(true) || inner.get("blanket_impl").is_some_and(|b| !b.is_null()) || has_attr(item, "automatically_derived") {
+                        continue;
+                    }
+                    let Some((self_ty, ty_id)) = inner.get("for").and_then(resolved_name) else { continue; };
+                    // klepto records the impl block's own (inline) module; rustdoc only has the
+                    // type's path, which matches when the impl lives next to the type
+                    let module_path = ty_id
+                        .and_then(|t| doc.path_of(t))
+                        .and_then(|full| {
+                            let modules = &full[1.min(full.len())..full.len().saturating_sub(1)];
+                            modules.strip_prefix(file_module_path(&loc.path).as_slice()).map(<[String]>::to_vec)
+                        })
+                        .unwrap_or_default();
+                    // the full path when `paths` has the trait (`["core", "fmt", "Display"]`), else as written
+                    let trait_ty = inner.get("trait").and_then(|t| {
+                        t.get("id").and_then(|id| doc.path_of(id)).map(|p| p.join("::"))
+                            .or_else(|| t.get("path").or_else(|| t.get("name")).and_then(Value::as_str).map(str::to_string))
+                    });
+                    let self_ty_args = inner.get("for").and_then(resolved_path).map(path_args).unwrap_or_default();
+                    let trait_args = inner.get("trait").map(path_args).unwrap_or_default();
+
+                    for m in inner.get("items").and_then(Value::as_array).into_iter().flatten() {
+                        let Some(mi) = doc.item(m) else { continue; };
+                        if !matches!(item_kind(mi), Some(("function" | "method", _))) { continue; }
+                        let Some(mname) = mi.get("name").and_then(Value::as_str) else { continue; };
+                        let fq = doc.fq(&module_path, Some(self_ty), mname);
+                        functions.insert(fq.clone(), doc.function(fq, mi));
+                    }
+                    impls.push(ImplFinger { self_ty: self_ty.to_string(), self_ty_args, trait_ty, trait_args, module_path, location: loc });
+                }
+                "use" | "import" if is_public(item) => {
+                    let Some(source) = inner.get("source").and_then(Value::as_str) else { continue; };
+                    let glob = inner.get("is_glob").or_else(|| inner.get("glob")).and_then(Value::as_bool) == Some(true);
+                    let exported_as = if glob { "*".to_string() } else {
+                        inner.get("name").and_then(Value::as_str).unwrap_or_default().to_string()
+                    };
+                    let source_path = if glob { format!("{}::*", source) } else { source.to_string() };
+                    exports.push(ExportFinger { exported_as, source_path, location: loc });
+                }
+                _ => {}
+            }
+        }
+
+        types.sort_by(|a, b| a.fq_name.cmp(&b.fq_name));
+        Ok(Snapshot {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            crate_name: doc.crate_name,
+            no_std,
+            functions: functions.into_values().collect(),
+            exports,
+            imports: Vec::new(),
+            types,
+            impls,
+            findings: None,
+            files: BTreeMap::new(),
+            import_summary: None,
+        })
+    }
+
+    /// Writes the snapshot as a minimal rustdoc-shaped JSON document (root
+    /// module, `index` and `paths`), enough for path/kind based tooling and for
+    /// `from_rustdoc_value` to read back. Signatures are not translated; impls
+    /// are written with their methods as functions under the type, and generic
+    /// arguments as `generic` types holding klepto's text.
+    pub fn to_rustdoc_json(&self) -> Value {
+        let mut index = Map::new();
+        let mut paths = Map::new();
+        let mut root_items = Vec::new();
+        let mut next = 1u64;
+
+        let span = |l: &FileLocation| json!({
+            "filename": l.path,
+            "begin": [l.line.unwrap_or(0), l.column.unwrap_or(0)],
+            "end": [l.line.unwrap_or(0), l.column.unwrap_or(0)],
+        });
+        // `crate::a::B` -> ["<crate>", "a", "B"]
+        let rustdoc_path = |fq: &str| -> Vec<String> {
+            let mut p: Vec<String> = fq.split("::").map(str::to_string).collect();
+            if let Some(first) = p.first_mut() { *first = self.crate_name.clone(); }
+            p
+        };
+
+        let mut add = |name: &str, public: bool, kind: &str, inner: Value, loc: &FileLocation, path: Option<Vec<String>>| {
+            let id = next.to_string();
+            next += 1;
+            index.insert(id.clone(), json!({
+                "id": id,
+                "crate_id": 0,
+                "name": name,
+                "span": span(loc),
+                "visibility": if public { "public" } else { "default" },
+                "docs": null,
+                "attrs": [],
+                "inner": { kind: inner },
+            }));
+            if let Some(p) = path {
+                paths.insert(id.clone(), json!({ "crate_id": 0, "path": p, "kind": kind }));
+            }
+            id
+        };
+
+        for t in &self.types {
+            let kind = match t.kind {
+                TypeKind::Struct => "struct",
+                TypeKind::Enum => "enum",
+                TypeKind::Union => "union",
+                TypeKind::Trait => "trait",
+                TypeKind::TypeAlias => "type_alias",
+            };
+            let name = t.fq_name.rsplit("::").next().unwrap_or(&t.fq_name);
+            root_items.push(add(name, t.is_public, kind, json!({}), &t.location, Some(rustdoc_path(&t.fq_name))));
+        }
+        for f in &self.functions {
+            let name = f.fq_name.rsplit("::").next().unwrap_or(&f.fq_name);
+            let inner = json!({ "sig": Value::Null, "klepto_signature": f.signature });
+            root_items.push(add(name, f.is_public.unwrap_or(true), "function", inner, &f.location, Some(rustdoc_path(&f.fq_name))));
+        }
+        for e in &self.exports {
+            let inner = json!({ "source": e.source_path.trim_end_matches("::*"), "name": e.exported_as, "is_glob": e.exported_as == "*" });
+            root_items.push(add(&e.exported_as, true, "use", inner, &e.location, None));
+        }
+        let args = |args: &[String]| json!({
+            "angle_bracketed": { "args": args.iter().map(|a| json!({ "type": { "generic": a } })).collect::<Vec<_>>(), "constraints": [] },
+        });
+        // the self type's path carries the impl's modules back (see `from_rustdoc_value`)
+        let mut self_ty_paths = Vec::new();
+        for (n, i) in self.impls.iter().enumerate() {
+            let ty_id = format!("for-{}", n);
+            let mut ty_path = vec![self.crate_name.clone()];
+            ty_path.extend(file_module_path(&i.location.path));
+            ty_path.extend(i.module_path.iter().cloned());
+            ty_path.push(i.self_ty.clone());
+            self_ty_paths.push((ty_id.clone(), ty_path));
+            let inner = json!({
+                "for": { "resolved_path": { "path": i.self_ty, "id": ty_id, "args": args(&i.self_ty_args) } },
+                "trait": i.trait_ty.as_ref().map(|t| json!({ "path": t, "id": null, "args": args(&i.trait_args) })),
+                "items": [],
+                "is_synthetic": false,
+                "blanket_impl": null,
+            });
+            add("", true, "impl", inner, &i.location, None);
+        }
+        for (id, path) in self_ty_paths {
+            paths.insert(id, json!({ "crate_id": 0, "path": path, "kind": "struct" }));
+        }
+
+        let root = "0".to_string();
+        index.insert(root.clone(), json!({
+            "id": root,
+            "crate_id": 0,
+            "name": self.crate_name,
+            "visibility": "public",
+            "attrs": if self.no_std { json!(["#![no_std]"]) } else { json!([]) },
+            "inner": { "module": { "is_crate": true, "items": root_items } },
+        }));
+        paths.insert(root.clone(), json!({ "crate_id": 0, "path": [self.crate_name], "kind": "module" }));
+
+        json!({
+            "root": root,
+            "crate_version": null,
+            "includes_private": true,
+            "index": index,
+            "paths": paths,
+            "external_crates": {},
+            "format_version": RUSTDOC_FORMAT_VERSION,
+        })
+    }
+}
+
+/// Public API of a dependency, read from its rustdoc JSON.
+///
+/// rustdoc leaves `#[doc(hidden)]` items out unless asked, so generate it with
+/// `-Z unstable-options --output-format json --document-hidden-items`;
+/// otherwise nothing is known to be hidden.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepApi {
+    pub crate_name: String,
+    /// Every public path into the crate (`de::Visitor`, `Value::as_str`, crate name
+    /// left off), `true` when the item or anything above it is `#[doc(hidden)]`.
+    pub items: BTreeMap<String, bool>,
+}
+
+/// How a path into a dependency relates to its documented API.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DepItemVisibility {
+    Public,
+    Hidden,
+    /// Not in the rustdoc output: private, generated, or a variant/field rustdoc wasn't asked about.
+    Unknown,
+}
+
+// deep enough for any real re-export chain; stops `pub use self::*` cycles
+const MAX_DEPTH: usize = 16;
+
+impl DepApi {
+    pub fn from_rustdoc_json(path: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        let value: Value = serde_json::from_slice(&bytes).map_err(KleptoError::SnapshotFormat)?;
+        Self::from_rustdoc_value(&value)
+    }
+
+    pub fn from_rustdoc_value(value: &Value) -> Result<Self, KleptoError> {
+        let bad = |what: &str| KleptoError::RustdocFormat(format!("missing `{}`", what));
+        let index = value.get("index").and_then(Value::as_object).ok_or_else(|| bad("index"))?;
+        let root = value.get("root").ok_or_else(|| bad("root"))?;
+        let root_item = index.get(&id_key(root)).ok_or_else(|| bad("root item"))?;
+        let crate_name = root_item.get("name").and_then(Value::as_str).ok_or_else(|| bad("root item name"))?;
+
+        let mut api = DepApi { crate_name: crate_name.replace('-', "_"), items: BTreeMap::new() };
+        api.walk_module(index, root_item, &[], false, 0);
+        Ok(api)
+    }
+
+    fn record(&mut self, path: &[String], hidden: bool) {
+        let hidden_before = self.items.entry(path.join("::")).or_insert(hidden);
+        // reachable through a documented path wins over a hidden one
+        *hidden_before &= hidden;
+    }
+
+    fn walk_module(&mut self, index: &Map<String, Value>, module: &Value, prefix: &[String], hidden: bool, depth: usize) {
+        if depth > MAX_DEPTH { return; }
+        let Some((_, inner)) = item_kind(module) else { return; };
+        for id in inner.get("items").and_then(Value::as_array).into_iter().flatten() {
+            let Some(item) = index.get(&id_key(id)) else { continue; };
+            let Some((kind, inner)) = item_kind(item) else { continue; };
+            if !is_public(item) { continue; }
+            let hidden = hidden || has_attr(item, "doc(hidden)");
+
+            if matches!(kind, "use" | "import") {
+                let target = inner.get("id").filter(|t| !t.is_null()).and_then(|t| index.get(&id_key(t)));
+                let glob = inner.get("is_glob").or_else(|| inner.get("glob")).and_then(Value::as_bool) == Some(true);
+                match (target, glob) {
+                    (Some(t), true) => self.walk_module(index, t, prefix, hidden, depth + 1),
+                    (Some(t), false) => {
+                        let Some(name) = inner.get("name").and_then(Value::as_str) else { continue; };
+                        self.walk_item(index, t, prefix, name, hidden, depth + 1);
+                    }
+                    // re-export of another crate's item: only the name is known
+                    (None, false) => {
+                        let Some(name) = inner.get("name").and_then(Value::as_str) else { continue; };
+                        self.record(&[prefix, &[name.to_string()]].concat(), hidden);
+                    }
+                    (None, true) => {}
+                }
+                continue;
+            }
+            let Some(name) = item.get("name").and_then(Value::as_str) else { continue; };
+            self.walk_item(index, item, prefix, name, hidden, depth);
+        }
+    }
+
+    fn walk_item(&mut self, index: &Map<String, Value>, item: &Value, prefix: &[String], name: &str, hidden: bool, depth: usize) {
+        let Some((kind, inner)) = item_kind(item) else { return; };
+        let hidden = hidden || has_attr(item, "doc(hidden)");
+        let path = [prefix, &[name.to_string()]].concat();
+        self.record(&path, hidden);
+
+        let member = |id: &Value| index.get(&id_key(id)).and_then(|m| Some((m, m.get("name")?.as_str()?)));
+        match kind {
+            "module" => self.walk_module(index, item, &path, hidden, depth + 1),
+            // trait items and variants are as public as their parent
+            "trait" => {
+                for (m, mname) in inner.get("items").and_then(Value::as_array).into_iter().flatten().filter_map(member) {
+                    self.record(&[&path[..], &[mname.to_string()]].concat(), hidden || has_attr(m, "doc(hidden)"));
+                }
+            }
+            "struct" | "enum" | "union" => {
+                for (v, vname) in inner.get("variants").and_then(Value::as_array).into_iter().flatten().filter_map(member) {
+                    self.record(&[&path[..], &[vname.to_string()]].concat(), hidden || has_attr(v, "doc(hidden)"));
+                }
+                // inherent methods; trait methods are reached through the trait
+                for imp in inner.get("impls").and_then(Value::as_array).into_iter().flatten().filter_map(|i| index.get(&id_key(i))) {
+                    let Some((_, imp_inner)) = item_kind(imp) else { continue; };
+                    if imp_inner.get("trait").is_some_and(|t| !t.is_null()) { continue; }
+                    let imp_hidden = hidden || has_attr(imp, "doc(hidden)");
+                    for (m, mname) in imp_inner.get("items").and_then(Value::as_array).into_iter().flatten().filter_map(member) {
+                        if !is_public(m) { continue; }
+                        self.record(&[&path[..], &[mname.to_string()]].concat(), imp_hidden || has_attr(m, "doc(hidden)"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Visibility of `path` (segments after the crate name). Anything under a
+    /// hidden item is hidden; a path below a known public one (say a field or
+    /// an undocumented variant) isn't known.
+    pub fn visibility<S: AsRef<str>>(&self, path: &[S]) -> DepItemVisibility {
+        let mut key = String::new();
+        for (i, seg) in path.iter().enumerate() {
+            if i > 0 { key.push_str("::"); }
+            key.push_str(seg.as_ref());
+            match self.items.get(&key) {
+                Some(true) => return DepItemVisibility::Hidden,
+                Some(false) if i + 1 == path.len() => return DepItemVisibility::Public,
+                _ => {}
+            }
+        }
+        DepItemVisibility::Unknown
+    }
+}
+
+/// Where scanned code reaches into a dependency with a loaded `DepApi`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepItemUse {
+    pub dep: String,
+    /// Path inside the dependency, crate name left off (`__private::de::Content`).
+    pub path: String,
+    pub visibility: DepItemVisibility,
+    pub kind: DepItemUseKind,
+    /// Reached through a `use` of the dependency in the same module (which is reported itself).
+    pub via_import: bool,
+    pub location: FileLocation,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DepItemUseKind {
+    Import,
+    Path,
+    Call,
+    Macro,
+}
+
+impl Klepto {
+    /// Imports, paths, calls and macro invocations that resolve into a dependency
+    /// loaded with `KleptoBuilder::dependency_api`. Bare names resolve through the
+    /// `use`s of the same module; method calls and glob-imported names don't resolve.
+    pub fn dep_item_uses(&self) -> Vec<DepItemUse> {
+        let apis = &self.dependency_apis;
+        if apis.is_empty() { return Vec::new(); }
+
+        // (file, module, bound name) -> (dep, segments inside it)
+        let mut bindings: HashMap<(&Path, &ModulePath, &str), (&str, Vec<&str>)> = HashMap::new();
+        let mut out = Vec::new();
+        for imp in &self.imports {
+            let dep = self.dependency_package(imp.root.trim_start_matches("::"));
+            let Some(api) = apis.get(dep) else { continue; };
+            let segs: Vec<&str> = imp.segments.iter().map(|s| s.as_str()).filter(|s| *s != "self").collect();
+            if imp.kind == UseKind::Glob {
+                let segs = &segs[..segs.len().saturating_sub(1)];
+                if !segs.is_empty() {
+                    out.push(dep_use(api, segs, DepItemUseKind::Import, false, &imp.location));
+                }
+                continue;
+            }
+            let bound = match &imp.kind {
+                UseKind::Rename { alias } => alias.as_str(),
+                _ => segs.last().copied().unwrap_or(dep),
+            };
+            if !segs.is_empty() {
+                out.push(dep_use(api, &segs, DepItemUseKind::Import, false, &imp.location));
+            }
+            // a bare `use dep;` binds nothing the crate name doesn't already
+            if !segs.is_empty() || matches!(imp.kind, UseKind::Rename { .. }) {
+                bindings.insert((&imp.location.path, &imp.module_path, bound), (dep, segs));
+            }
+        }
+
+        let mut resolve = |text: &str, module: &ModulePath, loc: &FileLocation, kind: DepItemUseKind| {
+            let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+            if text.contains('<') { return; }
+            let text = text.strip_prefix("::").unwrap_or(&text);
+            let mut segs = text.split("::");
+            let Some(first) = segs.next() else { return; };
+            let rest: Vec<&str> = segs.collect();
+            if let Some((dep, base)) = bindings.get(&(&*loc.path, module, first)) {
+                let full: Vec<&str> = base.iter().copied().chain(rest.iter().copied()).collect();
+                if !full.is_empty() {
+                    out.push(dep_use(&apis[*dep], &full, kind, true, loc));
+                }
+            } else if let Some(api) = apis.get(self.dependency_package(first)) && !rest.is_empty() {
+                out.push(dep_use(api, &rest, kind, false, loc));
+            }
+        };
+        for p in &self.paths {
+            resolve(&p.path, &p.module_path, &p.location, DepItemUseKind::Path);
+        }
+        for c in self.calls.iter().filter(|c| c.receiver.is_none()) {
+            resolve(&c.callee, &c.module_path, &c.location, DepItemUseKind::Call);
+        }
+        for m in &self.macros_inv {
+            resolve(m.path.as_deref().unwrap_or(&m.name), &m.module_path, &m.location, DepItemUseKind::Macro);
+        }
+        out
+    }
+}
+
+fn dep_use(api: &DepApi, segs: &[&str], kind: DepItemUseKind, via_import: bool, loc: &FileLocation) -> DepItemUse {
+    DepItemUse {
+        dep: api.crate_name.clone(),
+        path: segs.join("::"),
+        visibility: api.visibility(segs),
+        kind,
+        via_import,
+        location: loc.clone(),
+    }
+}
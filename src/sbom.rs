@@ -0,0 +1,96 @@
+//! CycloneDX-flavored dependency usage document: every external crate the scan
+//! sees, with evidence of where it's actually used.
+//!
+//! Names are klepto's normalized package names (`serde_json`, never `serde-json`;
+//! renamed dependencies under their package) and carry no versions, so the purls are `pkg:cargo/<name>`; join on name with
+//! a lockfile-based SBOM to get exact packages.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{ImportOrigin, TargetKind, UseSite};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Occurrences listed per component; `klepto:use_sites` has the full count.
+pub const MAX_EVIDENCE: usize = 10;
+
+impl Klepto {
+    /// CycloneDX 1.5 JSON: one `library` component per dependency (declared
+    /// `normal_dependencies` and crates `use`d as dependencies), with use-site counts
+    /// as properties and up to `MAX_EVIDENCE` locations as `evidence.occurrences`.
+    /// Crates used only from tests/examples/benches, or not at all, are `optional`.
+    pub fn dependency_usage_bom(&self) -> Result<String, KleptoError> {
+        // `UnknownExternal` is a guess (`use submodule::X` looks the same); only
+        // trust it when the manifest's dependencies aren't known
+        let guess = self.normal_dependencies.is_empty();
+        let mut deps: BTreeSet<String> = self.normal_dependencies.clone();
+        deps.extend(self.imports.iter()
+            .filter(|i| match i.origin {
+                Some(ImportOrigin::Dependency | ImportOrigin::WorkspaceMember) => true,
+                Some(ImportOrigin::UnknownExternal) => guess,
+                _ => false,
+            })
+            .map(|i| i.root.trim_start_matches("::").replace('-', "_")));
+        deps.remove(&self.crate_name.replace('-', "_"));
+
+        let mut sites: BTreeMap<String, Vec<UseSite>> = deps.iter().map(|d| (d.clone(), Vec::new())).collect();
+        for s in self.dep_use_sites(&deps) {
+            sites.entry(s.dep.replace('-', "_")).or_default().push(s);
+        }
+
+        let components: Vec<serde_json::Value> = sites.iter().map(|(dep, sites)| {
+            let mut kinds: BTreeMap<String, usize> = BTreeMap::new();
+            let mut targets: BTreeSet<&str> = BTreeSet::new();
+            for s in sites {
+                *kinds.entry(format!("{:?}", s.kind)).or_default() += 1;
+                targets.insert(TargetKind::of_path(&s.location.path).as_str());
+            }
+            let runtime = sites.iter().any(|s| !TargetKind::of_path(&s.location.path).is_dev());
+            let mut properties = vec![
+                json!({ "name": "klepto:use_sites", "value": sites.len().to_string() }),
+                json!({ "name": "klepto:targets", "value": targets.into_iter().collect::<Vec<_>>().join(",") }),
+            ];
+            properties.extend(kinds.iter().map(|(k, n)| json!({ "name": format!("klepto:use_sites:{}", k), "value": n.to_string() })));
+            let occurrences: Vec<serde_json::Value> = sites.iter().take(MAX_EVIDENCE)
+                .map(|s| json!({
+                    "location": format!("{}:{}", s.location.path.portable(), s.location.line.unwrap_or(0)),
+                    "additionalContext": s.path,
+                }))
+                .collect();
+
+            let package = self.dependency_package(dep);
+            if package != dep {
+                properties.push(json!({ "name": "klepto:imported_as", "value": dep }));
+            }
+            let purl = format!("pkg:cargo/{}", package);
+            let mut c = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": package,
+                "purl": purl,
+                "scope": if runtime { "required" } else { "optional" },
+                "properties": properties,
+            });
+            if !occurrences.is_empty() {
+                c["evidence"] = json!({ "occurrences": occurrences });
+            }
+            c
+        }).collect();
+
+        let root = format!("pkg:cargo/{}", self.crate_name);
+        let bom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "tools": { "components": [{ "type": "application", "name": "klepto", "version": env!("CARGO_PKG_VERSION") }] },
+                "component": { "type": "library", "bom-ref": root, "name": self.crate_name, "purl": root },
+            },
+            "components": components,
+            "dependencies": [{
+                "ref": root,
+                "dependsOn": sites.keys().map(|d| format!("pkg:cargo/{}", self.dependency_package(d))).collect::<Vec<_>>(),
+            }],
+        });
+        serde_json::to_string_pretty(&bom).map_err(KleptoError::Json)
+    }
+}
@@ -0,0 +1,50 @@
+//! JSON Schemas for klepto's serialized output, for validating it or generating
+//! bindings in other languages.
+
+use crate::churn::FnChurn;
+use crate::config::Config;
+use crate::coverage::DocCoverageReport;
+use crate::diff::KleptoDiff;
+use crate::doc_links::BrokenDocLink;
+use crate::examples::ExampleCoverage;
+use crate::edition::EditionIdiom;
+use crate::dsl::QueryResults;
+use crate::features::{FeatureGate, FeatureInventory};
+use crate::fix::{ApplyReport, Fix};
+use crate::graph::{CallGraph, DependencyGraph, ModuleGraph};
+use crate::imports_ext::ImportSummary;
+use crate::metrics::{MetricsDiff, MetricsSnapshot};
+use crate::model::*;
+use crate::msrv::{MsrvTable, MsrvViolation};
+use crate::panics::PanicReachability;
+use crate::report::Report;
+use crate::rustdoc::{DepApi, DepItemUse};
+use crate::snapshot::{FileChanges, Snapshot, SnapshotDiff};
+use crate::stats::WorkspaceStats;
+use crate::test_map::TestMap;
+use crate::traits::TraitImpl;
+use crate::watch::WatchEvent;
+use crate::xref::Xref;
+use schemars::{JsonSchema, Schema, schema_for};
+use std::collections::BTreeMap;
+
+/// Schema of `T` (any klepto type that serializes).
+pub fn of<T: JsonSchema>() -> Schema {
+    schema_for!(T)
+}
+
+/// Schemas of the top-level serialized types, keyed by type name.
+pub fn all() -> BTreeMap<&'static str, Schema> {
+    macro_rules! schemas {
+        ($($t:ident),* $(,)?) => { BTreeMap::from([$((stringify!($t), of::<$t>())),*]) };
+    }
+    schemas![
+        Snapshot, SnapshotDiff, FileChanges, Finding, Report,
+        CapturedFn, CapturedType, CapturedImpl, StolenPath, ExportedSymbol,
+        MacroDef, MacroInvocation, PathOccurrence, CallOccurrence, UseSite,
+        DocCoverageReport, ImportSummary, FeatureInventory, TraitImpl, Xref,
+        CallGraph, ModuleGraph, DependencyGraph, KleptoDiff, MetricsSnapshot, MetricsDiff,
+        Config, QueryResults, WatchEvent, DepApi, DepItemUse, FnChurn, WorkspaceStats, Fix, ApplyReport,
+        BrokenDocLink, MsrvTable, MsrvViolation, EditionIdiom, PanicReachability, FeatureGate, TestMap, ExampleCoverage,
+    ]
+}
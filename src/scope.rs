@@ -0,0 +1,196 @@
+//! What every pass over a file's items shares: spans to locations, the module
+//! stack, impl/trait/test context, and how a function is named and classified
+//! there. `extract` and `index::Builder` both walk with a `Scope`, so they agree
+//! on `fq_name`s, kinds and publicness.
+
+use crate::extract::{is_test_only, self_ty_parts};
+use crate::model::{FileLocation, FnKind};
+use proc_macro2::Span;
+
+pub(crate) fn span_to_location(path: &std::path::Path, span: Span) -> FileLocation {
+    #[cfg(feature = "span-locations")]
+    {
+        let start = span.start();
+        FileLocation {
+            path: path.into(),
+            line: Some(start.line as u32),
+            column: Some(start.column as u32),
+            byte_range: Some(span.byte_range()),
+        }
+    }
+    #[cfg(not(feature = "span-locations"))]
+    {
+        let _ = span;
+        FileLocation {
+            path: path.into(),
+            line: None,
+            column: None,
+            byte_range: None,
+        }
+    }
+}
+
+/// (start, end) as (line, col); None without `span-locations`.
+pub(crate) type SpanBounds = (Option<(u32, u32)>, Option<(u32, u32)>);
+
+pub(crate) fn span_start_end(span: Span) -> SpanBounds {
+    #[cfg(feature = "span-locations")]
+    {
+        let s = span.start();
+        let e = span.end();
+        (Some((s.line as u32, s.column as u32)), Some((e.line as u32, e.column as u32)))
+    }
+    #[cfg(not(feature = "span-locations"))]
+    {
+        let _ = span;
+        (None, None)
+    }
+}
+
+pub(crate) fn span_bytes(span: Span) -> Option<std::ops::Range<usize>> {
+    #[cfg(feature = "span-locations")]
+    { Some(span.byte_range()) }
+    #[cfg(not(feature = "span-locations"))]
+    { let _ = span; None }
+}
+
+pub(crate) fn vis_is_public(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+/// Segment idents joined with `::`; generic arguments are dropped.
+pub(crate) fn path_to_string(p: &syn::Path) -> String {
+    p.segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// `crate::inline::mods::Name`, with the impl's type or the trait before a method's name.
+pub(crate) fn fq_name(crate_name: &str, module_path: &[String], kind: &FnKind, name: &str) -> String {
+    let mut parts = Vec::new();
+    parts.push(crate_name.to_string());
+    parts.extend(module_path.iter().cloned());
+    match kind {
+        FnKind::FreeFn => {}
+        FnKind::TraitMethod { trait_name } => parts.push(trait_name.clone()),
+        FnKind::ImplMethod { self_ty, .. } => parts.push(self_ty.clone()),
+    }
+    parts.push(name.to_string());
+    parts.join("::")
+}
+
+/// A function as its scope names it.
+pub(crate) struct FnInfo {
+    pub kind: FnKind,
+    pub fq_name: String,
+    /// Declared `pub`; trait methods are as public as their trait.
+    pub is_public: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Scope {
+    crate_name: String,
+    mod_stack: Vec<String>,
+    impl_self_ty: Option<String>,
+    impl_trait_ty: Option<String>,
+    in_trait: Option<String>,
+    trait_is_public: bool,
+    in_test: bool,
+}
+
+/// What `Scope::leave` restores.
+#[must_use]
+pub(crate) struct Saved {
+    pushed_mod: bool,
+    impl_self_ty: Option<String>,
+    impl_trait_ty: Option<String>,
+    in_trait: Option<String>,
+    trait_is_public: bool,
+    in_test: bool,
+}
+
+impl Scope {
+    pub fn new(crate_name: &str) -> Self {
+        Scope { crate_name: crate_name.to_string(), ..Default::default() }
+    }
+
+    /// Inline modules around the current item.
+    pub fn mod_stack(&self) -> &[String] { &self.mod_stack }
+
+    /// Inside `#[cfg(test)]` / `#[test]` code.
+    pub fn in_test(&self) -> bool { self.in_test }
+
+    // clears the impl/trait context: whatever is entered next holds free items
+    fn save(&mut self, pushed_mod: bool, attrs: &[syn::Attribute]) -> Saved {
+        let saved = Saved {
+            pushed_mod,
+            impl_self_ty: self.impl_self_ty.take(),
+            impl_trait_ty: self.impl_trait_ty.take(),
+            in_trait: self.in_trait.take(),
+            trait_is_public: std::mem::take(&mut self.trait_is_public),
+            in_test: self.in_test,
+        };
+        self.in_test |= is_test_only(attrs);
+        saved
+    }
+
+    /// An inline module's items (call only for `mod m { .. }`, not `mod m;`).
+    pub fn enter_mod(&mut self, m: &syn::ItemMod) -> Saved {
+        let saved = self.save(true, &m.attrs);
+        self.mod_stack.push(m.ident.to_string());
+        saved
+    }
+
+    pub fn enter_impl(&mut self, i: &syn::ItemImpl) -> Saved {
+        let saved = self.save(false, &i.attrs);
+        self.impl_self_ty = Some(self_ty_parts(&i.self_ty).0);
+        self.impl_trait_ty = i.trait_.as_ref().map(|(_, p, _)| path_to_string(p));
+        saved
+    }
+
+    pub fn enter_trait(&mut self, t: &syn::ItemTrait) -> Saved {
+        let saved = self.save(false, &t.attrs);
+        self.in_trait = Some(t.ident.to_string());
+        self.trait_is_public = vis_is_public(&t.vis);
+        saved
+    }
+
+    /// A function's body; items declared in it are free items again.
+    pub fn enter_fn(&mut self, attrs: &[syn::Attribute]) -> Saved {
+        self.save(false, attrs)
+    }
+
+    pub fn leave(&mut self, s: Saved) {
+        if s.pushed_mod { self.mod_stack.pop(); }
+        self.impl_self_ty = s.impl_self_ty;
+        self.impl_trait_ty = s.impl_trait_ty;
+        self.in_trait = s.in_trait;
+        self.trait_is_public = s.trait_is_public;
+        self.in_test = s.in_test;
+    }
+
+    fn info(&self, kind: FnKind, name: &str, is_public: bool) -> FnInfo {
+        FnInfo { fq_name: fq_name(&self.crate_name, &self.mod_stack, &kind, name), kind, is_public }
+    }
+
+    pub fn free_fn(&self, name: &str, vis: &syn::Visibility) -> FnInfo {
+        self.info(FnKind::FreeFn, name, vis_is_public(vis))
+    }
+
+    /// A method of the impl entered last.
+    pub fn impl_fn(&self, name: &str, vis: &syn::Visibility) -> FnInfo {
+        let kind = FnKind::ImplMethod {
+            self_ty: self.impl_self_ty.clone().unwrap_or_else(|| "<impl>".into()),
+            trait_ty: self.impl_trait_ty.clone(),
+        };
+        self.info(kind, name, vis_is_public(vis))
+    }
+
+    /// A method of the trait entered last.
+    pub fn trait_fn(&self, name: &str) -> FnInfo {
+        let kind = FnKind::TraitMethod { trait_name: self.in_trait.clone().unwrap_or_else(|| "<trait>".into()) };
+        self.info(kind, name, self.trait_is_public)
+    }
+}
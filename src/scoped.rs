@@ -0,0 +1,73 @@
+//! Sub-views of a scan: one module's subtree or the files matching a glob, so
+//! module owners can run queries, rules and snapshots over just their area.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::IStr;
+use crate::extract::file_module_path;
+use globset::Glob;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+impl Klepto {
+    /// The part of this scan declared in `module` (`"crate::api"`, `"api"` or
+    /// `"<crate>::api"`) and its submodules.
+    ///
+    /// A file holding the module inline is kept whole as a `ParsedFile`, so
+    /// analyses walking its AST (the unused-imports rule, `dep_use_sites`) still
+    /// see the items around it.
+    pub fn scoped_to_module(&self, module: &str) -> Klepto {
+        let mut target: Vec<&str> = module.split("::").filter(|s| !s.is_empty()).collect();
+        if matches!(target.first(), Some(&r) if r == "crate" || r == self.crate_name) {
+            target.remove(0);
+        }
+        self.scoped(|path, inline| {
+            let mut full = file_module_path(path);
+            full.extend(inline.iter().map(|s| s.to_string()));
+            full.len() >= target.len() && full.iter().zip(&target).all(|(a, b)| a == b)
+        })
+    }
+
+    /// The part of this scan in files matching `glob`, matched against the
+    /// stored (`/`-separated) path.
+    pub fn scoped_to_files(&self, glob: &str) -> Result<Klepto, KleptoError> {
+        let m = Glob::new(glob)?.compile_matcher();
+        Ok(self.scoped(|path, _| m.is_match(&*crate::intern::portable_path(path))))
+    }
+
+    // keeps what `keep(file, inline module path)` accepts, and the files it's in
+    fn scoped(&self, keep: impl Fn(&Path, &[IStr]) -> bool) -> Klepto {
+        let mut k = self.clone();
+        k.functions.retain(|x| keep(&x.location.path, &x.module_path));
+        k.types.retain(|x| keep(&x.location.path, &x.module_path));
+        k.impls.retain(|x| keep(&x.location.path, &x.module_path));
+        k.imports.retain(|x| keep(&x.location.path, &x.module_path));
+        k.exports.retain(|x| keep(&x.location.path, &x.module_path));
+        // a `mod` item belongs where it leads, so `mod api;` comes along with `api`
+        k.modules.retain(|m| {
+            let inner: Vec<IStr> = m.module_path.iter().cloned().chain([IStr::from(m.name.as_str())]).collect();
+            keep(&m.location.path, &inner)
+        });
+        k.macros_def.retain(|x| keep(&x.location.path, &x.module_path));
+        k.macros_inv.retain(|x| keep(&x.location.path, &x.module_path));
+        k.paths.retain(|x| keep(&x.location.path, &x.module_path));
+        k.calls.retain(|x| keep(&x.location.path, &x.module_path));
+
+        let mut files: HashSet<PathBuf> = k.files.iter()
+            .filter(|pf| keep(&pf.path, &[]))
+            .map(|pf| pf.path.clone())
+            .collect();
+        files.extend(k.functions.iter().map(|x| x.location.path.to_path_buf()));
+        files.extend(k.types.iter().map(|x| x.location.path.to_path_buf()));
+        files.extend(k.impls.iter().map(|x| x.location.path.to_path_buf()));
+        files.extend(k.imports.iter().map(|x| x.location.path.to_path_buf()));
+        files.extend(k.macros_inv.iter().map(|x| x.location.path.to_path_buf()));
+        k.files.retain(|pf| files.contains(&pf.path));
+        k.index.retain_files(|p| files.contains(p));
+        k.skipped_files.retain(|p| keep(p, &[]));
+        k.no_std_detected = k.files.iter().any(|pf| pf.is_no_std_crate_root);
+
+        k.file_table = Default::default();
+        k.rebuild_tables();
+        k
+    }
+}
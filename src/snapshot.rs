@@ -1,18 +1,26 @@
+use crate::extract::file_module_path;
 use crate::model::*;
-use crate::klepto::Klepto;
+use crate::imports_ext::ImportSummary;
+use crate::klepto::{Klepto, KleptoBuilder, KleptoError, ParsedFile};
 use blake3::Hasher;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct FnFinger {
     pub fq_name: String,
     pub sig_hash: String,
     pub signature: String,
     pub location: FileLocation,
+    // reachable as API: `pub` itself, and for methods, on a public type/trait; None = unknown
+    #[serde(default)]
+    pub is_public: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ExportFinger {
     pub exported_as: String,
     pub source_path: String,
@@ -20,40 +28,328 @@ pub struct ExportFinger {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TypeFinger {
+    pub fq_name: String,
+    pub kind: TypeKind,
+    pub is_public: bool,
+    pub location: FileLocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ImplFinger {
+    pub self_ty: String,
+    #[serde(default)]
+    pub self_ty_args: Vec<String>,
+    pub trait_ty: Option<String>,
+    #[serde(default)]
+    pub trait_args: Vec<String>,
+    pub module_path: Vec<String>,
+    pub location: FileLocation,
+}
+
+/// A finding as recorded in a snapshot baseline. `extra` is dropped: it's
+/// free-form and not needed to match findings across runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FindingFinger {
+    pub code: RuleCode,
+    pub severity: Severity,
+    pub message: String,
+    pub location: FileLocation,
+}
+
+impl FindingFinger {
+    // line numbers shift with unrelated edits, so a finding is identified by rule, file and message
+    fn key(&self) -> (&str, &Path, &str) {
+        (self.code.as_str(), self.location.path.as_path(), self.message.as_str())
+    }
+}
+
+impl From<&Finding> for FindingFinger {
+    fn from(f: &Finding) -> Self {
+        FindingFinger { code: f.code.clone(), severity: f.severity, message: f.message.clone(), location: f.location.clone() }
+    }
+}
+
+impl ImplFinger {
+    // `Trait<A> for Type<B>` / `Type<B>` (inherent), the type qualified by the
+    // file's module and the inline ones, so `From<u8>` and `From<u16>` impls, or
+    // same-named types in two files, don't share a key
+    fn key(&self) -> String {
+        let mut ty = file_module_path(&self.location.path);
+        ty.extend(self.module_path.iter().cloned());
+        ty.push(with_args(&self.self_ty, &self.self_ty_args));
+        match &self.trait_ty {
+            // `fmt::Display` vs `std::fmt::Display` is the same impl; compare by trait name
+            Some(t) => format!("{} for {}", with_args(&trait_key(t), &self.trait_args), ty.join("::")),
+            None => ty.join("::"),
+        }
+    }
+}
+
+// std traits by their `std::` path (`core::fmt::Display` is `std::fmt::Display`),
+// so a crate's own `Error` isn't `std::error::Error`; other traits by name, as
+// rustdoc and the source spell their paths differently
+fn trait_key(path: &str) -> String {
+    let path = path.trim().trim_start_matches("::");
+    match path.split_once("::") {
+        Some(("std" | "core" | "alloc", rest)) => format!("std::{}", rest),
+        _ => path.rsplit("::").next().unwrap_or(path).to_string(),
+    }
+}
+
+// the trait an impl names, through the `use`s of its module or the prelude:
+// `fmt::Display` after `use std::fmt` is `std::fmt::Display`, a bare `From` is
+// `std::convert::From`; as written when neither applies
+fn resolved_trait_path(k: &Klepto, i: &CapturedImpl, trait_ty: &str) -> String {
+    let (first, rest) = match trait_ty.split_once("::") {
+        Some((f, r)) => (f, Some(r)),
+        None => (trait_ty, None),
+    };
+    let bound = k.imports_in_file(i.location.path.as_path())
+        .filter(|u| u.module_path == i.module_path && u.enclosing_fn.is_none())
+        .find(|u| match &u.kind {
+            UseKind::Glob => false,
+            UseKind::Rename { alias } => alias == first,
+            UseKind::Name => u.segments.last().unwrap_or(&u.root) == first,
+        });
+    if let Some(u) = bound {
+        let mut full = std::iter::once(&u.root).chain(&u.segments).cloned().collect::<Vec<_>>().join("::");
+        if let Some(r) = rest {
+            full.push_str("::");
+            full.push_str(r);
+        }
+        return full;
+    }
+    let local = k.types.iter().any(|t| t.kind == TypeKind::Trait && t.name == trait_ty);
+    match prelude_trait(trait_ty) {
+        Some(path) if !local => path,
+        _ => trait_ty.to_string(),
+    }
+}
+
+fn prelude_trait(name: &str) -> Option<String> {
+    crate::rules::builtin::PRELUDE_ITEMS.iter()
+        .find(|(n, _)| *n == name)
+        .map(|(n, homes)| format!("std::{}::{}", homes[0], n))
+}
+
+// `Name<A, B>`, or `Name` without arguments
+fn with_args(name: &str, args: &[String]) -> String {
+    if args.is_empty() { name.to_string() } else { format!("{}<{}>", name, args.join(", ")) }
+}
+
+/// Current `Snapshot` schema. Bump it (and add a migration step) whenever the
+/// serialized shape changes.
+///
+/// - 1: functions, exports, imports (no `schema_version` field)
+/// - 2: + types, impls
+/// - 3: + optional findings baseline
+/// - 4: + per-file content hashes
+/// - 5: + byte ranges on locations
+/// - 6: + function visibility
+/// - 7: + optional import summary
+/// - 8: + generic arguments on impls
+/// - 9: impl trait paths resolved through imports and the prelude
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 9;
+
+fn schema_v1() -> u32 { 1 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Snapshot {
+    #[serde(default = "schema_v1")]
+    pub schema_version: u32,
     pub crate_name: String,
     pub no_std: bool,
     pub functions: Vec<FnFinger>,
     pub exports: Vec<ExportFinger>,
     pub imports: Vec<String>, // full paths
+    #[serde(default)]
+    pub types: Vec<TypeFinger>,
+    #[serde(default)]
+    pub impls: Vec<ImplFinger>,
+    /// Findings at snapshot time, when recorded with `with_findings`.
+    #[serde(default)]
+    pub findings: Option<Vec<FindingFinger>>,
+    /// blake3 of each scanned file's contents.
+    #[serde(default, with = "crate::intern::portable_keys")]
+    #[cfg_attr(feature = "schema", schemars(with = "BTreeMap<PathBuf, String>"))]
+    pub files: BTreeMap<PathBuf, String>,
+    /// Import counts at snapshot time, when recorded with `with_import_summary`.
+    #[serde(default)]
+    pub import_summary: Option<ImportSummary>,
+}
+
+/// Files whose contents differ between a snapshot and the current tree.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileChanges {
+    #[serde(with = "crate::intern::portable_list")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<PathBuf>"))]
+    pub added: Vec<PathBuf>,
+    #[serde(with = "crate::intern::portable_list")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<PathBuf>"))]
+    pub removed: Vec<PathBuf>,
+    #[serde(with = "crate::intern::portable_list")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<PathBuf>"))]
+    pub modified: Vec<PathBuf>,
+}
+
+impl FileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Added + modified: the files that need re-analysis.
+    pub fn to_reparse(&self) -> impl Iterator<Item = &PathBuf> {
+        self.added.iter().chain(&self.modified)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SnapshotDiff {
     pub added_functions: Vec<FnFinger>,
     pub removed_functions: Vec<FnFinger>,
     pub changed_signatures: Vec<(FnFinger, FnFinger)>, // (old,new)
+    /// Same `fq_name` and signature, different file. A function whose `fq_name`
+    /// changed is a removal plus an addition.
+    #[serde(default)]
+    pub moved_functions: Vec<(FnFinger, FnFinger)>, // (old,new)
 
     pub added_exports: Vec<ExportFinger>,
     pub removed_exports: Vec<ExportFinger>,
 
     pub added_imports: Vec<String>,
     pub removed_imports: Vec<String>,
+
+    #[serde(default)]
+    pub added_types: Vec<TypeFinger>,
+    #[serde(default)]
+    pub removed_types: Vec<TypeFinger>,
+    #[serde(default)]
+    pub changed_types: Vec<(TypeFinger, TypeFinger)>, // (old,new): kind or visibility changed
+
+    #[serde(default)]
+    pub added_impls: Vec<ImplFinger>,
+    #[serde(default)]
+    pub removed_impls: Vec<ImplFinger>,
+
+    // only populated when both snapshots carry a findings baseline
+    #[serde(default)]
+    pub new_findings: Vec<FindingFinger>,
+    #[serde(default)]
+    pub resolved_findings: Vec<FindingFinger>,
+}
+
+// binary layout: magic, schema version (u32 LE), zstd(bincode(Snapshot)).
+// bincode isn't self-describing, so old binary versions can't be migrated; re-save as JSON.
+#[cfg(feature = "binary-snapshots")]
+const BINARY_MAGIC: &[u8] = b"KLEPSNAP";
+
+#[cfg(feature = "binary-snapshots")]
+impl Snapshot {
+    pub fn to_binary_writer<W: std::io::Write>(&self, mut w: W) -> Result<(), KleptoError> {
+        w.write_all(BINARY_MAGIC).map_err(KleptoError::Stream)?;
+        w.write_all(&SNAPSHOT_SCHEMA_VERSION.to_le_bytes()).map_err(KleptoError::Stream)?;
+        let mut enc = zstd::Encoder::new(w, 0).map_err(KleptoError::Stream)?;
+        bincode::serialize_into(&mut enc, self).map_err(KleptoError::SnapshotBinary)?;
+        enc.finish().map_err(KleptoError::Stream)?.flush().map_err(KleptoError::Stream)
+    }
+
+    pub fn save_binary(&self, path: impl AsRef<Path>) -> Result<(), KleptoError> {
+        let path = path.as_ref();
+        let f = std::fs::File::create(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        self.to_binary_writer(std::io::BufWriter::new(f))
+    }
+
+    fn from_binary(bytes: &[u8]) -> Result<Self, KleptoError> {
+        let rest = &bytes[BINARY_MAGIC.len()..];
+        let (version, body) = rest.split_at_checked(4).ok_or_else(|| {
+            KleptoError::Stream(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+        })?;
+        let found = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+        if found != SNAPSHOT_SCHEMA_VERSION {
+            return Err(KleptoError::SnapshotVersion { found, supported: SNAPSHOT_SCHEMA_VERSION });
+        }
+        let dec = zstd::Decoder::new(body).map_err(KleptoError::Stream)?;
+        bincode::deserialize_from(dec).map_err(KleptoError::SnapshotBinary)
+    }
+}
+
+// upgrades a snapshot value from `from` to `from + 1`
+fn migrate_step(value: &mut serde_json::Map<String, serde_json::Value>, from: u32) {
+    if from == 1 {
+        for key in ["types", "impls"] {
+            value.entry(key).or_insert_with(|| serde_json::json!([]));
+        }
+    }
+    // 2 -> 3: `findings` is optional; absent means "no baseline recorded"
+    // 3 -> 4: `files` defaults to empty, so every file reads as added
+    // 4 -> 5: `byte_range` defaults to None
+    // 5 -> 6: `is_public` on functions defaults to None (treated as public)
+    // 6 -> 7: `import_summary` is optional
+    // 7 -> 8: `self_ty_args`/`trait_args` on impls default to empty
+    // 8 -> 9: bare prelude trait names get their std path; the rest needs the imports
+    if from == 8 {
+        let impls = value.get_mut("impls").and_then(serde_json::Value::as_array_mut).into_iter().flatten();
+        for t in impls.filter_map(|i| i.get_mut("trait_ty")) {
+            if let Some(path) = t.as_str().and_then(prelude_trait) {
+                *t = serde_json::json!(path);
+            }
+        }
+    }
 }
 
 fn hash_sig(s: &str) -> String {
+    hash_bytes(s.as_bytes())
+}
+
+/// blake3 of each scanned file's contents, keyed by path.
+pub(crate) fn file_hashes(k: &Klepto) -> BTreeMap<PathBuf, String> {
+    k.files.iter().map(|f| (f.path.clone(), f.content_hash.clone())).collect()
+}
+
+pub(crate) fn file_changes_between(old: &BTreeMap<PathBuf, String>, current: &BTreeMap<PathBuf, String>) -> FileChanges {
+    let mut changes = FileChanges::default();
+    for (path, hash) in current {
+        match old.get(path) {
+            None => changes.added.push(path.clone()),
+            Some(o) if o != hash => changes.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = old.keys().filter(|p| !current.contains_key(*p)).cloned().collect();
+    changes
+}
+
+pub(crate) fn hash_bytes(b: &[u8]) -> String {
     let mut h = Hasher::new();
-    h.update(s.as_bytes());
+    h.update(b);
     h.finalize().to_hex().to_string()
 }
 
 impl Snapshot {
     pub fn from_klepto(k: &Klepto) -> Self {
+        // methods are API when their type/trait is; unknown (external) types count as public
+        let type_public = |name: &str| {
+            let name = name.split('<').next().unwrap_or(name).trim();
+            let mut found = k.types.iter().filter(|t| t.name == name).peekable();
+            found.peek().is_none() || found.any(|t| t.is_public)
+        };
         let functions = k.functions.iter().map(|f| FnFinger {
             fq_name: f.fq_name.clone(),
             sig_hash: hash_sig(&f.signature),
             signature: f.signature.clone(),
             location: f.location.clone(),
+            is_public: Some(match &f.kind {
+                FnKind::FreeFn => f.is_public,
+                FnKind::ImplMethod { self_ty, trait_ty } => (f.is_public || trait_ty.is_some()) && type_public(self_ty),
+                FnKind::TraitMethod { trait_name } => type_public(trait_name),
+            }),
         }).collect();
 
         let exports = k.exports.iter().map(|e| ExportFinger {
@@ -63,45 +359,253 @@ impl Snapshot {
         }).collect();
 
         let imports = {
-            let mut v: Vec<String> = k.imports.iter().map(|i| i.full_path.clone()).collect();
+            let mut v: Vec<String> = k.imports.iter().filter(|i| !i.expanded_from_glob).map(|i| i.full_path.clone()).collect();
             v.sort();
             v.dedup();
             v
         };
 
-        Snapshot { crate_name: k.crate_name.clone(), no_std: k.no_std_detected, functions, exports, imports }
+        let types = k.types.iter().map(|t| TypeFinger {
+            fq_name: t.fq_name.clone(),
+            kind: t.kind,
+            is_public: t.is_public,
+            location: t.location.clone(),
+        }).collect();
+
+        let impls = k.impls.iter().map(|i| ImplFinger {
+            self_ty: i.self_ty.clone(),
+            self_ty_args: i.self_ty_args.clone(),
+            trait_ty: i.trait_ty.as_deref().map(|t| resolved_trait_path(k, i, t)),
+            trait_args: i.trait_args.clone(),
+            module_path: i.module_path.to_vec(),
+            location: i.location.clone(),
+        }).collect();
+
+        let files = file_hashes(k);
+
+        Snapshot { schema_version: SNAPSHOT_SCHEMA_VERSION, crate_name: k.crate_name.clone(), no_std: k.no_std_detected, functions, exports, imports, types, impls, findings: None, files, import_summary: None }
+    }
+
+    /// Compares the recorded file hashes against what's on disk now; paths are
+    /// resolved against `root` (use the `relative_to` root, or `""` for absolute
+    /// paths). Only a fresh scan can see new files, so `added` is always empty here;
+    /// see `Klepto::changed_files_since`.
+    pub fn changed_files(&self, root: impl AsRef<Path>) -> Result<FileChanges, KleptoError> {
+        let root = root.as_ref();
+        let mut changes = FileChanges::default();
+        for (path, hash) in &self.files {
+            let full = root.join(path);
+            match std::fs::read(&full) {
+                Ok(bytes) if hash_bytes(&bytes) == *hash => {}
+                Ok(_) => changes.modified.push(path.clone()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => changes.removed.push(path.clone()),
+                Err(e) => return Err(KleptoError::Io { path: full, source: e }),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// File-level changes between this snapshot (old) and a set of current hashes.
+    pub fn file_changes(&self, current: &BTreeMap<PathBuf, String>) -> FileChanges {
+        file_changes_between(&self.files, current)
+    }
+
+    /// Review-sized snapshot for API-stability tracking: public functions and
+    /// types, impls on/of them, and external imports only.
+    pub fn public_api_only(k: &Klepto) -> Self {
+        let mut s = Self::from_klepto(k);
+        let vis = &k.effective_visibility;
+        let public_fns: BTreeSet<&str> = k.functions.iter().filter(|f| vis.fn_public(f)).map(|f| f.fq_name.as_str()).collect();
+        s.functions.retain(|f| public_fns.contains(f.fq_name.as_str()));
+        let public_type_fqs: BTreeSet<&str> = k.types.iter().filter(|t| vis.type_public(t)).map(|t| t.fq_name.as_str()).collect();
+        s.types.retain(|t| public_type_fqs.contains(t.fq_name.as_str()));
+
+        let public_types: BTreeSet<&str> = k.types.iter().filter(|t| vis.type_public(t)).map(|t| t.name.as_str()).collect();
+        let base = |ty: &str| ty.split('<').next().unwrap_or(ty).trim().rsplit("::").next().unwrap_or("").trim().to_string();
+        s.impls.retain(|i| {
+            public_types.contains(base(&i.self_ty).as_str())
+                || i.trait_ty.as_deref().is_some_and(|t| public_types.contains(base(t).as_str()))
+        });
+
+        let mut imports: Vec<String> = k.imports.iter().filter(|i| !i.is_internal && !i.expanded_from_glob).map(|i| i.full_path.clone()).collect();
+        imports.sort();
+        imports.dedup();
+        s.imports = imports;
+        s
+    }
+
+    /// Rewrites every location path under `root` to be relative to it, so
+    /// snapshots taken on different machines/checkouts compare cleanly.
+    /// Paths outside `root` are left as-is.
+    pub fn relative_to(mut self, root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        self.for_each_location_mut(|l| {
+            if let Ok(rel) = l.path.strip_prefix(root) {
+                l.path = rel.into();
+            }
+        });
+        self.files = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(|(path, hash)| match path.strip_prefix(root) {
+                Ok(rel) => (rel.to_path_buf(), hash),
+                Err(_) => (path, hash),
+            })
+            .collect();
+        self
+    }
+
+    /// Drops all location info (path, line, column) and the file hashes, for
+    /// diffs that should only care about names and signatures.
+    pub fn without_locations(mut self) -> Self {
+        self.files.clear();
+        self.for_each_location_mut(|l| {
+            l.path = FilePath::default();
+            l.line = None;
+            l.column = None;
+            l.byte_range = None;
+        });
+        self
+    }
+
+    fn for_each_location_mut(&mut self, mut f: impl FnMut(&mut FileLocation)) {
+        self.functions.iter_mut().for_each(|x| f(&mut x.location));
+        self.exports.iter_mut().for_each(|x| f(&mut x.location));
+        self.types.iter_mut().for_each(|x| f(&mut x.location));
+        self.impls.iter_mut().for_each(|x| f(&mut x.location));
+        if let Some(findings) = &mut self.findings {
+            findings.iter_mut().for_each(|x| f(&mut x.location));
+        }
+    }
+
+    /// Embeds `findings` as the baseline, so `diff` also reports new/resolved findings.
+    pub fn with_findings(mut self, findings: &[Finding]) -> Self {
+        self.findings = Some(findings.iter().map(FindingFinger::from).collect());
+        self
+    }
+
+    /// Embeds import counts (usually `k.import_summary()`).
+    pub fn with_import_summary(mut self, summary: ImportSummary) -> Self {
+        self.import_summary = Some(summary);
+        self
+    }
+
+    pub fn to_json_string(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::SnapshotFormat)
+    }
+
+    /// Parses a snapshot of any known schema version, migrating older ones forward.
+    pub fn from_json_str(s: &str) -> Result<Self, KleptoError> {
+        let value: serde_json::Value = serde_json::from_str(s).map_err(KleptoError::SnapshotFormat)?;
+        Self::from_json_value(value)
+    }
+
+    /// Reads a JSON snapshot, or a binary one (detected by its header) when the
+    /// `binary-snapshots` feature is enabled.
+    pub fn from_reader<R: std::io::Read>(mut r: R) -> Result<Self, KleptoError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes).map_err(KleptoError::Stream)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KleptoError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KleptoError> {
+        #[cfg(feature = "binary-snapshots")]
+        if bytes.starts_with(BINARY_MAGIC) {
+            return Self::from_binary(bytes);
+        }
+        let value: serde_json::Value = serde_json::from_slice(bytes).map_err(KleptoError::SnapshotFormat)?;
+        Self::from_json_value(value)
+    }
+
+    pub fn to_writer<W: std::io::Write>(&self, mut w: W) -> Result<(), KleptoError> {
+        serde_json::to_writer_pretty(&mut w, self).map_err(KleptoError::SnapshotFormat)?;
+        w.flush().map_err(KleptoError::Stream)
     }
 
-    pub fn to_json_string(&self) -> String {
-        serde_json::to_string_pretty(self).unwrap()
+    /// Writes pretty JSON to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KleptoError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json_string()?).map_err(|e| KleptoError::Io { path: path.to_path_buf(), source: e })
+    }
+
+    pub fn from_json_value(mut value: serde_json::Value) -> Result<Self, KleptoError> {
+        use serde::de::Error as _;
+        let format_error = |msg: &str| KleptoError::SnapshotFormat(serde_json::Error::custom(msg));
+
+        let Some(map) = value.as_object_mut() else {
+            return Err(format_error("expected a JSON object"));
+        };
+        // absent in version 1
+        let found = match map.get("schema_version") {
+            None => 1,
+            Some(v) => v.as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| format_error("`schema_version` is not a version number"))?,
+        };
+        if found > SNAPSHOT_SCHEMA_VERSION || found == 0 {
+            return Err(KleptoError::SnapshotVersion { found, supported: SNAPSHOT_SCHEMA_VERSION });
+        }
+
+        let mut version = found;
+        while version < SNAPSHOT_SCHEMA_VERSION {
+            migrate_step(map, version);
+            version += 1;
+        }
+        map.insert("schema_version".to_string(), serde_json::json!(SNAPSHOT_SCHEMA_VERSION));
+
+        serde_json::from_value(value).map_err(KleptoError::SnapshotFormat)
     }
 
     pub fn diff(&self, old: &Snapshot) -> SnapshotDiff {
-        let mut old_map: BTreeMap<String, &FnFinger> = BTreeMap::new();
-        for f in &old.functions { old_map.insert(f.fq_name.clone(), f); }
+        // an fq_name can name several fns (`X::from` of `From<u8>` and `From<u16>`)
+        let mut old_map: BTreeMap<&str, Vec<&FnFinger>> = BTreeMap::new();
+        for f in &old.functions { old_map.entry(f.fq_name.as_str()).or_default().push(f); }
 
-        let mut new_map: BTreeMap<String, &FnFinger> = BTreeMap::new();
-        for f in &self.functions { new_map.insert(f.fq_name.clone(), f); }
+        let mut new_map: BTreeMap<&str, Vec<&FnFinger>> = BTreeMap::new();
+        for f in &self.functions { new_map.entry(f.fq_name.as_str()).or_default().push(f); }
 
         let mut added_functions = Vec::new();
         let mut removed_functions = Vec::new();
         let mut changed_signatures = Vec::new();
+        let mut moved_functions = Vec::new();
 
-        for (k, nf) in &new_map {
-            match old_map.get(k) {
-                None => added_functions.push((*nf).clone()),
-                Some(of) => {
-                    if of.sig_hash != nf.sig_hash {
-                        changed_signatures.push(((*of).clone(), (*nf).clone()));
+        // empty hash = signature unknown (rustdoc import)
+        let same_sig = |a: &FnFinger, b: &FnFinger| a.sig_hash == b.sig_hash || a.sig_hash.is_empty() || b.sig_hash.is_empty();
+        for (k, news) in &new_map {
+            let mut olds = old_map.get(k).cloned().unwrap_or_default();
+            // same signatures pair up first, the rest in order
+            let mut unpaired = Vec::new();
+            for nf in news {
+                match olds.iter().position(|of| same_sig(of, nf)) {
+                    Some(i) => {
+                        let of = olds.remove(i);
+                        if of.location.path != nf.location.path {
+                            moved_functions.push((of.clone(), (*nf).clone()));
+                        }
                     }
+                    None => unpaired.push(*nf),
+                }
+            }
+            let mut olds = olds.into_iter();
+            for nf in unpaired {
+                match olds.next() {
+                    Some(of) => changed_signatures.push((of.clone(), nf.clone())),
+                    None => added_functions.push(nf.clone()),
                 }
             }
+            removed_functions.extend(olds.cloned());
         }
-        for (k, of) in &old_map {
+        for (k, olds) in &old_map {
             if !new_map.contains_key(k) {
-                removed_functions.push((*of).clone());
+                removed_functions.extend(olds.iter().map(|of| (*of).clone()));
             }
         }
+        removed_functions.sort_by(|a, b| a.fq_name.cmp(&b.fq_name));
 
         let old_exports: BTreeSet<(String,String)> = old.exports.iter().map(|e| (e.exported_as.clone(), e.source_path.clone())).collect();
         let new_exports: BTreeSet<(String,String)> = self.exports.iter().map(|e| (e.exported_as.clone(), e.source_path.clone())).collect();
@@ -126,14 +630,593 @@ impl Snapshot {
         let added_imports = new_imports.difference(&old_imports).cloned().collect();
         let removed_imports = old_imports.difference(&new_imports).cloned().collect();
 
+        let old_types: BTreeMap<&str, &TypeFinger> = old.types.iter().map(|t| (t.fq_name.as_str(), t)).collect();
+        let new_types: BTreeMap<&str, &TypeFinger> = self.types.iter().map(|t| (t.fq_name.as_str(), t)).collect();
+
+        let mut added_types = Vec::new();
+        let mut changed_types = Vec::new();
+        for (name, nt) in &new_types {
+            match old_types.get(name) {
+                None => added_types.push((*nt).clone()),
+                Some(ot) if ot.kind != nt.kind || ot.is_public != nt.is_public => {
+                    changed_types.push(((*ot).clone(), (*nt).clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        let removed_types = old_types.iter()
+            .filter(|(name, _)| !new_types.contains_key(*name))
+            .map(|(_, t)| (*t).clone())
+            .collect();
+
+        let old_impls: BTreeSet<String> = old.impls.iter().map(|i| i.key()).collect();
+        let new_impls: BTreeSet<String> = self.impls.iter().map(|i| i.key()).collect();
+        let added_impls = self.impls.iter().filter(|i| !old_impls.contains(&i.key())).cloned().collect();
+        let removed_impls = old.impls.iter().filter(|i| !new_impls.contains(&i.key())).cloned().collect();
+
+        let (new_findings, resolved_findings) = match (&self.findings, &old.findings) {
+            (Some(new), Some(old)) => (findings_missing_from(new, old), findings_missing_from(old, new)),
+            _ => (Vec::new(), Vec::new()),
+        };
+
         SnapshotDiff {
             added_functions,
             removed_functions,
             changed_signatures,
+            moved_functions,
             added_exports,
             removed_exports,
             added_imports,
             removed_imports,
+            added_types,
+            removed_types,
+            changed_types,
+            added_impls,
+            removed_impls,
+            new_findings,
+            resolved_findings,
         }
     }
 }
+
+impl Klepto {
+    /// A `Klepto` over what a snapshot recorded, so a baseline answers the same
+    /// function, type, impl, export and import queries as a live scan.
+    ///
+    /// It's reduced: no sources, ASTs, modules, macros, paths or calls, so the
+    /// occurrence queries, use sites and most rules come back empty. Docs and
+    /// attributes aren't recorded; method kinds are inferred from the `fq_name`
+    /// (trait impls show as inherent); every import is a plain `Name` use at
+    /// module level; `files` keeps only the content hashes.
+    pub fn from_snapshot(s: &Snapshot) -> Klepto {
+        let type_kind = |fq: &str| s.types.iter().find(|t| t.fq_name == fq).map(|t| t.kind);
+
+        let functions = s.functions.iter().map(|f| {
+            let mut parts: Vec<String> = f.fq_name.split("::").map(str::to_string).collect();
+            let name = parts.pop().unwrap_or_default();
+            let parent = parts.join("::");
+            let owner = (parts.len() > 1).then(|| parts[parts.len() - 1].clone());
+            let has_impl = |ty: &str| s.impls.iter().any(|i| i.self_ty == ty && i.module_path[..] == parts[1..parts.len() - 1]);
+            let kind = match (owner, type_kind(&parent)) {
+                (Some(trait_name), Some(TypeKind::Trait)) => FnKind::TraitMethod { trait_name },
+                (Some(self_ty), Some(_)) => FnKind::ImplMethod { self_ty, trait_ty: None },
+                (Some(self_ty), None) if self_ty.starts_with(char::is_uppercase) || has_impl(&self_ty) => {
+                    FnKind::ImplMethod { self_ty, trait_ty: None }
+                }
+                _ => FnKind::FreeFn,
+            };
+            if !matches!(kind, FnKind::FreeFn) { parts.pop(); }
+            let sig = syn::parse_str::<syn::Signature>(&f.signature).ok();
+            CapturedFn {
+                name,
+                fq_name: f.fq_name.clone(),
+                is_public: f.is_public.unwrap_or(true),
+                has_docs: false,
+                is_async: sig.as_ref().is_some_and(|s| s.asyncness.is_some()),
+                is_unsafe: sig.as_ref().is_some_and(|s| s.unsafety.is_some()),
+                is_const: sig.as_ref().is_some_and(|s| s.constness.is_some()),
+                is_generic: sig.as_ref().is_some_and(|s| !s.generics.params.is_empty()),
+                args: sig.as_ref().map(crate::extract::fn_args).unwrap_or_default(),
+                return_ty: sig.as_ref().and_then(crate::extract::fn_return),
+                kind,
+                module_path: parts.get(1..).unwrap_or_default().into(),
+                attrs: Vec::new(),
+                signature: f.signature.clone(),
+                location: f.location.clone(),
+                doc: None,
+                is_test_code: false,
+            }
+        }).collect();
+
+        let types = s.types.iter().map(|t| {
+            let parts: Vec<String> = t.fq_name.split("::").map(str::to_string).collect();
+            CapturedType {
+                name: parts.last().cloned().unwrap_or_default(),
+                fq_name: t.fq_name.clone(),
+                kind: t.kind,
+                is_public: t.is_public,
+                has_docs: false,
+                is_generic: false,
+                module_path: parts.get(1..parts.len().saturating_sub(1)).unwrap_or_default().into(),
+                attrs: Vec::new(),
+                location: t.location.clone(),
+                doc: None,
+                derives: Vec::new(),
+                is_test_code: false,
+            }
+        }).collect();
+
+        let impls = s.impls.iter().map(|i| CapturedImpl {
+            self_ty: i.self_ty.clone(),
+            self_ty_args: i.self_ty_args.clone(),
+            trait_ty: i.trait_ty.clone(),
+            trait_args: i.trait_args.clone(),
+            is_unsafe: false,
+            is_generic: false,
+            module_path: i.module_path.clone().into(),
+            location: i.location.clone(),
+            is_test_code: false,
+        }).collect();
+
+        let exports = s.exports.iter().map(|e| ExportedSymbol {
+            exported_as: e.exported_as.clone(),
+            source_path: e.source_path.clone(),
+            module_path: ModulePath::default(),
+            location: e.location.clone(),
+            resolved_target: None,
+        }).collect();
+
+        let mut imports: Vec<StolenPath> = s.imports.iter().map(|full| {
+            let mut segments: Vec<String> = full.split("::").map(str::to_string).collect();
+            let root = segments.remove(0);
+            StolenPath {
+                is_internal: matches!(root.as_str(), "crate" | "self" | "super"),
+                root,
+                segments,
+                module_path: ModulePath::default(),
+                is_public_use: false,
+                kind: UseKind::Name,
+                full_path: full.clone(),
+                location: FileLocation { path: PathBuf::new().into(), line: None, column: None, byte_range: None },
+                origin: None,
+                is_absolute: None,
+                is_test_code: false,
+                enclosing_fn: None,
+                expanded_from_glob: false,
+            }
+        }).collect();
+        crate::klepto::classify_imports(&mut imports, &Default::default(), &Default::default(), &Default::default());
+
+        let files = s.files.iter().map(|(path, hash)| ParsedFile {
+            id: FileId(0), // assigned by rebuild_tables
+            path: path.clone(),
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            source: String::new(),
+            content_hash: hash.clone(),
+            ast_key: Default::default(),
+            is_no_std_crate_root: false,
+            edition: None,
+        }).collect();
+
+        let mut k = Klepto {
+            crate_name: s.crate_name.clone(),
+            root: None,
+            files,
+            file_table: Default::default(),
+            functions,
+            types,
+            impls,
+            imports,
+            exports,
+            modules: Vec::new(),
+            macros_def: Vec::new(),
+            macros_inv: Vec::new(),
+            paths: Vec::new(),
+            calls: Vec::new(),
+            no_std_detected: s.no_std,
+            index: Default::default(),
+            items: Default::default(),
+            occurrences: Default::default(),
+            effective_visibility: Default::default(),
+            normal_dependencies: Default::default(),
+            declared_features: None,
+            dependency_renames: Default::default(),
+            dependency_apis: Default::default(),
+            code_owners: None,
+            skipped_files: Vec::new(),
+        };
+        k.rebuild_tables();
+        k
+    }
+}
+
+// findings in `a` without a counterpart in `b`; duplicates are matched one-for-one
+pub(crate) fn findings_missing_from(a: &[FindingFinger], b: &[FindingFinger]) -> Vec<FindingFinger> {
+    let mut remaining: BTreeMap<_, usize> = BTreeMap::new();
+    for f in b {
+        *remaining.entry(f.key()).or_default() += 1;
+    }
+    a.iter()
+        .filter(|f| match remaining.get_mut(&f.key()) {
+            Some(n) if *n > 0 => { *n -= 1; false }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Exports `git_ref` of the repository at `repo` into a fresh temp directory
+/// (via `git archive | tar -x`); the caller removes it.
+fn checkout_git_ref(repo: &Path, git_ref: &str) -> Result<std::path::PathBuf, KleptoError> {
+    use std::process::{Command, Stdio};
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let dir = std::env::temp_dir().join(format!("klepto-{}-{}", std::process::id(), nanos));
+    std::fs::create_dir_all(&dir).map_err(|e| KleptoError::Io { path: dir.clone(), source: e })?;
+
+    let mut git = Command::new("git")
+        .arg("-C").arg(repo)
+        .args(["archive", "--format=tar", git_ref])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| KleptoError::Git(format!("failed to run git: {}", e)))?;
+    // drained alongside tar: a full stderr pipe would stall git and the pipeline with it
+    let stderr = git.stderr.take().map(|mut e| std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = std::io::Read::read_to_end(&mut e, &mut buf);
+        buf
+    }));
+    let tar = Command::new("tar")
+        .arg("-x").arg("-C").arg(&dir)
+        .stdin(git.stdout.take().map(Stdio::from).unwrap_or_else(Stdio::null))
+        .stderr(Stdio::null())
+        .status();
+    let status = git.wait().map_err(KleptoError::Stream)?;
+    let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    let tar = tar.map_err(|e| KleptoError::Git(format!("failed to run tar: {}", e)))?;
+
+    if !status.success() || !tar.success() {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(KleptoError::Git(format!(
+            "git archive {} failed: {}",
+            git_ref,
+            String::from_utf8_lossy(&stderr).trim()
+        )));
+    }
+    Ok(dir)
+}
+
+/// Snapshot of the workspace at `git_ref`, scanned with the builder produced by `configure`.
+/// Locations are relative to the checkout root.
+pub fn snapshot_git_ref<F>(workspace: impl AsRef<Path>, git_ref: &str, configure: F) -> Result<Snapshot, KleptoError>
+where
+    F: Fn(&Path) -> Result<KleptoBuilder, KleptoError>,
+{
+    let dir = checkout_git_ref(workspace.as_ref(), git_ref)?;
+    let snap = configure(&dir).and_then(|b| b.parse()).map(|k| k.snapshot().relative_to(&dir));
+    let _ = std::fs::remove_dir_all(&dir);
+    snap
+}
+
+/// Diffs the current workspace against `git_ref` (e.g. `"origin/main"`): the usual CI gate.
+/// Both sides are scanned with `scan_workspace_root` + `exclude_generated`, under the
+/// current workspace's crate name (see `workspace_crate_name`).
+pub fn diff_against_git_ref(workspace: impl AsRef<Path>, git_ref: &str) -> Result<SnapshotDiff, KleptoError> {
+    let crate_name = workspace_crate_name(workspace.as_ref())?;
+    diff_against_git_ref_with(workspace, git_ref, |root| {
+        Klepto::new(crate_name.as_str()).scan_workspace_root(root)?.exclude_generated()
+    })
+}
+
+/// The root package's name as code spells it (`my-crate` -> `my_crate`), from
+/// `cargo metadata`; a virtual workspace falls back to its directory's name.
+pub fn workspace_crate_name(workspace: &Path) -> Result<String, KleptoError> {
+    let meta = cargo_metadata::MetadataCommand::new().manifest_path(workspace.join("Cargo.toml")).no_deps().exec()?;
+    let name = match meta.root_package() {
+        Some(p) => p.name.clone(),
+        None => meta.workspace_root.file_name().unwrap_or("crate").to_string(),
+    };
+    Ok(name.replace('-', "_"))
+}
+
+/// Like `diff_against_git_ref`, with a custom builder for both sides (`root` is the tree to scan).
+pub fn diff_against_git_ref_with<F>(workspace: impl AsRef<Path>, git_ref: &str, configure: F) -> Result<SnapshotDiff, KleptoError>
+where
+    F: Fn(&Path) -> Result<KleptoBuilder, KleptoError>,
+{
+    let workspace = workspace.as_ref();
+    let old = snapshot_git_ref(workspace, git_ref, &configure)?;
+    let new = configure(workspace)?.parse()?.snapshot().relative_to(workspace);
+    Ok(new.diff(&old))
+}
+
+fn fmt_loc(l: &FileLocation) -> String {
+    match l.line {
+        Some(line) => format!("{}:{}", l.path.display(), line),
+        None => l.path.display().to_string(),
+    }
+}
+
+// one rendered diff entry: (section, item, location, detail)
+type DiffRow = (&'static str, String, String, Option<String>);
+
+fn section_title(kind: &str) -> &'static str {
+    match kind {
+        "added fn" => "Added functions",
+        "removed fn" => "Removed functions",
+        "changed fn" => "Changed signatures",
+        "moved fn" => "Moved functions",
+        "added type" => "Added types",
+        "removed type" => "Removed types",
+        "changed type" => "Changed types",
+        "added impl" => "Added impls",
+        "removed impl" => "Removed impls",
+        "added export" => "Added exports",
+        "removed export" => "Removed exports",
+        "added import" => "Added imports",
+        "removed import" => "Removed imports",
+        "new finding" => "New findings",
+        "resolved finding" => "Resolved findings",
+        _ => "Other",
+    }
+}
+
+/// What a `SnapshotDiff` means for the crate's version number, least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum SemverImpact {
+    None,
+    Patch, // private or cosmetic changes only
+    Minor, // additive public API
+    Major, // something public went away or changed shape
+}
+
+fn fn_public(f: &FnFinger) -> bool { f.is_public != Some(false) }
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.rows().is_empty()
+    }
+
+    /// Human-readable reasons the diff breaks public API: public functions removed
+    /// or re-signed, public types removed, hidden or changed in kind, exports removed.
+    ///
+    /// Syntactic: moves between files, removed trait impls and changes inside
+    /// bodies aren't judged. A move that changed the `fq_name` (from older diffs)
+    /// counts as a removal.
+    pub fn breaking_changes(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        let renamed = self.moved_functions.iter().filter(|(o, n)| o.fq_name != n.fq_name).map(|(o, _)| o);
+        for f in self.removed_functions.iter().chain(renamed).filter(|f| fn_public(f)) {
+            out.push(format!("removed fn {}", f.fq_name));
+        }
+        for (o, n) in self.changed_signatures.iter().filter(|(o, _)| fn_public(o)) {
+            out.push(format!("changed fn {}: {} -> {}", n.fq_name, o.signature, n.signature));
+        }
+        for t in self.removed_types.iter().filter(|t| t.is_public) {
+            out.push(format!("removed type {}", t.fq_name));
+        }
+        for (o, n) in self.changed_types.iter().filter(|(o, _)| o.is_public) {
+            if !n.is_public {
+                out.push(format!("type {} is no longer public", n.fq_name));
+            } else if o.kind != n.kind {
+                out.push(format!("type {} changed from {:?} to {:?}", n.fq_name, o.kind, n.kind));
+            }
+        }
+        for e in &self.removed_exports {
+            out.push(format!("removed export {} ({})", e.exported_as, e.source_path));
+        }
+        out
+    }
+
+    pub fn semver_impact(&self) -> SemverImpact {
+        if !self.breaking_changes().is_empty() {
+            return SemverImpact::Major;
+        }
+        let additive = self.added_functions.iter().any(fn_public)
+            || self.added_types.iter().any(|t| t.is_public)
+            || self.changed_types.iter().any(|(o, n)| !o.is_public && n.is_public)
+            || !self.added_exports.is_empty()
+            || self.added_impls.iter().any(|i| i.trait_ty.is_some());
+        if additive {
+            SemverImpact::Minor
+        } else if self.is_empty() {
+            SemverImpact::None
+        } else {
+            SemverImpact::Patch
+        }
+    }
+
+    fn rows(&self) -> Vec<DiffRow> {
+        let mut rows: Vec<DiffRow> = Vec::new();
+        for f in &self.added_functions { rows.push(("added fn", f.fq_name.clone(), fmt_loc(&f.location), None)); }
+        for f in &self.removed_functions { rows.push(("removed fn", f.fq_name.clone(), fmt_loc(&f.location), None)); }
+        for (o, n) in &self.changed_signatures {
+            rows.push(("changed fn", n.fq_name.clone(), fmt_loc(&n.location), Some(format!("{} -> {}", o.signature, n.signature))));
+        }
+        for (o, n) in &self.moved_functions {
+            let from = if o.fq_name == n.fq_name { fmt_loc(&o.location) } else { format!("{} @ {}", o.fq_name, fmt_loc(&o.location)) };
+            rows.push(("moved fn", n.fq_name.clone(), fmt_loc(&n.location), Some(format!("from {}", from))));
+        }
+        for t in &self.added_types { rows.push(("added type", t.fq_name.clone(), fmt_loc(&t.location), None)); }
+        for t in &self.removed_types { rows.push(("removed type", t.fq_name.clone(), fmt_loc(&t.location), None)); }
+        for (o, n) in &self.changed_types {
+            let vis = |t: &TypeFinger| if t.is_public { "pub " } else { "" };
+            rows.push(("changed type", n.fq_name.clone(), fmt_loc(&n.location),
+                Some(format!("{}{:?} -> {}{:?}", vis(o), o.kind, vis(n), n.kind))));
+        }
+        for i in &self.added_impls { rows.push(("added impl", i.key(), fmt_loc(&i.location), None)); }
+        for i in &self.removed_impls { rows.push(("removed impl", i.key(), fmt_loc(&i.location), None)); }
+        for e in &self.added_exports { rows.push(("added export", e.exported_as.clone(), fmt_loc(&e.location), Some(e.source_path.clone()))); }
+        for e in &self.removed_exports { rows.push(("removed export", e.exported_as.clone(), fmt_loc(&e.location), Some(e.source_path.clone()))); }
+        for i in &self.added_imports { rows.push(("added import", i.clone(), String::new(), None)); }
+        for i in &self.removed_imports { rows.push(("removed import", i.clone(), String::new(), None)); }
+        for f in &self.new_findings { rows.push(("new finding", f.code.to_string(), fmt_loc(&f.location), Some(f.message.clone()))); }
+        for f in &self.resolved_findings { rows.push(("resolved finding", f.code.to_string(), fmt_loc(&f.location), Some(f.message.clone()))); }
+        rows
+    }
+
+    /// GitHub-flavored markdown: one section per non-empty change bucket.
+    pub fn to_markdown(&self) -> String {
+        let rows = self.rows();
+        let mut out = String::from("# Snapshot diff\n");
+        if rows.is_empty() {
+            out.push_str("\nNo changes.\n");
+            return out;
+        }
+
+        let mut section = "";
+        for (kind, item, loc, detail) in &rows {
+            if *kind != section {
+                section = kind;
+                let n = rows.iter().filter(|r| r.0 == section).count();
+                out.push_str(&format!("\n## {} ({})\n\n", section_title(section), n));
+            }
+            out.push_str(&format!("- `{}`", item));
+            if !loc.is_empty() {
+                out.push_str(&format!(" — {}", loc));
+            }
+            if let Some(d) = detail {
+                out.push_str(&format!("\n  `{}`", d));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Plain-text table, one line per change (same layout as `findings_to_table`).
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        out.push_str("CHANGE           ITEM                                     LOCATION\n");
+        out.push_str("---------------- ---------------------------------------- ------------------------------\n");
+        for (kind, item, loc, detail) in self.rows() {
+            out.push_str(&format!("{:<16} {:<40} {}", kind, item, loc));
+            if let Some(d) = detail {
+                out.push_str(&format!("  ({})", d));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scan(name: &str, lib: &str) -> Snapshot {
+        let dir = std::env::temp_dir().join(format!("klepto-snapshot-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+        let k = Klepto::new("mv").workspace_root(&dir).scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        k.snapshot()
+    }
+
+    fn finger(fq_name: &str, path: &str) -> FnFinger {
+        FnFinger {
+            fq_name: fq_name.to_string(),
+            sig_hash: "h".to_string(),
+            signature: "fn run()".to_string(),
+            location: FileLocation { path: PathBuf::from(path).into(), line: Some(1), column: Some(0), byte_range: None },
+            is_public: Some(true),
+        }
+    }
+
+    #[test]
+    fn non_object_json_is_a_format_error() {
+        for bad in [json!([]), json!("x"), json!(null), json!({ "schema_version": "7" }), json!({ "schema_version": 1u64 << 33 })] {
+            assert!(matches!(Snapshot::from_json_value(bad), Err(KleptoError::SnapshotFormat(_))));
+        }
+        assert!(matches!(
+            Snapshot::from_json_value(json!({ "schema_version": SNAPSHOT_SCHEMA_VERSION + 1 })),
+            Err(KleptoError::SnapshotVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn moving_a_pub_fn_between_modules_is_breaking() {
+        let old = scan("old", "pub mod a { pub fn run() {} }\npub mod b {}\n");
+        let new = scan("new", "pub mod a {}\npub mod b { pub fn run() {} }\n");
+        let d = new.diff(&old);
+        assert!(d.moved_functions.is_empty());
+        assert_eq!(d.breaking_changes(), vec!["removed fn mv::a::run".to_string()]);
+        assert_eq!(d.semver_impact(), SemverImpact::Major);
+    }
+
+    #[test]
+    fn renamed_move_from_an_older_diff_is_breaking() {
+        let mut d = scan("same", "pub fn run() {}\n").diff(&scan("same2", "pub fn run() {}\n"));
+        assert_eq!(d.semver_impact(), SemverImpact::None);
+        d.moved_functions.push((finger("mv::a::run", "src/a.rs"), finger("mv::b::run", "src/b.rs")));
+        assert_eq!(d.breaking_changes(), vec!["removed fn mv::a::run".to_string()]);
+        assert_eq!(d.semver_impact(), SemverImpact::Major);
+    }
+
+    #[test]
+    fn impls_of_one_trait_with_other_args_are_told_apart() {
+        let from = |ty: &str| format!("impl From<{ty}> for X {{ fn from(_: {ty}) -> X {{ X }} }}\n");
+        let old = scan("from-old", &format!("pub struct X;\n{}{}", from("u8"), from("u16")));
+        let new = scan("from-new", &format!("pub struct X;\n{}", from("u8")));
+        let d = new.diff(&old);
+        assert_eq!(d.removed_impls.len(), 1);
+        assert_eq!(d.removed_impls[0].trait_args, vec!["u16".to_string()]);
+        assert!(d.added_impls.is_empty());
+        assert!(d.changed_signatures.is_empty());
+        assert_eq!(d.removed_functions.len(), 1);
+        assert!(d.removed_functions[0].signature.contains("u16"));
+    }
+
+    #[test]
+    fn own_and_std_traits_of_one_name_are_told_apart() {
+        let lib = "use std::fmt;\npub trait Error {}\npub struct X;\nimpl Error for X {}\n\
+            impl std::error::Error for X {}\nimpl fmt::Display for X { fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result { Ok(()) } }\n\
+            impl fmt::Debug for X { fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result { Ok(()) } }\n";
+        let both = scan("err-both", lib);
+        let keys: BTreeSet<String> = both.impls.iter().map(ImplFinger::key).collect();
+        assert_eq!(keys.len(), 4);
+        assert!(keys.contains("std::fmt::Display for X"));
+
+        let own_only = scan("err-own", &lib.replace("impl std::error::Error for X {}\n", ""));
+        let d = own_only.diff(&both);
+        assert_eq!(d.removed_impls.iter().map(ImplFinger::key).collect::<Vec<_>>(), vec!["std::error::Error for X".to_string()]);
+
+        // and through rustdoc JSON and back
+        let back = Snapshot::from_rustdoc_value(&both.to_rustdoc_json()).unwrap();
+        let d = back.diff(&both);
+        assert!(d.added_impls.is_empty() && d.removed_impls.is_empty(), "{:?}", d);
+    }
+
+    #[test]
+    fn git_ref_diffs_use_the_packages_crate_name() {
+        let dir = std::env::temp_dir().join(format!("klepto-snapshot-git-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn kept() {}\n").unwrap();
+        let git = |args: &[&str]| {
+            let ok = std::process::Command::new("git").arg("-C").arg(&dir)
+                .args(["-c", "user.name=t", "-c", "user.email=t@t", "-c", "commit.gpgsign=false"])
+                .args(args).output().unwrap().status.success();
+            assert!(ok, "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        git(&["add", "-A"]);
+        git(&["commit", "-qm", "base"]);
+        std::fs::write(dir.join("src/lib.rs"), "pub fn kept() {}\npub fn added() {}\n").unwrap();
+
+        assert_eq!(workspace_crate_name(&dir).unwrap(), "my_crate");
+        let diff = diff_against_git_ref(&dir, "HEAD");
+        let missing = checkout_git_ref(&dir, "no-such-ref");
+        std::fs::remove_dir_all(&dir).unwrap();
+        let diff = diff.unwrap();
+        let added: Vec<&str> = diff.added_functions.iter().map(|f| f.fq_name.as_str()).collect();
+        assert_eq!(added, ["my_crate::added"]);
+        assert!(diff.removed_functions.is_empty());
+        assert!(matches!(missing, Err(KleptoError::Git(e)) if e.contains("no-such-ref")));
+    }
+}
@@ -0,0 +1,121 @@
+//! `WorkspaceStats`: the at-a-glance numbers of a scan in one struct.
+
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::{FnKind, ImportOrigin};
+use crate::unsafety::UnsafeCounts;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Function counts by kind and visibility.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FnCounts {
+    pub total: usize,
+    pub free: usize,
+    pub impl_methods: usize,
+    pub trait_methods: usize,
+    pub public: usize, // reachable from outside the crate (see `EffectiveVisibility::fn_public`)
+    pub test: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkspaceStats {
+    pub crate_name: String,
+    pub files: usize,
+    /// See `Klepto::skipped_files`.
+    pub files_skipped: usize,
+    /// Lines of the scanned files, blank and comment lines included; None when
+    /// sources weren't retained.
+    pub lines: Option<usize>,
+    pub fns: FnCounts,
+    pub types: usize,
+    pub impls: usize,
+    pub imports: usize,
+    pub imports_by_origin: BTreeMap<ImportOrigin, usize>,
+    pub macro_defs: usize,
+    pub macro_invocations: usize,
+    pub unsafe_counts: UnsafeCounts,
+}
+
+impl WorkspaceStats {
+    pub fn from_klepto(k: &Klepto) -> Self {
+        let mut fns = FnCounts { total: k.functions.len(), ..Default::default() };
+        for f in &k.functions {
+            match f.kind {
+                FnKind::FreeFn => fns.free += 1,
+                FnKind::ImplMethod { .. } => fns.impl_methods += 1,
+                FnKind::TraitMethod { .. } => fns.trait_methods += 1,
+            }
+            if k.effective_visibility.fn_public(f) { fns.public += 1; }
+            if f.is_test_code { fns.test += 1; }
+        }
+
+        let mut imports_by_origin = BTreeMap::new();
+        for o in k.imports.iter().filter(|i| !i.expanded_from_glob).filter_map(|i| i.origin.clone()) {
+            *imports_by_origin.entry(o).or_default() += 1;
+        }
+
+        // a file with no text left is one whose source was dropped (or is empty)
+        let lines = k.files.iter()
+            .map(|pf| (!pf.source.is_empty()).then(|| pf.source.lines().count()))
+            .sum::<Option<usize>>()
+            .filter(|_| !k.files.is_empty());
+
+        WorkspaceStats {
+            crate_name: k.crate_name.clone(),
+            files: k.files.len(),
+            files_skipped: k.skipped_files.len(),
+            lines,
+            fns,
+            types: k.types.len(),
+            impls: k.impls.len(),
+            imports: k.imports.iter().filter(|i| !i.expanded_from_glob).count(),
+            imports_by_origin,
+            macro_defs: k.macros_def.len(),
+            macro_invocations: k.macros_inv.len(),
+            unsafe_counts: k.unsafe_stats().total,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::new();
+        s.push_str(&format!("# Workspace Stats: `{}`\n\n| Metric | Count |\n|---|---:|\n", self.crate_name));
+        let mut row = |name: &str, n: String| s.push_str(&format!("| {} | {} |\n", name, n));
+        row("Files", self.files.to_string());
+        row("Files skipped", self.files_skipped.to_string());
+        row("Lines", self.lines.map_or_else(|| "-".into(), |n| n.to_string()));
+        row("Functions", self.fns.total.to_string());
+        row("&nbsp;&nbsp;free", self.fns.free.to_string());
+        row("&nbsp;&nbsp;impl methods", self.fns.impl_methods.to_string());
+        row("&nbsp;&nbsp;trait methods", self.fns.trait_methods.to_string());
+        row("&nbsp;&nbsp;public", self.fns.public.to_string());
+        row("&nbsp;&nbsp;test", self.fns.test.to_string());
+        row("Types", self.types.to_string());
+        row("Impls", self.impls.to_string());
+        row("Imports", self.imports.to_string());
+        for (o, n) in &self.imports_by_origin {
+            row(&format!("&nbsp;&nbsp;{:?}", o), n.to_string());
+        }
+        row("Macro definitions", self.macro_defs.to_string());
+        row("Macro invocations", self.macro_invocations.to_string());
+        row("Unsafe (fns/blocks/impls/traits)", format!(
+            "{} ({}/{}/{}/{})",
+            self.unsafe_counts.total(), self.unsafe_counts.fns, self.unsafe_counts.blocks,
+            self.unsafe_counts.impls, self.unsafe_counts.traits,
+        ));
+        s
+    }
+}
+
+impl Klepto {
+    /// Files, lines, functions, imports, macros and unsafe in one summary. Unsafe
+    /// blocks and traits come from the ASTs (see `ParsedFile::ast`).
+    pub fn stats(&self) -> WorkspaceStats {
+        WorkspaceStats::from_klepto(self)
+    }
+}
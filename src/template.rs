@@ -0,0 +1,56 @@
+use crate::imports_ext::{ImportSummary, ImportVecExt};
+use crate::klepto::{Klepto, KleptoError};
+use crate::metrics::ModuleCoupling;
+use crate::model::*;
+use crate::report::{Report, SeverityCounts};
+use crate::snapshot::SnapshotDiff;
+use serde::Serialize;
+
+/// Everything a report template can see.
+///
+/// Templates get `findings`, `summary` (severity counts), `doc_coverage`,
+/// `import_summary`, `snapshot_diff` and `coupling` (per-module fan-in/fan-out);
+/// the optional ones are `none` when unset.
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TemplateData {
+    pub crate_name: String,
+    pub findings: Vec<Finding>,
+    pub summary: SeverityCounts,
+    pub doc_coverage: Option<DocCoverage>,
+    pub import_summary: Option<ImportSummary>,
+    pub snapshot_diff: Option<SnapshotDiff>,
+    pub coupling: Vec<ModuleCoupling>,
+}
+
+impl TemplateData {
+    pub fn new(findings: Vec<Finding>) -> Self {
+        let report = Report::new(findings);
+        Self { summary: report.severity_counts(), findings: report.findings, ..Default::default() }
+    }
+
+    /// Findings plus the doc coverage and import summary of `k`.
+    pub fn from_klepto(k: &Klepto, findings: Vec<Finding>) -> Self {
+        let report = Report::new(findings);
+        Self {
+            crate_name: k.crate_name.clone(),
+            summary: report.severity_counts(),
+            findings: report.findings,
+            doc_coverage: Some(k.doc_coverage()),
+            import_summary: Some(k.imports.clone().unique().summary()),
+            snapshot_diff: None,
+            coupling: k.module_coupling(),
+        }
+    }
+
+    pub fn with_snapshot_diff(mut self, diff: SnapshotDiff) -> Self { self.snapshot_diff = Some(diff); self }
+    pub fn with_doc_coverage(mut self, cov: DocCoverage) -> Self { self.doc_coverage = Some(cov); self }
+    pub fn with_import_summary(mut self, s: ImportSummary) -> Self { self.import_summary = Some(s); self }
+}
+
+/// Renders a minijinja template string against `data`.
+pub fn render_template(template: &str, data: &TemplateData) -> Result<String, KleptoError> {
+    let mut env = minijinja::Environment::new();
+    env.add_template("report", template)?;
+    Ok(env.get_template("report")?.render(data)?)
+}
@@ -0,0 +1,110 @@
+//! Which functions each test exercises, going by the call graph.
+//!
+//! A test exercises the non-test functions it calls or names, directly or
+//! through test-only helpers (code under `#[cfg(test)]` or in `tests/`). Calls
+//! from there on aren't followed: a function only reached through another one
+//! counts as untested.
+
+use crate::coverage::module_key;
+use crate::graph::CallGraph;
+use crate::klepto::Klepto;
+use crate::model::{CapturedFn, FileLocation, TargetKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A `#[test]` function and what it exercises.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TestTarget {
+    pub test: String,
+    pub location: FileLocation,
+    /// fq_names of the non-test functions it reaches.
+    pub exercises: BTreeSet<String>,
+    /// Their modules, `crate::a::b`.
+    pub modules: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TestMap {
+    pub tests: Vec<TestTarget>,
+    /// Non-test function -> tests exercising it.
+    pub tested_by: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TestMap {
+    pub fn tests_of(&self, fq_name: &str) -> impl Iterator<Item = &str> {
+        self.tested_by.get(fq_name).into_iter().flatten().map(String::as_str)
+    }
+
+    pub fn is_tested(&self, fq_name: &str) -> bool { self.tested_by.contains_key(fq_name) }
+}
+
+fn is_test_fn(f: &CapturedFn) -> bool {
+    // `#[test]`, `#[tokio::test]`, `#[rstest::rstest]`...
+    f.attrs.iter().any(|a| matches!(a.rsplit("::").next(), Some("test" | "rstest")))
+}
+
+fn is_test_side(f: &CapturedFn) -> bool {
+    f.is_test_code || is_test_fn(f) || TargetKind::of_path(&f.location.path) == TargetKind::Test
+}
+
+/// For each of `roots`, the functions outside `harness` it reaches, following
+/// calls only while they stay inside `harness`.
+fn reached_outside<'k>(
+    k: &'k Klepto,
+    graph: &CallGraph,
+    roots: impl IntoIterator<Item = &'k CapturedFn>,
+    harness: impl Fn(&CapturedFn) -> bool,
+) -> Vec<(&'k CapturedFn, BTreeSet<String>)> {
+    roots.into_iter()
+        .map(|root| {
+            let mut seen = BTreeSet::from([root.fq_name.as_str()]);
+            let mut stack = vec![root.fq_name.as_str()];
+            let mut out = BTreeSet::new();
+            while let Some(cur) = stack.pop() {
+                for callee in graph.callees_of(cur) {
+                    let Some(f) = k.fn_by_name(callee) else { continue; };
+                    if harness(f) {
+                        if seen.insert(callee) { stack.push(callee); }
+                    } else {
+                        out.insert(callee.to_string());
+                    }
+                }
+            }
+            (root, out)
+        })
+        .collect()
+}
+
+impl Klepto {
+    /// Tests mapped to the functions and modules they exercise (see the module docs).
+    pub fn test_map(&self) -> TestMap { self.test_map_with(&self.call_graph()) }
+
+    pub(crate) fn test_map_with(&self, graph: &CallGraph) -> TestMap {
+        let mut map = TestMap::default();
+        for (t, exercises) in reached_outside(self, graph, self.functions.iter().filter(|f| is_test_fn(f)), is_test_side) {
+            for f in &exercises {
+                map.tested_by.entry(f.clone()).or_default().insert(t.fq_name.to_string());
+            }
+            let modules = exercises.iter()
+                .filter_map(|f| self.fn_by_name(f))
+                .map(|f| module_key(&f.location.path, &f.module_path))
+                .collect();
+            map.tests.push(TestTarget { test: t.fq_name.to_string(), location: t.location.clone(), exercises, modules });
+        }
+        map
+    }
+
+    /// Non-test public functions no test exercises. Empty when the scan has no
+    /// tests at all, rather than every public function.
+    pub fn untested_public_fns(&self) -> Vec<&CapturedFn> { self.untested_public_fns_with(&self.call_graph()) }
+
+    pub(crate) fn untested_public_fns_with(&self, graph: &CallGraph) -> Vec<&CapturedFn> {
+        let map = self.test_map_with(graph);
+        if map.tests.is_empty() { return Vec::new(); }
+        self.functions.iter()
+            .filter(|f| !is_test_side(f) && self.effective_visibility.fn_public(f) && !map.is_tested(&f.fq_name))
+            .collect()
+    }
+}
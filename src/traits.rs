@@ -0,0 +1,84 @@
+use crate::klepto::Klepto;
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+
+/// One `Trait for Type` relationship, from an `impl` block or a `#[derive]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TraitImpl {
+    pub self_ty: String,  // as written, generics stripped: "Wrapper"
+    pub trait_ty: String, // as written, generics stripped: "fmt::Display"
+    pub derived: bool,
+    pub location: FileLocation,
+}
+
+// `& 'a mut Foo < T >` -> "Foo"; `std :: fmt :: Display` -> "std::fmt::Display"
+fn normalize(ty: &str) -> String {
+    let mut out = String::new();
+    let mut depth = 0usize;
+    for tok in ty.split_whitespace() {
+        let opens = tok.matches('<').count();
+        let closes = tok.matches('>').count();
+        if depth == 0 && opens == 0 && !matches!(tok, "&" | "mut" | "dyn") && !tok.starts_with('\'') {
+            out.push_str(tok.trim_start_matches('&'));
+        }
+        depth = (depth + opens).saturating_sub(closes);
+    }
+    out.trim_start_matches("::").to_string()
+}
+
+/// `Display` matches `fmt::Display` and `std::fmt::Display`: the shorter path
+/// must be a segment-aligned tail of the longer one.
+fn same_path(written: &str, query: &str) -> bool {
+    let a: Vec<&str> = written.split("::").collect();
+    let b: Vec<&str> = query.split("::").collect();
+    let n = a.len().min(b.len());
+    a[a.len() - n..] == b[b.len() - n..]
+}
+
+impl Klepto {
+    /// Every `impl Trait for Type` and `#[derive(Trait)]` in the scan.
+    pub fn trait_impls(&self) -> Vec<TraitImpl> {
+        let mut out: Vec<TraitImpl> = self.impls.iter()
+            .filter_map(|i| Some(TraitImpl {
+                self_ty: normalize(&i.self_ty),
+                trait_ty: normalize(i.trait_ty.as_deref()?),
+                derived: false,
+                location: i.location.clone(),
+            }))
+            .collect();
+        for t in &self.types {
+            out.extend(t.derives.iter().map(|d| TraitImpl {
+                self_ty: t.name.clone(),
+                trait_ty: d.clone(),
+                derived: true,
+                location: t.location.clone(),
+            }));
+        }
+        out
+    }
+
+    /// Types implementing `trait_path` (e.g. `"serde::Serialize"` or just `"Serialize"`).
+    ///
+    /// Matching is by path tail, since impls name traits however they were
+    /// imported; auto traits (`Send`, `Sync`) and blanket impls aren't visible.
+    pub fn implementors_of(&self, trait_path: &str) -> Vec<TraitImpl> {
+        let q = normalize(trait_path);
+        self.trait_impls().into_iter().filter(|t| same_path(&t.trait_ty, &q)).collect()
+    }
+
+    /// Traits implemented (or derived) by the type named `ty` (e.g. `"MyType"` or `"crate::a::MyType"`).
+    pub fn traits_implemented_by(&self, ty: &str) -> Vec<TraitImpl> {
+        let q = normalize(ty);
+        let q = q.strip_prefix("crate::").unwrap_or(&q);
+        // a qualified query can't be checked against the written self type, so compare names
+        let name = q.rsplit("::").next().unwrap_or(q);
+        self.trait_impls().into_iter().filter(|t| same_path(&t.self_ty, name)).collect()
+    }
+
+    /// Whether `ty` implements every trait in `traits` (derives included).
+    pub fn implements_all(&self, ty: &str, traits: &[&str]) -> bool {
+        let have = self.traits_implemented_by(ty);
+        traits.iter().all(|t| have.iter().any(|h| same_path(&h.trait_ty, &normalize(t))))
+    }
+}
@@ -0,0 +1,114 @@
+use crate::coverage::module_key;
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::IStr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use syn::visit::Visit;
+
+/// Unsafe surface of one scope.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UnsafeCounts {
+    pub fns: usize,    // `unsafe fn`, methods included
+    pub blocks: usize, // `unsafe { .. }`
+    pub impls: usize,  // `unsafe impl`
+    pub traits: usize, // `unsafe trait`
+}
+
+impl UnsafeCounts {
+    pub fn total(&self) -> usize { self.fns + self.blocks + self.impls + self.traits }
+}
+
+/// Unsafe usage for the whole crate and per module (`crate::a::b`), in the
+/// spirit of cargo-geiger (which counts dependencies too; this is the scanned code only).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UnsafeStats {
+    pub crate_name: String,
+    pub total: UnsafeCounts,
+    pub by_module: BTreeMap<String, UnsafeCounts>, // modules with no unsafe are left out
+}
+
+// unsafe blocks and traits aren't captured, so they come from the ASTs
+struct Walker<'a> {
+    file: &'a Path,
+    mods: Vec<IStr>,
+    out: &'a mut UnsafeStats,
+}
+
+impl Walker<'_> {
+    fn bump(&mut self, pick: fn(&mut UnsafeCounts) -> &mut usize) {
+        bump(self.out, module_key(self.file, &self.mods), pick);
+    }
+}
+
+fn bump(s: &mut UnsafeStats, module: String, pick: fn(&mut UnsafeCounts) -> &mut usize) {
+    *pick(&mut s.total) += 1;
+    *pick(s.by_module.entry(module).or_default()) += 1;
+}
+
+impl<'ast> Visit<'ast> for Walker<'_> {
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        self.mods.push(i.ident.to_string().into());
+        syn::visit::visit_item_mod(self, i);
+        self.mods.pop();
+    }
+
+    fn visit_expr_unsafe(&mut self, e: &'ast syn::ExprUnsafe) {
+        self.bump(|c| &mut c.blocks);
+        syn::visit::visit_expr_unsafe(self, e);
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+        if i.unsafety.is_some() { self.bump(|c| &mut c.traits); }
+        syn::visit::visit_item_trait(self, i);
+    }
+}
+
+impl UnsafeStats {
+    pub fn from_klepto(k: &Klepto) -> Self {
+        let mut s = UnsafeStats { crate_name: k.crate_name.clone(), ..Default::default() };
+        for f in k.functions.iter().filter(|f| f.is_unsafe) {
+            bump(&mut s, module_key(&f.location.path, &f.module_path), |c| &mut c.fns);
+        }
+        for i in k.impls.iter().filter(|i| i.is_unsafe) {
+            bump(&mut s, module_key(&i.location.path, &i.module_path), |c| &mut c.impls);
+        }
+        for pf in &k.files {
+            Walker { file: &pf.path, mods: Vec::new(), out: &mut s }.visit_file(&pf.ast());
+        }
+        s
+    }
+
+    pub fn to_json(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        fn row(name: &str, c: &UnsafeCounts) -> String {
+            format!("| {} | {} | {} | {} | {} | {} |\n", name, c.total(), c.fns, c.blocks, c.impls, c.traits)
+        }
+        const HEADER: &str = "| Total | Fns | Blocks | Impls | Traits |\n|---|---:|---:|---:|---:|---:|\n";
+
+        let mut s = String::new();
+        s.push_str("# Unsafe Usage\n\n");
+        s.push_str(&format!("| Scope {}", HEADER));
+        s.push_str(&row(&format!("**{}**", self.crate_name), &self.total));
+
+        if !self.by_module.is_empty() {
+            s.push_str(&format!("\n## By module\n\n| Module {}", HEADER));
+            for (m, c) in &self.by_module {
+                s.push_str(&row(&format!("`{}`", m), c));
+            }
+        }
+        s
+    }
+}
+
+impl Klepto {
+    /// Unsafe fns, blocks, impls and traits, per module and in total.
+    pub fn unsafe_stats(&self) -> UnsafeStats {
+        UnsafeStats::from_klepto(self)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::graph::{module_of, resolve_path, CallGraph};
+use crate::klepto::Klepto;
+use crate::model::*;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// One occurrence that refers to the item passed to `Klepto::usages_of`.
+#[derive(Debug, Clone, Copy)]
+pub enum Usage<'k> {
+    Import(&'k StolenPath),
+    Path(&'k PathOccurrence),
+    Call(&'k CallOccurrence),
+}
+
+impl<'k> Usage<'k> {
+    pub fn location(&self) -> &'k FileLocation {
+        match self {
+            Usage::Import(i) => &i.location,
+            Usage::Path(p) => &p.location,
+            Usage::Call(c) => &c.location,
+        }
+    }
+
+    pub fn enclosing_fn(&self) -> Option<&'k str> {
+        match self {
+            Usage::Import(_) => None,
+            Usage::Path(p) => p.enclosing_fn.as_deref(),
+            Usage::Call(c) => c.enclosing_fn.as_deref(),
+        }
+    }
+}
+
+fn split(path: &str) -> Vec<String> {
+    let s: String = path.chars().filter(|c| !c.is_whitespace()).collect();
+    let s = s.strip_prefix("::").unwrap_or(&s);
+    s.split("::").filter(|x| !x.is_empty()).map(str::to_string).collect()
+}
+
+struct Matcher<'k> {
+    crate_name: &'k str,
+    // crate-relative absolute paths that name the target (definition + re-exports)
+    known: BTreeSet<Vec<String>>,
+    // names bound to the target by `use` (or by being defined there), per (file, inline module)
+    bindings: HashMap<(&'k Path, &'k [IStr]), BTreeSet<String>>,
+}
+
+impl<'k> Matcher<'k> {
+    fn resolve(&self, segs: &[String], base: &[String]) -> Vec<Vec<String>> {
+        resolve_path(segs, base, self.crate_name)
+    }
+
+    fn names_target(&self, abs: &[String]) -> bool {
+        self.known.iter().any(|k| abs.starts_with(k))
+    }
+
+    // `use` of the target itself (or a glob over its parent): the local name it binds
+    fn binding(&self, imp: &StolenPath) -> Option<String> {
+        let mut segs = vec![imp.root.clone()];
+        segs.extend(imp.segments.iter().cloned());
+        let base = module_of(&imp.location.path, &imp.module_path);
+        let glob = matches!(imp.kind, UseKind::Glob);
+        if glob { segs.pop(); }
+
+        for abs in self.resolve(&segs, &base) {
+            for k in &self.known {
+                let Some((name, parent)) = k.split_last() else { continue; };
+                if glob && abs == parent {
+                    return Some(name.clone());
+                }
+                if !glob && abs == *k {
+                    return Some(match &imp.kind {
+                        UseKind::Rename { alias } => alias.clone(),
+                        _ => name.clone(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn matches(&self, path: &str, file: &Path, module_path: &[IStr]) -> bool {
+        let segs = split(path);
+        let Some(first) = segs.first() else { return false; };
+        if self.bindings.get(&(file, module_path)).is_some_and(|b| b.contains(first)) {
+            return true;
+        }
+        let base = module_of(file, module_path);
+        self.resolve(&segs, &base).iter().any(|abs| self.names_target(abs))
+    }
+}
+
+impl Klepto {
+    /// Every import, path and call that refers to `target` (e.g.
+    /// `"crate::model::Finding"`, or a captured `fq_name` like `"crate::Klepto::diff"`).
+    ///
+    /// Follows `use` aliases, `pub use` re-exports (including globs) and, for
+    /// functions, calls resolved by the call graph (`self.method()`, `Self::f()`).
+    /// Resolution is syntactic, so shadowing and trait dispatch aren't modeled.
+    pub fn usages_of(&self, target: &str) -> Vec<Usage<'_>> {
+        let mut m = Matcher { crate_name: &self.crate_name, known: BTreeSet::new(), bindings: HashMap::new() };
+        let target_segs = split(target);
+        let tail: Vec<String> = m.resolve(&target_segs, &[]).into_iter().next().unwrap_or_default();
+
+        // map the target to its definition(s): by klepto fq_name or by file-qualified path
+        let mut fn_targets: BTreeSet<&str> = BTreeSet::new();
+        let mut defined = false;
+        for f in &self.functions {
+            let owner = match &f.kind {
+                FnKind::FreeFn => None,
+                FnKind::ImplMethod { self_ty, .. } => Some(self_ty),
+                FnKind::TraitMethod { trait_name } => Some(trait_name),
+            };
+            let mut abs = module_of(&f.location.path, &f.module_path);
+            abs.extend(owner.cloned());
+            abs.push(f.name.clone());
+            if f.fq_name == target || abs == tail {
+                fn_targets.insert(&f.fq_name);
+                // a free fn is in scope by name in its own module; a method via its type
+                let name = owner.unwrap_or(&f.name).clone();
+                m.bindings.entry((f.location.path.as_path(), &f.module_path[..])).or_default().insert(name);
+                m.known.insert(abs);
+                defined = true;
+            }
+        }
+        for t in &self.types {
+            let mut abs = module_of(&t.location.path, &t.module_path);
+            abs.push(t.name.clone());
+            if t.fq_name == target || abs == tail {
+                m.bindings.entry((t.location.path.as_path(), &t.module_path[..])).or_default().insert(t.name.clone());
+                m.known.insert(abs);
+                defined = true;
+            }
+        }
+        if !defined {
+            m.known.insert(tail);
+        }
+
+        // re-exports publish the target under new paths; follow chains to a fixpoint
+        for _ in 0..8 {
+            let before = m.known.len();
+            for imp in self.imports.iter().filter(|i| i.is_public_use) {
+                if let Some(name) = m.binding(imp) {
+                    let mut abs = module_of(&imp.location.path, &imp.module_path);
+                    abs.push(name);
+                    m.known.insert(abs);
+                }
+            }
+            if m.known.len() == before { break; }
+        }
+
+        let mut out = Vec::new();
+        for imp in &self.imports {
+            if let Some(name) = m.binding(imp) {
+                m.bindings.entry((imp.location.path.as_path(), &imp.module_path[..])).or_default().insert(name);
+                out.push(Usage::Import(imp));
+            }
+        }
+        for p in &self.paths {
+            if m.matches(&p.path, &p.location.path, &p.module_path) {
+                out.push(Usage::Path(p));
+            }
+        }
+
+        let resolved_calls: BTreeSet<(FilePath, Option<u32>, Option<u32>)> = if fn_targets.is_empty() {
+            BTreeSet::new()
+        } else {
+            let g = CallGraph::build(self);
+            g.edges().iter()
+                .filter(|e| fn_targets.contains(e.callee.as_str()))
+                .map(|e| (e.location.path.clone(), e.location.line, e.location.column))
+                .collect()
+        };
+        for c in &self.calls {
+            let by_graph = !resolved_calls.is_empty()
+                && resolved_calls.contains(&(c.location.path.clone(), c.location.line, c.location.column));
+            if by_graph || (c.receiver.is_none() && m.matches(&c.callee, &c.location.path, &c.module_path)) {
+                out.push(Usage::Call(c));
+            }
+        }
+        out
+    }
+}
@@ -1,4 +1,7 @@
-use crate::model::{UseSite, UseSiteKind};
+use crate::model::{IStr, ModulePath, UseSite, UseSiteKind, UseSites};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use syn::visit::Visit;
 
 fn norm_crate_root(s: &str) -> String {
     s.replace('-', "_")
@@ -21,9 +24,9 @@ fn split_dep_path(raw: &str) -> Option<(String, String, String)> {
     Some((dep, head, full))
 }
 
-fn scope_from(enclosing_fn: &Option<String>, module_path: &[String]) -> String {
-    if let Some(f) = enclosing_fn.clone() {
-        f
+fn scope_from(enclosing_fn: &Option<IStr>, module_path: &[IStr]) -> String {
+    if let Some(f) = enclosing_fn {
+        f.to_string()
     } else if module_path.is_empty() {
         "file".to_string()
     } else {
@@ -31,11 +34,220 @@ fn scope_from(enclosing_fn: &Option<String>, module_path: &[String]) -> String {
     }
 }
 
+/// Counting helpers for the output of `dep_use_sites`/`internal_use_sites`.
+///
+/// ```rust,no_run
+/// use klepto::prelude::*;
+/// # let k = Klepto::new("crate").scan_in_folder("src").parse().unwrap();
+/// # let deps = Default::default();
+/// let sites = k.dep_use_sites(&deps);
+/// println!("{}", klepto::use_sites_to_markdown(&sites.aggregate()));
+/// let by_file = sites.aggregate_by_file();
+/// println!("{}", klepto::grouped_use_sites_to_markdown(by_file.iter().map(|(p, s)| (p.display(), s))));
+/// ```
+pub trait UseSiteVecExt {
+    /// dep -> head -> number of sites.
+    fn aggregate(&self) -> UseSites;
+
+    /// `aggregate` per `UseSite::scope` (enclosing fn, module or `"file"`).
+    fn aggregate_by_scope(&self) -> BTreeMap<String, UseSites>;
+
+    /// `aggregate` per file.
+    fn aggregate_by_file(&self) -> BTreeMap<PathBuf, UseSites>;
+
+    /// `aggregate` per kind of site (`use`, attribute, macro call, ..).
+    fn aggregate_by_kind(&self) -> BTreeMap<UseSiteKind, UseSites>;
+}
+
+fn count_into(out: &mut UseSites, s: &UseSite) {
+    *out.entry(s.dep.clone()).or_default().entry(s.head.clone()).or_insert(0) += 1;
+}
+
+impl UseSiteVecExt for [UseSite] {
+    fn aggregate(&self) -> UseSites {
+        let mut out = UseSites::new();
+        for s in self { count_into(&mut out, s); }
+        out
+    }
+
+    fn aggregate_by_scope(&self) -> BTreeMap<String, UseSites> {
+        let mut out: BTreeMap<String, UseSites> = BTreeMap::new();
+        for s in self { count_into(out.entry(s.scope.clone()).or_default(), s); }
+        out
+    }
+
+    fn aggregate_by_file(&self) -> BTreeMap<PathBuf, UseSites> {
+        let mut out: BTreeMap<PathBuf, UseSites> = BTreeMap::new();
+        for s in self { count_into(out.entry(s.location.path.to_path_buf()).or_default(), s); }
+        out
+    }
+
+    fn aggregate_by_kind(&self) -> BTreeMap<UseSiteKind, UseSites> {
+        let mut out: BTreeMap<UseSiteKind, UseSites> = BTreeMap::new();
+        for s in self { count_into(out.entry(s.kind).or_default(), s); }
+        out
+    }
+}
+
+/// `| Dependency | Item | Sites |` table, a dependency's total on its first row.
+pub fn use_sites_to_markdown(sites: &UseSites) -> String {
+    let mut s = String::from("| Dependency | Item | Sites |\n|---|---|---:|\n");
+    for (dep, heads) in sites {
+        let total: usize = heads.values().sum();
+        s.push_str(&format!("| `{}` | | **{}** |\n", dep, total));
+        for (head, n) in heads {
+            s.push_str(&format!("| | `{}` | {} |\n", head, n));
+        }
+    }
+    s
+}
+
+/// One `use_sites_to_markdown` table per group under a `###` heading, e.g.
+/// `grouped_use_sites_to_markdown(&sites.aggregate_by_scope())`; for files, map
+/// the keys with `Path::display`.
+pub fn grouped_use_sites_to_markdown<'a, L: std::fmt::Display>(groups: impl IntoIterator<Item = (L, &'a UseSites)>) -> String {
+    let mut s = String::new();
+    for (group, sites) in groups {
+        s.push_str(&format!("### `{}`\n\n{}\n", group, use_sites_to_markdown(sites)));
+    }
+    s
+}
+
+/// Which crate provides an unqualified derive or attribute macro (`Serialize` -> `serde`).
+///
+/// Consulted only when the name isn't brought in by a `use` in the same module;
+/// qualified paths (`#[tokio::main]`, `#[derive(serde::Serialize)]`) need no mapping.
+#[derive(Debug, Clone)]
+pub struct AttributeCrates(BTreeMap<String, String>);
+
+impl Default for AttributeCrates {
+    fn default() -> Self {
+        let pairs = [
+            ("Serialize", "serde"),
+            ("Deserialize", "serde"),
+            ("Error", "thiserror"),
+            ("Parser", "clap"),
+            ("Subcommand", "clap"),
+            ("Args", "clap"),
+            ("ValueEnum", "clap"),
+            ("JsonSchema", "schemars"),
+            ("Builder", "derive_builder"),
+            ("EnumString", "strum"),
+            ("EnumIter", "strum"),
+            ("Zeroize", "zeroize"),
+            ("async_trait", "async_trait"),
+            ("instrument", "tracing"),
+        ];
+        Self(pairs.iter().map(|(n, c)| (n.to_string(), c.to_string())).collect())
+    }
+}
+
+impl AttributeCrates {
+    /// No mappings at all; only qualified paths and `use`d names resolve.
+    pub fn empty() -> Self { Self(BTreeMap::new()) }
+
+    pub fn insert(mut self, name: impl Into<String>, krate: impl Into<String>) -> Self {
+        self.0.insert(name.into(), krate.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> { self.0.get(name).map(String::as_str) }
+}
+
+// every `#[derive(..)]` entry and non-derive attribute path (through `cfg_attr`),
+// plus paths written as strings in attribute arguments (`#[serde(with = "dep::m")]`),
+// with its inline module
+struct AttrPaths<'a> {
+    file: &'a std::path::Path,
+    mod_stack: Vec<String>,
+    out: Vec<(Vec<String>, Vec<String>, crate::model::FileLocation)>, // (path segs, module, location)
+}
+
+impl AttrPaths<'_> {
+    fn meta(&mut self, m: &syn::Meta, loc: &crate::model::FileLocation) {
+        let segs = |p: &syn::Path| p.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>();
+        let nested = |l: &syn::MetaList| l.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+        match m {
+            syn::Meta::List(l) if l.path.is_ident("derive") => {
+                let parsed = l.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated);
+                for p in parsed.iter().flatten() {
+                    self.out.push((segs(p), self.mod_stack.clone(), loc.clone()));
+                }
+            }
+            // `cfg_attr(predicate, attr, ..)`
+            syn::Meta::List(l) if l.path.is_ident("cfg_attr") => {
+                for inner in nested(l).iter().flatten().skip(1) {
+                    self.meta(inner, loc);
+                }
+            }
+            syn::Meta::List(l) => {
+                self.out.push((segs(&l.path), self.mod_stack.clone(), loc.clone()));
+                let mut found = Vec::new();
+                string_paths(l.tokens.clone(), &mut found);
+                for p in found {
+                    self.out.push((p, self.mod_stack.clone(), loc.clone()));
+                }
+            }
+            _ => self.out.push((segs(m.path()), self.mod_stack.clone(), loc.clone())),
+        }
+    }
+}
+
+// `key = "a::b"` anywhere in attribute arguments, when the string is a path of
+// two or more segments
+fn string_paths(tokens: proc_macro2::TokenStream, out: &mut Vec<Vec<String>>) {
+    use proc_macro2::TokenTree;
+    let toks: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, t) in toks.iter().enumerate() {
+        match t {
+            TokenTree::Group(g) => string_paths(g.stream(), out),
+            TokenTree::Punct(eq) if eq.as_char() == '=' => {
+                let Some(TokenTree::Literal(lit)) = toks.get(i + 1) else { continue };
+                let Ok(syn::Lit::Str(s)) = syn::parse_str::<syn::Lit>(&lit.to_string()) else { continue };
+                let Ok(path) = syn::parse_str::<syn::Path>(&s.value()) else { continue };
+                if path.segments.len() > 1 {
+                    out.push(path.segments.iter().map(|s| s.ident.to_string()).collect());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for AttrPaths<'_> {
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        for a in &i.attrs { self.visit_attribute(a); }
+        if let Some((_, items)) = &i.content {
+            self.mod_stack.push(i.ident.to_string());
+            for it in items { self.visit_item(it); }
+            self.mod_stack.pop();
+        }
+    }
+
+    fn visit_attribute(&mut self, a: &'ast syn::Attribute) {
+        let loc = crate::scope::span_to_location(self.file, syn::spanned::Spanned::span(a));
+        self.meta(&a.meta, &loc);
+    }
+}
+
 impl crate::Klepto {
     /// Equivalent to your `scan_dep_use_sites(content, used_deps)` but AST-based.
+    ///
+    /// Derives, attribute macros (also under `cfg_attr`) and paths in attribute
+    /// arguments (`#[serde(with = "dep::module")]`) count too, as
+    /// `UseSiteKind::Attribute`; unqualified names resolve with `AttributeCrates::default()`.
     pub fn dep_use_sites(
         &self,
         used_deps: &std::collections::BTreeSet<String>,
+    ) -> Vec<UseSite> {
+        self.dep_use_sites_with(used_deps, &AttributeCrates::default())
+    }
+
+    /// `dep_use_sites` with a custom derive/attribute name -> crate mapping.
+    pub fn dep_use_sites_with(
+        &self,
+        used_deps: &std::collections::BTreeSet<String>,
+        attribute_crates: &AttributeCrates,
     ) -> Vec<UseSite> {
         use std::collections::HashSet;
 
@@ -51,7 +263,7 @@ impl crate::Klepto {
             if imp.segments.is_empty() || imp.segments[0] == "*" {
                 // still a use site; head can be "*" or empty
             }
-            let head = imp.segments.get(0).cloned().unwrap_or_else(|| "*".to_string());
+            let head = imp.segments.first().cloned().unwrap_or_else(|| "*".to_string());
 
             out.push(UseSite {
                 dep: imp.root.clone(),
@@ -59,13 +271,13 @@ impl crate::Klepto {
                 head,
                 kind: UseSiteKind::UseStmt,
                 location: imp.location.clone(),
-                scope: scope_from(&None, &imp.module_path), // imports are usually not inside fns
+                scope: scope_from(&imp.enclosing_fn, &imp.module_path),
             });
         }
 
         // 2) dep::... paths anywhere (your regex’s main job)
         for p in &self.paths {
-            let Some((dep, head, full)) = split_dep_path(&p.path) else { continue; };
+            let Some((dep, head, full)) = split_dep_path(p.resolved.as_deref().unwrap_or(&p.path)) else { continue; };
             if !used.contains(&norm_crate_root(&dep)) {
                 continue;
             }
@@ -114,6 +326,54 @@ for m in &self.macros_inv {
     });
 }
 
+        // 4) derives, attribute macros and attribute arguments: `#[derive(Serialize)]`,
+        //    `#[tokio::main]`, `#[serde(with = "chrono::serde::ts_seconds")]`
+        for pf in &self.files {
+            let mut v = AttrPaths { file: &pf.path, mod_stack: Vec::new(), out: Vec::new() };
+            v.visit_file(&pf.ast());
+            for (segs, module_path, location) in v.out {
+                let Some(name) = segs.last() else { continue; };
+                let (dep, path) = if segs.len() > 1 {
+                    (segs[0].clone(), segs.join("::"))
+                } else {
+                    // `use serde::Serialize;` in scope wins over the name table
+                    let imported = self.imports.iter().find(|i| {
+                        i.location.path == pf.path && i.module_path == module_path && match &i.kind {
+                            crate::model::UseKind::Rename { alias } => alias == name,
+                            _ => i.segments.last() == Some(name),
+                        }
+                    });
+                    match (imported, attribute_crates.get(name)) {
+                        (Some(i), _) => (i.root.clone(), i.full_path.clone()),
+                        (None, Some(dep)) => (dep.to_string(), format!("{}::{}", dep, name)),
+                        (None, None) => continue,
+                    }
+                };
+                if !used.contains(&norm_crate_root(&dep)) {
+                    continue;
+                }
+                // qualified attribute macros are already path occurrences; retag those
+                let same_path = out.iter_mut().find(|s| {
+                    matches!(s.kind, UseSiteKind::Path)
+                        && s.path == path
+                        && s.location.path == location.path
+                        && s.location.line == location.line
+                });
+                if let Some(site) = same_path {
+                    site.kind = UseSiteKind::Attribute;
+                    continue;
+                }
+                out.push(UseSite {
+                    head: path.split("::").nth(1).unwrap_or(name).to_string(),
+                    dep,
+                    path,
+                    kind: UseSiteKind::Attribute,
+                    location,
+                    scope: scope_from(&None, &ModulePath::from(&module_path[..])),
+                });
+            }
+        }
+
         out
     }
 
@@ -129,7 +389,7 @@ for m in &self.macros_inv {
             if !is_internal_root(&imp.root) {
                 continue;
             }
-            let head = imp.segments.get(0).cloned().unwrap_or_else(|| "*".to_string());
+            let head = imp.segments.first().cloned().unwrap_or_else(|| "*".to_string());
             out.push(UseSite {
                 dep: imp.root.clone(),
                 path: imp.full_path.clone(),
@@ -142,7 +402,7 @@ for m in &self.macros_inv {
 
         // Internal `crate::...` / `self::...` / `super::...` / `<crate_id>::...` paths
         for p in &self.paths {
-            let Some((dep, head, full)) = split_dep_path(&p.path) else { continue; };
+            let Some((dep, head, full)) = split_dep_path(p.resolved.as_deref().unwrap_or(&p.path)) else { continue; };
             if !is_internal_root(&dep) {
                 continue;
             }
@@ -193,4 +453,76 @@ for m in &self.macros_inv {
 
         out
     }
-}
\ No newline at end of file
+
+    /// `normal_dependencies` whose every use site is in a test, example or bench:
+    /// candidates for `[dev-dependencies]`. Unused deps aren't reported here.
+    ///
+    /// Only meaningful when those targets were scanned (`include_tests` etc.).
+    pub fn dev_only_dependencies(&self) -> Vec<crate::model::DevOnlyDependency> {
+        use crate::model::{DevOnlyDependency, TargetKind};
+        use std::collections::BTreeMap;
+
+        let mut by_dep: BTreeMap<String, Vec<UseSite>> = BTreeMap::new();
+        for site in self.dep_use_sites(&self.normal_dependencies) {
+            by_dep.entry(norm_crate_root(&site.dep)).or_default().push(site);
+        }
+        by_dep.into_iter()
+            .filter(|(_, sites)| sites.iter().all(|s| TargetKind::of_path(&s.location.path).is_dev()))
+            .map(|(dep, sites)| DevOnlyDependency {
+                dep,
+                targets: sites.iter().map(|s| TargetKind::of_path(&s.location.path)).collect(),
+                sites,
+            })
+            .collect()
+    }
+
+    /// External crates each function body touches, keyed by `fq_name`.
+    ///
+    /// Joins paths, calls and macro invocations on `enclosing_fn`; a first segment
+    /// counts when it is a crate root (`tokio::spawn`) or a name `use`d from one
+    /// in the same module (`spawn` after `use tokio::spawn`). `std`/`core`/`alloc` included.
+    pub fn fn_dep_profile(&self) -> BTreeMap<String, std::collections::BTreeSet<String>> {
+        use std::collections::{BTreeSet, HashMap};
+
+        let own = norm_crate_root(&self.crate_name);
+        let is_local = |root: &str| matches!(root, "crate" | "self" | "super" | "Self") || norm_crate_root(root) == own;
+
+        let mut crates: BTreeSet<String> = ["std", "core", "alloc"].iter().map(|s| s.to_string()).collect();
+        crates.extend(self.normal_dependencies.iter().cloned());
+        let mut bound: HashMap<(&std::path::Path, &[IStr]), HashMap<&str, &str>> = HashMap::new();
+        for imp in self.imports.iter().filter(|i| !i.is_internal && !is_local(&i.root)) {
+            crates.insert(norm_crate_root(&imp.root));
+            let name = match &imp.kind {
+                crate::model::UseKind::Rename { alias } => alias.as_str(),
+                crate::model::UseKind::Name => imp.segments.last().unwrap_or(&imp.root).as_str(),
+                crate::model::UseKind::Glob => continue,
+            };
+            bound.entry((imp.location.path.as_path(), &imp.module_path[..])).or_default().insert(name, &imp.root);
+        }
+
+        let mut out: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut touch = |fq: Option<&IStr>, path: &str, file: &std::path::Path, module: &[IStr]| {
+            let Some(fq) = fq else { return; };
+            let first: String = path.split("::").next().unwrap_or("").chars().filter(|c| !c.is_whitespace()).collect();
+            let first = first.as_str();
+            if first.is_empty() || is_local(first) { return; }
+            let root = match bound.get(&(file, module)).and_then(|b| b.get(first)) {
+                Some(root) => norm_crate_root(root),
+                None if path.contains("::") && crates.contains(&norm_crate_root(first)) => norm_crate_root(first),
+                None => return,
+            };
+            out.entry(fq.to_string()).or_default().insert(root);
+        };
+        for p in &self.paths {
+            touch(p.enclosing_fn.as_ref(), p.path.trim_start_matches("::"), &p.location.path, &p.module_path);
+        }
+        for c in self.calls.iter().filter(|c| c.receiver.is_none()) {
+            touch(c.enclosing_fn.as_ref(), c.callee.trim_start_matches(':').trim(), &c.location.path, &c.module_path);
+        }
+        for m in &self.macros_inv {
+            let path = m.path.as_deref().unwrap_or(&m.name);
+            touch(m.enclosing_fn.as_ref(), path.trim_start_matches("::"), &m.location.path, &m.module_path);
+        }
+        out
+    }
+}
@@ -0,0 +1,45 @@
+//! `KleptoView`: a cheaply cloned, thread-safe handle on a parsed `Klepto`.
+
+use crate::klepto::Klepto;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A `Klepto` behind an `Arc`, shareable across threads (`Clone + Send + Sync`).
+///
+/// Derefs to `Klepto`, so every query and analysis works on it. syn's trees
+/// don't cross threads: analyses that walk `ParsedFile::ast` parse each file
+/// from its source again on the first thread that needs it, so a scan with
+/// `retain_source(false)` only has trees on the thread that ran it.
+#[derive(Debug, Clone)]
+pub struct KleptoView(Arc<Klepto>);
+
+// a field that can't be shared across threads fails to compile here
+const _: fn() = || {
+    fn check<T: Send + Sync>() {}
+    check::<KleptoView>();
+};
+
+impl KleptoView {
+    pub fn new(k: Klepto) -> Self { Self(Arc::new(k)) }
+
+    /// Number of views sharing this `Klepto`.
+    pub fn handles(this: &Self) -> usize { Arc::strong_count(&this.0) }
+}
+
+impl Deref for KleptoView {
+    type Target = Klepto;
+    fn deref(&self) -> &Klepto { &self.0 }
+}
+
+impl AsRef<Klepto> for KleptoView {
+    fn as_ref(&self) -> &Klepto { &self.0 }
+}
+
+impl From<Klepto> for KleptoView {
+    fn from(k: Klepto) -> Self { Self::new(k) }
+}
+
+impl Klepto {
+    /// Shareable, thread-safe handle on this scan (see `KleptoView`).
+    pub fn into_view(self) -> KleptoView { KleptoView::new(self) }
+}
@@ -0,0 +1,191 @@
+//! Effective visibility: whether an item can be named from outside the crate.
+//!
+//! A `pub` item is public API only when every module above it is `pub` as well,
+//! or when a `pub use` in such a module re-exports it, the module holding it
+//! (`pub use inner::*`) or one of its ancestors. Re-exports of re-exports are
+//! followed. Module paths here are full: file modules, then inline ones.
+//!
+//! The same resolution follows each `pub use` down to the item it names
+//! (`ExportedSymbol::resolved_target`).
+
+use crate::extract::file_module_path;
+use crate::model::{CapturedFn, CapturedType, ExportedSymbol, FnKind, MacroDef, ModuleDecl, ModulePath};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// longest `pub use` chain followed
+const MAX_CHAIN: usize = 32;
+
+// (module, exported name) -> (module, source); globs under the name "*"
+type ExportsByName<'a> = HashMap<(Vec<String>, &'a str), Vec<(Vec<String>, &'a str)>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveVisibility {
+    decls: HashMap<Vec<String>, bool>,           // module -> declared `pub`
+    types: HashMap<String, Vec<(Vec<String>, bool)>>, // type name -> (module, declared `pub`)
+    reexported: HashSet<Vec<String>>,            // items and modules named by a reachable `pub use`
+    globbed: HashSet<Vec<String>>,               // modules whose `pub` items a reachable `pub use m::*` exposes
+}
+
+/// Full module path of an item: its file's modules, then `module_path`.
+pub fn full_module_path(file: &Path, module_path: &ModulePath) -> Vec<String> {
+    let mut m = file_module_path(file);
+    m.extend(module_path.iter().map(|s| s.to_string()));
+    m
+}
+
+impl EffectiveVisibility {
+    pub fn build(modules: &[ModuleDecl], exports: &[ExportedSymbol], types: &[CapturedType]) -> Self {
+        let mut v = Self::default();
+        for m in modules {
+            let mut full = full_module_path(&m.location.path, &m.module_path);
+            full.push(m.name.clone());
+            *v.decls.entry(full).or_default() |= m.is_public;
+        }
+        for t in types {
+            v.types.entry(t.name.clone()).or_default().push((full_module_path(&t.location.path, &t.module_path), t.is_public));
+        }
+
+        // a re-export can make another one reachable; at most one round per export
+        for _ in 0..=exports.len() {
+            let mut changed = false;
+            for e in exports {
+                let here = full_module_path(&e.location.path, &e.module_path);
+                if !v.module_public(&here) { continue; }
+                let Some(mut target) = v.resolve(&here, &e.source_path) else { continue; };
+                changed |= if target.last().is_some_and(|s| s == "*") {
+                    target.pop();
+                    v.globbed.insert(target)
+                } else {
+                    v.reexported.insert(target)
+                };
+            }
+            if !changed { break; }
+        }
+        v
+    }
+
+    // `pub use` source -> full path inside this crate; None for other crates
+    fn resolve(&self, here: &[String], source: &str) -> Option<Vec<String>> {
+        let mut segs = source.split("::").peekable();
+        let mut base: Vec<String> = match *segs.peek()? {
+            "crate" => { segs.next(); Vec::new() }
+            "self" => { segs.next(); here.to_vec() }
+            "super" => {
+                let mut b = here.to_vec();
+                while segs.next_if_eq(&"super").is_some() { b.pop(); }
+                b
+            }
+            // 2018 paths: a child module of `here`, or (from the root) any top-level module
+            first => {
+                let mut child = here.to_vec();
+                child.push(first.to_string());
+                if !self.decls.contains_key(&child) { return None; }
+                here.to_vec()
+            }
+        };
+        base.extend(segs.map(str::to_string));
+        Some(base)
+    }
+
+    /// Sets `resolved_target` on every non-glob export whose chain of `pub use`s
+    /// (renames and globs included) ends at a module, type, free fn or macro in the scan.
+    pub fn resolve_exports(&self, exports: &mut [ExportedSymbol], fns: &[CapturedFn], types: &[CapturedType], macros: &[MacroDef]) {
+        let mut defined: HashSet<Vec<String>> = self.decls.keys().cloned().collect();
+        let mut add = |file: &Path, m: &ModulePath, name: &str| {
+            let mut full = full_module_path(file, m);
+            full.push(name.to_string());
+            defined.insert(full);
+        };
+        for f in fns.iter().filter(|f| matches!(f.kind, FnKind::FreeFn)) { add(&f.location.path, &f.module_path, &f.name); }
+        for t in types { add(&t.location.path, &t.module_path, &t.name); }
+        for m in macros { add(&m.location.path, &m.module_path, &m.name); }
+
+        let mut by_name: ExportsByName = HashMap::new();
+        for e in exports.iter() {
+            let here = full_module_path(&e.location.path, &e.module_path);
+            by_name.entry((here.clone(), e.exported_as.as_str())).or_default().push((here, e.source_path.as_str()));
+        }
+
+        let targets: Vec<Option<String>> = exports.iter().map(|e| {
+            if e.exported_as == "*" { return None; }
+            let here = full_module_path(&e.location.path, &e.module_path);
+            let target = self.resolve(&here, &e.source_path)?;
+            let t = self.chase(target, &defined, &by_name, 0)?;
+            Some(std::iter::once("crate").chain(t.iter().map(String::as_str)).collect::<Vec<_>>().join("::"))
+        }).collect();
+        for (e, t) in exports.iter_mut().zip(targets) {
+            e.resolved_target = t;
+        }
+    }
+
+    // follows `target` through re-exports in its module until it names a defined item
+    fn chase(
+        &self,
+        target: Vec<String>,
+        defined: &HashSet<Vec<String>>,
+        by_name: &ExportsByName,
+        depth: usize,
+    ) -> Option<Vec<String>> {
+        if defined.contains(&target) { return Some(target); }
+        // a cycle of re-exports is a compile error, but don't loop on one
+        if depth > MAX_CHAIN { return None; }
+        let (name, module) = target.split_last()?;
+        let named = by_name.get(&(module.to_vec(), name.as_str())).into_iter().flatten()
+            .filter_map(|(here, src)| self.resolve(here, src));
+        let globbed = by_name.get(&(module.to_vec(), "*")).into_iter().flatten()
+            .filter_map(|(here, src)| {
+                let mut t = self.resolve(here, src)?;
+                t.pop();
+                t.push(name.clone());
+                Some(t)
+            });
+        named.chain(globbed).find_map(|t| self.chase(t, defined, by_name, depth + 1))
+    }
+
+    /// Whether code outside the crate can name module `m` (a full path; `[]` is the root).
+    /// Modules whose `mod` item wasn't scanned count as public.
+    pub fn module_public(&self, m: &[String]) -> bool {
+        let Some((_, parent)) = m.split_last() else { return true; };
+        if self.reexported.contains(m) { return true; }
+        self.decls.get(m).copied().unwrap_or(true) && (self.globbed.contains(parent) || self.module_public(parent))
+    }
+
+    /// An item named `name`, declared `pub` or not, in module `m`.
+    pub fn item_public(&self, m: &[String], name: &str, declared_pub: bool) -> bool {
+        if declared_pub && (self.globbed.contains(m) || self.module_public(m)) { return true; }
+        let mut full = m.to_vec();
+        full.push(name.to_string());
+        self.reexported.contains(&full)
+    }
+
+    /// Test-only items (`is_test_code`) are never public.
+    pub fn type_public(&self, t: &CapturedType) -> bool {
+        !t.is_test_code && self.item_public(&full_module_path(&t.location.path, &t.module_path), &t.name, t.is_public)
+    }
+
+    /// Free fns by their module; methods also need their type (or trait) to be public.
+    /// Test-only fns never are.
+    pub fn fn_public(&self, f: &CapturedFn) -> bool {
+        if f.is_test_code { return false; }
+        let m = full_module_path(&f.location.path, &f.module_path);
+        match &f.kind {
+            FnKind::FreeFn => self.item_public(&m, &f.name, f.is_public),
+            FnKind::ImplMethod { self_ty, .. } => f.is_public && self.named_type_public(self_ty, &m),
+            FnKind::TraitMethod { trait_name } => f.is_public && self.named_type_public(trait_name, &m),
+        }
+    }
+
+    // by name: the type in `m` if there is one, else any type so named; an
+    // uncaptured type (another crate's) leaves it to `m`
+    fn named_type_public(&self, ty: &str, m: &[String]) -> bool {
+        let name = ty.split('<').next().unwrap_or(ty).trim().rsplit("::").next().unwrap_or("").trim();
+        match self.types.get(name) {
+            Some(defs) => match defs.iter().find(|(dm, _)| dm == m) {
+                Some((dm, p)) => self.item_public(dm, name, *p),
+                None => defs.iter().any(|(dm, p)| self.item_public(dm, name, *p)),
+            },
+            None => self.module_public(m),
+        }
+    }
+}
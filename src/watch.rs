@@ -0,0 +1,96 @@
+use crate::klepto::{Klepto, KleptoBuilder, KleptoError};
+use crate::model::Finding;
+use crate::snapshot::{FileChanges, FindingFinger, file_changes_between, file_hashes, findings_missing_from};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+type RuleFn = Box<dyn Fn(&Klepto) -> Vec<Finding> + Send>;
+
+/// What changed since the previous poll.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WatchEvent {
+    pub changes: FileChanges,
+    pub new_findings: Vec<FindingFinger>,
+    pub resolved_findings: Vec<FindingFinger>,
+}
+
+/// Keeps a parsed `Klepto` and its findings current with the files on disk.
+///
+/// Polls file mtimes (no OS notification dependency); a change in any mtime
+/// re-parses the scan and re-runs the rules. Edits that leave the content
+/// unchanged (a `touch`, an editor save without edits) produce no event.
+pub struct Watch {
+    builder: KleptoBuilder,
+    rules: RuleFn,
+    stamps: BTreeMap<PathBuf, SystemTime>,
+    klepto: Klepto,
+    findings: Vec<Finding>,
+}
+
+impl Watch {
+    /// Parses once and runs the default rules; the builder is re-used for every re-scan.
+    pub fn new(builder: KleptoBuilder) -> Result<Self, KleptoError> {
+        Self::with_rules(builder, |k| k.rules().with_default_rules().run())
+    }
+
+    pub fn with_rules(builder: KleptoBuilder, rules: impl Fn(&Klepto) -> Vec<Finding> + Send + 'static) -> Result<Self, KleptoError> {
+        let stamps = builder.candidates()?.into_iter().collect();
+        let klepto = builder.clone().parse()?;
+        let findings = rules(&klepto);
+        Ok(Self { builder, rules: Box::new(rules), stamps, klepto, findings })
+    }
+
+    pub fn klepto(&self) -> &Klepto { &self.klepto }
+    pub fn findings(&self) -> &[Finding] { &self.findings }
+
+    /// Re-scans if any file was added, removed or touched; `None` when the
+    /// sources are unchanged. On a parse error the previous state is kept.
+    pub fn poll(&mut self) -> Result<Option<WatchEvent>, KleptoError> {
+        let stamps: BTreeMap<PathBuf, SystemTime> = self.builder.candidates()?.into_iter().collect();
+        if stamps == self.stamps {
+            return Ok(None);
+        }
+        // record the stamps first so a file that fails to parse is reported once, not every poll
+        self.stamps = stamps;
+        let klepto = self.builder.clone().parse()?;
+
+        let changes = file_changes_between(&file_hashes(&self.klepto), &file_hashes(&klepto));
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let findings = (self.rules)(&klepto);
+        let old_f: Vec<FindingFinger> = self.findings.iter().map(FindingFinger::from).collect();
+        let new_f: Vec<FindingFinger> = findings.iter().map(FindingFinger::from).collect();
+        let event = WatchEvent {
+            changes,
+            new_findings: findings_missing_from(&new_f, &old_f),
+            resolved_findings: findings_missing_from(&old_f, &new_f),
+        };
+        self.klepto = klepto;
+        self.findings = findings;
+        Ok(Some(event))
+    }
+
+    /// Polls every `interval` and hands each event to `on_event`, until it returns `false`.
+    /// Poll errors (usually a half-saved file that doesn't parse) go to `on_error` and the loop continues.
+    pub fn run(
+        &mut self,
+        interval: Duration,
+        mut on_event: impl FnMut(&Self, &WatchEvent) -> bool,
+        mut on_error: impl FnMut(&KleptoError),
+    ) {
+        loop {
+            std::thread::sleep(interval);
+            match self.poll() {
+                Ok(Some(event)) => {
+                    if !on_event(self, &event) { return; }
+                }
+                Ok(None) => {}
+                Err(e) => on_error(&e),
+            }
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::graph::{module_of, resolve_path, CallGraph};
+use crate::klepto::{Klepto, KleptoError};
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Stable symbol id: `<kind>:crate::<file modules>::<inline modules>::<name>`,
+/// with `#n` appended when the same path is defined more than once (e.g. `fmt`
+/// from both `Display` and `Debug` impls). Unchanged as long as the item keeps
+/// its name and module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct SymbolId(pub String);
+
+impl std::fmt::Display for SymbolId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.pad(&self.0) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum SymbolKind {
+    Fn,
+    Method,
+    Type(TypeKind),
+    Macro,
+}
+
+impl SymbolKind {
+    fn tag(&self) -> &'static str {
+        match self {
+            SymbolKind::Fn => "fn",
+            SymbolKind::Method => "method",
+            SymbolKind::Type(TypeKind::Trait) => "trait",
+            SymbolKind::Type(_) => "type",
+            SymbolKind::Macro => "macro",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Definition {
+    pub id: SymbolId,
+    pub kind: SymbolKind,
+    pub name: String,
+    pub path: String,    // crate::snapshot::Snapshot::diff (file modules included)
+    pub fq_name: String, // as captured (`CapturedFn::fq_name` etc.)
+    pub location: FileLocation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReferenceKind {
+    Import,
+    Path,
+    Call,
+    MethodCall,
+    MacroInvocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Reference {
+    pub symbol: SymbolId,
+    pub kind: ReferenceKind,
+    pub location: FileLocation,
+    pub enclosing_fn: Option<String>,
+}
+
+/// Definition/reference cross-reference over everything klepto captured.
+///
+/// Each occurrence is linked to the definition it most plausibly names:
+/// paths and imports through `use` bindings, aliases and `pub use`
+/// re-exports; method calls through the call graph; macro invocations by
+/// name. Occurrences naming nothing in the workspace are dropped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Xref {
+    definitions: Vec<Definition>,
+    references: Vec<Reference>,
+    #[serde(skip)]
+    by_symbol: HashMap<SymbolId, Vec<usize>>,
+}
+
+impl Xref {
+    pub fn build(k: &Klepto) -> Self {
+        let mut b = XrefBuilder { k, defs: Vec::new(), abs: BTreeMap::new(), by_fq: HashMap::new(), bindings: HashMap::new(), seen: HashMap::new() };
+        b.definitions();
+        b.reexports();
+        let references = b.references();
+        let mut x = Xref { definitions: b.defs, references, by_symbol: HashMap::new() };
+        x.reindex();
+        x
+    }
+
+    fn reindex(&mut self) {
+        self.by_symbol.clear();
+        for (i, r) in self.references.iter().enumerate() {
+            self.by_symbol.entry(r.symbol.clone()).or_default().push(i);
+        }
+    }
+
+    pub fn definitions(&self) -> &[Definition] { &self.definitions }
+
+    pub fn definition(&self, id: &SymbolId) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.id == *id)
+    }
+
+    /// Definitions whose id, path or captured `fq_name` equals `name`.
+    pub fn lookup(&self, name: &str) -> Vec<&Definition> {
+        self.definitions.iter().filter(|d| d.id.0 == name || d.path == name || d.fq_name == name).collect()
+    }
+
+    pub fn references(&self, symbol: &SymbolId) -> Vec<&Reference> {
+        self.by_symbol.get(symbol).into_iter().flatten().map(|&i| &self.references[i]).collect()
+    }
+
+    pub fn all_references(&self) -> &[Reference] { &self.references }
+
+    /// Definitions with no references anywhere in the scan.
+    pub fn unreferenced(&self) -> Vec<&Definition> {
+        self.definitions.iter().filter(|d| !self.by_symbol.contains_key(&d.id)).collect()
+    }
+
+    pub fn to_json_string(&self) -> Result<String, KleptoError> {
+        serde_json::to_string_pretty(self).map_err(KleptoError::Json)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self, KleptoError> {
+        let mut x: Xref = serde_json::from_str(s).map_err(KleptoError::SnapshotFormat)?;
+        x.reindex();
+        Ok(x)
+    }
+}
+
+// (file, inline module) -> local name -> absolute path it refers to
+type Scopes<'k> = HashMap<(&'k Path, &'k [IStr]), HashMap<String, Vec<String>>>;
+
+struct XrefBuilder<'k> {
+    k: &'k Klepto,
+    defs: Vec<Definition>,
+    // crate-relative absolute path -> definition index (definitions + re-export aliases)
+    abs: BTreeMap<Vec<String>, usize>,
+    by_fq: HashMap<&'k str, usize>,
+    // names in scope per (file, inline module)
+    bindings: Scopes<'k>,
+    // base id -> definitions already given it, for the `#n` suffix
+    seen: HashMap<String, usize>,
+}
+
+impl<'k> XrefBuilder<'k> {
+    fn define(&mut self, kind: SymbolKind, name: &str, abs: Vec<String>, fq_name: &'k str, location: &FileLocation) -> usize {
+        let path = std::iter::once("crate").chain(abs.iter().map(String::as_str)).collect::<Vec<_>>().join("::");
+        let base = format!("{}:{}", kind.tag(), path);
+        let dupes = self.seen.entry(base.clone()).or_insert(0);
+        *dupes += 1;
+        let id = if *dupes == 1 { base } else { format!("{}#{}", base, dupes) };
+
+        let i = self.defs.len();
+        self.defs.push(Definition { id: SymbolId(id), kind, name: name.to_string(), path, fq_name: fq_name.to_string(), location: location.clone() });
+        self.abs.entry(abs).or_insert(i);
+        self.by_fq.entry(fq_name).or_insert(i);
+        i
+    }
+
+    fn bind(&mut self, file: &'k Path, module_path: &'k [IStr], name: String, abs: Vec<String>) {
+        self.bindings.entry((file, module_path)).or_default().entry(name).or_insert(abs);
+    }
+
+    fn definitions(&mut self) {
+        let k = self.k;
+        for t in &k.types {
+            let mut abs = module_of(&t.location.path, &t.module_path);
+            abs.push(t.name.clone());
+            self.define(SymbolKind::Type(t.kind), &t.name, abs.clone(), &t.fq_name, &t.location);
+            self.bind(&t.location.path, &t.module_path, t.name.clone(), abs);
+        }
+        for f in &k.functions {
+            let mut abs = module_of(&f.location.path, &f.module_path);
+            let kind = match &f.kind {
+                FnKind::FreeFn => SymbolKind::Fn,
+                FnKind::ImplMethod { self_ty, .. } => { abs.push(self_ty.clone()); SymbolKind::Method }
+                FnKind::TraitMethod { trait_name } => { abs.push(trait_name.clone()); SymbolKind::Method }
+            };
+            abs.push(f.name.clone());
+            self.define(kind, &f.name, abs.clone(), &f.fq_name, &f.location);
+            if kind == SymbolKind::Fn {
+                self.bind(&f.location.path, &f.module_path, f.name.clone(), abs);
+            }
+        }
+        for m in &k.macros_def {
+            let mut abs = module_of(&m.location.path, &m.module_path);
+            abs.push(m.name.clone());
+            self.define(SymbolKind::Macro, &m.name, abs, &m.name, &m.location);
+        }
+    }
+
+    // absolute reading of a `use`, plus the local name it binds (None for globs)
+    fn use_target(&self, imp: &StolenPath) -> (Vec<Vec<String>>, Option<String>) {
+        let mut segs = vec![imp.root.clone()];
+        segs.extend(imp.segments.iter().cloned());
+        let base = module_of(&imp.location.path, &imp.module_path);
+        let binding = match &imp.kind {
+            UseKind::Glob => { segs.pop(); None }
+            UseKind::Rename { alias } => Some(alias.clone()),
+            UseKind::Name => segs.last().cloned(),
+        };
+        (resolve_path(&segs, &base, &self.k.crate_name), binding)
+    }
+
+    // `pub use` publishes items under new paths; follow chains to a fixpoint
+    fn reexports(&mut self) {
+        let k = self.k;
+        for _ in 0..8 {
+            let before = self.abs.len();
+            for imp in k.imports.iter().filter(|i| i.is_public_use) {
+                let (readings, binding) = self.use_target(imp);
+                let base = module_of(&imp.location.path, &imp.module_path);
+                let mut new = Vec::new();
+                for r in &readings {
+                    match &binding {
+                        Some(name) => if let Some(&i) = self.abs.get(r) {
+                            new.push(([base.clone(), vec![name.clone()]].concat(), i));
+                        },
+                        None => for (a, &i) in self.abs.range(r.clone()..).take_while(|(a, _)| a.starts_with(r)) {
+                            if a.len() == r.len() + 1 {
+                                new.push(([base.clone(), vec![a[r.len()].clone()]].concat(), i));
+                            }
+                        },
+                    }
+                }
+                for (a, i) in new {
+                    self.abs.entry(a).or_insert(i);
+                }
+            }
+            if self.abs.len() == before { break; }
+        }
+    }
+
+    // longest known prefix among the readings of `segs` written at (file, module)
+    fn resolve(&self, segs: &[String], file: &Path, module_path: &[IStr]) -> Option<usize> {
+        let base = module_of(file, module_path);
+        let mut readings = resolve_path(segs, &base, &self.k.crate_name);
+        if let Some(abs) = segs.first().and_then(|f| self.bindings.get(&(file, module_path))?.get(f)) {
+            readings.push([abs.as_slice(), &segs[1..]].concat());
+        }
+        readings.iter()
+            .filter_map(|r| (1..=r.len()).rev().find_map(|n| self.abs.get(&r[..n]).map(|&i| (n, i))))
+            .max_by_key(|(n, _)| *n)
+            .map(|(_, i)| i)
+    }
+
+    fn references(&mut self) -> Vec<Reference> {
+        let k = self.k;
+        let mut out = Vec::new();
+        let mut push = |defs: &[Definition], i: usize, kind, location: &FileLocation, enclosing_fn: Option<&IStr>| {
+            out.push(Reference { symbol: defs[i].id.clone(), kind, location: location.clone(), enclosing_fn: enclosing_fn.map(|f| f.to_string()) });
+        };
+
+        // imports first: they add the bindings later paths resolve through
+        for imp in &k.imports {
+            let (readings, binding) = self.use_target(imp);
+            if matches!(imp.kind, UseKind::Glob) {
+                // `use m::*` brings every item directly under `m` into scope
+                let glob: Vec<(String, Vec<String>)> = readings.iter()
+                    .flat_map(|r| self.abs.range(r.clone()..).take_while(move |(a, _)| a.starts_with(r)).filter(move |(a, _)| a.len() == r.len() + 1))
+                    .map(|(a, _)| (a[a.len() - 1].clone(), a.clone()))
+                    .collect();
+                for (name, abs) in glob {
+                    self.bind(&imp.location.path, &imp.module_path, name, abs);
+                }
+                continue;
+            }
+            let hit = readings.iter().find_map(|r| self.abs.get(r).copied());
+            if let Some(i) = hit {
+                push(&self.defs, i, ReferenceKind::Import, &imp.location, None);
+                if let Some(name) = binding {
+                    let abs = readings.into_iter().find(|r| self.abs.contains_key(r)).unwrap_or_default();
+                    self.bind(&imp.location.path, &imp.module_path, name, abs);
+                }
+            }
+        }
+
+        let split = |p: &str| -> Vec<String> {
+            p.split("::").map(|s| s.trim().to_string()).filter(|s| !s.is_empty() && !s.starts_with('<')).collect()
+        };
+        for p in &k.paths {
+            if let Some(i) = self.resolve(&split(&p.path), &p.location.path, &p.module_path) {
+                push(&self.defs, i, ReferenceKind::Path, &p.location, p.enclosing_fn.as_ref());
+            }
+        }
+
+        let graph = CallGraph::build(k);
+        let mut by_site: HashMap<(&Path, Option<u32>, Option<u32>), &str> = HashMap::new();
+        for e in graph.edges() {
+            by_site.entry((e.location.path.as_path(), e.location.line, e.location.column)).or_insert(&e.callee);
+        }
+        for c in &k.calls {
+            let site = (c.location.path.as_path(), c.location.line, c.location.column);
+            let hit = match c.receiver {
+                Some(_) => by_site.get(&site).and_then(|fq| self.by_fq.get(fq)).map(|&i| (i, ReferenceKind::MethodCall)),
+                None => self.resolve(&split(&c.callee), &c.location.path, &c.module_path)
+                    .or_else(|| by_site.get(&site).and_then(|fq| self.by_fq.get(fq)).copied())
+                    .map(|i| (i, ReferenceKind::Call)),
+            };
+            if let Some((i, kind)) = hit {
+                push(&self.defs, i, kind, &c.location, c.enclosing_fn.as_ref());
+            }
+        }
+
+        let mut macros: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, d) in self.defs.iter().enumerate().filter(|(_, d)| d.kind == SymbolKind::Macro) {
+            macros.entry(d.name.as_str()).or_default().push(i);
+        }
+        for m in &k.macros_inv {
+            if let Some([i]) = macros.get(m.name.as_str()).map(Vec::as_slice) {
+                push(&self.defs, *i, ReferenceKind::MacroInvocation, &m.location, m.enclosing_fn.as_ref());
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIB: &str = "mod shapes;\n\
+        pub use shapes::Circle as Round;\n\
+        use shapes::area;\n\
+        macro_rules! twice { ($e:expr) => { $e + $e } }\n\
+        pub fn total() -> f64 { let c = shapes::Circle::new(1.0); twice!(1.0) + area(&c) + c.radius() }\n\
+        pub fn make() -> Round { Round::new(2.0) }\n\
+        pub struct Wrapper;\n\
+        impl std::fmt::Display for Wrapper { fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { Ok(()) } }\n\
+        impl std::fmt::Debug for Wrapper { fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { Ok(()) } }\n";
+    const SHAPES: &str = "pub struct Circle { r: f64 }\n\
+        impl Circle {\n    pub fn new(r: f64) -> Self { Circle { r } }\n    pub fn radius(&self) -> f64 { self.r }\n}\n\
+        pub fn area(c: &Circle) -> f64 { c.r * c.r }\n\
+        pub fn unused() {}\n";
+
+    fn build() -> Xref {
+        let dir = std::env::temp_dir().join(format!("klepto-xref-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), LIB).unwrap();
+        std::fs::write(dir.join("src/shapes.rs"), SHAPES).unwrap();
+        let k = Klepto::new("x").scan_in_folder(dir.join("src")).parse().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        Xref::build(&k)
+    }
+
+    fn refs(x: &Xref, id: &str) -> Vec<(ReferenceKind, Option<u32>)> {
+        let mut out: Vec<_> = x.references(&SymbolId(id.to_string())).iter().map(|r| (r.kind, r.location.line)).collect();
+        out.sort_by_key(|(k, l)| (*l, format!("{k:?}")));
+        out
+    }
+
+    #[test]
+    fn ids_carry_file_modules_and_number_repeats() {
+        let x = build();
+        let ids: Vec<&str> = x.definitions().iter().map(|d| d.id.0.as_str()).collect();
+        for id in ["type:crate::shapes::Circle", "method:crate::shapes::Circle::new", "fn:crate::shapes::area", "macro:crate::twice",
+                   "method:crate::Wrapper::fmt", "method:crate::Wrapper::fmt#2"] {
+            assert!(ids.contains(&id), "{id} not in {ids:?}");
+        }
+        assert_eq!(x.lookup("x::Circle::new").len(), 1);
+        assert_eq!(x.lookup("crate::shapes::area")[0].name, "area");
+        assert_eq!(x.lookup("x::Wrapper::fmt").len(), 2);
+    }
+
+    #[test]
+    fn references_resolve_through_imports_aliases_and_receivers() {
+        use ReferenceKind::*;
+        let x = build();
+        assert_eq!(refs(&x, "fn:crate::shapes::area"), [(Import, Some(3)), (Call, Some(5))]);
+        assert_eq!(refs(&x, "type:crate::shapes::Circle"), [(Import, Some(2))]);
+        // `Round::new` goes through the `pub use .. as Round` alias
+        assert_eq!(refs(&x, "method:crate::shapes::Circle::new"), [(Call, Some(5)), (Path, Some(5)), (Call, Some(6)), (Path, Some(6))]);
+        assert_eq!(refs(&x, "method:crate::shapes::Circle::radius"), [(MethodCall, Some(5))]);
+        assert_eq!(refs(&x, "macro:crate::twice"), [(MacroInvocation, Some(5))]);
+
+        let unreferenced: Vec<&str> = x.unreferenced().iter().map(|d| d.id.0.as_str()).collect();
+        assert!(unreferenced.contains(&"fn:crate::shapes::unused"));
+        assert!(!unreferenced.contains(&"fn:crate::shapes::area"));
+    }
+
+    #[test]
+    fn json_round_trips_keep_the_reference_index() {
+        let x = build();
+        let back = Xref::from_json_str(&x.to_json_string().unwrap()).unwrap();
+        assert_eq!(back.definitions().len(), x.definitions().len());
+        assert_eq!(refs(&back, "fn:crate::shapes::area"), refs(&x, "fn:crate::shapes::area"));
+    }
+}